@@ -19,7 +19,7 @@ use oorandom::Rand32;
 
 // Next we need to actually `use` the pieces of ggez that we are going
 // to need frequently.
-use ggez::event::{KeyCode, KeyMods};
+use ggez::event::{KeyCode, KeyMods, ScanCode};
 use ggez::{event, graphics, timer, Context, GameResult};
 
 // We'll bring in some things from `std` to help us in the future.
@@ -434,6 +434,7 @@ impl event::EventHandler<ggez::GameError> for GameState {
         &mut self,
         _ctx: &mut Context,
         keycode: KeyCode,
+        _scancode: ScanCode,
         _keymod: KeyMods,
         _repeat: bool,
     ) {
@@ -5,7 +5,7 @@
 use ggez::audio;
 use ggez::audio::SoundSource;
 use ggez::conf;
-use ggez::event::{self, EventHandler, KeyCode, KeyMods};
+use ggez::event::{self, EventHandler, KeyCode, KeyMods, ScanCode};
 use ggez::graphics::{self, Color};
 use ggez::timer;
 use ggez::{Context, ContextBuilder, GameResult};
@@ -544,6 +544,7 @@ impl EventHandler<ggez::GameError> for MainState {
         &mut self,
         ctx: &mut Context,
         keycode: KeyCode,
+        _scancode: ScanCode,
         _keymod: KeyMods,
         _repeat: bool,
     ) {
@@ -570,7 +571,13 @@ impl EventHandler<ggez::GameError> for MainState {
         }
     }
 
-    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods) {
+    fn key_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        _scancode: ScanCode,
+        _keymod: KeyMods,
+    ) {
         match keycode {
             KeyCode::Up => {
                 self.input.yaxis = 0.0;
@@ -83,7 +83,7 @@ impl MainState {
     fn draw_venn_diagrams(&mut self, ctx: &mut Context) -> GameResult<()> {
         let (w, h) = graphics::drawable_size(ctx);
         let y = h / 4.;
-        const MODE_COUNT: usize = 8;
+        const MODE_COUNT: usize = 10;
         let x_step = w / (MODE_COUNT + 1) as f32;
 
         // draw with Alpha
@@ -118,6 +118,14 @@ impl MainState {
         self.circle.set_blend_mode(Some(BlendMode::Lighten));
         self.draw_venn(ctx, [x_step * 8., y].into(), "Lighten")?;
 
+        // draw with Screen
+        self.circle.set_blend_mode(Some(BlendMode::Screen));
+        self.draw_venn(ctx, [x_step * 9., y].into(), "Screen")?;
+
+        // draw with Erase
+        self.circle.set_blend_mode(Some(BlendMode::Erase));
+        self.draw_venn(ctx, [x_step * 10., y].into(), "Erase")?;
+
         Ok(())
     }
 }
@@ -179,6 +187,7 @@ impl EventHandler<ggez::GameError> for MainState {
         &mut self,
         _ctx: &mut Context,
         _keycode: ggez::event::KeyCode,
+        _scancode: ggez::event::ScanCode,
         _keymod: ggez::event::KeyMods,
         repeat: bool,
     ) {
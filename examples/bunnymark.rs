@@ -145,6 +145,7 @@ impl event::EventHandler<ggez::GameError> for GameState {
         &mut self,
         _ctx: &mut Context,
         keycode: event::KeyCode,
+        _scancode: event::ScanCode,
         _keymods: event::KeyMods,
         _repeat: bool,
     ) {
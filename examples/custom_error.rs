@@ -0,0 +1,83 @@
+//! Uses the game's own error type in the `EventHandler`, rather than
+//! `GameError`, with `?` still working on ggez's functions.
+
+use ggez::event::{self, ErrorOrigin};
+use ggez::graphics::{self, Color};
+use ggez::{Context, GameError};
+use std::fmt;
+
+/// Errors from ggez, and errors of the game's own.
+#[derive(Debug)]
+enum MyError {
+    Game(GameError),
+    OutOfBounds(f32),
+}
+
+impl fmt::Display for MyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MyError::Game(e) => write!(f, "ggez error: {}", e),
+            MyError::OutOfBounds(x) => write!(f, "the circle got lost at x = {}", x),
+        }
+    }
+}
+
+impl std::error::Error for MyError {}
+
+// This is what lets `?` turn ggez's errors into ours.
+impl From<GameError> for MyError {
+    fn from(e: GameError) -> Self {
+        MyError::Game(e)
+    }
+}
+
+struct MainState {
+    pos_x: f32,
+}
+
+impl event::EventHandler<MyError> for MainState {
+    fn update(&mut self, _ctx: &mut Context) -> Result<(), MyError> {
+        self.pos_x += 4.0;
+        if self.pos_x > 800.0 {
+            return Err(MyError::OutOfBounds(self.pos_x));
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> Result<(), MyError> {
+        graphics::clear(ctx, [0.1, 0.2, 0.3, 1.0].into());
+        let circle = graphics::Mesh::new_circle(
+            ctx,
+            graphics::DrawMode::fill(),
+            glam::Vec2::new(self.pos_x, 380.0),
+            100.0,
+            2.0,
+            Color::WHITE,
+        )?;
+        graphics::draw(ctx, &circle, graphics::DrawParam::default())?;
+        graphics::present(ctx)?;
+        Ok(())
+    }
+
+    fn on_error(&mut self, _ctx: &mut Context, _origin: ErrorOrigin, e: MyError) -> bool {
+        match e {
+            // Not fatal; bring it back and carry on.
+            MyError::OutOfBounds(x) => {
+                println!("Circle went out of bounds at {}, wrapping around", x);
+                self.pos_x = 0.0;
+                false
+            }
+            MyError::Game(e) => {
+                println!("Giving up: {}", e);
+                true
+            }
+        }
+    }
+}
+
+pub fn main() -> Result<(), MyError> {
+    let cb = ggez::ContextBuilder::new("custom_error", "ggez");
+    let (ctx, event_loop) = cb.build()?;
+    let state = MainState { pos_x: 0.0 };
+    event::run(ctx, event_loop, state)
+}
@@ -5,7 +5,7 @@
 use std::convert::TryFrom;
 
 use ggez::conf;
-use ggez::event::{self, KeyCode, KeyMods};
+use ggez::event::{self, KeyCode, KeyMods, ScanCode};
 use ggez::graphics::{self, Color, DrawMode};
 use ggez::timer;
 use ggez::{Context, GameResult};
@@ -129,7 +129,13 @@ impl event::EventHandler<ggez::GameError> for MainState {
         println!("Button clicked at: {} {}", x, y);
     }
 
-    fn key_up_event(&mut self, ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods) {
+    fn key_up_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _scancode: ScanCode,
+        _keymod: KeyMods,
+    ) {
         match keycode {
             KeyCode::F => {
                 self.window_settings.toggle_fullscreen = true;
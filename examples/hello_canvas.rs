@@ -90,6 +90,7 @@ impl event::EventHandler<ggez::GameError> for MainState {
         &mut self,
         _ctx: &mut Context,
         _keycode: ggez::event::KeyCode,
+        _scancode: ggez::event::ScanCode,
         _keymod: ggez::event::KeyMods,
         repeat: bool,
     ) {
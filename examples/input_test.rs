@@ -1,6 +1,8 @@
 //! Example that just prints out all the input events.
 
-use ggez::event::{self, Axis, Button, GamepadId, KeyCode, KeyMods, MouseButton};
+use ggez::event::{
+    self, Axis, Button, GamepadId, KeyCode, KeyMods, MouseButton, ScanCode, ScrollDelta,
+};
 use ggez::graphics::{self, Color, DrawMode};
 use ggez::{conf, input};
 use ggez::{Context, GameResult};
@@ -89,25 +91,35 @@ impl event::EventHandler<ggez::GameError> for MainState {
         );
     }
 
-    fn mouse_wheel_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
-        println!("Mousewheel event, x: {}, y: {}", x, y);
+    fn mouse_scroll_event(&mut self, _ctx: &mut Context, delta: ScrollDelta) {
+        println!("Mouse scroll event, {:?}", delta);
     }
 
     fn key_down_event(
         &mut self,
         _ctx: &mut Context,
         keycode: KeyCode,
+        scancode: ScanCode,
         keymod: KeyMods,
         repeat: bool,
     ) {
         println!(
-            "Key pressed: {:?}, modifier {:?}, repeat: {}",
-            keycode, keymod, repeat
+            "Key pressed: {:?}, scancode {}, modifier {:?}, repeat: {}",
+            keycode, scancode, keymod, repeat
         );
     }
 
-    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, keymod: KeyMods) {
-        println!("Key released: {:?}, modifier {:?}", keycode, keymod);
+    fn key_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        scancode: ScanCode,
+        keymod: KeyMods,
+    ) {
+        println!(
+            "Key released: {:?}, scancode {}, modifier {:?}",
+            keycode, scancode, keymod
+        );
     }
 
     fn text_input_event(&mut self, _ctx: &mut Context, ch: char) {
@@ -136,6 +148,10 @@ impl event::EventHandler<ggez::GameError> for MainState {
             println!("Focus lost");
         }
     }
+
+    fn file_dropped(&mut self, _ctx: &mut Context, path: &std::path::Path) {
+        println!("File dropped: {}", path.display());
+    }
 }
 
 pub fn main() -> GameResult {
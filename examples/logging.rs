@@ -9,7 +9,7 @@
 use log::*;
 
 use ggez::conf::{WindowMode, WindowSetup};
-use ggez::event::{EventHandler, KeyCode, KeyMods};
+use ggez::event::{EventHandler, KeyCode, KeyMods, ScanCode};
 use ggez::filesystem::{self, File};
 use ggez::graphics;
 use ggez::timer;
@@ -101,6 +101,7 @@ impl EventHandler<ggez::GameError> for App {
         &mut self,
         ctx: &mut Context,
         keycode: KeyCode,
+        _scancode: ScanCode,
         keymod: KeyMods,
         repeat: bool,
     ) {
@@ -95,6 +95,7 @@ impl event::EventHandler<ggez::GameError> for MainState {
         &mut self,
         ctx: &mut Context,
         keycode: input::keyboard::KeyCode,
+        _scancode: input::keyboard::ScanCode,
         _keymod: input::keyboard::KeyMods,
         _repeat: bool,
     ) {
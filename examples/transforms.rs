@@ -1,5 +1,5 @@
 //! Demonstrates various projection and matrix fiddling/testing.
-use ggez::event::{self, KeyCode, KeyMods};
+use ggez::event::{self, KeyCode, KeyMods, ScanCode};
 use ggez::graphics::{self, Color, DrawMode};
 use ggez::{Context, GameResult};
 use glam::*;
@@ -106,6 +106,7 @@ impl event::EventHandler<ggez::GameError> for MainState {
         &mut self,
         ctx: &mut Context,
         keycode: KeyCode,
+        _scancode: ScanCode,
         _keymod: KeyMods,
         _repeat: bool,
     ) {
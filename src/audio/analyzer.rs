@@ -0,0 +1,334 @@
+//! Listening in on sounds as they play, for visualizers.
+
+use std::time;
+
+use std::sync::{Arc, Mutex};
+
+/// Listens in on sounds as they play, for audio visualizers and
+/// effects that react to the music.  Attach one to a source with
+/// [`SoundSource::set_analyzer()`](trait.SoundSource.html#method.set_analyzer)
+/// or to everything on a mixer bus with
+/// [`Mixer::set_analyzer()`](struct.Mixer.html#method.set_analyzer),
+/// then call [`spectrum()`](#method.spectrum) or [`rms()`](#method.rms)
+/// each frame.
+///
+/// The audio thread only keeps the last [`WINDOW`](#associatedconstant.WINDOW)
+/// frames of sound around; the analysis itself happens when you ask for
+/// it.  An `Analyzer` is cheap to clone, and clones listen to the same
+/// sounds.
+#[derive(Debug, Clone)]
+pub struct Analyzer {
+    tap: Arc<Mutex<Tap>>,
+    bands: usize,
+}
+
+/// The sound an `Analyzer` has heard lately, mixed down to mono.
+#[derive(Debug)]
+struct Tap {
+    ring: Vec<f32>,
+    /// How many frames have been heard in all.
+    head: u64,
+    /// When the last sound heard will have played out of the window, if
+    /// nothing else comes along.
+    expires: Option<time::Instant>,
+}
+
+impl Tap {
+    /// Mixes in frames of a sound, the first being its `start`th frame
+    /// since the tap was attached.  Frames of sounds playing at the
+    /// same time line up well enough to add together.
+    fn add(&mut self, start: u64, frames: &[f32]) {
+        let len = self.ring.len() as u64;
+        for (i, frame) in frames.iter().enumerate() {
+            let index = start + i as u64;
+            while self.head <= index {
+                self.ring[(self.head % len) as usize] = 0.0;
+                self.head += 1;
+            }
+            if index + len >= self.head {
+                self.ring[(index % len) as usize] += frame;
+            }
+        }
+    }
+
+    /// Forgets the sound heard if nothing has been heard for a whole
+    /// window, so a sound that stopped reads as silence rather than
+    /// its last moments.
+    fn expire(&mut self) {
+        if let Some(expires) = self.expires {
+            if time::Instant::now() >= expires {
+                self.ring.iter_mut().for_each(|frame| *frame = 0.0);
+                self.expires = None;
+            }
+        }
+    }
+
+    /// The frames in the order they were heard.
+    fn frames(&self) -> Vec<f32> {
+        let split = (self.head % self.ring.len() as u64) as usize;
+        let mut frames = self.ring[split..].to_vec();
+        frames.extend_from_slice(&self.ring[..split]);
+        frames
+    }
+}
+
+impl Analyzer {
+    /// How many frames are analyzed at a time.  At 44.1 kHz it's about
+    /// 23 ms of sound.
+    pub const WINDOW: usize = 1024;
+
+    /// Creates a new `Analyzer` splitting the spectrum into the given
+    /// number of bands.
+    pub fn new(bands: usize) -> Self {
+        Analyzer {
+            tap: Arc::new(Mutex::new(Tap {
+                ring: vec![0.0; Self::WINDOW],
+                head: 0,
+                expires: None,
+            })),
+            bands: bands.max(1),
+        }
+    }
+
+    /// The root mean square level of the sound heard lately, from `0.0`
+    /// for silence up to `1.0` for as loud as can be.
+    pub fn rms(&self) -> f32 {
+        let mut tap = match self.tap.lock() {
+            Ok(tap) => tap,
+            Err(_) => return 0.0,
+        };
+        tap.expire();
+        let sum: f32 = tap.ring.iter().map(|frame| frame * frame).sum();
+        (sum / tap.ring.len() as f32).sqrt()
+    }
+
+    /// How loud each band of frequencies is in the sound heard lately,
+    /// from the lowest band to the highest.  The bands are spaced
+    /// logarithmically, like pitch is heard, and each is the average
+    /// magnitude of its frequencies, so `1.0` is about as loud as a
+    /// pure tone can be.
+    pub fn spectrum(&self) -> Vec<f32> {
+        let frames = match self.tap.lock() {
+            Ok(mut tap) => {
+                tap.expire();
+                tap.frames()
+            }
+            Err(_) => return vec![0.0; self.bands],
+        };
+        let magnitudes = magnitudes(&frames);
+        let bins = magnitudes.len() as f32;
+        (0..self.bands)
+            .map(|band| {
+                let edge = |band: usize| bins.powf(band as f32 / self.bands as f32) as usize;
+                let low = edge(band).max(1);
+                let high = edge(band + 1).max(low + 1).min(magnitudes.len());
+                if low >= high {
+                    return 0.0;
+                }
+                magnitudes[low..high].iter().sum::<f32>() / (high - low) as f32
+            })
+            .collect()
+    }
+
+    /// The number of bands the spectrum is split into.
+    pub fn bands(&self) -> usize {
+        self.bands
+    }
+}
+
+/// The magnitudes of the frequencies in the given frames, whose number
+/// must be a power of two, after a Hann window.
+fn magnitudes(frames: &[f32]) -> Vec<f32> {
+    use std::f32::consts::PI;
+    let n = frames.len();
+    let mut re: Vec<f32> = frames
+        .iter()
+        .enumerate()
+        .map(|(i, frame)| frame * (0.5 - 0.5 * (2.0 * PI * i as f32 / n as f32).cos()))
+        .collect();
+    let mut im = vec![0.0; n];
+
+    // An iterative radix-2 FFT: put the input in bit-reversed order...
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+        }
+    }
+    // ...then combine ever longer runs.
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+
+    // The Hann window halves the amplitude, and a real signal's energy
+    // is split between positive and negative frequencies.
+    let scale = 4.0 / n as f32;
+    (0..n / 2)
+        .map(|i| (re[i] * re[i] + im[i] * im[i]).sqrt() * scale)
+        .collect()
+}
+
+/// Feeds a sound to `Analyzer`s as it plays.
+pub(crate) struct Tapped<I> {
+    input: I,
+    taps: Vec<(Arc<Mutex<Tap>>, Option<u64>)>,
+    batch: Vec<f32>,
+    frame: f32,
+    channel: u16,
+    written: u64,
+}
+
+impl<I> Tapped<I> {
+    /// How many frames are gathered before handing them to the taps.
+    const BATCH: usize = 256;
+
+    pub(crate) fn new(input: I, analyzers: Vec<Analyzer>) -> Self {
+        Tapped {
+            input,
+            taps: analyzers
+                .into_iter()
+                .map(|analyzer| (analyzer.tap, None))
+                .collect(),
+            batch: Vec::new(),
+            frame: 0.0,
+            channel: 0,
+            written: 0,
+        }
+    }
+
+    fn flush(&mut self)
+    where
+        I: rodio::Source<Item = i16>,
+    {
+        let window = Analyzer::WINDOW as f64 / f64::from(self.input.sample_rate().max(1));
+        let expires = time::Instant::now() + time::Duration::from_secs_f64(window);
+        for (tap, start) in &mut self.taps {
+            if let Ok(mut tap) = tap.try_lock() {
+                let start = *start.get_or_insert(tap.head);
+                tap.add(start + self.written, &self.batch);
+                tap.expires = Some(expires);
+            }
+        }
+        self.written += self.batch.len() as u64;
+        self.batch.clear();
+    }
+}
+
+impl<I> Iterator for Tapped<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let channels = self.input.channels().max(1);
+        let sample = self.input.next();
+        if self.taps.is_empty() {
+            return sample;
+        }
+        match sample {
+            Some(sample) => {
+                self.frame += f32::from(sample) / 32768.0;
+                self.channel += 1;
+                if self.channel >= channels {
+                    self.batch.push(self.frame / f32::from(channels));
+                    self.frame = 0.0;
+                    self.channel = 0;
+                    if self.batch.len() >= Self::BATCH {
+                        self.flush();
+                    }
+                }
+            }
+            None if !self.batch.is_empty() => self.flush(),
+            None => (),
+        }
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> rodio::Source for Tapped<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_analyzer() {
+        use std::f32::consts::PI;
+        let n = Analyzer::WINDOW;
+        // A sine wave that fits exactly 64 times into the window.
+        let sine = (0..n)
+            .map(|i| (2.0 * PI * 64.0 * i as f32 / n as f32).sin())
+            .collect::<Vec<_>>();
+        let magnitudes = magnitudes(&sine);
+        assert!((magnitudes[64] - 1.0).abs() < 0.01);
+        assert!(magnitudes[200] < 0.01);
+
+        let analyzer = Analyzer::new(8);
+        assert_eq!(analyzer.spectrum(), vec![0.0; 8]);
+        let samples = sine
+            .iter()
+            .map(|s| (s * 16384.0) as i16)
+            .collect::<Vec<_>>();
+        let input = rodio::buffer::SamplesBuffer::new(1, 44100, samples);
+        assert_eq!(Tapped::new(input, vec![analyzer.clone()]).count(), n);
+        let rms = analyzer.rms();
+        assert!((rms - 0.5 / 2.0f32.sqrt()).abs() < 0.01);
+        let spectrum = analyzer.spectrum();
+        let loudest = spectrum
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .map(|(band, _)| band);
+        // Bin 64 of 512 is in the sixth of eight logarithmic bands.
+        assert_eq!(loudest, Some(5));
+
+        // Once nothing has been heard for a window, it's all silence.
+        analyzer.tap.lock().unwrap().expires = Some(time::Instant::now());
+        assert_eq!(analyzer.rms(), 0.0);
+        assert_eq!(analyzer.spectrum(), vec![0.0; 8]);
+    }
+}
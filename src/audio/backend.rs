@@ -0,0 +1,680 @@
+//! Where sound goes: the audio backend and the voices it plays sounds on.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::thread;
+use std::time;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use crate::context::Context;
+use crate::error::GameError;
+use crate::error::GameResult;
+
+/// A sound ready to play, with its effects, volume and everything
+/// else already applied, as handed to a [`Voice`](trait.Voice.html).
+/// It's an iterator over 16-bit samples, with the samples of each
+/// channel interleaved.
+pub struct Sound(Box<dyn rodio::Source<Item = i16> + Send>);
+
+impl Sound {
+    pub(crate) fn new<S>(source: S) -> Self
+    where
+        S: rodio::Source<Item = i16> + Send + 'static,
+    {
+        Sound(Box::new(source))
+    }
+
+    /// The number of channels.  This can change partway through a
+    /// sound that's several sounds one after another, and only does so
+    /// between frames.
+    pub fn channels(&self) -> u16 {
+        rodio::Source::channels(&self.0)
+    }
+
+    /// The number of frames a second.  Like the number of channels
+    /// this can change partway through.
+    pub fn sample_rate(&self) -> u32 {
+        rodio::Source::sample_rate(&self.0)
+    }
+}
+
+impl Iterator for Sound {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl fmt::Debug for Sound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<Sound: {:p}>", self)
+    }
+}
+
+/// Somewhere sounds are played one after another, made by the
+/// [`AudioContext`](trait.AudioContext.html) for each source; it's what
+/// a `rodio::Sink` is to rodio.  Dropping a voice stops its sounds.
+pub trait Voice {
+    /// Queues a sound to play after the ones already queued.
+    fn append(&self, sound: Sound);
+
+    /// Resumes playing after [`pause()`](#tymethod.pause).
+    fn play(&self);
+
+    /// Pauses playing.
+    fn pause(&self);
+
+    /// Whether the voice is paused.
+    fn is_paused(&self) -> bool;
+
+    /// Whether the queued sounds have all played.
+    fn empty(&self) -> bool;
+
+    /// Lets the queued sounds play on after the voice is gone.
+    fn detach(self: Box<Self>);
+}
+
+/// A trait object defining an audio context, the backend that sound is
+/// played through.  The default one plays sound with `rodio`.
+///
+/// Swap in your own with [`set_backend()`](fn.set_backend.html) to play
+/// sound through something else, like FMOD, kira or nothing at all on a
+/// server, while keeping the [`Source`](struct.Source.html) API: each
+/// source asks the backend for a [`Voice`](trait.Voice.html) and hands
+/// it ready-made streams of samples.  Decoding, effects and mixing
+/// still use `rodio`, so it stays a dependency even with another
+/// backend, though no `rodio` types show up in the API.
+pub trait AudioContext {
+    /// Makes a new voice to play sounds on.
+    fn new_voice(&self) -> GameResult<Box<dyn Voice>>;
+
+    /// The name of the output device sound is played on, if known.
+    fn device_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Switches to the output device with the given name, or to the
+    /// system's default if `None`.
+    fn set_device(&mut self, _name: Option<&str>) -> GameResult {
+        Err(GameError::AudioError(
+            "This audio context can't change output devices".to_string(),
+        ))
+    }
+
+    /// Checks that sound is still going to the right output device,
+    /// switching if it's gone away or the system's default changed,
+    /// and returns whether it switched.  Called every frame by the
+    /// event loop, so it should be quick; a switch is passed on to
+    /// [`EventHandler::audio_device_changed()`](../event/trait.EventHandler.html#method.audio_device_changed).
+    fn check_device(&mut self) -> bool {
+        false
+    }
+}
+
+/// Replaces the audio backend, see [`AudioContext`](trait.AudioContext.html).
+/// Sounds already playing stop, and sounds play through the new backend
+/// from their next [`play()`](trait.SoundSource.html#method.play).
+pub fn set_backend(ctx: &mut Context, backend: Box<dyn AudioContext>) {
+    ctx.audio_context = backend;
+}
+
+/// Lists the names of the output devices sound can be played on.
+pub fn output_devices() -> GameResult<Vec<String>> {
+    use rodio::cpal::traits::{DeviceTrait, HostTrait};
+    let devices = rodio::cpal::default_host().output_devices().map_err(|e| {
+        GameError::AudioError(format!("Could not list audio output devices: {}", e))
+    })?;
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+/// Gets the name of the output device sound is played on.
+pub fn output_device(ctx: &Context) -> Option<&str> {
+    ctx.audio_context.device_name()
+}
+
+/// Switches to the output device with the given name, from
+/// [`output_devices()`](fn.output_devices.html), or to the system's
+/// default if `None`.  ggez sticks to the chosen device while it's
+/// there, and goes back to the default if it's unplugged.  Sounds
+/// already playing carry on on the new device.
+pub fn set_output_device(ctx: &mut Context, name: Option<&str>) -> GameResult {
+    ctx.audio_context.set_device(name)
+}
+
+/// Takes whether sound moved to another output device by itself since
+/// the last call, because the one in use went away or the system's
+/// default changed.  Only needed with a custom event loop;
+/// [`event::run()`](../event/fn.run.html) calls
+/// `EventHandler::audio_device_changed()` instead.
+pub fn output_device_changed(ctx: &mut Context) -> bool {
+    std::mem::take(&mut ctx.audio_device_changed)
+}
+
+/// A struct that contains all information for tracking sound info.
+///
+/// You generally don't have to create this yourself, it will be part
+/// of your `Context` object.
+///
+/// The output stream lives on a thread of its own, which also looks
+/// for changes to the output devices, so none of that holds up the
+/// game.  Voices are mixed from there rather than tied to the stream,
+/// so when it moves to another device they carry on where they were.
+pub(crate) struct RodioAudioContext {
+    voices: Arc<Voices>,
+    requests: mpsc::Sender<DeviceRequest>,
+    /// The devices the output thread switched to by itself.
+    switches: mpsc::Receiver<String>,
+    device_name: String,
+}
+
+impl RodioAudioContext {
+    /// Create new `RodioAudioContext`, playing on the output device with
+    /// the given name or the default one.
+    pub fn new(device: Option<&str>) -> GameResult<Self> {
+        let voices = Arc::new(Voices::default());
+        let (requests, received) = mpsc::channel();
+        let (switched, switches) = mpsc::channel();
+        let (opened, ready) = mpsc::channel();
+        let shared = voices.clone();
+        let device = device.map(str::to_string);
+        let _ = thread::Builder::new()
+            .name("ggez audio output".to_string())
+            .spawn(move || Output::run(shared, device, received, switched, opened))
+            .map_err(|e| {
+                GameError::AudioError(format!("Could not start the audio output thread: {}", e))
+            })?;
+        let device_name = ready
+            .recv()
+            .map_err(|_| Self::stopped())?
+            .map_err(GameError::AudioError)?;
+        Ok(Self {
+            voices,
+            requests,
+            switches,
+            device_name,
+        })
+    }
+
+    fn stopped() -> GameError {
+        GameError::AudioError("The audio output thread has stopped".to_string())
+    }
+}
+
+impl AudioContext for RodioAudioContext {
+    fn new_voice(&self) -> GameResult<Box<dyn Voice>> {
+        let voice = Arc::new(VoiceQueue::default());
+        self.voices.add(voice.clone());
+        Ok(Box::new(RodioVoice(voice)))
+    }
+
+    fn device_name(&self) -> Option<&str> {
+        Some(&self.device_name)
+    }
+
+    fn set_device(&mut self, name: Option<&str>) -> GameResult {
+        let (reply, response) = mpsc::channel();
+        self.requests
+            .send((name.map(str::to_string), reply))
+            .map_err(|_| Self::stopped())?;
+        self.device_name = response
+            .recv()
+            .map_err(|_| Self::stopped())?
+            .map_err(GameError::AudioError)?;
+        Ok(())
+    }
+
+    fn check_device(&mut self) -> bool {
+        let mut switched = false;
+        while let Ok(device_name) = self.switches.try_recv() {
+            self.device_name = device_name;
+            switched = true;
+        }
+        switched
+    }
+}
+
+impl fmt::Debug for RodioAudioContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<RodioAudioContext: {:p}>", self)
+    }
+}
+
+/// A request to switch to the named output device, or the default,
+/// answered with the name of the device switched to.
+type DeviceRequest = (Option<String>, mpsc::Sender<Result<String, String>>);
+
+/// An output stream playing the voices, owned by the output thread
+/// since rodio's streams can't be moved between threads.
+struct Output {
+    _stream: rodio::OutputStream,
+    /// Cleared when the stream is being replaced, so it lets go of the
+    /// voices.
+    active: Arc<AtomicBool>,
+    device_name: String,
+}
+
+impl Output {
+    /// How often to look for changes to the output devices.
+    const CHECK_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+    /// Opens the named output device if it's there, or else the
+    /// default, and starts playing the voices on it.
+    fn open(name: Option<&str>, voices: &Arc<Voices>) -> Result<Self, String> {
+        use rodio::cpal::traits::{DeviceTrait, HostTrait};
+        let host = rodio::cpal::default_host();
+        let named = name.and_then(|name| {
+            host.output_devices()
+                .ok()?
+                .find(|device| device.name().ok().as_deref() == Some(name))
+        });
+        if let (Some(name), None) = (name, &named) {
+            warn!(
+                "Audio output device {:?} not found, using the default",
+                name
+            );
+        }
+        let device = named
+            .or_else(|| host.default_output_device())
+            .ok_or_else(|| String::from("Could not find an audio output device"))?;
+        let device_name = device.name().unwrap_or_default();
+        let (stream, stream_handle) =
+            rodio::OutputStream::try_from_device(&device).map_err(|e| {
+                format!(
+                    "Could not initialize sound system using output device {:?}: {}",
+                    device_name, e
+                )
+            })?;
+        let active = Arc::new(AtomicBool::new(true));
+        stream_handle
+            .play_raw(VoiceMixer::new(voices.clone(), active.clone()))
+            .map_err(|e| format!("Could not play on output device {:?}: {}", device_name, e))?;
+        Ok(Output {
+            _stream: stream,
+            active,
+            device_name,
+        })
+    }
+
+    /// Moves the voices over to another device, staying on this one if
+    /// that can't be opened.
+    fn switch(&mut self, name: Option<&str>, voices: &Arc<Voices>) -> Result<(), String> {
+        self.active.store(false, Ordering::SeqCst);
+        match Output::open(name, voices) {
+            Ok(output) => {
+                *self = output;
+                Ok(())
+            }
+            Err(e) => {
+                self.active.store(true, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+
+    /// The name of the device that ought to be in use right now: the
+    /// one asked for while it's there, otherwise the default.
+    fn wanted_device(requested: Option<&str>) -> Option<String> {
+        use rodio::cpal::traits::{DeviceTrait, HostTrait};
+        let host = rodio::cpal::default_host();
+        if let Some(requested) = requested {
+            let mut devices = host.output_devices().ok()?;
+            if devices.any(|device| device.name().ok().as_deref() == Some(requested)) {
+                return Some(requested.to_string());
+            }
+        }
+        host.default_output_device()
+            .and_then(|device| device.name().ok())
+    }
+
+    /// The output thread: plays the voices, switches devices when asked
+    /// to, and follows the default device around once a second, so
+    /// sound keeps working when headphones are plugged in or out.
+    fn run(
+        voices: Arc<Voices>,
+        mut requested: Option<String>,
+        requests: mpsc::Receiver<DeviceRequest>,
+        switched: mpsc::Sender<String>,
+        opened: mpsc::Sender<Result<String, String>>,
+    ) {
+        let mut output = match Output::open(requested.as_deref(), &voices) {
+            Ok(output) => output,
+            Err(e) => {
+                let _ = opened.send(Err(e));
+                return;
+            }
+        };
+        let _ = opened.send(Ok(output.device_name.clone()));
+        loop {
+            match requests.recv_timeout(Self::CHECK_INTERVAL) {
+                Ok((name, reply)) => {
+                    let result = output.switch(name.as_deref(), &voices);
+                    if result.is_ok() {
+                        requested = name;
+                    }
+                    let _ = reply.send(result.map(|()| output.device_name.clone()));
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let wanted = Self::wanted_device(requested.as_deref())
+                        .filter(|wanted| *wanted != output.device_name);
+                    if let Some(wanted) = wanted {
+                        match output.switch(Some(&wanted), &voices) {
+                            Ok(()) => {
+                                info!("Switching audio output to {:?}", output.device_name);
+                                if switched.send(output.device_name.clone()).is_err() {
+                                    return;
+                                }
+                            }
+                            Err(e) => warn!("Could not switch audio output device: {}", e),
+                        }
+                    }
+                }
+                // The audio context is gone.
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// A sound converted to the format voices are mixed in.
+type Converted =
+    rodio::source::UniformSourceIterator<Box<dyn rodio::Source<Item = i16> + Send>, f32>;
+
+/// The sounds queued on a voice of the `rodio` backend, shared between
+/// its `RodioVoice` and the output thread.
+struct VoiceQueue {
+    sounds: Mutex<VecDeque<Converted>>,
+    paused: AtomicBool,
+    /// Whether the `RodioVoice` is still around.
+    owned: AtomicBool,
+    detached: AtomicBool,
+}
+
+impl Default for VoiceQueue {
+    fn default() -> Self {
+        VoiceQueue {
+            sounds: Mutex::new(VecDeque::new()),
+            paused: AtomicBool::new(false),
+            owned: AtomicBool::new(true),
+            detached: AtomicBool::new(false),
+        }
+    }
+}
+
+impl VoiceQueue {
+    /// Whether the voice should still be played: while it's owned, or
+    /// after it's been detached until it runs out of sound.
+    fn wanted(&self) -> bool {
+        self.owned.load(Ordering::SeqCst) || (self.detached.load(Ordering::SeqCst) && !self.empty())
+    }
+
+    fn empty(&self) -> bool {
+        self.sounds.lock().map_or(true, |sounds| sounds.is_empty())
+    }
+
+    /// Adds the voice's sound into `out`, moving on to the next queued
+    /// sound whenever one ends.
+    fn mix_into(&self, out: &mut [f32]) {
+        if self.paused.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut sounds = match self.sounds.lock() {
+            Ok(sounds) => sounds,
+            Err(_) => return,
+        };
+        let mut i = 0;
+        while i < out.len() {
+            let sound = match sounds.front_mut() {
+                Some(sound) => sound,
+                None => return,
+            };
+            match sound.next() {
+                Some(sample) => {
+                    out[i] += sample;
+                    i += 1;
+                }
+                None => {
+                    let _ = sounds.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// Every voice of the `rodio` backend.
+#[derive(Default)]
+struct Voices(Mutex<Vec<Arc<VoiceQueue>>>);
+
+impl Voices {
+    fn add(&self, voice: Arc<VoiceQueue>) {
+        if let Ok(mut voices) = self.0.lock() {
+            voices.push(voice);
+        }
+    }
+
+    /// Mixes the voices into `out`, forgetting the ones that are done.
+    fn mix_into(&self, out: &mut [f32]) {
+        if let Ok(mut voices) = self.0.lock() {
+            voices.retain(|voice| {
+                let wanted = voice.wanted();
+                if wanted {
+                    voice.mix_into(out);
+                }
+                wanted
+            });
+        }
+    }
+}
+
+/// The source an output stream plays: every voice, mixed together.
+struct VoiceMixer {
+    voices: Arc<Voices>,
+    active: Arc<AtomicBool>,
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl VoiceMixer {
+    /// The format voices are mixed in; rodio converts it to whatever
+    /// the device wants, so it doesn't change when the device does.
+    const CHANNELS: u16 = 2;
+    const SAMPLE_RATE: u32 = 44100;
+    /// How many frames are mixed at a time.
+    const FRAMES: usize = 256;
+
+    fn new(voices: Arc<Voices>, active: Arc<AtomicBool>) -> Self {
+        let len = Self::FRAMES * usize::from(Self::CHANNELS);
+        VoiceMixer {
+            voices,
+            active,
+            buffer: vec![0.0; len],
+            position: len,
+        }
+    }
+}
+
+impl Iterator for VoiceMixer {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.buffer.len() {
+            self.buffer.fill(0.0);
+            if self.active.load(Ordering::SeqCst) {
+                self.voices.mix_into(&mut self.buffer);
+            }
+            self.position = 0;
+        }
+        let sample = self.buffer[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl rodio::Source for VoiceMixer {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        Self::CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        Self::SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        None
+    }
+}
+
+/// A voice of the `rodio` backend.
+struct RodioVoice(Arc<VoiceQueue>);
+
+impl Voice for RodioVoice {
+    fn append(&self, sound: Sound) {
+        let sound = rodio::source::UniformSourceIterator::new(
+            sound.0,
+            VoiceMixer::CHANNELS,
+            VoiceMixer::SAMPLE_RATE,
+        );
+        if let Ok(mut sounds) = self.0.sounds.lock() {
+            sounds.push_back(sound);
+        }
+    }
+
+    fn play(&self) {
+        self.0.paused.store(false, Ordering::Relaxed);
+    }
+
+    fn pause(&self) {
+        self.0.paused.store(true, Ordering::Relaxed);
+    }
+
+    fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    fn empty(&self) -> bool {
+        self.0.empty()
+    }
+
+    fn detach(self: Box<Self>) {
+        self.0.detached.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for RodioVoice {
+    fn drop(&mut self) {
+        self.0.owned.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A structure that implements `AudioContext` but does nothing; serves as a
+/// stub for when you don't need audio.  Sounds "play" on it in no time
+/// at all, without a sound.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct NullAudioContext;
+
+impl AudioContext for NullAudioContext {
+    fn new_voice(&self) -> GameResult<Box<dyn Voice>> {
+        Ok(Box::new(NullVoice::default()))
+    }
+}
+
+/// A voice that throws away its sounds.
+#[derive(Debug, Default)]
+struct NullVoice {
+    paused: AtomicBool,
+}
+
+impl Voice for NullVoice {
+    fn append(&self, _sound: Sound) {}
+
+    fn play(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    fn empty(&self) -> bool {
+        true
+    }
+
+    fn detach(self: Box<Self>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(voices: &Voices, samples: Vec<i16>) -> RodioVoice {
+        let voice = Arc::new(VoiceQueue::default());
+        voices.add(voice.clone());
+        let voice = RodioVoice(voice);
+        let sound = rodio::buffer::SamplesBuffer::new(2, VoiceMixer::SAMPLE_RATE, samples);
+        voice.append(Sound::new(sound));
+        voice
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn headless_test_voice_mixing() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        let voices = Voices::default();
+        let first = voice(&voices, vec![8192; 8]);
+        let second = voice(&voices, vec![8192; 2]);
+        let mut out = [0.0; 6];
+        voices.mix_into(&mut out);
+        assert!(close(out[0], 0.5) && close(out[1], 0.5));
+        assert!(out[2..].iter().all(|sample| close(*sample, 0.25)));
+        assert!(second.empty());
+        assert!(!first.empty());
+
+        // Paused voices are skipped, and dropped ones are let go of.
+        first.pause();
+        drop(second);
+        let mut out = [0.0; 2];
+        voices.mix_into(&mut out);
+        assert_eq!(out, [0.0, 0.0]);
+        assert_eq!(voices.0.lock().unwrap().len(), 1);
+
+        // Detached voices play on until they run out.
+        first.play();
+        Box::new(first).detach();
+        let mut out = [0.0; 4];
+        voices.mix_into(&mut out);
+        assert!(close(out[0], 0.25) && close(out[1], 0.25));
+        assert_eq!(&out[2..], &[0.0, 0.0]);
+        voices.mix_into(&mut out);
+        assert!(voices.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn headless_test_voices_change_streams() {
+        let voices = Arc::new(Voices::default());
+        let _voice = voice(&voices, (1..=8).map(|i| i * 1000).collect());
+        // A stream being replaced plays silence and leaves the voices
+        // alone, so the new one picks them up where they were.
+        let mut old = VoiceMixer::new(voices.clone(), Arc::new(AtomicBool::new(false)));
+        let samples = VoiceMixer::FRAMES * usize::from(VoiceMixer::CHANNELS);
+        assert!(old.by_ref().take(samples).all(|sample| sample == 0.0));
+        let mut new = VoiceMixer::new(voices, Arc::new(AtomicBool::new(true)));
+        let first = new.next().unwrap();
+        assert!((first - 1000.0 / 32767.0).abs() < 1e-4, "{}", first);
+    }
+}
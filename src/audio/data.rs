@@ -0,0 +1,304 @@
+//! Sound files loaded into memory, and the formats they can be in.
+
+use std::io;
+use std::io::Read;
+use std::path;
+
+use std::sync::Arc;
+
+use crate::context::Context;
+use crate::error::GameError;
+use crate::error::GameResult;
+use crate::filesystem;
+
+/// The audio file formats ggez knows about.  Which of them can be
+/// played depends on the cargo features ggez is built with: WAV and
+/// Ogg Vorbis always can, FLAC needs the `flac` feature, MP3 the `mp3`
+/// one (both on by default) and Opus the `opus` one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AudioFormat {
+    /// A WAV file.
+    Wav,
+    /// Vorbis sound in an Ogg file.
+    Vorbis,
+    /// A FLAC file.
+    Flac,
+    /// An MP3 file.
+    Mp3,
+    /// Opus sound in an Ogg file.
+    Opus,
+}
+
+impl AudioFormat {
+    /// Works out the format of a sound file from its first few bytes.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+            Some(AudioFormat::Wav)
+        } else if bytes.starts_with(b"OggS") {
+            if bytes.get(28..36) == Some(b"OpusHead") {
+                Some(AudioFormat::Opus)
+            } else {
+                Some(AudioFormat::Vorbis)
+            }
+        } else if bytes.starts_with(b"fLaC") {
+            Some(AudioFormat::Flac)
+        } else if bytes.starts_with(b"ID3")
+            || (bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0)
+        {
+            Some(AudioFormat::Mp3)
+        } else {
+            None
+        }
+    }
+
+    /// The cargo feature needed to play the format, if any.
+    pub fn feature(self) -> Option<&'static str> {
+        match self {
+            AudioFormat::Wav | AudioFormat::Vorbis => None,
+            AudioFormat::Flac => Some("flac"),
+            AudioFormat::Mp3 => Some("mp3"),
+            AudioFormat::Opus => Some("opus"),
+        }
+    }
+
+    /// Whether ggez was built able to play the format.
+    pub fn supported(self) -> bool {
+        match self {
+            AudioFormat::Wav | AudioFormat::Vorbis => true,
+            AudioFormat::Flac => cfg!(feature = "flac"),
+            AudioFormat::Mp3 => cfg!(feature = "mp3"),
+            AudioFormat::Opus => cfg!(feature = "opus"),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "WAV",
+            AudioFormat::Vorbis => "Ogg Vorbis",
+            AudioFormat::Flac => "FLAC",
+            AudioFormat::Mp3 => "MP3",
+            AudioFormat::Opus => "Opus",
+        }
+    }
+
+    /// The error for sound data that couldn't be decoded, saying why as
+    /// best it can.
+    pub(crate) fn decode_error(format: Option<Self>) -> GameError {
+        let message = match format {
+            Some(format) if !format.supported() => format!(
+                "Can't play {} audio, ggez was built without its `{}` feature",
+                format.name(),
+                format.feature().unwrap_or_default()
+            ),
+            Some(format) => format!("Could not decode the given {} audio data", format.name()),
+            None => {
+                "Could not decode the given audio data, it isn't WAV, Ogg Vorbis, FLAC, MP3 or Opus"
+                    .to_string()
+            }
+        };
+        GameError::AudioError(message)
+    }
+}
+
+/// Decodes a whole Ogg Opus file, since rodio can't.
+#[cfg(feature = "opus")]
+fn decode_opus(bytes: &[u8]) -> GameResult<SoundData> {
+    let error = || AudioFormat::decode_error(Some(AudioFormat::Opus));
+    let mut packets = ogg::PacketReader::new(io::Cursor::new(bytes));
+    let mut next_packet = || packets.read_packet().map_err(|_| error());
+
+    // The "OpusHead" header has the channel count and how many samples
+    // at the start are decoder warm-up, and is followed by comments.
+    let head = next_packet()?.ok_or_else(error)?.data;
+    if head.len() < 12 || !head.starts_with(b"OpusHead") {
+        return Err(error());
+    }
+    let channels = match head[9] {
+        1 => libopus::Channels::Mono,
+        2 => libopus::Channels::Stereo,
+        _ => return Err(error()),
+    };
+    let channel_count = usize::from(head[9]);
+    let pre_skip = usize::from(u16::from_le_bytes([head[10], head[11]])) * channel_count;
+    let _tags = next_packet()?;
+
+    let mut decoder = libopus::Decoder::new(48000, channels).map_err(|_| error())?;
+    // The longest an Opus packet can be is 120 ms.
+    let mut frame = vec![0i16; 5760 * channel_count];
+    let mut samples = Vec::new();
+    while let Some(packet) = next_packet()? {
+        let len = decoder
+            .decode(&packet.data, &mut frame, false)
+            .map_err(|_| error())?;
+        samples.extend_from_slice(&frame[..len * channel_count]);
+    }
+    let start = pre_skip.min(samples.len());
+    Ok(SoundData::from_pcm(
+        &samples[start..],
+        48000,
+        channel_count as u16,
+    ))
+}
+
+/// Static sound data stored in memory.
+/// It is `Arc`'ed, so cheap to clone.
+#[derive(Clone, Debug)]
+pub struct SoundData(Arc<[u8]>);
+
+impl SoundData {
+    /// Load the file at the given path and create a new `SoundData` from it.
+    pub fn new<P: AsRef<path::Path>>(context: &mut Context, path: P) -> GameResult<Self> {
+        let path = path.as_ref();
+        let file = &mut filesystem::open(context, path)?;
+        SoundData::from_read(file)
+    }
+
+    /// Copies the data in the given slice into a new `SoundData` object.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        SoundData(Arc::from(data))
+    }
+
+    /// Creates a `SoundData` from any `Read` object; this involves
+    /// copying it into a buffer.
+    pub fn from_read<R>(reader: &mut R) -> GameResult<Self>
+    where
+        R: Read,
+    {
+        let mut buffer = Vec::new();
+        let _ = reader.read_to_end(&mut buffer)?;
+
+        Ok(SoundData::from(buffer))
+    }
+
+    /// Creates a `SoundData` from raw 16-bit samples, with the samples
+    /// of each channel interleaved, by wrapping them up as a WAV file.
+    pub fn from_pcm(samples: &[i16], sample_rate: u32, channels: u16) -> Self {
+        let data_len = (samples.len() * 2) as u32;
+        let block_align = channels * 2;
+        let mut wav = Vec::with_capacity(44 + samples.len() * 2);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        SoundData::from(wav)
+    }
+
+    /// Works out the format of the sound, see
+    /// [`AudioFormat::detect()`](enum.AudioFormat.html#method.detect).
+    pub fn format(&self) -> Option<AudioFormat> {
+        AudioFormat::detect(self.as_ref())
+    }
+
+    /// Checks that the data can be played, decoding it up front if it's
+    /// in a format rodio can't decode as it plays.
+    pub(crate) fn playable(self) -> GameResult<Self> {
+        if self.can_play() {
+            return Ok(self);
+        }
+        match self.format() {
+            #[cfg(feature = "opus")]
+            Some(AudioFormat::Opus) => decode_opus(self.as_ref()),
+            format => Err(AudioFormat::decode_error(format)),
+        }
+    }
+
+    /// Indicates if the data can be played as a sound.  Opus sounds,
+    /// which need decoding up front, can't be played as is but can be
+    /// given to a [`Source`](struct.Source.html) all the same.
+    pub fn can_play(&self) -> bool {
+        let cursor = io::Cursor::new(self.clone());
+        rodio::Decoder::new(cursor).is_ok()
+    }
+}
+
+impl From<Arc<[u8]>> for SoundData {
+    #[inline]
+    fn from(arc: Arc<[u8]>) -> Self {
+        SoundData(arc)
+    }
+}
+
+impl From<Vec<u8>> for SoundData {
+    fn from(v: Vec<u8>) -> Self {
+        SoundData(Arc::from(v))
+    }
+}
+
+impl From<Box<[u8]>> for SoundData {
+    fn from(b: Box<[u8]>) -> Self {
+        SoundData(Arc::from(b))
+    }
+}
+
+impl AsRef<[u8]> for SoundData {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+/// Loads the sound data at the given path, or hands out the data
+/// already loaded from there.  See the [`cache`](../cache/index.html)
+/// module.
+pub fn load_cached_sound_data<P: AsRef<path::Path>>(
+    context: &mut Context,
+    path: P,
+) -> GameResult<SoundData> {
+    let path = path.as_ref();
+    if let Some(data) = context.cache.sound_data(path) {
+        return Ok(data);
+    }
+    let data = SoundData::new(context, path)?;
+    context.cache.insert_sound_data(path, data.clone());
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_audio_format() {
+        let pcm = SoundData::from_pcm(&[0, 1], 8000, 1);
+        assert_eq!(pcm.format(), Some(AudioFormat::Wav));
+        assert_eq!(AudioFormat::detect(b"fLaC\0\0"), Some(AudioFormat::Flac));
+        assert_eq!(AudioFormat::detect(b"ID3\x04"), Some(AudioFormat::Mp3));
+        assert_eq!(
+            AudioFormat::detect(&[0xFF, 0xFB, 0x90]),
+            Some(AudioFormat::Mp3)
+        );
+        let mut ogg = b"OggS".to_vec();
+        ogg.resize(28, 0);
+        ogg.extend_from_slice(b"OpusHead");
+        assert_eq!(AudioFormat::detect(&ogg), Some(AudioFormat::Opus));
+        assert_eq!(AudioFormat::detect(&ogg[..28]), Some(AudioFormat::Vorbis));
+        assert_eq!(AudioFormat::detect(b"not a sound"), None);
+        assert!(AudioFormat::Wav.supported());
+
+        match SoundData::from_bytes(b"not a sound").playable() {
+            Err(GameError::AudioError(message)) => assert!(message.contains("isn't WAV")),
+            _ => panic!("garbage should not be playable"),
+        }
+    }
+
+    #[test]
+    fn headless_test_pcm() {
+        let samples = [0, 100, -100, i16::MAX, i16::MIN, 7];
+        let data = SoundData::from_pcm(&samples, 8000, 2);
+        let decoder = rodio::Decoder::new(io::Cursor::new(data)).unwrap();
+        assert_eq!(rodio::Source::channels(&decoder), 2);
+        assert_eq!(rodio::Source::sample_rate(&decoder), 8000);
+        assert_eq!(decoder.collect::<Vec<_>>(), samples.to_vec());
+    }
+}
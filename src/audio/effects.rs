@@ -0,0 +1,606 @@
+//! Effects sounds are run through on their way to the speakers.
+
+use std::collections::VecDeque;
+use std::mem;
+use std::time;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// An effect that sounds can be run through, set on a source with
+/// [`SoundSource::set_effects()`](trait.SoundSource.html#method.set_effects)
+/// or on every sound of a mixer bus with
+/// [`Mixer::set_effects()`](struct.Mixer.html#method.set_effects).
+/// Muffle sounds underwater with a `LowPass`, say, or make them echo
+/// in a cave with a `Reverb`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Effect {
+    /// Lets through frequencies below the cutoff, in Hz, and turns
+    /// down the ones above it.
+    LowPass {
+        /// The frequency from which sounds are turned down.
+        cutoff: f32,
+    },
+    /// Lets through frequencies above the cutoff, in Hz, and turns
+    /// down the ones below it.
+    HighPass {
+        /// The frequency below which sounds are turned down.
+        cutoff: f32,
+    },
+    /// A simple reverb, which feeds the sound back into itself after
+    /// the delay so it echoes on and dies away.
+    Reverb {
+        /// How long until the sound comes back.
+        delay: time::Duration,
+        /// How loud the echo is compared to what came before, from
+        /// `0.0` to just under `1.0`.
+        decay: f32,
+    },
+}
+
+pub(crate) type SharedEffects = Arc<Mutex<Vec<Effect>>>;
+
+/// What an effect remembers of the sound so far.
+#[derive(Debug, Clone)]
+enum EffectState {
+    /// The last input and output sample of each channel.
+    Filter { input: Vec<f32>, output: Vec<f32> },
+    /// The last `delay` worth of output.
+    Delay { buffer: Vec<f32>, position: usize },
+}
+
+impl EffectState {
+    /// Makes the state for an effect on a sound with the given number
+    /// of channels and sample rate, with everything it needs allocated
+    /// up front.
+    fn new(effect: &Effect, channels: usize, sample_rate: u32) -> Self {
+        match effect {
+            Effect::LowPass { .. } | Effect::HighPass { .. } => EffectState::Filter {
+                input: vec![0.0; channels],
+                output: vec![0.0; channels],
+            },
+            Effect::Reverb { delay, .. } => EffectState::Delay {
+                buffer: vec![0.0; Self::delay_frames(*delay, sample_rate) * channels],
+                position: 0,
+            },
+        }
+    }
+
+    fn delay_frames(delay: time::Duration, sample_rate: u32) -> usize {
+        (delay.as_secs_f32() * sample_rate as f32).max(1.0) as usize
+    }
+
+    /// Runs a sample of the given channel through the effect.
+    fn process(
+        &mut self,
+        effect: &Effect,
+        sample: f32,
+        channel: usize,
+        channels: usize,
+        sample_rate: u32,
+    ) -> f32 {
+        let dt = 1.0 / sample_rate as f32;
+        match (effect, self) {
+            (Effect::LowPass { cutoff }, EffectState::Filter { output, .. }) => {
+                output.resize(channels, 0.0);
+                let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff.max(1.0));
+                let last = output[channel];
+                output[channel] = last + dt / (rc + dt) * (sample - last);
+                output[channel]
+            }
+            (Effect::HighPass { cutoff }, EffectState::Filter { input, output }) => {
+                input.resize(channels, 0.0);
+                output.resize(channels, 0.0);
+                let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff.max(1.0));
+                output[channel] = rc / (rc + dt) * (output[channel] + sample - input[channel]);
+                input[channel] = sample;
+                output[channel]
+            }
+            (Effect::Reverb { delay, decay }, EffectState::Delay { buffer, position }) => {
+                let frames = Self::delay_frames(*delay, sample_rate);
+                if buffer.len() != frames * channels {
+                    *buffer = vec![0.0; frames * channels];
+                    *position = 0;
+                }
+                let out = sample + buffer[*position] * decay.max(0.0).min(0.99);
+                buffer[*position] = out;
+                *position = (*position + 1) % buffer.len();
+                out
+            }
+            _ => sample,
+        }
+    }
+}
+
+/// Runs a sound through a chain of effects, which may be changed
+/// while it plays.
+pub(crate) struct Effects<I> {
+    input: I,
+    shared: SharedEffects,
+    chain: Vec<(Effect, EffectState)>,
+    channel: usize,
+    /// Samples left until the chain is checked for changes.
+    until_refresh: usize,
+}
+
+impl<I> Effects<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    /// How many samples go by between checking for changes.
+    const REFRESH_SAMPLES: usize = 256;
+
+    pub(crate) fn new(input: I, shared: SharedEffects) -> Self {
+        let mut effects = Effects {
+            input,
+            shared,
+            chain: Vec::new(),
+            channel: 0,
+            until_refresh: 0,
+        };
+        effects.refresh();
+        effects
+    }
+
+    /// Picks up changes to the effects, keeping the state of ones
+    /// that are still the same kind so changing, say, a cutoff doesn't
+    /// make the sound click.  This runs on the audio thread, so the
+    /// chain is updated in place and only allocates when an effect is
+    /// added or changes kind.
+    fn refresh(&mut self) {
+        self.until_refresh = Self::REFRESH_SAMPLES;
+        let effects = match self.shared.try_lock() {
+            Ok(effects) => effects,
+            Err(_) => return,
+        };
+        let channels = usize::from(self.input.channels().max(1));
+        let sample_rate = self.input.sample_rate();
+        self.chain.truncate(effects.len());
+        for (i, effect) in effects.iter().enumerate() {
+            match self.chain.get_mut(i) {
+                Some((previous, state)) => {
+                    if mem::discriminant(previous) != mem::discriminant(effect) {
+                        *state = EffectState::new(effect, channels, sample_rate);
+                    }
+                    *previous = *effect;
+                }
+                None => self
+                    .chain
+                    .push((*effect, EffectState::new(effect, channels, sample_rate))),
+            }
+        }
+    }
+}
+
+impl<I> Iterator for Effects<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.until_refresh == 0 {
+            self.refresh();
+        }
+        self.until_refresh -= 1;
+        let channels = usize::from(self.input.channels().max(1));
+        let sample_rate = self.input.sample_rate();
+        let sample = self.input.next()?;
+        if self.chain.is_empty() {
+            return Some(sample);
+        }
+
+        let channel = self.channel % channels;
+        self.channel = (channel + 1) % channels;
+        let mut value = f32::from(sample);
+        for (effect, state) in &mut self.chain {
+            value = state.process(effect, value, channel, channels, sample_rate);
+        }
+        Some(value.max(f32::from(i16::MIN)).min(f32::from(i16::MAX)) as i16)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> rodio::Source for Effects<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Pans a sound between the left and right speakers, turning mono
+/// sounds into stereo ones.
+pub(crate) struct Panned<I> {
+    input: I,
+    pan: Arc<AtomicU32>,
+    gains: (f32, f32),
+    /// The right channel of a mono sample, still to be played.
+    pending: Option<i16>,
+    channel: u16,
+}
+
+impl<I> Panned<I> {
+    pub(crate) fn new(input: I, pan: Arc<AtomicU32>) -> Self {
+        Panned {
+            input,
+            pan,
+            gains: (1.0, 1.0),
+            pending: None,
+            channel: 0,
+        }
+    }
+
+    fn update_gains(&mut self) {
+        let pan = f32::from_bits(self.pan.load(Ordering::Relaxed));
+        self.gains = ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0));
+    }
+}
+
+fn scaled(sample: i16, gain: f32) -> i16 {
+    (f32::from(sample) * gain) as i16
+}
+
+impl<I> Iterator for Panned<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        match self.input.channels() {
+            1 => {
+                if let Some(right) = self.pending.take() {
+                    return Some(right);
+                }
+                let sample = self.input.next()?;
+                self.update_gains();
+                self.pending = Some(scaled(sample, self.gains.1));
+                Some(scaled(sample, self.gains.0))
+            }
+            2 => {
+                let sample = self.input.next()?;
+                let gain = if self.channel == 0 {
+                    self.update_gains();
+                    self.gains.0
+                } else {
+                    self.gains.1
+                };
+                self.channel = 1 - self.channel;
+                Some(scaled(sample, gain))
+            }
+            _ => self.input.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (low, high) = self.input.size_hint();
+        if self.input.channels() == 1 {
+            (low * 2, high.map(|high| high * 2))
+        } else {
+            (low, high)
+        }
+    }
+}
+
+impl<I> rodio::Source for Panned<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let len = self.input.current_frame_len();
+        if self.input.channels() == 1 {
+            len.map(|len| len * 2 + self.pending.iter().count())
+        } else {
+            len
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self.input.channels() {
+            1 => 2,
+            channels => channels,
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Changes the speed of a sound without changing its pitch, by cutting
+/// it into overlapping grains and laying them out closer together or
+/// further apart.
+pub(crate) struct TimeStretch<I> {
+    input: I,
+    rate: f32,
+    channels: usize,
+    /// Hann window over a grain, whose halves add up to one when
+    /// overlapped.
+    window: Vec<f32>,
+    /// The input from frame `buffer_start` on.
+    buffer: VecDeque<f32>,
+    buffer_start: u64,
+    /// Which frame of the input the next grain starts at.
+    analysis: f64,
+    /// The second half of the last grain, to add to the next.
+    overlap: Vec<f32>,
+    output: VecDeque<i16>,
+    input_done: bool,
+    done: bool,
+}
+
+impl<I> TimeStretch<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    pub(crate) fn new(input: I, rate: f32) -> Self {
+        use std::f32::consts::PI;
+        let channels = usize::from(input.channels().max(1));
+        // Grains of about 40 ms.
+        let grain = ((input.sample_rate() / 25) as usize).max(64) & !1;
+        TimeStretch {
+            input,
+            rate,
+            channels,
+            window: (0..grain)
+                .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / grain as f32).cos())
+                .collect(),
+            buffer: VecDeque::new(),
+            buffer_start: 0,
+            analysis: 0.0,
+            overlap: vec![0.0; grain / 2 * channels],
+            output: VecDeque::new(),
+            input_done: false,
+            done: false,
+        }
+    }
+
+    /// Whether the sound goes through untouched.
+    fn bypassed(&self) -> bool {
+        (self.rate - 1.0).abs() < std::f32::EPSILON || self.rate <= 0.0
+    }
+
+    /// Adds the next grain to the output, returning `false` when
+    /// there's no more sound.
+    fn step(&mut self) -> bool {
+        let grain = self.window.len();
+        let hop = grain / 2;
+        let channels = self.channels;
+
+        // Skip ahead to where the grain starts...
+        let start = self.analysis as u64;
+        while self.buffer_start < start {
+            if self.buffer.is_empty() {
+                if self.input_done || self.input.next().is_none() {
+                    self.input_done = true;
+                    break;
+                }
+                for _ in 1..channels {
+                    let _ = self.input.next();
+                }
+            } else {
+                for _ in 0..channels {
+                    let _ = self.buffer.pop_front();
+                }
+            }
+            self.buffer_start += 1;
+        }
+        // ...and make sure all of it is at hand.
+        while self.buffer.len() < grain * channels && !self.input_done {
+            match self.input.next() {
+                Some(sample) => self.buffer.push_back(f32::from(sample)),
+                None => self.input_done = true,
+            }
+        }
+
+        if self.buffer.is_empty() {
+            if self.done {
+                return false;
+            }
+            // Let the last grain die away.
+            self.done = true;
+            self.output
+                .extend(self.overlap.iter().map(|sample| *sample as i16));
+            return true;
+        }
+
+        for i in 0..grain {
+            for c in 0..channels {
+                let index = i * channels + c;
+                let sample = self.buffer.get(index).cloned().unwrap_or(0.0) * self.window[i];
+                if i < hop {
+                    let mixed = self.overlap[index] + sample;
+                    self.output
+                        .push_back(mixed.max(f32::from(i16::MIN)).min(f32::from(i16::MAX)) as i16);
+                } else {
+                    self.overlap[index - hop * channels] = sample;
+                }
+            }
+        }
+        self.analysis += hop as f64 * f64::from(self.rate);
+        true
+    }
+}
+
+impl<I> Iterator for TimeStretch<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.bypassed() {
+            return self.input.next();
+        }
+        loop {
+            if let Some(sample) = self.output.pop_front() {
+                return Some(sample);
+            }
+            if !self.step() {
+                return None;
+            }
+        }
+    }
+}
+
+impl<I> rodio::Source for TimeStretch<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.bypassed() {
+            self.input.current_frame_len()
+        } else {
+            None
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        let duration = self.input.total_duration()?;
+        if self.bypassed() {
+            Some(duration)
+        } else {
+            Some(duration.div_f32(self.rate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_effects(input: Vec<i16>, effects: &[Effect]) -> Vec<i16> {
+        let len = input.len();
+        let input = rodio::buffer::SamplesBuffer::new(1, 10, input);
+        let shared = Arc::new(Mutex::new(effects.to_vec()));
+        Effects::new(input, shared).take(len).collect()
+    }
+
+    #[test]
+    fn headless_test_effects() {
+        let square = (0..8)
+            .map(|i| if i % 2 == 0 { 1000 } else { -1000 })
+            .collect::<Vec<i16>>();
+        assert_eq!(with_effects(square.clone(), &[]), square);
+
+        // At ten samples a second this alternates at the highest
+        // frequency there is, so a low cutoff takes off most of it.
+        let low = with_effects(square.clone(), &[Effect::LowPass { cutoff: 1.0 }]);
+        assert!(low.iter().all(|s| s.abs() < 500));
+        let high = with_effects(vec![1000; 8], &[Effect::HighPass { cutoff: 1.0 }]);
+        assert!(high[7].abs() < high[0].abs());
+
+        let reverb = Effect::Reverb {
+            delay: time::Duration::from_millis(200),
+            decay: 0.5,
+        };
+        assert_eq!(
+            with_effects(vec![1000, 0, 0, 0, 0], &[reverb]),
+            vec![1000, 0, 500, 0, 250]
+        );
+    }
+
+    #[test]
+    fn headless_test_effects_refresh_in_place() {
+        let input = rodio::buffer::SamplesBuffer::new(1, 10, vec![1000i16; 600]);
+        let low = Effect::LowPass { cutoff: 1.0 };
+        let shared = Arc::new(Mutex::new(vec![low]));
+        let mut effects = Effects::new(input, shared.clone());
+        let _ = effects.by_ref().take(10).count();
+        let filtered = match &effects.chain[0].1 {
+            EffectState::Filter { output, .. } => output[0],
+            _ => unreachable!(),
+        };
+        assert!(filtered > 0.0);
+
+        // Changing the cutoff keeps the filter going where it was.
+        *shared.lock().unwrap() = vec![Effect::LowPass { cutoff: 2.0 }];
+        effects.refresh();
+        assert_eq!(effects.chain[0].0, Effect::LowPass { cutoff: 2.0 });
+        match &effects.chain[0].1 {
+            EffectState::Filter { output, .. } => assert!(output[0] >= filtered),
+            _ => unreachable!(),
+        }
+
+        // A different kind of effect starts afresh, and the chain
+        // grows and shrinks with the effects.
+        let reverb = Effect::Reverb {
+            delay: time::Duration::from_millis(200),
+            decay: 0.5,
+        };
+        *shared.lock().unwrap() = vec![reverb, low];
+        effects.refresh();
+        assert_eq!(effects.chain.len(), 2);
+        match &effects.chain[0].1 {
+            EffectState::Delay { buffer, .. } => assert_eq!(buffer.len(), 2),
+            _ => unreachable!(),
+        }
+        *shared.lock().unwrap() = vec![];
+        effects.refresh();
+        assert!(effects.chain.is_empty());
+    }
+
+    #[test]
+    fn headless_test_pan() {
+        let pan = Arc::new(AtomicU32::new((-0.5f32).to_bits()));
+        let mono = rodio::buffer::SamplesBuffer::new(1, 10, vec![1000i16, -1000]);
+        let panned = Panned::new(mono, pan.clone());
+        assert_eq!(rodio::Source::channels(&panned), 2);
+        assert_eq!(panned.collect::<Vec<_>>(), vec![1000, 500, -1000, -500]);
+
+        pan.store(1.0f32.to_bits(), Ordering::Relaxed);
+        let stereo = rodio::buffer::SamplesBuffer::new(2, 10, vec![1000i16, 1000, 200, 200]);
+        assert_eq!(
+            Panned::new(stereo, pan).collect::<Vec<_>>(),
+            vec![0, 1000, 0, 200]
+        );
+    }
+
+    #[test]
+    fn headless_test_time_stretch() {
+        let stretched = |rate: f32| {
+            let sine = (0..8000)
+                .map(|i| ((i as f32 * 0.1).sin() * 8000.0) as i16)
+                .collect::<Vec<_>>();
+            let input = rodio::buffer::SamplesBuffer::new(1, 8000, sine);
+            TimeStretch::new(input, rate).collect::<Vec<_>>()
+        };
+        assert_eq!(stretched(1.0).len(), 8000);
+        // Give or take a grain, which is 320 frames at 8 kHz.
+        let slow = stretched(0.5).len();
+        assert!((15680..=16640).contains(&slow), "{}", slow);
+        let fast = stretched(2.0).len();
+        assert!((3680..=4640).contains(&fast), "{}", fast);
+        // The pitch stays put, so the peaks are as loud as before.
+        assert!(stretched(0.5).iter().any(|s| *s > 7000));
+    }
+}
@@ -0,0 +1,212 @@
+//! Mixer buses and the master volume.
+
+use std::collections::HashMap;
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::audio::*;
+use crate::context::Context;
+
+/// The volume and effects of a mixer bus, shared with the sounds
+/// playing on it.
+#[derive(Debug)]
+pub(crate) struct BusLevel {
+    volume: AtomicU32,
+    muted: AtomicBool,
+    pub(crate) effects: SharedEffects,
+    pub(crate) analyzer: Mutex<Option<Analyzer>>,
+}
+
+impl BusLevel {
+    fn new() -> Self {
+        BusLevel {
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            muted: AtomicBool::new(false),
+            effects: SharedEffects::default(),
+            analyzer: Mutex::new(None),
+        }
+    }
+
+    fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    fn muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    /// What the sounds on the bus are multiplied by.
+    pub(crate) fn gain(&self) -> f32 {
+        if self.muted() {
+            0.0
+        } else {
+            self.volume()
+        }
+    }
+}
+
+/// The master volume and pause switch, shared with every sound.
+#[derive(Debug)]
+pub(crate) struct MasterLevel {
+    volume: AtomicU32,
+    pub(crate) paused: AtomicBool,
+}
+
+impl Default for MasterLevel {
+    fn default() -> Self {
+        MasterLevel {
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            paused: AtomicBool::new(false),
+        }
+    }
+}
+
+impl MasterLevel {
+    pub(crate) fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+}
+
+/// Groups sounds into named buses, like `"music"`, `"sfx"` and
+/// `"voice"`, whose volume can be turned up and down or muted all at
+/// once.  Changes apply right away, to sounds that are already playing
+/// too, so this is what the volume sliders of a settings screen should
+/// talk to.
+///
+/// There's one mixer, kept in the `Context`; get it with
+/// [`mixer()`](fn.mixer.html) or [`mixer_mut()`](fn.mixer_mut.html).
+/// Sounds are sent to a bus with
+/// [`SoundSource::set_bus()`](trait.SoundSource.html#method.set_bus),
+/// and buses are created the first time they're used.  A sound's own
+/// volume is multiplied by the volume of its bus.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # use ggez::audio::SoundSource;
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let mut music = audio::StreamingSource::new(ctx, "/music.ogg")?;
+/// music.set_bus(ctx, Some("music"));
+/// music.play(ctx)?;
+/// // ...and later, in the settings screen:
+/// audio::mixer_mut(ctx).set_volume("music", 0.5);
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Default)]
+pub struct Mixer {
+    buses: HashMap<String, Arc<BusLevel>>,
+    pub(crate) master: Arc<MasterLevel>,
+}
+
+impl Mixer {
+    /// Gets the bus with the given name, creating it if need be.
+    pub(crate) fn bus(&mut self, name: &str) -> Arc<BusLevel> {
+        self.buses
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(BusLevel::new()))
+            .clone()
+    }
+
+    /// Sets the volume of a bus, where `1.0` is unchanged.
+    pub fn set_volume(&mut self, bus: &str, volume: f32) {
+        self.bus(bus)
+            .volume
+            .store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Gets the volume of a bus.  Buses that haven't been used yet
+    /// have a volume of `1.0`.
+    pub fn volume(&self, bus: &str) -> f32 {
+        self.buses.get(bus).map_or(1.0, |level| level.volume())
+    }
+
+    /// Mutes or unmutes a bus, without changing its volume.
+    pub fn set_muted(&mut self, bus: &str, muted: bool) {
+        self.bus(bus).muted.store(muted, Ordering::Relaxed);
+    }
+
+    /// Gets whether a bus is muted.
+    pub fn muted(&self, bus: &str) -> bool {
+        self.buses.get(bus).map_or(false, |level| level.muted())
+    }
+
+    /// Sets the effects every sound on a bus is run through, in order,
+    /// replacing any set before.  Takes effect right away.
+    pub fn set_effects(&mut self, bus: &str, effects: &[Effect]) {
+        if let Ok(mut current) = self.bus(bus).effects.lock() {
+            *current = effects.to_vec();
+        }
+    }
+
+    /// Gets the effects of a bus.
+    pub fn effects(&self, bus: &str) -> Vec<Effect> {
+        self.buses
+            .get(bus)
+            .and_then(|level| level.effects.lock().ok().map(|effects| effects.clone()))
+            .unwrap_or_default()
+    }
+
+    /// Attaches an [`Analyzer`](struct.Analyzer.html) to a bus, which
+    /// hears all the sounds on it played from then on, or detaches it
+    /// if `None`.
+    pub fn set_analyzer(&mut self, bus: &str, analyzer: Option<Analyzer>) {
+        if let Ok(mut current) = self.bus(bus).analyzer.lock() {
+            *current = analyzer;
+        }
+    }
+
+    /// The names of the buses used so far.
+    pub fn buses(&self) -> impl Iterator<Item = &str> {
+        self.buses.keys().map(String::as_str)
+    }
+}
+
+/// Gets the audio [`Mixer`](struct.Mixer.html).
+pub fn mixer(ctx: &Context) -> &Mixer {
+    &ctx.audio_mixer
+}
+
+/// Gets the audio [`Mixer`](struct.Mixer.html), for changing it.
+pub fn mixer_mut(ctx: &mut Context) -> &mut Mixer {
+    &mut ctx.audio_mixer
+}
+
+/// Sets the master volume, which every sound is multiplied by on top
+/// of its own volume and that of its bus.  `1.0` is unchanged.
+pub fn set_master_volume(ctx: &mut Context, volume: f32) {
+    ctx.audio_mixer
+        .master
+        .volume
+        .store(volume.to_bits(), Ordering::Relaxed);
+}
+
+/// Gets the master volume.
+pub fn master_volume(ctx: &Context) -> f32 {
+    ctx.audio_mixer.master.volume()
+}
+
+/// Pauses every sound, including ones played later, until
+/// [`resume_all()`](fn.resume_all.html) is called; for pausing the
+/// game or when its window loses focus.  Sounds paused on their own
+/// with [`SoundSource::pause()`](trait.SoundSource.html#tymethod.pause)
+/// stay paused after `resume_all()`.
+pub fn pause_all(ctx: &mut Context) {
+    ctx.audio_mixer.master.paused.store(true, Ordering::Relaxed);
+}
+
+/// Resumes the sounds paused with [`pause_all()`](fn.pause_all.html).
+pub fn resume_all(ctx: &mut Context) {
+    ctx.audio_mixer
+        .master
+        .paused
+        .store(false, Ordering::Relaxed);
+}
+
+/// Gets whether sound is paused with [`pause_all()`](fn.pause_all.html).
+pub fn all_paused(ctx: &Context) -> bool {
+    ctx.audio_mixer.master.paused()
+}
@@ -0,0 +1,252 @@
+//! Provides an interface to output sound to the user's speakers.
+//!
+//! It consists of two main types: [`SoundData`](struct.SoundData.html)
+//! is just an array of raw sound data bytes, and a [`Source`](struct.Source.html) is a
+//! `SoundData` connected to a particular sound channel ready to be played.
+//! Long sounds like music can instead be played with a
+//! [`StreamingSource`](struct.StreamingSource.html), which decodes the
+//! file bit by bit as it plays, and a
+//! [`SpatialSource`](struct.SpatialSource.html) pans and fades
+//! depending on where it is relative to the [`Listener`](struct.Listener.html).
+
+use std::time;
+
+use crate::context::Context;
+use crate::error::GameError;
+use crate::error::GameResult;
+
+pub(crate) mod analyzer;
+pub(crate) mod backend;
+pub(crate) mod data;
+pub(crate) mod effects;
+pub(crate) mod mixer;
+pub(crate) mod pool;
+pub(crate) mod recording;
+pub(crate) mod source;
+pub(crate) mod spatial;
+pub(crate) mod state;
+pub(crate) mod streaming;
+
+pub use crate::audio::analyzer::*;
+pub use crate::audio::backend::*;
+pub use crate::audio::data::*;
+pub use crate::audio::effects::*;
+pub use crate::audio::mixer::*;
+pub use crate::audio::pool::*;
+pub use crate::audio::recording::*;
+pub use crate::audio::source::*;
+pub use crate::audio::spatial::*;
+pub use crate::audio::streaming::*;
+
+pub(crate) use crate::audio::state::*;
+
+/// A trait defining the operations possible on a sound;
+/// it is implemented by both `Source` and `SpatialSource`.
+///
+/// Only the basic operations have to be implemented.  The rest come
+/// with defaults that do the nearest thing a source without them can,
+/// like [`seek()`](#method.seek) returning an error, so a source made
+/// outside ggez only needs to implement what it supports.
+pub trait SoundSource {
+    /// Plays the audio source; restarts the sound if currently playing
+    fn play(&mut self, ctx: &Context) -> GameResult {
+        self.stop(ctx)?;
+        self.play_later()
+    }
+
+    /// Plays the `SoundSource`; waits until done if the sound is currently playing
+    fn play_later(&self) -> GameResult;
+
+    /// Play source "in the background"; cannot be stopped
+    fn play_detached(&mut self, ctx: &Context) -> GameResult;
+
+    /// Sets the source to repeat playback infinitely on next [`play()`](#method.play)
+    fn set_repeat(&mut self, repeat: bool);
+
+    /// Sets the fade-in time of the source
+    fn set_fade_in(&mut self, dur: time::Duration);
+
+    /// Sets the speed ratio (by adjusting the playback speed)
+    fn set_pitch(&mut self, ratio: f32);
+
+    /// Sets the speed the sound plays at, where `1.0` is normal.  If
+    /// `preserve_pitch` is `false` this is the same as
+    /// [`set_pitch()`](#tymethod.set_pitch), so a slowed down sound is
+    /// lower too; otherwise the sound is time-stretched to keep its
+    /// pitch, for slow motion without demon voices.  Time-stretching
+    /// costs more and may sound a little rough at extreme rates.
+    /// Takes effect on the next [`play()`](#method.play).
+    fn set_speed(&mut self, rate: f32, preserve_pitch: bool) {
+        let _ = preserve_pitch;
+        self.set_pitch(rate)
+    }
+
+    /// Gets whether or not the source is set to repeat.
+    fn repeat(&self) -> bool;
+
+    /// Pauses playback
+    fn pause(&self);
+
+    /// Resumes playback
+    fn resume(&self);
+
+    /// Stops playback
+    fn stop(&mut self, ctx: &Context) -> GameResult;
+
+    /// Returns whether or not the source is stopped
+    /// -- that is, has no more data to play.
+    fn stopped(&self) -> bool;
+
+    /// Returns whether the sound has played to the end, which the audio
+    /// thread notes as soon as it happens.  Unlike
+    /// [`stopped()`](#tymethod.stopped) this isn't true of a sound that
+    /// was never played, or was stopped before the end.  If several
+    /// sounds were queued with [`play_later()`](#tymethod.play_later)
+    /// it's true once they've all finished, and a repeating sound never
+    /// finishes.
+    fn finished(&self) -> bool {
+        false
+    }
+
+    /// Gets the current volume.
+    fn volume(&self) -> f32;
+
+    /// Sets the current volume, stopping any fade.
+    fn set_volume(&mut self, value: f32);
+
+    /// Changes the volume gradually, reaching `volume` after
+    /// `duration`.  The fade is timed by the audio itself, so it's
+    /// smooth however slowly the game runs, and it only moves on while
+    /// the sound is playing.  [`volume()`](#tymethod.volume) gives the
+    /// volume the fade has reached so far.
+    fn fade_to(&mut self, volume: f32, duration: time::Duration) {
+        let _ = duration;
+        self.set_volume(volume)
+    }
+
+    /// Get whether or not the source is paused.
+    fn paused(&self) -> bool;
+
+    /// Get whether or not the source is playing (ie, not paused
+    /// and not stopped).
+    fn playing(&self) -> bool;
+
+    /// Get the time the source has been playing since the last call to [`play()`](#method.play).
+    ///
+    /// Time measurement is based on audio samples consumed, so it may drift from the system
+    fn elapsed(&self) -> time::Duration;
+
+    /// Set the update interval of the internal sample counter.
+    ///
+    /// This parameter determines the precision of the time measured by [`elapsed()`](#method.elapsed).
+    fn set_query_interval(&mut self, t: time::Duration);
+
+    /// Jumps to the given position in the sound, measured from its
+    /// start, and plays on from there.  If the source was paused it
+    /// stays paused, otherwise it starts playing.
+    ///
+    /// Seeking decodes and skips everything before the position, so
+    /// jumping far into a long streamed file may take a moment.
+    fn seek(&mut self, ctx: &Context, position: time::Duration) -> GameResult {
+        let _ = (ctx, position);
+        Err(GameError::AudioError(
+            "This sound source can't seek".to_string(),
+        ))
+    }
+
+    /// Get the current position in the sound, measured from its start.
+    ///
+    /// Unlike [`elapsed()`](#method.elapsed) this takes seeking and the
+    /// pitch into account, and starts over when a repeating sound
+    /// loops, if the length of the sound is known.  It's as precise as
+    /// the interval set with [`set_query_interval()`](#method.set_query_interval).
+    fn position(&self) -> time::Duration {
+        self.elapsed()
+    }
+
+    /// Sets the part of the sound that's repeated when the source is set
+    /// to repeat: the sound plays from the start up to `end`, and then
+    /// from `start` to `end` over and over, without any gap.  `end` is
+    /// the end of the sound if it's `None`.  This is how music with an
+    /// intro is usually looped.  Takes effect on the next
+    /// [`play()`](#method.play).
+    ///
+    /// By default the whole sound is repeated.
+    fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>) {
+        let _ = (start, end);
+    }
+
+    /// Gets the part of the sound that's repeated, see
+    /// [`set_loop_points()`](#method.set_loop_points).
+    fn loop_points(&self) -> (time::Duration, Option<time::Duration>) {
+        (time::Duration::from_secs(0), None)
+    }
+
+    /// Sends the sound to the given bus of the [`Mixer`](struct.Mixer.html),
+    /// or to none if `None`.  Takes effect on the next
+    /// [`play()`](#method.play).
+    fn set_bus(&mut self, ctx: &mut Context, bus: Option<&str>) {
+        let _ = (ctx, bus);
+    }
+
+    /// Gets the name of the mixer bus the sound is sent to.
+    fn bus(&self) -> Option<&str> {
+        None
+    }
+
+    /// Sets the [`Effect`](enum.Effect.html)s the sound is run through,
+    /// in order, before the ones of its mixer bus.  Replaces any set
+    /// before, and takes effect right away, even while it plays.
+    fn set_effects(&mut self, effects: &[Effect]) {
+        let _ = effects;
+    }
+
+    /// Gets the effects the sound is run through.
+    fn effects(&self) -> Vec<Effect> {
+        Vec::new()
+    }
+
+    /// Attaches an [`Analyzer`](struct.Analyzer.html) that hears the
+    /// sound, or detaches it if `None`.  Takes effect on the next
+    /// [`play()`](#method.play).
+    fn set_analyzer(&mut self, analyzer: Option<Analyzer>) {
+        let _ = analyzer;
+    }
+
+    /// Pans the sound between the speakers, from `-1.0` for only the
+    /// left one through `0.0` for both to `1.0` for only the right one.
+    /// Takes effect right away, even while the sound plays.
+    fn set_pan(&mut self, pan: f32) {
+        let _ = pan;
+    }
+
+    /// Gets how the sound is panned.
+    fn pan(&self) -> f32 {
+        0.0
+    }
+}
+
+/// Fades `from` out and `to` in over the same `duration`, like when
+/// the music changes between levels.  `to` is started if it isn't
+/// playing yet, from silence, and fades up to a volume of `1.0`; set
+/// how loud music is overall on a [`Mixer`](struct.Mixer.html) bus.
+///
+/// `from` goes on playing silently, so stop it once its
+/// [`volume()`](trait.SoundSource.html#tymethod.volume) reaches `0.0`
+/// if you won't come back to it.
+pub fn crossfade(
+    ctx: &Context,
+    from: &mut dyn SoundSource,
+    to: &mut dyn SoundSource,
+    duration: time::Duration,
+) -> GameResult {
+    from.fade_to(0.0, duration);
+    if to.stopped() {
+        to.set_volume(0.0);
+        to.play(ctx)?;
+    } else if to.paused() {
+        to.resume();
+    }
+    to.fade_to(1.0, duration);
+    Ok(())
+}
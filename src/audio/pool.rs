@@ -0,0 +1,209 @@
+//! Many overlapping instances of one sound.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::path;
+use std::time;
+
+use std::sync::Arc;
+
+use crate::audio::*;
+use crate::context::Context;
+use crate::error::GameResult;
+
+/// Decoded sound shared between the instances of a `SoundPool`.
+#[derive(Debug, Clone)]
+struct SharedSamples {
+    samples: Arc<[i16]>,
+    position: usize,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Iterator for SharedSamples {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.samples.get(self.position).cloned();
+        self.position += 1;
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.samples.len().saturating_sub(self.position);
+        (left, Some(left))
+    }
+}
+
+impl rodio::Source for SharedSamples {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        let frames = self.samples.len() as u64 / u64::from(self.channels.max(1));
+        Some(time::Duration::from_secs_f64(
+            frames as f64 / f64::from(self.sample_rate.max(1)),
+        ))
+    }
+}
+
+/// Plays many overlapping instances of one sound, fire-and-forget,
+/// like gunshots or footsteps.
+///
+/// The sound is decoded once, up front, and at most `max_voices`
+/// instances play at the same time: when a new one is started with all
+/// of them busy the oldest is cut off to make room.  Sinks whose sound
+/// has finished are reused, so playing the sound over and over doesn't
+/// make ever more of them or turn into a wall of noise.
+///
+/// The volume, pitch, mixer bus and effects of the pool apply to the
+/// instances played after they're set, like the settings of a
+/// [`Source`](struct.Source.html).
+pub struct SoundPool {
+    samples: SharedSamples,
+    sinks: VecDeque<Box<dyn Voice>>,
+    max_voices: usize,
+    state: SourceState<()>,
+}
+
+impl SoundPool {
+    /// Creates a new `SoundPool` from the given file, playing at most
+    /// `max_voices` instances at once.
+    pub fn new<P: AsRef<path::Path>>(
+        context: &mut Context,
+        path: P,
+        max_voices: usize,
+    ) -> GameResult<Self> {
+        let data = SoundData::new(context, path)?;
+        SoundPool::from_data(data, max_voices)
+    }
+
+    /// Creates a new `SoundPool` using the given `SoundData` object.
+    /// A `max_voices` of 0 is taken as 1.
+    pub fn from_data(data: SoundData, max_voices: usize) -> GameResult<Self> {
+        use rodio::Source;
+        let max_voices = max_voices.max(1);
+        let decoder = rodio::Decoder::new(io::Cursor::new(data.playable()?))?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        Ok(SoundPool {
+            samples: SharedSamples {
+                samples: decoder.collect::<Vec<_>>().into(),
+                position: 0,
+                channels,
+                sample_rate,
+            },
+            sinks: VecDeque::with_capacity(max_voices),
+            max_voices,
+            state: SourceState::new(()),
+        })
+    }
+
+    /// Plays a new instance of the sound, cutting off the oldest one
+    /// if `max_voices` are already playing.
+    pub fn play(&mut self, ctx: &Context) -> GameResult {
+        use rodio::Source;
+        let sink = match self.sinks.iter().position(|sink| sink.empty()) {
+            Some(idle) => self.sinks.remove(idle),
+            None => None,
+        };
+        let sink = match sink {
+            Some(sink) => sink,
+            None => ctx.audio_context.new_voice()?,
+        };
+        // There may be more than one to cut off if `max_voices` was
+        // lowered since.  Dropping a sink stops its sound.
+        while self.sinks.len() >= self.max_voices {
+            let _ = self.sinks.pop_front();
+        }
+        // The pool is made without a context, so this is the first
+        // chance to pick up the master volume.
+        self.state.master = ctx.audio_mixer.master.clone();
+        let sound = self.samples.clone().speed(self.state.pitch());
+        sink.append(Sound::new(self.state.mixed(sound)));
+        self.sinks.push_back(sink);
+        Ok(())
+    }
+
+    /// Stops all the instances playing.
+    pub fn stop_all(&mut self) {
+        self.sinks.clear();
+        self.state.clear_queue();
+    }
+
+    /// The number of instances playing.
+    pub fn playing(&self) -> usize {
+        self.sinks.iter().filter(|sink| !sink.empty()).count()
+    }
+
+    /// The most instances that play at once.
+    pub fn max_voices(&self) -> usize {
+        self.max_voices
+    }
+
+    /// Sets the most instances that play at once, at least 1.
+    /// Instances already playing go on until they end, or until the
+    /// next [`play()`](#method.play) cuts the oldest ones off.
+    pub fn set_max_voices(&mut self, max_voices: usize) {
+        self.max_voices = max_voices.max(1);
+    }
+
+    /// Sets the volume.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.state.set_volume(volume)
+    }
+
+    /// Gets the volume.
+    pub fn volume(&self) -> f32 {
+        self.state.volume()
+    }
+
+    /// Sets the speed ratio (by adjusting the playback speed).
+    pub fn set_pitch(&mut self, ratio: f32) {
+        self.state.set_pitch(ratio)
+    }
+
+    /// Sets the speed the sound plays at, see
+    /// [`SoundSource::set_speed()`](trait.SoundSource.html#method.set_speed).
+    pub fn set_speed(&mut self, rate: f32, preserve_pitch: bool) {
+        self.state.set_speed(rate, preserve_pitch)
+    }
+
+    /// Sends the sound to the given bus of the [`Mixer`](struct.Mixer.html),
+    /// or to none if `None`.
+    pub fn set_bus(&mut self, ctx: &mut Context, bus: Option<&str>) {
+        self.state.set_bus(ctx, bus)
+    }
+
+    /// Gets the name of the mixer bus the sound is sent to.
+    pub fn bus(&self) -> Option<&str> {
+        self.state.bus()
+    }
+
+    /// Sets the [`Effect`](enum.Effect.html)s the sound is run through.
+    pub fn set_effects(&mut self, effects: &[Effect]) {
+        self.state.set_effects(effects)
+    }
+
+    /// Pans the sound between the speakers, from `-1.0` for only the
+    /// left one to `1.0` for only the right one.
+    pub fn set_pan(&mut self, pan: f32) {
+        self.state.set_pan(pan)
+    }
+}
+
+impl fmt::Debug for SoundPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<SoundPool: {:p}>", self)
+    }
+}
@@ -0,0 +1,185 @@
+//! Recording sound from a microphone.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::error::GameError;
+use crate::error::GameResult;
+
+/// A device sound can be recorded from, like a microphone, for voice
+/// chat, recording or games that react to the player's noises.
+///
+/// Find them with [`list()`](#method.list) or
+/// [`default_device()`](#method.default_device), and start recording
+/// with [`capture()`](#method.capture), which hands each chunk of sound
+/// to a callback as it comes in, or [`record()`](#method.record), which
+/// keeps the latest sound around to be picked up whenever it suits.
+pub struct InputDevice {
+    device: rodio::cpal::Device,
+    name: String,
+}
+
+impl InputDevice {
+    fn from_device(device: rodio::cpal::Device) -> Self {
+        use rodio::cpal::traits::DeviceTrait;
+        let name = device
+            .name()
+            .unwrap_or_else(|_| "Unknown input device".to_string());
+        InputDevice { device, name }
+    }
+
+    /// Lists the devices sound can be recorded from.
+    pub fn list() -> GameResult<Vec<InputDevice>> {
+        use rodio::cpal::traits::HostTrait;
+        let devices = rodio::cpal::default_host().input_devices().map_err(|e| {
+            GameError::AudioError(format!("Could not list audio input devices: {}", e))
+        })?;
+        Ok(devices.map(InputDevice::from_device).collect())
+    }
+
+    /// Gets the system's default device for recording, if there is one.
+    pub fn default_device() -> Option<InputDevice> {
+        use rodio::cpal::traits::HostTrait;
+        rodio::cpal::default_host()
+            .default_input_device()
+            .map(InputDevice::from_device)
+    }
+
+    /// The name of the device.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Starts recording, calling `callback` on the audio thread with
+    /// each chunk of 16-bit samples as it comes in, with the samples of
+    /// each channel interleaved.  Recording goes on until the returned
+    /// [`Recording`](struct.Recording.html) is dropped.
+    pub fn capture<F>(&self, callback: F) -> GameResult<Recording>
+    where
+        F: FnMut(&[i16]) + Send + 'static,
+    {
+        use rodio::cpal::traits::{DeviceTrait, StreamTrait};
+        use rodio::cpal::SampleFormat;
+
+        let error = |e: &dyn std::error::Error| {
+            GameError::AudioError(format!("Could not record from {}: {}", self.name, e))
+        };
+        let supported = self.device.default_input_config().map_err(|e| error(&e))?;
+        let config = supported.config();
+        let stream = match supported.sample_format() {
+            SampleFormat::I16 => capture_stream::<i16, F>(&self.device, &config, callback),
+            SampleFormat::U16 => capture_stream::<u16, F>(&self.device, &config, callback),
+            SampleFormat::F32 => capture_stream::<f32, F>(&self.device, &config, callback),
+        }
+        .map_err(|e| error(&e))?;
+        stream.play().map_err(|e| error(&e))?;
+        Ok(Recording {
+            _stream: stream,
+            buffer: None,
+            sample_rate: config.sample_rate.0,
+            channels: config.channels,
+        })
+    }
+
+    /// Starts recording into a buffer that holds on to the last
+    /// `length` of sound, to be picked up with
+    /// [`Recording::take_samples()`](struct.Recording.html#method.take_samples).
+    /// Recording goes on until the `Recording` is dropped.
+    pub fn record(&self, length: time::Duration) -> GameResult<Recording> {
+        let buffer = Arc::new(Mutex::new(VecDeque::new()));
+        let shared = buffer.clone();
+        let capacity = Arc::new(AtomicUsize::new(0));
+        let shared_capacity = capacity.clone();
+        let mut recording = self.capture(move |samples| {
+            if let Ok(mut buffer) = shared.lock() {
+                buffer.extend(samples.iter().cloned());
+                let capacity = shared_capacity.load(Ordering::Relaxed);
+                let excess = buffer.len().saturating_sub(capacity);
+                let _ = buffer.drain(..excess);
+            }
+        })?;
+        let samples_per_second = recording.sample_rate as f32 * f32::from(recording.channels);
+        capacity.store(
+            (length.as_secs_f32() * samples_per_second) as usize,
+            Ordering::Relaxed,
+        );
+        recording.buffer = Some(buffer);
+        Ok(recording)
+    }
+}
+
+impl fmt::Debug for InputDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<InputDevice: {}>", self.name)
+    }
+}
+
+fn capture_stream<T, F>(
+    device: &rodio::cpal::Device,
+    config: &rodio::cpal::StreamConfig,
+    mut callback: F,
+) -> Result<rodio::cpal::Stream, rodio::cpal::BuildStreamError>
+where
+    T: rodio::cpal::Sample,
+    F: FnMut(&[i16]) + Send + 'static,
+{
+    use rodio::cpal::traits::DeviceTrait;
+    let mut converted = Vec::new();
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &rodio::cpal::InputCallbackInfo| {
+            converted.clear();
+            converted.extend(data.iter().map(rodio::cpal::Sample::to_i16));
+            callback(&converted);
+        },
+        |e| warn!("Error while recording audio: {}", e),
+    )
+}
+
+/// Sound being recorded from an [`InputDevice`](struct.InputDevice.html).
+/// Recording stops when this is dropped.
+pub struct Recording {
+    _stream: rodio::cpal::Stream,
+    buffer: Option<Arc<Mutex<VecDeque<i16>>>>,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl Recording {
+    /// The number of samples recorded each second, per channel.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The number of channels recorded.
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Takes the samples recorded since the last call, up to the length
+    /// the recording was started with, with the samples of each channel
+    /// interleaved.  Always empty for recordings started with
+    /// [`InputDevice::capture()`](struct.InputDevice.html#method.capture),
+    /// which hand the sound straight to their callback.
+    pub fn take_samples(&mut self) -> Vec<i16> {
+        self.buffer
+            .as_ref()
+            .and_then(|buffer| {
+                buffer
+                    .lock()
+                    .ok()
+                    .map(|mut buffer| buffer.drain(..).collect())
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<Recording: {:p}>", self)
+    }
+}
@@ -0,0 +1,226 @@
+//! Sounds decoded from memory.
+
+use std::fmt;
+use std::io;
+use std::mem;
+use std::path;
+use std::time;
+
+use crate::audio::*;
+use crate::context::Context;
+use crate::error::GameResult;
+
+/// A source of audio data that is connected to an output
+/// channel and ready to play.  It will stop playing when
+/// dropped.
+// TODO LATER: Check and see if this matches Love2d's semantics!
+// Eventually it might read from a streaming decoder of some kind,
+// but for now it is just an in-memory SoundData structure.
+// The source of a rodio decoder must be Send, which something
+// that contains a reference to a ZipFile is not, so we are going
+// to just slurp all the data into memory for now.
+// There's really a lot of work that needs to be done here, since
+// rodio has gotten better (if still somewhat arcane) and our filesystem
+// code has done the data-slurping-from-zip's for us
+// but for now it works.
+pub struct Source {
+    sink: Box<dyn Voice>,
+    state: SourceState,
+}
+
+impl Source {
+    /// Create a new `Source` from the given file.
+    pub fn new<P: AsRef<path::Path>>(context: &mut Context, path: P) -> GameResult<Self> {
+        let path = path.as_ref();
+        let data = SoundData::new(context, path)?;
+        Source::from_data(context, data)
+    }
+
+    /// Creates a new `Source` using the given `SoundData` object.
+    pub fn from_data(context: &mut Context, data: SoundData) -> GameResult<Self> {
+        let data = data.playable()?;
+        let sink = context.audio_context.new_voice()?;
+        let cursor = io::Cursor::new(data);
+        let duration = decoded_duration(cursor.clone());
+        Ok(Source {
+            sink,
+            state: SourceState::new(cursor)
+                .with_duration(duration)
+                .with_master(&context.audio_mixer),
+        })
+    }
+
+    /// Creates a new `Source` from raw 16-bit samples, with the samples
+    /// of each channel interleaved, for playing sounds made by the game
+    /// itself.  See [`SoundData::from_pcm()`](struct.SoundData.html#method.from_pcm).
+    pub fn from_pcm(
+        context: &mut Context,
+        samples: &[i16],
+        sample_rate: u32,
+        channels: u16,
+    ) -> GameResult<Self> {
+        Source::from_data(context, SoundData::from_pcm(samples, sample_rate, channels))
+    }
+}
+
+impl SoundSource for Source {
+    fn play_later(&self) -> GameResult {
+        // Creating a new Decoder each time seems a little messy,
+        // since it may do checking and data-type detection that is
+        // redundant, but it's not super expensive.
+        // See https://github.com/ggez/ggez/issues/98 for discussion
+        use rodio::Source;
+        let cursor = self.state.data.clone();
+
+        if self.state.repeat() {
+            let sound = LoopRegion::new(rodio::Decoder::new(cursor)?, &self.state);
+            let sound = self.state.timed(sound);
+            self.sink.append(Sound::new(self.state.mixed(sound)));
+        } else {
+            let sound = rodio::Decoder::new(cursor)?.skip_duration(self.state.offset);
+            let sound = self.state.timed(sound);
+            self.sink.append(Sound::new(self.state.mixed(sound)));
+        }
+
+        Ok(())
+    }
+
+    fn play_detached(&mut self, ctx: &Context) -> GameResult {
+        self.stop(ctx)?;
+        self.play_later()?;
+
+        let new_sink = ctx.audio_context.new_voice()?;
+        let old_sink = mem::replace(&mut self.sink, new_sink);
+        old_sink.detach();
+
+        Ok(())
+    }
+
+    fn set_repeat(&mut self, repeat: bool) {
+        self.state.set_repeat(repeat)
+    }
+    fn set_fade_in(&mut self, dur: time::Duration) {
+        self.state.set_fade_in(dur)
+    }
+    fn set_pitch(&mut self, ratio: f32) {
+        self.state.set_pitch(ratio)
+    }
+
+    fn set_speed(&mut self, rate: f32, preserve_pitch: bool) {
+        self.state.set_speed(rate, preserve_pitch)
+    }
+    fn repeat(&self) -> bool {
+        self.state.repeat()
+    }
+    fn pause(&self) {
+        self.sink.pause()
+    }
+    fn resume(&self) {
+        self.sink.play()
+    }
+
+    fn stop(&mut self, ctx: &Context) -> GameResult {
+        // Sinks cannot be reused after calling `.stop()`. See
+        // https://github.com/tomaka/rodio/issues/171 for information.
+        // To stop the current sound we have to drop the old sink and
+        // create a new one in its place, which is why this needs the
+        // context: the new sink comes from its audio backend.
+
+        self.sink = ctx.audio_context.new_voice()?;
+        self.state.rewind();
+        Ok(())
+    }
+
+    fn stopped(&self) -> bool {
+        self.sink.empty()
+    }
+
+    fn finished(&self) -> bool {
+        self.state.finished()
+    }
+
+    fn volume(&self) -> f32 {
+        self.state.volume()
+    }
+
+    fn set_volume(&mut self, value: f32) {
+        self.state.set_volume(value)
+    }
+
+    fn fade_to(&mut self, volume: f32, duration: time::Duration) {
+        self.state.fade_to(volume, duration)
+    }
+
+    fn paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    fn playing(&self) -> bool {
+        !self.paused() && !self.stopped()
+    }
+
+    fn elapsed(&self) -> time::Duration {
+        self.state.elapsed()
+    }
+
+    fn set_query_interval(&mut self, t: time::Duration) {
+        self.state.set_query_interval(t)
+    }
+
+    fn seek(&mut self, ctx: &Context, position: time::Duration) -> GameResult {
+        let paused = self.paused();
+        self.stop(ctx)?;
+        self.state.offset = position;
+        self.play_later()?;
+        if paused {
+            self.pause();
+        }
+        Ok(())
+    }
+
+    fn position(&self) -> time::Duration {
+        self.state.position()
+    }
+
+    fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>) {
+        self.state.set_loop_points(start, end)
+    }
+
+    fn loop_points(&self) -> (time::Duration, Option<time::Duration>) {
+        self.state.loop_points()
+    }
+
+    fn set_bus(&mut self, ctx: &mut Context, bus: Option<&str>) {
+        self.state.set_bus(ctx, bus)
+    }
+
+    fn bus(&self) -> Option<&str> {
+        self.state.bus()
+    }
+
+    fn set_effects(&mut self, effects: &[Effect]) {
+        self.state.set_effects(effects)
+    }
+
+    fn effects(&self) -> Vec<Effect> {
+        self.state.effects()
+    }
+
+    fn set_analyzer(&mut self, analyzer: Option<Analyzer>) {
+        self.state.set_analyzer(analyzer)
+    }
+
+    fn set_pan(&mut self, pan: f32) {
+        self.state.set_pan(pan)
+    }
+
+    fn pan(&self) -> f32 {
+        self.state.pan()
+    }
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<Audio source: {:p}>", self)
+    }
+}
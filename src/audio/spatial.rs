@@ -0,0 +1,517 @@
+//! Sounds placed in the world, heard from where the listener is.
+
+use std::fmt;
+use std::io;
+use std::mem;
+use std::path;
+use std::time;
+
+use std::sync::{Arc, Mutex};
+
+use crate::audio::*;
+use crate::context::Context;
+use crate::error::GameResult;
+
+/// Where sounds are heard from, for [`SpatialSource`](struct.SpatialSource.html)s;
+/// set it with [`set_listener()`](fn.set_listener.html).
+///
+/// Sounds to the listener's right, as worked out from the direction it
+/// faces and which way is up, are panned to the right speaker.  The
+/// default faces down the negative z axis with positive y up, so with
+/// everything at `z = 0` sounds with a greater x are on the right,
+/// whichever way the y axis of your game points.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Listener {
+    /// Where the listener is.
+    pub position: mint::Point3<f32>,
+    /// The direction the listener faces.
+    pub forward: mint::Vector3<f32>,
+    /// The direction of the top of the listener's head.
+    pub up: mint::Vector3<f32>,
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Listener {
+            position: [0.0, 0.0, 0.0].into(),
+            forward: [0.0, 0.0, -1.0].into(),
+            up: [0.0, 1.0, 0.0].into(),
+        }
+    }
+}
+
+impl Listener {
+    /// A listener at the given position, facing the default way.
+    pub fn at<P>(position: P) -> Self
+    where
+        P: Into<mint::Point3<f32>>,
+    {
+        Listener {
+            position: position.into(),
+            ..Listener::default()
+        }
+    }
+}
+
+/// Sets where all [`SpatialSource`](struct.SpatialSource.html)s are
+/// heard from, including ones already playing.
+pub fn set_listener(ctx: &mut Context, listener: Listener) {
+    if let Ok(mut current) = ctx.audio_listener.lock() {
+        *current = listener;
+    }
+}
+
+/// Gets where spatial sounds are heard from.
+pub fn listener(ctx: &Context) -> Listener {
+    ctx.audio_listener
+        .lock()
+        .map(|listener| *listener)
+        .unwrap_or_default()
+}
+
+/// How a [`SpatialSource`](struct.SpatialSource.html) gets quieter
+/// the further it is from the listener.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Attenuation {
+    /// The volume doesn't change with distance.
+    None,
+    /// Full volume up to `min_distance`, fading evenly to silence at
+    /// `max_distance`.
+    Linear {
+        /// The distance up to which the sound is at full volume.
+        min_distance: f32,
+        /// The distance from which the sound is silent.
+        max_distance: f32,
+    },
+    /// Full volume up to `reference_distance`, and then falling off
+    /// like `reference / (reference + rolloff * (distance - reference))`,
+    /// which is roughly how sound behaves in the real world.  It never
+    /// quite reaches silence.
+    Inverse {
+        /// The distance up to which the sound is at full volume.
+        reference_distance: f32,
+        /// How quickly the sound gets quieter; `1.0` halves the volume
+        /// at twice the reference distance.
+        rolloff: f32,
+    },
+    /// Full volume up to `reference_distance`, and then falling off
+    /// like `(distance / reference) ^ -rolloff`.
+    Exponential {
+        /// The distance up to which the sound is at full volume.
+        reference_distance: f32,
+        /// How quickly the sound gets quieter.
+        rolloff: f32,
+    },
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation::Inverse {
+            reference_distance: 1.0,
+            rolloff: 1.0,
+        }
+    }
+}
+
+impl Attenuation {
+    /// What the volume is multiplied by at the given distance.
+    pub fn gain(&self, distance: f32) -> f32 {
+        match *self {
+            Attenuation::None => 1.0,
+            Attenuation::Linear {
+                min_distance,
+                max_distance,
+            } => {
+                if distance <= min_distance {
+                    1.0
+                } else if distance >= max_distance {
+                    0.0
+                } else {
+                    1.0 - (distance - min_distance) / (max_distance - min_distance)
+                }
+            }
+            Attenuation::Inverse {
+                reference_distance,
+                rolloff,
+            } => {
+                if distance <= reference_distance {
+                    1.0
+                } else {
+                    reference_distance
+                        / (reference_distance + rolloff * (distance - reference_distance))
+                }
+            }
+            Attenuation::Exponential {
+                reference_distance,
+                rolloff,
+            } => {
+                if distance <= reference_distance {
+                    1.0
+                } else {
+                    (distance / reference_distance).powf(-rolloff)
+                }
+            }
+        }
+    }
+}
+
+/// Where a spatial sound is, shared with it while it plays.
+#[derive(Debug, Copy, Clone)]
+struct Placement {
+    emitter: mint::Point3<f32>,
+    /// Ears that override the listener, if they were set.
+    ears: Option<(mint::Point3<f32>, mint::Point3<f32>)>,
+    attenuation: Attenuation,
+}
+
+impl Placement {
+    /// The volumes of the left and right channel.
+    fn gains(&self, listener: &Listener) -> (f32, f32) {
+        fn vec3<V: Into<mint::Vector3<f32>>>(v: V) -> glam::Vec3 {
+            let v = v.into();
+            glam::vec3(v.x, v.y, v.z)
+        }
+        fn point<P: Into<mint::Point3<f32>>>(p: P) -> glam::Vec3 {
+            let p = p.into();
+            glam::vec3(p.x, p.y, p.z)
+        }
+        let (center, right) = match self.ears {
+            Some((left, right)) => {
+                let (left, right) = (point(left), point(right));
+                ((left + right) / 2.0, right - left)
+            }
+            None => (
+                point(listener.position),
+                vec3(listener.forward).cross(vec3(listener.up)),
+            ),
+        };
+        let offset = point(self.emitter) - center;
+        let distance = offset.length();
+        let gain = self.attenuation.gain(distance);
+        let scale = distance * right.length();
+        let pan = if scale > 0.0 {
+            (offset.dot(right) / scale).max(-1.0).min(1.0)
+        } else {
+            0.0
+        };
+        (gain * (1.0 - pan).min(1.0), gain * (1.0 + pan).min(1.0))
+    }
+}
+
+/// A source of audio data located in space, which is panned between the
+/// speakers and fades with distance depending on where it is relative to
+/// the [`Listener`](struct.Listener.html).  Moving the source or the
+/// listener affects it while it plays.  Will stop playing when dropped.
+pub struct SpatialSource {
+    sink: Box<dyn Voice>,
+    state: SourceState,
+    placement: Arc<Mutex<Placement>>,
+    listener: Arc<Mutex<Listener>>,
+}
+
+impl SpatialSource {
+    /// Create a new `SpatialSource` from the given file.
+    pub fn new<P: AsRef<path::Path>>(context: &mut Context, path: P) -> GameResult<Self> {
+        let path = path.as_ref();
+        let data = SoundData::new(context, path)?;
+        SpatialSource::from_data(context, data)
+    }
+
+    /// Creates a new `SpatialSource` using the given `SoundData` object.
+    pub fn from_data(context: &mut Context, data: SoundData) -> GameResult<Self> {
+        let data = data.playable()?;
+        let sink = context.audio_context.new_voice()?;
+
+        let cursor = io::Cursor::new(data);
+        let duration = decoded_duration(cursor.clone());
+
+        Ok(SpatialSource {
+            sink,
+            state: SourceState::new(cursor)
+                .with_duration(duration)
+                .with_master(&context.audio_mixer),
+            placement: Arc::new(Mutex::new(Placement {
+                emitter: [0.0, 0.0, 0.0].into(),
+                ears: None,
+                attenuation: Attenuation::default(),
+            })),
+            listener: context.audio_listener.clone(),
+        })
+    }
+
+    /// Pans and attenuates the sound as it plays.
+    fn spatialized<S>(&self, sound: S) -> impl rodio::Source<Item = i16> + Send
+    where
+        S: rodio::Source<Item = i16> + Send,
+    {
+        use rodio::Source;
+        let placement = self.placement.clone();
+        let listener = self.listener.clone();
+        rodio::source::ChannelVolume::new(sound, vec![1.0, 1.0]).periodic_access(
+            time::Duration::from_millis(5),
+            move |sound| {
+                if let (Ok(placement), Ok(listener)) = (placement.lock(), listener.lock()) {
+                    let (left, right) = placement.gains(&listener);
+                    sound.set_volume(0, left);
+                    sound.set_volume(1, right);
+                }
+            },
+        )
+    }
+
+    fn update_placement<F: FnOnce(&mut Placement)>(&mut self, f: F) {
+        if let Ok(mut placement) = self.placement.lock() {
+            f(&mut placement);
+        }
+    }
+}
+
+impl SoundSource for SpatialSource {
+    /// Plays the `SpatialSource`; waits until done if the sound is currently playing.
+    fn play_later(&self) -> GameResult {
+        // Creating a new Decoder each time seems a little messy,
+        // since it may do checking and data-type detection that is
+        // redundant, but it's not super expensive.
+        // See https://github.com/ggez/ggez/issues/98 for discussion
+        use rodio::Source;
+        let cursor = self.state.data.clone();
+
+        if self.state.repeat() {
+            let sound = LoopRegion::new(rodio::Decoder::new(cursor)?, &self.state);
+            let sound = self.state.timed(sound);
+            self.sink
+                .append(Sound::new(self.state.mixed(self.spatialized(sound))));
+        } else {
+            let sound = rodio::Decoder::new(cursor)?.skip_duration(self.state.offset);
+            let sound = self.state.timed(sound);
+            self.sink
+                .append(Sound::new(self.state.mixed(self.spatialized(sound))));
+        }
+
+        Ok(())
+    }
+
+    fn play_detached(&mut self, ctx: &Context) -> GameResult {
+        self.stop(ctx)?;
+        self.play_later()?;
+
+        let new_sink = ctx.audio_context.new_voice()?;
+        let old_sink = mem::replace(&mut self.sink, new_sink);
+        old_sink.detach();
+
+        Ok(())
+    }
+
+    fn set_repeat(&mut self, repeat: bool) {
+        self.state.set_repeat(repeat)
+    }
+
+    fn set_fade_in(&mut self, dur: time::Duration) {
+        self.state.set_fade_in(dur)
+    }
+
+    fn set_pitch(&mut self, ratio: f32) {
+        self.state.set_pitch(ratio)
+    }
+
+    fn set_speed(&mut self, rate: f32, preserve_pitch: bool) {
+        self.state.set_speed(rate, preserve_pitch)
+    }
+
+    fn repeat(&self) -> bool {
+        self.state.repeat()
+    }
+
+    fn pause(&self) {
+        self.sink.pause()
+    }
+
+    fn resume(&self) {
+        self.sink.play()
+    }
+
+    fn stop(&mut self, ctx: &Context) -> GameResult {
+        // Sinks cannot be reused after calling `.stop()`. See
+        // https://github.com/tomaka/rodio/issues/171 for information.
+        // To stop the current sound we have to drop the old sink and
+        // create a new one in its place, which is why this needs the
+        // context: the new sink comes from its audio backend.
+
+        self.sink = ctx.audio_context.new_voice()?;
+        self.state.rewind();
+        Ok(())
+    }
+
+    fn stopped(&self) -> bool {
+        self.sink.empty()
+    }
+
+    fn finished(&self) -> bool {
+        self.state.finished()
+    }
+
+    fn volume(&self) -> f32 {
+        self.state.volume()
+    }
+
+    fn set_volume(&mut self, value: f32) {
+        self.state.set_volume(value)
+    }
+
+    fn fade_to(&mut self, volume: f32, duration: time::Duration) {
+        self.state.fade_to(volume, duration)
+    }
+
+    fn paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    fn playing(&self) -> bool {
+        !self.paused() && !self.stopped()
+    }
+
+    fn elapsed(&self) -> time::Duration {
+        self.state.elapsed()
+    }
+
+    fn set_query_interval(&mut self, t: time::Duration) {
+        self.state.set_query_interval(t)
+    }
+
+    fn seek(&mut self, ctx: &Context, position: time::Duration) -> GameResult {
+        let paused = self.paused();
+        self.stop(ctx)?;
+        self.state.offset = position;
+        self.play_later()?;
+        if paused {
+            self.pause();
+        }
+        Ok(())
+    }
+
+    fn position(&self) -> time::Duration {
+        self.state.position()
+    }
+
+    fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>) {
+        self.state.set_loop_points(start, end)
+    }
+
+    fn loop_points(&self) -> (time::Duration, Option<time::Duration>) {
+        self.state.loop_points()
+    }
+
+    fn set_bus(&mut self, ctx: &mut Context, bus: Option<&str>) {
+        self.state.set_bus(ctx, bus)
+    }
+
+    fn bus(&self) -> Option<&str> {
+        self.state.bus()
+    }
+
+    fn set_effects(&mut self, effects: &[Effect]) {
+        self.state.set_effects(effects)
+    }
+
+    fn effects(&self) -> Vec<Effect> {
+        self.state.effects()
+    }
+
+    fn set_analyzer(&mut self, analyzer: Option<Analyzer>) {
+        self.state.set_analyzer(analyzer)
+    }
+
+    fn set_pan(&mut self, pan: f32) {
+        self.state.set_pan(pan)
+    }
+
+    fn pan(&self) -> f32 {
+        self.state.pan()
+    }
+}
+
+impl SpatialSource {
+    /// Set location of the sound.
+    pub fn set_position<P>(&mut self, pos: P)
+    where
+        P: Into<mint::Point3<f32>>,
+    {
+        let emitter = pos.into();
+        self.update_placement(|placement| placement.emitter = emitter);
+    }
+
+    /// Set locations of the listener's ears, for hearing this sound from
+    /// somewhere other than the [`Listener`](struct.Listener.html) set
+    /// with [`set_listener()`](fn.set_listener.html).
+    pub fn set_ears<P>(&mut self, left: P, right: P)
+    where
+        P: Into<mint::Point3<f32>>,
+    {
+        let ears = (left.into(), right.into());
+        self.update_placement(|placement| placement.ears = Some(ears));
+    }
+
+    /// Hear this sound from the [`Listener`](struct.Listener.html) again,
+    /// rather than from the ears set with [`set_ears()`](#method.set_ears).
+    pub fn clear_ears(&mut self) {
+        self.update_placement(|placement| placement.ears = None);
+    }
+
+    /// Set how the sound fades with distance.
+    pub fn set_attenuation(&mut self, attenuation: Attenuation) {
+        self.update_placement(|placement| placement.attenuation = attenuation);
+    }
+
+    /// Get how the sound fades with distance.
+    pub fn attenuation(&self) -> Attenuation {
+        self.placement
+            .lock()
+            .map(|placement| placement.attenuation)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for SpatialSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<Spatial audio source: {:p}>", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_spatial_gains() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        let listener = Listener::default();
+        let mut placement = Placement {
+            emitter: [0.0, 0.0, 0.0].into(),
+            ears: None,
+            attenuation: Attenuation::None,
+        };
+        let (left, right) = placement.gains(&listener);
+        assert!(close(left, 1.0) && close(right, 1.0));
+
+        placement.emitter = [5.0, 0.0, 0.0].into();
+        let (left, right) = placement.gains(&listener);
+        assert!(close(left, 0.0) && close(right, 1.0));
+
+        placement.ears = Some(([1.0, 0.0, 0.0].into(), [-1.0, 0.0, 0.0].into()));
+        let (left, right) = placement.gains(&listener);
+        assert!(close(left, 1.0) && close(right, 0.0));
+
+        placement.ears = None;
+        placement.attenuation = Attenuation::default();
+        let (_, right) = placement.gains(&listener);
+        assert!(close(right, 0.2));
+
+        let linear = Attenuation::Linear {
+            min_distance: 1.0,
+            max_distance: 3.0,
+        };
+        assert!(close(linear.gain(0.5), 1.0));
+        assert!(close(linear.gain(2.0), 0.5));
+        assert!(close(linear.gain(4.0), 0.0));
+    }
+}
@@ -0,0 +1,681 @@
+//! The state shared by every kind of source, and the chain of
+//! effects and volume controls each sound is played through.
+
+use std::io;
+use std::io::{Read, Seek};
+use std::time;
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::audio::*;
+use crate::context::Context;
+
+/// Lets a source know when a sound it queued has played to the end.
+struct Finishing<I> {
+    input: I,
+    queued: Arc<AtomicUsize>,
+    finished: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl<I> Iterator for Finishing<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.input.next();
+        if sample.is_none() && !self.done {
+            self.done = true;
+            if self.queued.fetch_sub(1, Ordering::SeqCst) == 1 {
+                self.finished.store(true, Ordering::SeqCst);
+            }
+        }
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<I> rodio::Source for Finishing<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// A change of volume over time, moved on by the audio thread.
+#[derive(Debug, Copy, Clone)]
+struct Fade {
+    from: f32,
+    to: f32,
+    length: time::Duration,
+    elapsed: time::Duration,
+    /// Bumped whenever the fade is replaced, so playing sounds know to
+    /// pick up the new one.
+    generation: u32,
+}
+
+impl Fade {
+    /// A volume that stays the same.
+    fn constant(volume: f32) -> Self {
+        Fade {
+            from: volume,
+            to: volume,
+            length: time::Duration::from_secs(0),
+            elapsed: time::Duration::from_secs(0),
+            generation: 0,
+        }
+    }
+
+    /// The volume the fade has reached.
+    fn current(&self) -> f32 {
+        if self.elapsed >= self.length {
+            self.to
+        } else {
+            let t = self.elapsed.as_secs_f32() / self.length.as_secs_f32();
+            self.from + (self.to - self.from) * t
+        }
+    }
+
+    fn advance(&mut self, dt: time::Duration) {
+        self.elapsed = (self.elapsed + dt).min(self.length);
+    }
+
+    /// Moves a playing sound's own copy of the fade on by `dt`, taking
+    /// up the shared one first if it was replaced.  Every sound playing
+    /// from a source has its own copy, so the fade doesn't run faster
+    /// the more of them there are; the shared one is kept at the
+    /// furthest any of them got, for `volume()`.
+    fn follow(&mut self, shared: &mut Fade, dt: time::Duration) {
+        if self.generation != shared.generation {
+            *self = *shared;
+            if self.elapsed > time::Duration::from_secs(0) {
+                // Another sound already moved the new fade on; catch up.
+                return;
+            }
+        }
+        self.advance(dt);
+        shared.elapsed = shared.elapsed.max(self.elapsed);
+    }
+}
+
+/// Internal state used by audio sources.
+#[derive(Debug)]
+pub(crate) struct SourceState<D = io::Cursor<SoundData>> {
+    pub(crate) data: D,
+    repeat: bool,
+    fade_in: time::Duration,
+    speed: f32,
+    /// Whether `speed` time-stretches the sound rather than resampling it.
+    preserve_pitch: bool,
+    query_interval: time::Duration,
+    play_time: Arc<AtomicUsize>,
+    /// Where in the sound playback started.
+    pub(crate) offset: time::Duration,
+    /// The length of the sound, if the decoder knows it.
+    duration: Option<time::Duration>,
+    loop_start: time::Duration,
+    loop_end: Option<time::Duration>,
+    bus: Option<(String, Arc<BusLevel>)>,
+    effects: SharedEffects,
+    fade: Arc<Mutex<Fade>>,
+    /// How many sounds are queued that haven't played to the end.
+    queued: Arc<AtomicUsize>,
+    finished: Arc<AtomicBool>,
+    analyzer: Option<Analyzer>,
+    /// The pan, as the bits of an `f32`.
+    pan: Arc<AtomicU32>,
+    pub(crate) master: Arc<MasterLevel>,
+}
+
+impl<D> SourceState<D> {
+    /// Create a new `SourceState` based around the given data, usually
+    /// a cursor over a `SoundData`.
+    pub fn new(data: D) -> Self {
+        SourceState {
+            data,
+            repeat: false,
+            fade_in: time::Duration::from_millis(0),
+            speed: 1.0,
+            preserve_pitch: false,
+            query_interval: time::Duration::from_millis(100),
+            play_time: Arc::new(AtomicUsize::new(0)),
+            offset: time::Duration::from_secs(0),
+            duration: None,
+            loop_start: time::Duration::from_secs(0),
+            loop_end: None,
+            bus: None,
+            effects: SharedEffects::default(),
+            fade: Arc::new(Mutex::new(Fade::constant(1.0))),
+            queued: Arc::new(AtomicUsize::new(0)),
+            finished: Arc::new(AtomicBool::new(false)),
+            analyzer: None,
+            pan: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+            master: Arc::new(MasterLevel::default()),
+        }
+    }
+
+    /// Sets the length of the sound, for wrapping the position of
+    /// repeating sounds.
+    pub(crate) fn with_duration(mut self, duration: Option<time::Duration>) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Puts the sound under the master volume and pause switch of
+    /// the given mixer.
+    pub(crate) fn with_master(mut self, mixer: &Mixer) -> Self {
+        self.master = mixer.master.clone();
+        self
+    }
+    /// Sets the source to repeat playback infinitely on next [`play()`](#method.play)
+    pub fn set_repeat(&mut self, repeat: bool) {
+        self.repeat = repeat;
+    }
+
+    /// Sets the fade-in time of the source.
+    pub fn set_fade_in(&mut self, dur: time::Duration) {
+        self.fade_in = dur;
+    }
+
+    /// Sets the pitch ratio (by adjusting the playback speed).
+    pub fn set_pitch(&mut self, ratio: f32) {
+        self.speed = ratio;
+        self.preserve_pitch = false;
+    }
+
+    /// Sets the playback speed, keeping the pitch the same or not.
+    pub fn set_speed(&mut self, rate: f32, preserve_pitch: bool) {
+        self.speed = rate;
+        self.preserve_pitch = preserve_pitch;
+    }
+
+    /// How much the sound is sped up by resampling it, which changes
+    /// its pitch.
+    pub(crate) fn pitch(&self) -> f32 {
+        if self.preserve_pitch {
+            1.0
+        } else {
+            self.speed
+        }
+    }
+
+    /// How much the sound is sped up by time-stretching it, which
+    /// doesn't.
+    fn stretch(&self) -> f32 {
+        if self.preserve_pitch {
+            self.speed
+        } else {
+            1.0
+        }
+    }
+
+    /// Gets whether or not the source is set to repeat.
+    pub fn repeat(&self) -> bool {
+        self.repeat
+    }
+
+    /// Get the time the source has been playing since the last call to [`play()`](#method.play).
+    ///
+    /// Time measurement is based on audio samples consumed, so it may drift from the system
+    /// clock over longer periods of time.
+    pub fn elapsed(&self) -> time::Duration {
+        let t = self.play_time.load(Ordering::SeqCst);
+        time::Duration::from_micros(t as u64)
+    }
+
+    /// Set the update interval of the internal sample counter.
+    ///
+    /// This parameter determines the precision of the time measured by [`elapsed()`](#method.elapsed).
+    pub fn set_query_interval(&mut self, t: time::Duration) {
+        self.query_interval = t;
+    }
+
+    /// Sets the part of the sound that's repeated.
+    pub fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>) {
+        self.loop_start = start;
+        self.loop_end = end;
+    }
+
+    /// Gets the part of the sound that's repeated.
+    pub fn loop_points(&self) -> (time::Duration, Option<time::Duration>) {
+        (self.loop_start, self.loop_end)
+    }
+
+    /// Sends the sound to the given mixer bus.
+    pub fn set_bus(&mut self, ctx: &mut Context, bus: Option<&str>) {
+        self.bus = bus.map(|name| (name.to_string(), mixer_mut(ctx).bus(name)));
+    }
+
+    /// Gets the name of the mixer bus the sound is sent to.
+    pub fn bus(&self) -> Option<&str> {
+        self.bus.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// Sets the effects the sound is run through.
+    pub fn set_effects(&mut self, effects: &[Effect]) {
+        if let Ok(mut current) = self.effects.lock() {
+            *current = effects.to_vec();
+        }
+    }
+
+    /// Gets the effects the sound is run through.
+    pub fn effects(&self) -> Vec<Effect> {
+        self.effects
+            .lock()
+            .map(|effects| effects.clone())
+            .unwrap_or_default()
+    }
+
+    /// Gets the current volume.
+    pub fn volume(&self) -> f32 {
+        self.fade.lock().map_or(1.0, |fade| fade.current())
+    }
+
+    /// Sets the current volume, stopping any fade.
+    pub fn set_volume(&mut self, value: f32) {
+        if let Ok(mut fade) = self.fade.lock() {
+            *fade = Fade {
+                generation: fade.generation.wrapping_add(1),
+                ..Fade::constant(value)
+            };
+        }
+    }
+
+    /// Starts fading from the current volume to the given one.
+    pub fn fade_to(&mut self, volume: f32, duration: time::Duration) {
+        if let Ok(mut fade) = self.fade.lock() {
+            *fade = Fade {
+                from: fade.current(),
+                to: volume,
+                length: duration,
+                elapsed: time::Duration::from_secs(0),
+                generation: fade.generation.wrapping_add(1),
+            };
+        }
+    }
+
+    /// Pans the sound between the speakers.
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan
+            .store(pan.max(-1.0).min(1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Gets how the sound is panned.
+    pub fn pan(&self) -> f32 {
+        f32::from_bits(self.pan.load(Ordering::Relaxed))
+    }
+
+    /// Gets whether the sounds queued since the last stop have all
+    /// played to the end.
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    /// Attaches an analyzer that hears the sounds played from now on.
+    pub fn set_analyzer(&mut self, analyzer: Option<Analyzer>) {
+        self.analyzer = analyzer;
+    }
+
+    /// Forgets about the queued sounds, when the sink is replaced.
+    pub(crate) fn clear_queue(&mut self) {
+        self.queued.store(0, Ordering::SeqCst);
+        self.finished.store(false, Ordering::SeqCst);
+    }
+
+    /// Goes back to the start, when the source is stopped.
+    pub(crate) fn rewind(&mut self) {
+        self.play_time.store(0, Ordering::SeqCst);
+        self.offset = time::Duration::from_secs(0);
+        self.clear_queue();
+    }
+
+    /// Applies the pitch and fade-in to the sound, and counts how long
+    /// it's been playing for `elapsed()`.
+    pub(crate) fn timed<S>(&self, sound: S) -> impl rodio::Source<Item = i16> + Send
+    where
+        S: rodio::Source<Item = i16> + Send,
+    {
+        use rodio::Source;
+        let counter = self.play_time.clone();
+        let period_mus = self.query_interval.as_secs() as usize * 1_000_000
+            + self.query_interval.subsec_micros() as usize;
+        sound
+            .speed(self.pitch())
+            .fade_in(self.fade_in)
+            .periodic_access(self.query_interval, move |_| {
+                let _ = counter.fetch_add(period_mus, Ordering::SeqCst);
+            })
+    }
+
+    /// Applies the effects, the volume and the volume of the mixer bus
+    /// to the sound as it plays, and keeps track of when it finishes.
+    pub(crate) fn mixed<S>(&self, sound: S) -> impl rodio::Source<Item = i16> + Send
+    where
+        S: rodio::Source<Item = i16> + Send,
+    {
+        use rodio::Source;
+        const PERIOD: time::Duration = time::Duration::from_millis(5);
+        let level = self.bus.as_ref().map(|(_, level)| level.clone());
+        let master = self.master.clone();
+        let fade = self.fade.clone();
+        let mut cursor = fade.lock().map_or(Fade::constant(1.0), |fade| *fade);
+        let bus_effects = level
+            .as_ref()
+            .map_or_else(SharedEffects::default, |level| level.effects.clone());
+        let _ = self.queued.fetch_add(1, Ordering::SeqCst);
+        self.finished.store(false, Ordering::SeqCst);
+        let sound = Finishing {
+            input: TimeStretch::new(sound, self.stretch()),
+            queued: self.queued.clone(),
+            finished: self.finished.clone(),
+            done: false,
+        };
+        let analyzers = self
+            .analyzer
+            .iter()
+            .cloned()
+            .chain(level.as_ref().and_then(|level| {
+                level
+                    .analyzer
+                    .lock()
+                    .ok()
+                    .and_then(|analyzer| analyzer.clone())
+            }))
+            .collect();
+        let sound = Effects::new(Effects::new(sound, self.effects.clone()), bus_effects);
+        let sound = Panned::new(sound, self.pan.clone())
+            .pausable(false)
+            .amplify(1.0)
+            .periodic_access(PERIOD, move |sound| {
+                let paused = master.paused();
+                sound.inner_mut().set_paused(paused);
+                let volume = fade.lock().map_or(1.0, |mut fade| {
+                    let dt = if paused {
+                        time::Duration::from_secs(0)
+                    } else {
+                        PERIOD
+                    };
+                    cursor.follow(&mut fade, dt);
+                    cursor.current()
+                });
+                let bus = level.as_ref().map_or(1.0, |level| level.gain());
+                sound.set_factor(volume * bus * master.volume());
+            });
+        Tapped::new(sound, analyzers)
+    }
+
+    /// Whether only part of the sound is repeated.
+    pub(crate) fn has_loop_points(&self) -> bool {
+        self.loop_start.as_nanos() > 0 || self.loop_end.is_some()
+    }
+
+    /// Get the current position in the sound.
+    pub fn position(&self) -> time::Duration {
+        let position = self.offset + self.elapsed().mul_f32(self.pitch());
+        if !self.repeat {
+            return self.duration.map_or(position, |d| position.min(d));
+        }
+        match self.loop_end.or(self.duration) {
+            Some(end) if end > self.loop_start && position >= end => {
+                let length = (end - self.loop_start).as_nanos();
+                let into_loop = (position - end).as_nanos() % length;
+                self.loop_start + time::Duration::from_nanos(into_loop as u64)
+            }
+            _ => position,
+        }
+    }
+}
+
+/// The length of the sound the reader holds, if its decoder knows it.
+pub(crate) fn decoded_duration<R>(reader: R) -> Option<time::Duration>
+where
+    R: Read + Seek + Send + 'static,
+{
+    use rodio::Source;
+    rodio::Decoder::new(reader).ok()?.total_duration()
+}
+
+/// Converts a time in a sound to a number of samples, rounded down to
+/// a whole frame.
+pub(crate) fn samples_at(time: time::Duration, sample_rate: u32, channels: u16) -> u64 {
+    let frames = time.as_secs_f64() * f64::from(sample_rate);
+    frames as u64 * u64::from(channels)
+}
+
+/// Plays a sound from the given offset through to the end of its loop
+/// region, and then the loop region over and over.  The loop region is
+/// kept in memory the first time through, so the loop is seamless;
+/// that's fine for sounds that are in memory anyway, streamed ones loop
+/// with a `StreamLoop` instead.
+pub(crate) struct LoopRegion<I> {
+    input: I,
+    channels: u16,
+    sample_rate: u32,
+    /// The sample of the sound `input` is at.
+    position: u64,
+    /// The samples before this are skipped.
+    offset: u64,
+    start: u64,
+    end: Option<u64>,
+    region: Vec<i16>,
+    /// Where in `region` it's at, once the loop is being replayed.
+    replay: Option<usize>,
+}
+
+impl<I> LoopRegion<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    pub(crate) fn new<D>(input: I, state: &SourceState<D>) -> Self {
+        let channels = input.channels();
+        let sample_rate = input.sample_rate();
+        let samples = |time| samples_at(time, sample_rate, channels);
+        LoopRegion {
+            input,
+            channels,
+            sample_rate,
+            position: 0,
+            offset: samples(state.offset),
+            start: samples(state.loop_start),
+            end: state.loop_end.map(samples),
+            region: Vec::new(),
+            replay: None,
+        }
+    }
+
+    /// The next sample from the input, up to the end of the loop.
+    fn next_input(&mut self) -> Option<i16> {
+        if self.end.map_or(false, |end| self.position >= end) {
+            return None;
+        }
+        let sample = self.input.next()?;
+        if self.position >= self.start {
+            self.region.push(sample);
+        }
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl<I> Iterator for LoopRegion<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.replay.is_none() {
+            // Everything before the offset still has to be read to fill
+            // in the loop region.
+            while self.position < self.offset {
+                if self.next_input().is_none() {
+                    break;
+                }
+            }
+            if let Some(sample) = self.next_input() {
+                return Some(sample);
+            }
+            if self.region.is_empty() {
+                return None;
+            }
+            self.replay = Some(0);
+        }
+        let index = self.replay.unwrap_or(0);
+        self.replay = Some((index + 1) % self.region.len());
+        self.region.get(index).copied()
+    }
+}
+
+impl<I> rodio::Source for LoopRegion<I>
+where
+    I: rodio::Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.replay {
+            Some(_) => None,
+            None => self.input.current_frame_len(),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn looped(offset_ms: u64, start_ms: u64, end_ms: Option<u64>) -> Vec<i16> {
+        // Ten samples a second, so each sample is 100 ms.
+        let input = rodio::buffer::SamplesBuffer::new(1, 10, (0..10).collect::<Vec<i16>>());
+        let mut state = SourceState::new(());
+        state.offset = time::Duration::from_millis(offset_ms);
+        state.set_loop_points(
+            time::Duration::from_millis(start_ms),
+            end_ms.map(time::Duration::from_millis),
+        );
+        LoopRegion::new(input, &state).take(12).collect()
+    }
+
+    #[test]
+    fn headless_test_loop_region() {
+        assert_eq!(
+            looped(0, 200, Some(500)),
+            vec![0, 1, 2, 3, 4, 2, 3, 4, 2, 3, 4, 2]
+        );
+        assert_eq!(
+            looped(0, 700, None),
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 7, 8]
+        );
+        // Starting after the loop start still loops the whole region.
+        assert_eq!(
+            looped(300, 200, Some(500)),
+            vec![3, 4, 2, 3, 4, 2, 3, 4, 2, 3, 4, 2]
+        );
+    }
+
+    #[test]
+    fn headless_test_finished() {
+        let state = SourceState::new(());
+        let first = state.mixed(rodio::buffer::SamplesBuffer::new(1, 10, vec![1i16, 2]));
+        let second = state.mixed(rodio::buffer::SamplesBuffer::new(1, 10, vec![3i16]));
+        assert!(!state.finished());
+        // Mono sounds come out panned into stereo.
+        assert_eq!(first.count(), 4);
+        assert!(!state.finished());
+        assert_eq!(second.count(), 2);
+        assert!(state.finished());
+    }
+
+    #[test]
+    fn headless_test_pause_all() {
+        let mixer = Mixer::default();
+        mixer.master.paused.store(true, Ordering::Relaxed);
+        let state = SourceState::new(()).with_master(&mixer);
+        let sound = rodio::buffer::SamplesBuffer::new(2, 1000, vec![1000i16; 4]);
+        // Paused sounds play silence without running out.
+        let played = state.mixed(sound).take(100).collect::<Vec<_>>();
+        assert_eq!(played, vec![0; 100]);
+        assert!(!state.finished());
+    }
+
+    #[test]
+    fn headless_test_fade() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        let mut state = SourceState::new(());
+        state.set_volume(0.5);
+        assert!(close(state.volume(), 0.5));
+
+        state.fade_to(1.5, time::Duration::from_secs(1));
+        assert!(close(state.volume(), 0.5));
+        state
+            .fade
+            .lock()
+            .unwrap()
+            .advance(time::Duration::from_millis(250));
+        assert!(close(state.volume(), 0.75));
+        state
+            .fade
+            .lock()
+            .unwrap()
+            .advance(time::Duration::from_secs(2));
+        assert!(close(state.volume(), 1.5));
+    }
+
+    #[test]
+    fn headless_test_fade_many_instances() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        let mut state = SourceState::new(());
+        state.set_volume(0.5);
+        let mut first = *state.fade.lock().unwrap();
+        let mut second = first;
+
+        state.fade_to(1.5, time::Duration::from_secs(1));
+        for _ in 0..2 {
+            let mut shared = state.fade.lock().unwrap();
+            first.follow(&mut shared, time::Duration::from_millis(125));
+            second.follow(&mut shared, time::Duration::from_millis(125));
+        }
+        // Both sounds heard a quarter of the fade, not half of it.
+        assert!(close(first.current(), 0.75));
+        assert!(close(second.current(), 0.75));
+        assert!(close(state.volume(), 0.75));
+
+        // A sound started partway through picks up where the fade is.
+        let mut third = Fade::constant(1.0);
+        third.follow(
+            &mut state.fade.lock().unwrap(),
+            time::Duration::from_secs(0),
+        );
+        assert!(close(third.current(), 0.75));
+    }
+}
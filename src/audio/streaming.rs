@@ -0,0 +1,834 @@
+//! Sounds decoded bit by bit as they play, from a file or a callback.
+
+use std::fmt;
+use std::io;
+use std::io::{Read, Seek};
+use std::mem;
+use std::path;
+use std::thread;
+use std::time;
+
+#[cfg(feature = "tracker")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::audio::*;
+use crate::context::Context;
+use crate::error::GameError;
+use crate::error::GameResult;
+use crate::filesystem;
+
+/// A file shared between several streams that each read it from their
+/// own position, so a sound can be queued or looped while it's still
+/// being played without the streams getting in each other's way.
+#[derive(Debug, Clone)]
+struct SharedFile {
+    file: Arc<Mutex<filesystem::File>>,
+    position: u64,
+}
+
+impl SharedFile {
+    fn new(file: filesystem::File) -> Self {
+        SharedFile {
+            file: Arc::new(Mutex::new(file)),
+            position: 0,
+        }
+    }
+
+    /// Another reader of the same file, starting from the beginning.
+    fn rewound(&self) -> Self {
+        SharedFile {
+            file: self.file.clone(),
+            position: 0,
+        }
+    }
+
+    fn lock(&self) -> io::Result<MutexGuard<filesystem::File>> {
+        self.file
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Sound file lock poisoned"))
+    }
+}
+
+impl Read for SharedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = {
+            let mut file = self.lock()?;
+            let _ = file.seek(io::SeekFrom::Start(self.position))?;
+            file.read(buf)?
+        };
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for SharedFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            io::SeekFrom::Start(n) => Some(n),
+            io::SeekFrom::Current(n) => {
+                if n >= 0 {
+                    self.position.checked_add(n as u64)
+                } else {
+                    self.position.checked_sub(n.wrapping_neg() as u64)
+                }
+            }
+            io::SeekFrom::End(_) => {
+                let end = self.lock()?.seek(pos)?;
+                Some(end)
+            }
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek in sound file")
+        })?;
+        Ok(self.position)
+    }
+}
+
+/// Decodes the file from the start, skipping the given number of
+/// samples.  There's no seeking in the decoders, so that means decoding
+/// them, which is why it's done before the sound is handed to the
+/// audio thread.
+fn wound(file: &SharedFile, samples: u64) -> GameResult<rodio::Decoder<SharedFile>> {
+    let mut decoder = rodio::Decoder::new(file.rewound())?;
+    for _ in 0..samples {
+        if decoder.next().is_none() {
+            break;
+        }
+    }
+    Ok(decoder)
+}
+
+/// Decodes a sound file bit by bit as it's played, starting over from
+/// the beginning when it runs out if it's set to repeat.
+struct Stream {
+    file: SharedFile,
+    decoder: rodio::Decoder<SharedFile>,
+    repeat: bool,
+}
+
+impl Stream {
+    /// Starts the stream `offset` into the file.
+    fn new(file: &SharedFile, repeat: bool, offset: time::Duration) -> GameResult<Self> {
+        use rodio::Source;
+        let decoder = rodio::Decoder::new(file.rewound())?;
+        let skip = samples_at(offset, decoder.sample_rate(), decoder.channels());
+        Ok(Stream {
+            file: file.rewound(),
+            decoder: wound(file, skip)?,
+            repeat,
+        })
+    }
+}
+
+impl Iterator for Stream {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some(sample) = self.decoder.next() {
+            return Some(sample);
+        }
+        if !self.repeat {
+            return None;
+        }
+        // Give up rather than spin if the file has no samples at all.
+        self.decoder = rodio::Decoder::new(self.file.rewound()).ok()?;
+        self.decoder.next()
+    }
+}
+
+impl rodio::Source for Stream {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoder.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.decoder.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoder.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        if self.repeat {
+            None
+        } else {
+            self.decoder.total_duration()
+        }
+    }
+}
+
+/// Plays a streamed file from the given offset through to the end of
+/// its loop region, and then the loop region over and over, without
+/// keeping any of it in memory.  A helper thread gets a decoder ready
+/// at the start of the loop while the previous one plays, so it can
+/// take over the moment the loop ends.
+struct StreamLoop {
+    decoder: rodio::Decoder<SharedFile>,
+    /// The sample of the sound `decoder` is at.
+    position: u64,
+    start: u64,
+    end: Option<u64>,
+    /// Decoders wound to the start of the loop, one at a time.
+    ready: mpsc::Receiver<rodio::Decoder<SharedFile>>,
+}
+
+impl StreamLoop {
+    fn new<D>(file: &SharedFile, state: &SourceState<D>) -> GameResult<Self> {
+        use rodio::Source;
+        let decoder = rodio::Decoder::new(file.rewound())?;
+        let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+        let samples = |time| samples_at(time, sample_rate, channels);
+        let (start, end) = state.loop_points();
+        let (start, end) = (samples(start), end.map(samples));
+        // Starting past the end of the loop starts at its start.
+        let offset = match samples(state.offset) {
+            offset if end.map_or(false, |end| offset >= end) => start,
+            offset => offset,
+        };
+
+        let (prepared, ready) = mpsc::sync_channel(0);
+        let shared = file.rewound();
+        let _ = thread::Builder::new()
+            .name("ggez audio loop".to_string())
+            .spawn(move || {
+                // Each send waits for the decoder before to be taken,
+                // and fails once the sound is gone.
+                while let Ok(decoder) = wound(&shared, start) {
+                    if prepared.send(decoder).is_err() {
+                        break;
+                    }
+                }
+            })?;
+        Ok(StreamLoop {
+            decoder: wound(file, offset)?,
+            position: offset,
+            start,
+            end,
+            ready,
+        })
+    }
+}
+
+impl Iterator for StreamLoop {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        loop {
+            if self.end.map_or(true, |end| self.position < end) {
+                if let Some(sample) = self.decoder.next() {
+                    self.position += 1;
+                    return Some(sample);
+                }
+            }
+            // An empty loop would go round forever without a sound.
+            if self.position <= self.start {
+                return None;
+            }
+            // The helper has had the whole time through the loop to get
+            // this ready, so this only waits for very short loops.
+            self.decoder = self.ready.recv().ok()?;
+            self.position = self.start;
+        }
+    }
+}
+
+impl rodio::Source for StreamLoop {
+    fn current_frame_len(&self) -> Option<usize> {
+        let len = self.decoder.current_frame_len()?;
+        match self.end {
+            Some(end) => Some(len.min(end.saturating_sub(self.position) as usize)),
+            None => Some(len),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.decoder.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoder.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        None
+    }
+}
+
+type GeneratorFn = dyn FnMut(&mut [i16]) -> usize + Send;
+
+/// A callback making up a sound as it plays, shared between the
+/// streams playing it.
+#[derive(Clone)]
+struct Generator {
+    callback: Arc<Mutex<Box<GeneratorFn>>>,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<Generator: {:p}>", self)
+    }
+}
+
+/// A sound being made by a `Generator`.
+struct Generated {
+    generator: Generator,
+    buffer: Vec<i16>,
+    position: usize,
+    filled: usize,
+    done: bool,
+}
+
+impl Generated {
+    /// How many frames the callback is asked for at a time.
+    const FRAMES: usize = 1024;
+
+    fn new(generator: Generator) -> Self {
+        let len = Self::FRAMES * usize::from(generator.channels.max(1));
+        Generated {
+            generator,
+            buffer: vec![0; len],
+            position: 0,
+            filled: 0,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Generated {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.position == self.filled {
+            if self.done {
+                return None;
+            }
+            let buffer = &mut self.buffer[..];
+            let len = buffer.len();
+            self.filled = self
+                .generator
+                .callback
+                .lock()
+                .map_or(0, |mut callback| (&mut **callback)(buffer).min(len));
+            self.position = 0;
+            if self.filled == 0 {
+                self.done = true;
+                return None;
+            }
+        }
+        let sample = self.buffer[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl rodio::Source for Generated {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.generator.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.generator.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<time::Duration> {
+        None
+    }
+}
+
+/// Where a `StreamingSource` gets its sound from.
+#[derive(Debug, Clone)]
+enum StreamData {
+    File(SharedFile),
+    Generator(Generator),
+}
+
+/// A source of audio data that's decoded a little at a time while it
+/// plays, reading from the file as it goes, instead of being loaded
+/// into memory and decoded up front like a [`Source`](struct.Source.html).
+///
+/// This is meant for music and other long sounds, where decoding the
+/// whole file would take lots of memory and a noticeable while.  For
+/// short sound effects that are played often a `Source` is better,
+/// since it doesn't need to read the file every time.
+///
+/// Repeating a streaming source starts decoding the file over from the
+/// beginning rather than keeping the decoded sound around, so it stays
+/// small however long it plays.  It will stop playing when dropped.
+///
+/// A streaming source can also play sound made up by the game as it
+/// goes, see [`from_generator()`](#method.from_generator).
+pub struct StreamingSource {
+    sink: Box<dyn Voice>,
+    state: SourceState<StreamData>,
+    #[cfg(feature = "tracker")]
+    tracker: Option<Arc<TrackerState>>,
+}
+
+impl StreamingSource {
+    /// Opens the given file for streaming.  The file stays open as long
+    /// as the `StreamingSource` does.
+    pub fn new<P: AsRef<path::Path>>(context: &mut Context, path: P) -> GameResult<Self> {
+        let file = SharedFile::new(filesystem::open(context, path)?);
+        if rodio::Decoder::new(file.rewound()).is_err() {
+            let mut header = [0; 64];
+            let len = file.rewound().read(&mut header)?;
+            let format = AudioFormat::detect(&header[..len]);
+            if format == Some(AudioFormat::Opus) && cfg!(feature = "opus") {
+                return Err(GameError::AudioError(
+                    "Opus audio can't be streamed, play it with a `Source` instead".to_string(),
+                ));
+            }
+            return Err(AudioFormat::decode_error(format));
+        }
+        let sink = context.audio_context.new_voice()?;
+        let duration = decoded_duration(file.rewound());
+        Ok(StreamingSource {
+            sink,
+            state: SourceState::new(StreamData::File(file))
+                .with_duration(duration)
+                .with_master(&context.audio_mixer),
+            #[cfg(feature = "tracker")]
+            tracker: None,
+        })
+    }
+
+    /// Plays sound made by the given callback, for synthesizers,
+    /// procedural audio or voice chat.  Whenever more sound is needed
+    /// the callback is given a buffer to fill with 16-bit samples, with
+    /// the samples of each channel interleaved, and returns how many it
+    /// filled in; returning `0` ends the sound.  It should fill in
+    /// whole frames, that is a sample for every channel.
+    ///
+    /// The callback is run on the audio thread, so it needs to be quick
+    /// about it or the sound will stutter.  It keeps going where it
+    /// left off each time the source is played; repeating and loop
+    /// points have no effect on it, and it can't
+    /// [`seek()`](trait.SoundSource.html#method.seek).
+    pub fn from_generator<F>(
+        context: &mut Context,
+        sample_rate: u32,
+        channels: u16,
+        callback: F,
+    ) -> GameResult<Self>
+    where
+        F: FnMut(&mut [i16]) -> usize + Send + 'static,
+    {
+        let sink = context.audio_context.new_voice()?;
+        let generator = Generator {
+            callback: Arc::new(Mutex::new(Box::new(callback))),
+            sample_rate,
+            channels,
+        };
+        Ok(StreamingSource {
+            sink,
+            state: SourceState::new(StreamData::Generator(generator))
+                .with_master(&context.audio_mixer),
+            #[cfg(feature = "tracker")]
+            tracker: None,
+        })
+    }
+
+    /// Loads a tracker module, in any of the formats libopenmpt
+    /// reads, like MOD, XM, IT and S3M, for playing like any other
+    /// sound.  Needs the `tracker` feature.
+    ///
+    /// Setting the source to repeat makes the song loop back to its
+    /// start when it ends, as the module says to.  Stopping it goes
+    /// back to the start, and it can seek like a file.
+    #[cfg(feature = "tracker")]
+    pub fn from_tracker<P: AsRef<path::Path>>(context: &mut Context, path: P) -> GameResult<Self> {
+        let module = TrackerModule::load(SoundData::new(context, path)?)?;
+        let tracker = Arc::new(TrackerState::default());
+        let callback = module.player(tracker.clone());
+        let mut source =
+            StreamingSource::from_generator(context, TrackerModule::SAMPLE_RATE, 2, callback)?;
+        source.tracker = Some(tracker);
+        Ok(source)
+    }
+
+    /// Where a tracker module loaded with
+    /// [`from_tracker()`](#method.from_tracker) is in its song, for
+    /// timing the game to the music.  It's where the module has been
+    /// played up to, which is a few milliseconds ahead of what's being
+    /// heard.  `None` for other sounds.
+    #[cfg(feature = "tracker")]
+    pub fn tracker_position(&self) -> Option<TrackerPosition> {
+        let tracker = self.tracker.as_ref()?;
+        tracker.position.lock().ok().map(|position| *position)
+    }
+
+    /// Whether the sound can be played from anywhere but where it left
+    /// off; everything but plain generators can.
+    fn can_seek(&self) -> bool {
+        #[cfg(feature = "tracker")]
+        {
+            if self.tracker.is_some() {
+                return true;
+            }
+        }
+        match self.state.data {
+            StreamData::File(_) => true,
+            StreamData::Generator(_) => false,
+        }
+    }
+
+    /// Has a tracker module jump to the given position the next time
+    /// it's played.  Files are decoded afresh from `state.offset`
+    /// instead, and plain generators can't seek.
+    fn seek_tracker(&self, position: time::Duration) {
+        #[cfg(feature = "tracker")]
+        {
+            if let Some(tracker) = &self.tracker {
+                if let Ok(mut seek) = tracker.seek.lock() {
+                    *seek = Some(position);
+                }
+            }
+        }
+        let _ = position;
+    }
+}
+
+/// Where a tracker module is in its song.
+#[cfg(feature = "tracker")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct TrackerPosition {
+    /// The place in the order list, the list of patterns that makes
+    /// up the song.
+    pub order: u32,
+    /// The pattern being played.
+    pub pattern: u32,
+    /// The row of the pattern being played.
+    pub row: u32,
+}
+
+/// What a tracker module shares with its `StreamingSource`.
+#[cfg(feature = "tracker")]
+#[derive(Debug, Default)]
+struct TrackerState {
+    position: Mutex<TrackerPosition>,
+    repeat: AtomicBool,
+    /// Where to jump to before playing on.
+    seek: Mutex<Option<time::Duration>>,
+}
+
+#[cfg(feature = "tracker")]
+struct TrackerModule(openmpt::module::Module);
+
+#[cfg(feature = "tracker")]
+impl TrackerModule {
+    /// The sample rate modules are played at.
+    const SAMPLE_RATE: u32 = 48000;
+
+    fn load(data: SoundData) -> GameResult<Self> {
+        openmpt::module::Module::create(
+            &mut io::Cursor::new(data),
+            openmpt::module::Logger::None,
+            &[],
+        )
+        .map(TrackerModule)
+        .map_err(|_| GameError::AudioError("Could not load the given tracker module".to_string()))
+    }
+
+    /// The generator callback that plays the module, keeping `shared`
+    /// up to date with where it is.
+    fn player(mut self, shared: Arc<TrackerState>) -> impl FnMut(&mut [i16]) -> usize + Send {
+        move |buffer| {
+            let module = &mut self.0;
+            if let Some(position) = shared.seek.lock().ok().and_then(|mut seek| seek.take()) {
+                let _ = module.set_position_seconds(position.as_secs_f64());
+            }
+            let repeat = shared.repeat.load(Ordering::Relaxed);
+            module.set_repeat_count(if repeat { -1 } else { 0 });
+            let frames = module.read_interleaved_stereo(Self::SAMPLE_RATE as i32, buffer);
+            if let Ok(mut position) = shared.position.lock() {
+                *position = TrackerPosition {
+                    order: module.get_current_order().max(0) as u32,
+                    pattern: module.get_current_pattern().max(0) as u32,
+                    row: module.get_current_row().max(0) as u32,
+                };
+            }
+            frames * 2
+        }
+    }
+}
+
+// libopenmpt modules can be used from any thread as long as it's one at
+// a time, which the lock around the generator makes sure of.
+#[cfg(feature = "tracker")]
+#[allow(unsafe_code)]
+unsafe impl Send for TrackerModule {}
+
+impl SoundSource for StreamingSource {
+    fn play_later(&self) -> GameResult {
+        let stream: Box<dyn rodio::Source<Item = i16> + Send> = match &self.state.data {
+            StreamData::Generator(generator) => Box::new(Generated::new(generator.clone())),
+            StreamData::File(file) if self.state.repeat() && self.state.has_loop_points() => {
+                Box::new(StreamLoop::new(file, &self.state)?)
+            }
+            StreamData::File(file) => {
+                Box::new(Stream::new(file, self.state.repeat(), self.state.offset)?)
+            }
+        };
+        let sound = self.state.timed(stream);
+        self.sink.append(Sound::new(self.state.mixed(sound)));
+        Ok(())
+    }
+
+    fn play_detached(&mut self, ctx: &Context) -> GameResult {
+        self.stop(ctx)?;
+        self.play_later()?;
+
+        let new_sink = ctx.audio_context.new_voice()?;
+        let old_sink = mem::replace(&mut self.sink, new_sink);
+        old_sink.detach();
+
+        Ok(())
+    }
+
+    fn set_repeat(&mut self, repeat: bool) {
+        #[cfg(feature = "tracker")]
+        {
+            if let Some(tracker) = &self.tracker {
+                tracker.repeat.store(repeat, Ordering::Relaxed);
+            }
+        }
+        self.state.set_repeat(repeat)
+    }
+    fn set_fade_in(&mut self, dur: time::Duration) {
+        self.state.set_fade_in(dur)
+    }
+    fn set_pitch(&mut self, ratio: f32) {
+        self.state.set_pitch(ratio)
+    }
+
+    fn set_speed(&mut self, rate: f32, preserve_pitch: bool) {
+        self.state.set_speed(rate, preserve_pitch)
+    }
+    fn repeat(&self) -> bool {
+        self.state.repeat()
+    }
+    fn pause(&self) {
+        self.sink.pause()
+    }
+    fn resume(&self) {
+        self.sink.play()
+    }
+
+    fn stop(&mut self, ctx: &Context) -> GameResult {
+        // See `Source::stop()` for why the sink is replaced.
+        self.sink = ctx.audio_context.new_voice()?;
+        self.state.rewind();
+        self.seek_tracker(time::Duration::from_secs(0));
+        Ok(())
+    }
+
+    fn stopped(&self) -> bool {
+        self.sink.empty()
+    }
+
+    fn finished(&self) -> bool {
+        self.state.finished()
+    }
+
+    fn volume(&self) -> f32 {
+        self.state.volume()
+    }
+
+    fn set_volume(&mut self, value: f32) {
+        self.state.set_volume(value)
+    }
+
+    fn fade_to(&mut self, volume: f32, duration: time::Duration) {
+        self.state.fade_to(volume, duration)
+    }
+
+    fn paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    fn playing(&self) -> bool {
+        !self.paused() && !self.stopped()
+    }
+
+    fn elapsed(&self) -> time::Duration {
+        self.state.elapsed()
+    }
+
+    fn set_query_interval(&mut self, t: time::Duration) {
+        self.state.set_query_interval(t)
+    }
+
+    fn seek(&mut self, ctx: &Context, position: time::Duration) -> GameResult {
+        if !self.can_seek() {
+            return Err(GameError::AudioError(
+                "Sound made by a generator can't seek".to_string(),
+            ));
+        }
+        let paused = self.paused();
+        self.stop(ctx)?;
+        self.state.offset = position;
+        self.seek_tracker(position);
+        self.play_later()?;
+        if paused {
+            self.pause();
+        }
+        Ok(())
+    }
+
+    fn position(&self) -> time::Duration {
+        self.state.position()
+    }
+
+    fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>) {
+        self.state.set_loop_points(start, end)
+    }
+
+    fn loop_points(&self) -> (time::Duration, Option<time::Duration>) {
+        self.state.loop_points()
+    }
+
+    fn set_bus(&mut self, ctx: &mut Context, bus: Option<&str>) {
+        self.state.set_bus(ctx, bus)
+    }
+
+    fn bus(&self) -> Option<&str> {
+        self.state.bus()
+    }
+
+    fn set_effects(&mut self, effects: &[Effect]) {
+        self.state.set_effects(effects)
+    }
+
+    fn effects(&self) -> Vec<Effect> {
+        self.state.effects()
+    }
+
+    fn set_analyzer(&mut self, analyzer: Option<Analyzer>) {
+        self.state.set_analyzer(analyzer)
+    }
+
+    fn set_pan(&mut self, pan: f32) {
+        self.state.set_pan(pan)
+    }
+
+    fn pan(&self) -> f32 {
+        self.state.pan()
+    }
+}
+
+impl fmt::Debug for StreamingSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<Streaming audio source: {:p}>", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_generator() {
+        let mut left = 3;
+        let callback = move |buffer: &mut [i16]| {
+            if left == 0 {
+                return 0;
+            }
+            left -= 1;
+            buffer[0] = left;
+            buffer[1] = -left;
+            2
+        };
+        let generator = Generator {
+            callback: Arc::new(Mutex::new(Box::new(callback))),
+            sample_rate: 8000,
+            channels: 2,
+        };
+        assert_eq!(
+            Generated::new(generator).collect::<Vec<_>>(),
+            vec![2, -2, 1, -1, 0, 0]
+        );
+    }
+
+    #[cfg(feature = "tracker")]
+    #[test]
+    fn headless_test_tracker() {
+        match TrackerModule::load(SoundData::from_bytes(b"not a module")) {
+            Err(GameError::AudioError(message)) => assert!(message.contains("tracker module")),
+            _ => panic!("garbage should not load as a tracker module"),
+        }
+
+        // A four-channel ProTracker module with no samples and one
+        // empty pattern: 31 blank sample headers after the title, then
+        // the song length, the order list and the format tag.
+        let mut bytes = vec![0u8; 1084 + 64 * 4 * 4];
+        bytes[950] = 1;
+        bytes[1080..1084].copy_from_slice(b"M.K.");
+        let module = TrackerModule::load(SoundData::from(bytes)).unwrap();
+        let shared = Arc::new(TrackerState::default());
+        let mut player = module.player(shared.clone());
+        let mut buffer = vec![1i16; 2048];
+        let mut played = 0;
+        loop {
+            let filled = player(&mut buffer[..]);
+            if filled == 0 {
+                break;
+            }
+            assert!(buffer[..filled].iter().all(|sample| *sample == 0));
+            played += filled;
+            // The pattern lasts a few seconds, not a minute.
+            assert!(played < 48000 * 2 * 60, "the module should end");
+        }
+        assert!(played > 0);
+        assert_eq!(shared.position.lock().unwrap().pattern, 0);
+
+        // Seeking back to the start plays the finished module again.
+        *shared.seek.lock().unwrap() = Some(time::Duration::from_secs(0));
+        assert!(player(&mut buffer[..]) > 0);
+        assert!(shared.seek.lock().unwrap().is_none());
+    }
+
+    fn stream_looped(offset_ms: u64, start_ms: u64, end_ms: Option<u64>) -> Vec<i16> {
+        // Ten samples a second, so each sample is 100 ms.
+        let data = SoundData::from_pcm(&(0..10).collect::<Vec<i16>>(), 10, 1);
+        let file = io::Cursor::new(data.as_ref().to_vec());
+        let file = SharedFile::new(filesystem::File::VfsFile(Box::new(file)));
+        let mut state = SourceState::new(());
+        state.offset = time::Duration::from_millis(offset_ms);
+        state.set_loop_points(
+            time::Duration::from_millis(start_ms),
+            end_ms.map(time::Duration::from_millis),
+        );
+        StreamLoop::new(&file, &state).unwrap().take(12).collect()
+    }
+
+    #[test]
+    fn headless_test_stream_loop() {
+        assert_eq!(
+            stream_looped(0, 200, Some(500)),
+            vec![0, 1, 2, 3, 4, 2, 3, 4, 2, 3, 4, 2]
+        );
+        assert_eq!(
+            stream_looped(0, 700, None),
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 7, 8]
+        );
+        assert_eq!(
+            stream_looped(300, 200, Some(500)),
+            vec![3, 4, 2, 3, 4, 2, 3, 4, 2, 3, 4, 2]
+        );
+        // Past the end of the loop it starts at the loop's start.
+        assert_eq!(
+            stream_looped(800, 200, Some(500)),
+            vec![2, 3, 4, 2, 3, 4, 2, 3, 4, 2, 3, 4]
+        );
+        assert_eq!(stream_looped(0, 2000, None).len(), 10);
+    }
+}
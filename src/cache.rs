@@ -0,0 +1,147 @@
+//! A cache of loaded assets, keyed by their path in the
+//! [`filesystem`](../filesystem/index.html).
+//!
+//! Loading the same spritesheet from ten places with
+//! [`graphics::Image::new()`](../graphics/struct.Image.html#method.new)
+//! decodes it ten times and makes ten GPU textures.  Loading it with
+//! [`graphics::load_cached_image()`](../graphics/fn.load_cached_image.html)
+//! instead only does that the first time, and hands out clones of the
+//! same `Image` after that.  Fonts and sound data have their own
+//! [`graphics::load_cached_font()`](../graphics/fn.load_cached_font.html)
+//! and [`audio::load_cached_sound_data()`](../audio/fn.load_cached_sound_data.html).
+//!
+//! Cached assets stay loaded until they're [`invalidate()`](fn.invalidate.html)d
+//! or the cache is [`clear()`](fn.clear.html)ed, even if nothing else
+//! uses them any more.  Files that change while resources are watched
+//! with [`filesystem::watch_resources()`](../filesystem/fn.watch_resources.html)
+//! are invalidated on their own, so they're loaded fresh next time.
+
+use std::collections::HashMap;
+use std::path;
+
+#[cfg(feature = "audio")]
+use crate::audio::SoundData;
+use crate::context::Context;
+use crate::graphics::{Font, Image};
+
+/// How much memory the assets in the cache take up, roughly, in bytes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheMemory {
+    /// The texture memory of the cached images.
+    pub images: usize,
+    /// The size of the font files the cached fonts were loaded from.
+    pub fonts: usize,
+    /// The size of the cached sound data.
+    pub sounds: usize,
+}
+
+impl CacheMemory {
+    /// The memory used by everything in the cache.
+    pub fn total(&self) -> usize {
+        self.images + self.fonts + self.sounds
+    }
+}
+
+/// The assets loaded so far, kept in the `Context`.
+#[derive(Debug, Default)]
+pub(crate) struct Cache {
+    images: HashMap<path::PathBuf, Image>,
+    /// Fonts, with the size of the file they came from.
+    fonts: HashMap<path::PathBuf, (Font, usize)>,
+    #[cfg(feature = "audio")]
+    sounds: HashMap<path::PathBuf, SoundData>,
+}
+
+impl Cache {
+    pub(crate) fn image(&self, path: &path::Path) -> Option<Image> {
+        self.images.get(path).cloned()
+    }
+
+    pub(crate) fn insert_image(&mut self, path: &path::Path, image: Image) {
+        let _ = self.images.insert(path.to_path_buf(), image);
+    }
+
+    pub(crate) fn font(&self, path: &path::Path) -> Option<Font> {
+        self.fonts.get(path).map(|(font, _)| *font)
+    }
+
+    pub(crate) fn insert_font(&mut self, path: &path::Path, font: Font, bytes: usize) {
+        let _ = self.fonts.insert(path.to_path_buf(), (font, bytes));
+    }
+
+    #[cfg(feature = "audio")]
+    pub(crate) fn sound_data(&self, path: &path::Path) -> Option<SoundData> {
+        self.sounds.get(path).cloned()
+    }
+
+    #[cfg(feature = "audio")]
+    pub(crate) fn insert_sound_data(&mut self, path: &path::Path, data: SoundData) {
+        let _ = self.sounds.insert(path.to_path_buf(), data);
+    }
+
+    pub(crate) fn invalidate(&mut self, path: &path::Path) -> bool {
+        let image = self.images.remove(path).is_some();
+        let font = self.fonts.remove(path).is_some();
+        #[cfg(feature = "audio")]
+        let sound = self.sounds.remove(path).is_some();
+        #[cfg(not(feature = "audio"))]
+        let sound = false;
+        image || font || sound
+    }
+
+    fn clear(&mut self) {
+        self.images.clear();
+        self.fonts.clear();
+        #[cfg(feature = "audio")]
+        self.sounds.clear();
+    }
+
+    pub(crate) fn contains(&self, path: &path::Path) -> bool {
+        #[cfg(feature = "audio")]
+        let sound = self.sounds.contains_key(path);
+        #[cfg(not(feature = "audio"))]
+        let sound = false;
+        self.images.contains_key(path) || self.fonts.contains_key(path) || sound
+    }
+
+    fn memory(&self) -> CacheMemory {
+        CacheMemory {
+            images: self
+                .images
+                .values()
+                .map(|image| usize::from(image.width) * usize::from(image.height) * 4)
+                .sum(),
+            fonts: self.fonts.values().map(|(_, bytes)| bytes).sum(),
+            #[cfg(feature = "audio")]
+            sounds: self.sounds.values().map(|data| data.as_ref().len()).sum(),
+            #[cfg(not(feature = "audio"))]
+            sounds: 0,
+        }
+    }
+}
+
+/// Drops the cached assets loaded from the given path, so they're
+/// loaded again the next time they're asked for.  Returns whether
+/// anything was cached for it.
+///
+/// Images and sound data already handed out keep working, and their
+/// memory is only freed once they're dropped too.  Fonts are never
+/// freed, since ggez can't unload a font once it's loaded.
+pub fn invalidate<P: AsRef<path::Path>>(ctx: &mut Context, path: P) -> bool {
+    ctx.cache.invalidate(path.as_ref())
+}
+
+/// Drops everything in the cache; see [`invalidate()`](fn.invalidate.html).
+pub fn clear(ctx: &mut Context) {
+    ctx.cache.clear()
+}
+
+/// Whether anything loaded from the given path is cached.
+pub fn contains<P: AsRef<path::Path>>(ctx: &Context, path: P) -> bool {
+    ctx.cache.contains(path.as_ref())
+}
+
+/// How much memory the assets in the cache take up.
+pub fn memory(ctx: &Context) -> CacheMemory {
+    ctx.cache.memory()
+}
@@ -5,15 +5,17 @@
 //! which specifies hardware setup stuff, mostly video display settings.
 //!
 //! By default a ggez game will search its resource paths for a `/conf.toml`
-//! file and load values from it when the [`Context`](../struct.Context.html) is created.  This file
-//! must be complete (ie you cannot just fill in some fields and have the
-//! rest be default) and provides a nice way to specify settings that
-//! can be tweaked such as window resolution, multisampling options, etc.
-//! If no file is found, it will create a `Conf` object from the settings
-//! passed to the [`ContextBuilder`](../struct.ContextBuilder.html).
+//! file, or a `/conf.json` or, with the `ron` feature, `/conf.ron` one,
+//! and load values from it when the [`Context`](../struct.Context.html) is created.
+//! The file only needs the settings it changes from the ones passed to the
+//! [`ContextBuilder`](../struct.ContextBuilder.html), and provides a nice
+//! way to specify settings that can be tweaked such as window resolution,
+//! multisampling options, etc.  If no file is found, the settings passed
+//! to the `ContextBuilder` are used as they are.
 
 use std::convert::TryFrom;
 use std::io;
+use std::path::Path;
 
 use crate::error::{GameError, GameResult};
 
@@ -32,6 +34,47 @@ pub enum FullscreenType {
     Desktop,
 }
 
+/// How window sizes and mouse coordinates relate to the monitor's hidpi
+/// scale factor.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, SmartDefault)]
+pub enum DpiPolicy {
+    /// Everything is in physical pixels: window sizes, mouse positions,
+    /// resize events and the default screen coordinates.  On a hidpi
+    /// display things drawn this way look small, so check
+    /// [`graphics::scale_factor()`](../graphics/fn.scale_factor.html) and
+    /// handle [`EventHandler::scale_factor_changed_event()`](../event/trait.EventHandler.html#method.scale_factor_changed_event)
+    /// if that matters.
+    #[default]
+    Physical,
+    /// Window sizes, mouse positions and resize events are in logical
+    /// pixels, which is physical pixels divided by the scale factor.
+    /// The window keeps its logical size when the scale factor changes,
+    /// and the screen coordinates are reset to the window's logical size
+    /// whenever it's resized, so a game draws at the same apparent size
+    /// on any display while the framebuffer stays at full resolution.
+    Logical,
+}
+
+/// What [`event::run()`](../event/fn.run.html) does while the window is
+/// in the background: unfocused, minimized, or suspended on mobile.
+/// Events, including
+/// [`EventHandler::focus_event()`](../event/trait.EventHandler.html#method.focus_event),
+/// are still delivered either way.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, SmartDefault)]
+#[serde(tag = "type", content = "fps")]
+pub enum BackgroundPolicy {
+    /// Keeps updating and drawing as fast as in the foreground.
+    #[default]
+    Run,
+    /// Updates and draws at most this many times a second, and sleeps
+    /// in between.
+    LimitFps(u32),
+    /// Stops calling `update()` and `draw()` until the window comes back,
+    /// only waking up now and then to handle events.  The time spent
+    /// paused isn't counted by the [`timer`](../timer/index.html).
+    Pause,
+}
+
 /// A builder structure containing window settings
 /// that can be set at runtime and changed with [`graphics::set_mode()`](../graphics/fn.set_mode.html).
 ///
@@ -53,15 +96,19 @@ pub enum FullscreenType {
 ///     resizable: false,
 ///     visible: true,
 ///     resize_on_scale_factor_change: false,
+///     refresh_rate: 0,
+///     always_on_top: false,
 /// }
 /// # , WindowMode::default());}
 /// ```
 #[derive(Debug, Copy, Clone, SmartDefault, Serialize, Deserialize, PartialEq)]
 pub struct WindowMode {
-    /// Window width in physical pixels
+    /// Window width in physical pixels, or logical ones with
+    /// [`DpiPolicy::Logical`](enum.DpiPolicy.html#variant.Logical)
     #[default = 800.0]
     pub width: f32,
-    /// Window height in physical pixels
+    /// Window height in physical pixels, or logical ones with
+    /// [`DpiPolicy::Logical`](enum.DpiPolicy.html#variant.Logical)
     #[default = 600.0]
     pub height: f32,
     /// Whether or not to maximize the window
@@ -95,6 +142,9 @@ pub struct WindowMode {
     /// when its hidpi factor changes, i.e. when [`WindowEvent::ScaleFactorChanged`](https://docs.rs/winit/0.25.0/winit/event/enum.WindowEvent.html#variant.ScaleFactorChanged)
     /// is fired.
     ///
+    /// Only applies with [`DpiPolicy::Physical`](enum.DpiPolicy.html#variant.Physical);
+    /// with logical sizes the window always keeps its logical size.
+    ///
     /// You usually want this to be false, since the window suddenly changing size may break your game.
     /// Setting this to true may be desirable if you plan for it and want your window to behave like
     /// windows of other programs when being dragged from one screen to another, for example.
@@ -102,6 +152,17 @@ pub struct WindowMode {
     /// For more context on this take a look at [this conversation](https://github.com/ggez/ggez/pull/949#issuecomment-854731226).
     #[default = false]
     pub resize_on_scale_factor_change: bool,
+    /// Refresh rate in Hz to ask for in true fullscreen mode; 0 means
+    /// the highest one the monitor offers at the chosen resolution.
+    /// See [`graphics::video_modes()`](../graphics/fn.video_modes.html)
+    /// for what's available.
+    #[serde(default)]
+    #[default = 0]
+    pub refresh_rate: u16,
+    /// Whether the window should stay above other windows
+    #[serde(default)]
+    #[default = false]
+    pub always_on_top: bool,
 }
 
 impl WindowMode {
@@ -161,6 +222,18 @@ impl WindowMode {
         self.resize_on_scale_factor_change = resize_on_scale_factor_change;
         self
     }
+
+    /// Set the refresh rate to use in true fullscreen mode.
+    pub fn refresh_rate(mut self, refresh_rate: u16) -> Self {
+        self.refresh_rate = refresh_rate;
+        self
+    }
+
+    /// Set whether the window stays above other windows.
+    pub fn always_on_top(mut self, always_on_top: bool) -> Self {
+        self.always_on_top = always_on_top;
+        self
+    }
 }
 
 /// A builder structure containing window settings
@@ -177,6 +250,8 @@ impl WindowMode {
 ///     vsync: true,
 ///     icon: "".to_owned(),
 ///     srgb: true,
+///     dpi_policy: DpiPolicy::Physical,
+///     background_policy: BackgroundPolicy::Run,
 /// }
 /// # , WindowSetup::default()); }
 /// ```
@@ -200,6 +275,17 @@ pub struct WindowSetup {
     /// handling on the display.
     #[default = true]
     pub srgb: bool,
+    /// Whether sizes and mouse coordinates are in physical or logical
+    /// pixels.
+    #[serde(default)]
+    #[default(DpiPolicy::Physical)]
+    pub dpi_policy: DpiPolicy,
+    /// What to do while the window is unfocused or minimized; it can
+    /// also be changed later with
+    /// [`event::set_background_policy()`](../event/fn.set_background_policy.html).
+    #[serde(default)]
+    #[default(BackgroundPolicy::Run)]
+    pub background_policy: BackgroundPolicy,
 }
 
 impl WindowSetup {
@@ -232,6 +318,18 @@ impl WindowSetup {
         self.srgb = active;
         self
     }
+
+    /// Set the hidpi handling policy.
+    pub fn dpi_policy(mut self, dpi_policy: DpiPolicy) -> Self {
+        self.dpi_policy = dpi_policy;
+        self
+    }
+
+    /// Set what to do while the window is unfocused or minimized.
+    pub fn background_policy(mut self, background_policy: BackgroundPolicy) -> Self {
+        self.background_policy = background_policy;
+        self
+    }
 }
 
 /// Possible backends.
@@ -390,6 +488,67 @@ impl ModuleConf {
     }
 }
 
+/// The formats [`Conf::load()`](struct.Conf.html#method.load) and
+/// [`Conf::save()`](struct.Conf.html#method.save) can read and write.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConfFormat {
+    /// TOML, what `conf.toml` is in.
+    Toml,
+    /// JSON.
+    Json,
+    /// RON, with the `ron` feature.
+    #[cfg(feature = "ron")]
+    Ron,
+}
+
+impl ConfFormat {
+    /// The formats there are, in the order the `conf` file is looked
+    /// for in.
+    pub(crate) fn all() -> Vec<ConfFormat> {
+        #[allow(unused_mut)]
+        let mut formats = vec![ConfFormat::Toml, ConfFormat::Json];
+        #[cfg(feature = "ron")]
+        formats.push(ConfFormat::Ron);
+        formats
+    }
+
+    /// The file extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConfFormat::Toml => "toml",
+            ConfFormat::Json => "json",
+            #[cfg(feature = "ron")]
+            ConfFormat::Ron => "ron",
+        }
+    }
+
+    /// Picks the format from a file name's extension, if it's a known
+    /// one.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ConfFormat> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        Self::all()
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+}
+
+/// Turns a JSON error into a `GameError`.
+fn json_error(e: serde_json::Error) -> GameError {
+    GameError::ConfigError(format!("JSON error: {}", e))
+}
+
+/// Merges `changes` over `value`, objects field by field.
+fn merge_values(value: &mut serde_json::Value, changes: serde_json::Value) {
+    match (value, changes) {
+        (serde_json::Value::Object(value), serde_json::Value::Object(changes)) => {
+            for (key, change) in changes {
+                merge_values(value.entry(key).or_insert(serde_json::Value::Null), change);
+            }
+        }
+        (value, change) => *value = change,
+    }
+}
+
 /// A structure containing configuration data
 /// for the game engine.
 ///
@@ -402,6 +561,7 @@ impl ModuleConf {
 ///     window_mode: WindowMode::default(),
 ///     window_setup: WindowSetup::default(),
 ///     backend: Backend::default(),
+///     backend_fallbacks: vec![],
 ///     modules: ModuleConf::default(),
 /// }
 /// # , Conf::default()); }
@@ -414,6 +574,12 @@ pub struct Conf {
     pub window_setup: WindowSetup,
     /// Graphics backend configuration
     pub backend: Backend,
+    /// Backends to try in order when `backend` can't be had, such as
+    /// OpenGL 3.2 and then OpenGL ES 3.0 after asking for OpenGL 4.5.
+    /// [`graphics::backend()`](../graphics/fn.backend.html) says which
+    /// one was picked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backend_fallbacks: Vec<Backend>,
     /// Which modules to enable.
     pub modules: ModuleConf,
 }
@@ -441,6 +607,50 @@ impl Conf {
         Ok(())
     }
 
+    /// Loads a `Conf` in the given format from the given `Read`.  The
+    /// file only needs the settings that differ from `Conf::default()`;
+    /// see [`merge()`](#method.merge).
+    pub fn load<R: io::Read>(file: &mut R, format: ConfFormat) -> GameResult<Conf> {
+        Conf::default().merge(file, format)
+    }
+
+    /// Reads settings in the given format from the given `Read` over
+    /// this `Conf`, so a file can change just a few of them.  Tables and
+    /// objects are merged field by field, and anything else in the file
+    /// replaces what was there.
+    pub fn merge<R: io::Read>(self, file: &mut R, format: ConfFormat) -> GameResult<Conf> {
+        let mut s = String::new();
+        let _ = file.read_to_string(&mut s)?;
+        let changes: serde_json::Value = match format {
+            ConfFormat::Toml => toml::from_str(&s)?,
+            ConfFormat::Json => serde_json::from_str(&s).map_err(json_error)?,
+            #[cfg(feature = "ron")]
+            ConfFormat::Ron => ron::from_str(&s)
+                .map_err(|e| GameError::ConfigError(format!("RON decode error: {}", e)))?,
+        };
+        let mut merged = serde_json::to_value(&self).map_err(json_error)?;
+        merge_values(&mut merged, changes);
+        serde_json::from_value(merged).map_err(json_error)
+    }
+
+    /// Saves the `Conf` to the given `Write`, in the given format.
+    pub fn save<W: io::Write>(&self, file: &mut W, format: ConfFormat) -> GameResult {
+        match format {
+            ConfFormat::Toml => self.to_toml_file(file),
+            ConfFormat::Json => {
+                serde_json::to_writer_pretty(&mut *file, self).map_err(json_error)?;
+                Ok(())
+            }
+            #[cfg(feature = "ron")]
+            ConfFormat::Ron => {
+                let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+                    .map_err(|e| GameError::ConfigError(format!("RON encode error: {}", e)))?;
+                file.write_all(s.as_bytes())?;
+                Ok(())
+            }
+        }
+    }
+
     /// Sets the window mode
     pub fn window_mode(mut self, window_mode: WindowMode) -> Self {
         self.window_mode = window_mode;
@@ -453,6 +663,12 @@ impl Conf {
         self
     }
 
+    /// Sets the backends to fall back on, in order
+    pub fn backend_fallbacks(mut self, fallbacks: Vec<Backend>) -> Self {
+        self.backend_fallbacks = fallbacks;
+        self
+    }
+
     /// Sets the backend
     pub fn modules(mut self, modules: ModuleConf) -> Self {
         self.modules = modules;
@@ -475,4 +691,115 @@ mod tests {
         let c2 = conf::Conf::from_toml_file(&mut reader).unwrap();
         assert_eq!(c1, c2);
     }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn headless_partial_confs() {
+        let base = conf::Conf::new().window_mode(conf::WindowMode::default().dimensions(1.0, 2.0));
+
+        let mut json = br#"{ "window_mode": { "width": 640.0 } }"#.as_ref();
+        let c = base
+            .clone()
+            .merge(&mut json, conf::ConfFormat::Json)
+            .unwrap();
+        assert_eq!(c.window_mode.width, 640.0);
+        assert_eq!(c.window_mode.height, 2.0);
+
+        let mut toml = b"[window_setup]\ntitle = \"Partial\"\n".as_ref();
+        let c = conf::Conf::load(&mut toml, conf::ConfFormat::Toml).unwrap();
+        assert_eq!(c.window_setup.title, "Partial");
+        assert_eq!(c.window_mode, conf::WindowMode::default());
+
+        let mut writer = Vec::new();
+        base.save(&mut writer, conf::ConfFormat::Json).unwrap();
+        let mut reader = writer.as_slice();
+        let loaded = conf::Conf::load(&mut reader, conf::ConfFormat::Json).unwrap();
+        assert_eq!(base, loaded);
+        assert_eq!(
+            conf::ConfFormat::from_path("/conf.json"),
+            Some(conf::ConfFormat::Json)
+        );
+    }
+
+    #[test]
+    fn headless_backend_fallbacks_round_trip() {
+        let c1 = conf::Conf::new()
+            .backend(conf::Backend::default().version(4, 5))
+            .backend_fallbacks(vec![
+                conf::Backend::default(),
+                conf::Backend::default().gles().version(3, 0),
+            ]);
+        let mut writer = Vec::new();
+        c1.to_toml_file(&mut writer).unwrap();
+        let mut reader = writer.as_slice();
+        let c2 = conf::Conf::from_toml_file(&mut reader).unwrap();
+        assert_eq!(c1, c2);
+    }
+
+    #[test]
+    fn headless_background_policy_round_trip() {
+        for &policy in &[
+            conf::BackgroundPolicy::Run,
+            conf::BackgroundPolicy::LimitFps(10),
+            conf::BackgroundPolicy::Pause,
+        ] {
+            let c1 = conf::Conf {
+                window_setup: conf::WindowSetup::default().background_policy(policy),
+                ..conf::Conf::default()
+            };
+            let mut writer = Vec::new();
+            c1.to_toml_file(&mut writer).unwrap();
+            let mut reader = writer.as_slice();
+            let c2 = conf::Conf::from_toml_file(&mut reader).unwrap();
+            assert_eq!(c1, c2);
+        }
+    }
+
+    /// A `conf.toml` saved by an older ggez, before the window settings
+    /// added since then, still has to load with those left at their
+    /// defaults.
+    #[test]
+    fn headless_old_conf_file() {
+        let mut toml = br#"
+[window_mode]
+width = 1024.0
+height = 768.0
+maximized = false
+fullscreen_type = "Windowed"
+borderless = false
+min_width = 0.0
+min_height = 0.0
+max_width = 0.0
+max_height = 0.0
+resizable = true
+visible = true
+resize_on_scale_factor_change = false
+
+[window_setup]
+title = "Old game"
+samples = "One"
+vsync = true
+icon = ""
+srgb = true
+
+[backend]
+type = "OpenGL"
+major = 3
+minor = 2
+
+[modules]
+gamepad = true
+audio = true
+"#
+        .as_ref();
+        let c = conf::Conf::from_toml_file(&mut toml).unwrap();
+        let expected = conf::Conf {
+            window_mode: conf::WindowMode::default()
+                .dimensions(1024.0, 768.0)
+                .resizable(true),
+            window_setup: conf::WindowSetup::default().title("Old game"),
+            ..conf::Conf::default()
+        };
+        assert_eq!(c, expected);
+    }
 }
@@ -1,14 +1,19 @@
 use std::fmt;
+#[cfg(feature = "audio")]
+use std::sync::{Arc, Mutex};
 /// We re-export winit so it's easy for people to use the same version as we are
 /// without having to mess around figuring it out.
 pub use winit;
 
+#[cfg(feature = "audio")]
 use crate::audio;
+use crate::cache;
 use crate::conf;
 use crate::error::GameResult;
-use crate::filesystem::Filesystem;
+use crate::filesystem::{self, Filesystem};
 use crate::graphics;
-use crate::input::{gamepad, keyboard, mouse};
+use crate::input::{gamepad, gestures, keyboard, mapping, mouse, pen, touch, virtual_pad};
+use crate::tasks;
 use crate::timer;
 
 /// A `Context` is an object that holds on to global resources.
@@ -41,14 +46,58 @@ pub struct Context {
     pub(crate) gfx_context: crate::graphics::context::GraphicsContext,
     /// Timer state
     pub timer_context: timer::TimeContext,
+    /// Scheduled timers
+    pub(crate) scheduler: timer::Scheduler,
+    /// Spawned futures
+    pub(crate) executor: tasks::Executor,
     /// Audio context
+    #[cfg(feature = "audio")]
     pub audio_context: Box<dyn audio::AudioContext>,
+    /// Audio mixer buses
+    #[cfg(feature = "audio")]
+    pub(crate) audio_mixer: audio::Mixer,
+    /// Where spatial sounds are heard from
+    #[cfg(feature = "audio")]
+    pub(crate) audio_listener: Arc<Mutex<audio::Listener>>,
+    /// Whether sound moved to another output device by itself
+    #[cfg(feature = "audio")]
+    pub(crate) audio_device_changed: bool,
+    /// Assets loaded through the cache
+    pub(crate) cache: cache::Cache,
     /// Keyboard context
     pub keyboard_context: keyboard::KeyboardContext,
     /// Mouse context
     pub mouse_context: mouse::MouseContext,
+    /// Touch context
+    pub touch_context: touch::TouchContext,
+    /// Pen context
+    pub pen_context: pen::PenContext,
+    /// Gesture context
+    pub gesture_context: gestures::GestureContext,
     /// Gamepad context
     pub gamepad_context: Box<dyn gamepad::GamepadContext>,
+    /// Gamepad axis deadzones and curves
+    pub(crate) axis_filters: gamepad::AxisFilters,
+    /// Gamepad buttons pressed and released this frame
+    pub(crate) gamepad_edges: gamepad::ButtonEdges,
+    /// Stable ids of the connected gamepads
+    pub(crate) gamepad_slots: gamepad::GamepadSlots,
+    /// Bindings of named actions and axes to inputs
+    pub(crate) input_map: mapping::InputMap,
+    /// The on-screen gamepad, if it's on
+    pub(crate) virtual_pad: Option<virtual_pad::VirtualPad>,
+    /// Whether the app is suspended, and shouldn't draw
+    pub(crate) suspended: bool,
+    /// Whether the window has the keyboard focus
+    pub(crate) focused: bool,
+    /// Whether the window is minimized
+    pub(crate) minimized: bool,
+    /// What `event::run()` does while the window is in the background
+    pub(crate) background_policy: conf::BackgroundPolicy,
+    /// Whether `event::run()` catches panics in the game's callbacks
+    pub(crate) catch_panics: bool,
+    /// Filters events before they reach the `EventHandler`
+    pub(crate) event_filters: crate::event::EventFilters,
 
     /// The Conf object the Context was created with.
     /// It's here just so that we can see the original settings,
@@ -77,31 +126,52 @@ impl Context {
     fn from_conf(
         conf: conf::Conf,
         mut fs: Filesystem,
+        audio_device: Option<&str>,
+        gamepad_mappings: &str,
     ) -> GameResult<(Context, winit::event_loop::EventLoop<()>)> {
         let debug_id = DebugId::new();
+        #[cfg(feature = "audio")]
         let audio_context: Box<dyn audio::AudioContext> = if conf.modules.audio {
-            Box::new(audio::RodioAudioContext::new()?)
+            Box::new(audio::RodioAudioContext::new(audio_device)?)
         } else {
             Box::new(audio::NullAudioContext::default())
         };
+        #[cfg(not(feature = "audio"))]
+        let _ = audio_device;
         let events_loop = winit::event_loop::EventLoop::new();
         let timer_context = timer::TimeContext::new();
         let backend_spec = graphics::GlBackendSpec::from(conf.backend);
+        let fallback_specs: Vec<_> = conf
+            .backend_fallbacks
+            .iter()
+            .map(|&backend| graphics::GlBackendSpec::from(backend))
+            .collect();
         let graphics_context = graphics::context::GraphicsContext::new(
             &mut fs,
             &events_loop,
             &conf.window_setup,
             conf.window_mode,
             backend_spec,
+            &fallback_specs,
             debug_id,
         )?;
         let mouse_context = mouse::MouseContext::new();
         let keyboard_context = keyboard::KeyboardContext::new();
+        let touch_context = touch::TouchContext::new();
+        let pen_context = pen::PenContext::new();
+        let gesture_context = gestures::GestureContext::new();
         let gamepad_context: Box<dyn gamepad::GamepadContext> = if conf.modules.gamepad {
-            Box::new(gamepad::GilrsGamepadContext::new()?)
+            Box::new(gamepad::GilrsGamepadContext::new(gamepad_mappings)?)
         } else {
             Box::new(gamepad::NullGamepadContext::default())
         };
+        let mut gamepad_slots = gamepad::GamepadSlots::default();
+        if conf.modules.gamepad {
+            for (id, pad) in gamepad_context.gamepads() {
+                let _ = gamepad_slots.connect(id, pad.uuid());
+            }
+        }
+        let background_policy = conf.window_setup.background_policy;
 
         let ctx = Context {
             conf,
@@ -109,10 +179,34 @@ impl Context {
             gfx_context: graphics_context,
             continuing: true,
             timer_context,
+            scheduler: timer::Scheduler::new(),
+            executor: tasks::Executor::new(),
+            #[cfg(feature = "audio")]
             audio_context,
+            #[cfg(feature = "audio")]
+            audio_mixer: audio::Mixer::default(),
+            #[cfg(feature = "audio")]
+            audio_listener: Arc::new(Mutex::new(audio::Listener::default())),
+            #[cfg(feature = "audio")]
+            audio_device_changed: false,
+            cache: cache::Cache::default(),
             keyboard_context,
             gamepad_context,
+            axis_filters: gamepad::AxisFilters::default(),
+            gamepad_edges: gamepad::ButtonEdges::default(),
+            gamepad_slots,
+            input_map: mapping::InputMap::default(),
+            virtual_pad: None,
+            suspended: false,
+            focused: true,
+            minimized: false,
+            background_policy,
+            catch_panics: false,
+            event_filters: crate::event::EventFilters::default(),
             mouse_context,
+            touch_context,
+            pen_context,
+            gesture_context,
 
             debug_id,
         };
@@ -122,6 +216,7 @@ impl Context {
 }
 
 use std::borrow::Cow;
+use std::io::Read;
 use std::path;
 
 /// A builder object for creating a [`Context`](struct.Context.html).
@@ -132,7 +227,11 @@ pub struct ContextBuilder {
     pub(crate) conf: conf::Conf,
     pub(crate) paths: Vec<path::PathBuf>,
     pub(crate) memory_zip_files: Vec<Cow<'static, [u8]>>,
+    pub(crate) embedded_files: Vec<(&'static str, &'static [u8])>,
     pub(crate) load_conf_file: bool,
+    pub(crate) audio_device: Option<String>,
+    pub(crate) path_case: filesystem::PathCase,
+    pub(crate) gamepad_mappings: String,
 }
 
 impl ContextBuilder {
@@ -144,7 +243,15 @@ impl ContextBuilder {
             conf: conf::Conf::default(),
             paths: vec![],
             memory_zip_files: vec![],
+            embedded_files: vec![],
             load_conf_file: true,
+            audio_device: None,
+            path_case: if cfg!(debug_assertions) {
+                filesystem::PathCase::Insensitive
+            } else {
+                filesystem::PathCase::Strict
+            },
+            gamepad_mappings: String::new(),
         }
     }
 
@@ -212,6 +319,14 @@ impl ContextBuilder {
         self
     }
 
+    /// Add files embedded in the executable, as pairs of absolute paths
+    /// and contents, to be searched for resources; see
+    /// [`filesystem::mount_embedded()`](../filesystem/fn.mount_embedded.html).
+    pub fn add_embedded_files(mut self, files: &'static [(&'static str, &'static [u8])]) -> Self {
+        self.embedded_files.extend_from_slice(files);
+        self
+    }
+
     /// Specifies whether or not to load the `conf.toml` file if it
     /// exists and use its settings to override the provided values.
     /// Defaults to `true` which is usually what you want, but being
@@ -221,9 +336,41 @@ impl ContextBuilder {
         self
     }
 
+    /// Sets the name of the audio output device to play sound on, from
+    /// [`audio::output_devices()`](../audio/fn.output_devices.html).  If
+    /// it isn't there the system's default is used, which is also the
+    /// default.  Without the `audio` feature there's no sound, so it
+    /// does nothing.
+    pub fn audio_device(mut self, name: &str) -> Self {
+        self.audio_device = Some(name.to_string());
+        self
+    }
+
+    /// Sets how the case of paths is matched when looking up files;
+    /// see [`filesystem::PathCase`](../filesystem/enum.PathCase.html).
+    /// Defaults to `PathCase::Insensitive` in debug builds, so paths
+    /// with the wrong case still work and get warned about, and
+    /// `PathCase::Strict` in release builds.
+    pub fn path_case(mut self, path_case: filesystem::PathCase) -> Self {
+        self.path_case = path_case;
+        self
+    }
+
+    /// Adds SDL_GameControllerDB-style gamepad mappings, one per line,
+    /// for controllers that the mappings built into `gilrs` don't know or
+    /// get wrong.  A `/gamecontrollerdb.txt` file in the resource
+    /// directories is added too, as are the mappings in the
+    /// `SDL_GAMECONTROLLERCONFIG` environment variable.
+    pub fn add_gamepad_mappings(mut self, mappings: &str) -> Self {
+        self.gamepad_mappings.push_str(mappings);
+        self.gamepad_mappings.push('\n');
+        self
+    }
+
     /// Build the `Context`.
     pub fn build(self) -> GameResult<(Context, winit::event_loop::EventLoop<()>)> {
         let mut fs = Filesystem::new(self.game_id.as_ref(), self.author.as_ref())?;
+        fs.set_path_case(self.path_case);
 
         for path in &self.paths {
             fs.mount(path, true);
@@ -233,13 +380,25 @@ impl ContextBuilder {
             fs.add_zip_file(std::io::Cursor::new(zipfile_bytes))?;
         }
 
+        if !self.embedded_files.is_empty() {
+            fs.add_embedded_files(self.embedded_files);
+        }
+
         let config = if self.load_conf_file {
-            fs.read_config().unwrap_or(self.conf)
+            fs.read_config_over(self.conf.clone()).unwrap_or(self.conf)
         } else {
             self.conf
         };
 
-        Context::from_conf(config, fs)
+        let mut gamepad_mappings = self.gamepad_mappings;
+        let mappings_path = path::Path::new(gamepad::MAPPINGS_NAME);
+        if fs.is_file(mappings_path) {
+            let _ = fs
+                .open(mappings_path)?
+                .read_to_string(&mut gamepad_mappings)?;
+        }
+
+        Context::from_conf(config, fs, self.audio_device.as_deref(), &gamepad_mappings)
     }
 }
 
@@ -3,7 +3,9 @@ use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
 
+#[cfg(feature = "audio")]
 use rodio::decoder::DecoderError;
+#[cfg(feature = "audio")]
 use rodio::PlayError;
 
 /// An enum containing all kinds of game framework errors.
@@ -37,6 +39,9 @@ pub enum GameError {
     VideoError(String),
     /// Something went wrong compiling shaders
     ShaderProgramError(gfx::shade::ProgramError),
+    /// A shader failed to compile or link; contains the driver's log parsed
+    /// into line numbers and messages where possible.
+    ShaderCompileError(crate::graphics::ShaderCompileError),
     /// Something went wrong with the `gilrs` gamepad-input library.
     GamepadError(String),
     /// Something went wrong with the `lyon` shape-tesselation library.
@@ -60,6 +65,7 @@ impl fmt::Display for GameError {
                 s, paths
             ),
             GameError::WindowError(ref e) => write!(f, "Window creation error: {}", e),
+            GameError::ShaderCompileError(ref e) => write!(f, "{}", e),
             GameError::CustomError(ref s) => write!(f, "Custom error: {}", s),
             _ => write!(f, "GameError {:?}", self),
         }
@@ -72,6 +78,7 @@ impl Error for GameError {
             GameError::WindowCreationError(ref e) => Some(&**e),
             GameError::IOError(ref e) => Some(&**e),
             GameError::ShaderProgramError(ref e) => Some(e),
+            GameError::ShaderCompileError(ref e) => Some(e),
             _ => None,
         }
     }
@@ -108,6 +115,7 @@ impl From<zip::result::ZipError> for GameError {
     }
 }
 
+#[cfg(feature = "audio")]
 impl From<DecoderError> for GameError {
     fn from(e: DecoderError) -> GameError {
         let errstr = format!("Audio decoder error: {:?}", e);
@@ -115,6 +123,7 @@ impl From<DecoderError> for GameError {
     }
 }
 
+#[cfg(feature = "audio")]
 impl From<PlayError> for GameError {
     fn from(e: PlayError) -> GameError {
         let errstr = format!("Audio playing error: {:?}", e);
@@ -12,8 +12,12 @@
 
 use winit::{self, dpi};
 
+/// Identifies an input device, such as one of several mice or keyboards.
+pub use winit::event::DeviceId;
 /// A mouse button.
 pub use winit::event::MouseButton;
+/// Whether a touch started, moved or ended.
+pub use winit::event::TouchPhase;
 
 /// An analog axis of some device (gamepad thumbstick, joystick...).
 pub use gilrs::Axis;
@@ -24,17 +28,29 @@ pub use gilrs::Button;
 pub mod winit_event {
     pub use super::winit::event::{
         DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, MouseScrollDelta,
-        TouchPhase, WindowEvent,
+        StartCause, TouchPhase, WindowEvent,
     };
 }
-pub use crate::input::gamepad::GamepadId;
-pub use crate::input::keyboard::{KeyCode, KeyMods};
+pub use crate::input::gamepad::{GamepadId, StableGamepadId};
+pub use crate::input::gestures::Gesture;
+pub use crate::input::keyboard::{KeyCode, KeyMods, ScanCode};
+pub use crate::input::mouse::ScrollDelta;
+pub use crate::input::pen::Pen;
 
 use self::winit_event::*;
 /// `winit` event loop.
 pub use winit::event_loop::{ControlFlow, EventLoop};
 
+use crate::conf::{BackgroundPolicy, DpiPolicy};
 use crate::context::Context;
+use crate::error::GameError;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+/// How often `run()` wakes up to handle events while it's paused in
+/// the background.
+const BACKGROUND_PAUSE_WAKEUP: Duration = Duration::from_millis(100);
 
 /// Used in [`EventHandler::on_error()`](trait.EventHandler.html#method.on_error)
 /// to specify where an error originated
@@ -57,14 +73,20 @@ pub enum ErrorOrigin {
 /// default exit the game if the escape key is pressed.  Just
 /// override the methods you want to use.
 ///
-/// For the error type simply choose [`GameError`](../error/enum.GameError.html),
-/// or something more generic, if your situation requires it.
-pub trait EventHandler<E>
+/// The error type defaults to [`GameError`](../error/enum.GameError.html).
+/// A game with its own error type can use that instead; if it implements
+/// `From<GameError>`, `?` works on ggez's functions in the callbacks, and
+/// the errors reach [`on_error()`](#method.on_error) as they were.  See
+/// the `custom_error` example.
+pub trait EventHandler<E = GameError>
 where
     E: std::error::Error,
 {
     /// Called upon each logic update to the game.
     /// This should be where the game's logic takes place.
+    /// It's called once per frame, or at a fixed rate if
+    /// [`timer::set_fixed_update_rate()`](../timer/fn.set_fixed_update_rate.html)
+    /// is on.
     fn update(&mut self, _ctx: &mut Context) -> Result<(), E>;
 
     /// Called to do the drawing of your game.
@@ -95,7 +117,9 @@ where
     }
 
     /// The mouse was moved; it provides both absolute x and y coordinates in the window,
-    /// and relative x and y coordinates compared to its last position.
+    /// and relative x and y coordinates compared to its last position.  In
+    /// [relative mode](../input/mouse/fn.set_relative_mode.html), it's called with
+    /// the raw motion of the mouse instead.
     fn mouse_motion_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32, _dx: f32, _dy: f32) {}
 
     /// mouse entered or left window area
@@ -103,9 +127,35 @@ where
 
     /// The mousewheel was scrolled, vertically (y, positive away from and negative toward the user)
     /// or horizontally (x, positive to the right and negative to the left).
+    /// Lines and pixels come in alike, and pixels are always logical; see
+    /// `mouse_scroll_event()` to tell them apart.
     fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32) {}
 
-    /// A keyboard button was pressed.
+    /// The mousewheel or a trackpad was scrolled, by lines or by precise
+    /// pixels.  Comes along with `mouse_wheel_event()`, so only handle one
+    /// of them.  Unlike there, scrolling a wheel with shift held scrolls
+    /// horizontally, as it does on macOS already, and pixels follow the
+    /// [`DpiPolicy`](../conf/enum.DpiPolicy.html).
+    fn mouse_scroll_event(&mut self, _ctx: &mut Context, _delta: ScrollDelta) {}
+
+    /// A finger touched, moved on or left the touchscreen; `id` tells
+    /// the fingers apart while they're on it.  See
+    /// [`input::touch`](../input/touch/index.html) for the touches
+    /// active right now.
+    fn touch_event(&mut self, _ctx: &mut Context, _phase: TouchPhase, _id: u64, _x: f32, _y: f32) {}
+
+    /// A tap, long press, pinch or swipe was recognized from touches;
+    /// see [`input::gestures`](../input/gestures/index.html).
+    fn gesture_event(&mut self, _ctx: &mut Context, _gesture: Gesture) {}
+
+    /// A pen touched, moved on or left the screen or tablet, with how
+    /// hard it's pressed; see [`input::pen`](../input/pen/index.html).
+    /// Comes along with `touch_event()`.
+    fn pen_event(&mut self, _ctx: &mut Context, _pen: Pen) {}
+
+    /// A keyboard button was pressed.  `keycode` is the key the keyboard
+    /// layout says it is, and `scancode` is where it is on the keyboard;
+    /// see [`input::keyboard`](../input/keyboard/index.html).
     ///
     /// The default implementation of this will call `ggez::event::quit()`
     /// when the escape key is pressed.  If you override this with
@@ -115,6 +165,7 @@ where
         &mut self,
         ctx: &mut Context,
         keycode: KeyCode,
+        _scancode: ScanCode,
         _keymods: KeyMods,
         _repeat: bool,
     ) {
@@ -124,12 +175,63 @@ where
     }
 
     /// A keyboard button was released.
-    fn key_up_event(&mut self, _ctx: &mut Context, _keycode: KeyCode, _keymods: KeyMods) {}
+    fn key_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        _keycode: KeyCode,
+        _scancode: ScanCode,
+        _keymods: KeyMods,
+    ) {
+    }
 
     /// A unicode character was received, usually from keyboard input.
     /// This is the intended way of facilitating text input.
+    ///
+    /// A character typed with a key comes after that key's
+    /// `key_down_event()`, and before its `key_up_event()`.  Characters
+    /// repeat as the system's settings say, whatever
+    /// [`keyboard::set_key_repeat()`](../input/keyboard/fn.set_key_repeat.html)
+    /// is set to.
     fn text_input_event(&mut self, _ctx: &mut Context, _character: char) {}
 
+    /// A key was pressed or released on a particular keyboard, for
+    /// routing several keyboards on one computer to different players.
+    /// Comes straight from the device, so it's sent even while the window
+    /// isn't focused, and isn't sent at all on platforms
+    /// without raw input, like the web and mobile; telling keyboards apart
+    /// works on Windows and on X11.
+    fn raw_key_event(
+        &mut self,
+        _ctx: &mut Context,
+        _device: DeviceId,
+        _keycode: Option<KeyCode>,
+        _scancode: ScanCode,
+        _pressed: bool,
+    ) {
+    }
+
+    /// A button was pressed or released on a particular mouse; see
+    /// `raw_key_event()`.  `button` is the platform's number for it.
+    fn raw_mouse_button_event(
+        &mut self,
+        _ctx: &mut Context,
+        _device: DeviceId,
+        _button: u32,
+        _pressed: bool,
+    ) {
+    }
+
+    /// A particular mouse moved, by its raw motion rather than the
+    /// cursor's; see `raw_key_event()`.
+    fn raw_mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        _device: DeviceId,
+        _dx: f32,
+        _dy: f32,
+    ) {
+    }
+
     /// A gamepad button was pressed; `id` identifies which gamepad.
     /// Use [`input::gamepad()`](../input/fn.gamepad.html) to get more info about
     /// the gamepad.
@@ -142,13 +244,59 @@ where
 
     /// A gamepad axis moved; `id` identifies which gamepad.
     /// Use [`input::gamepad()`](../input/fn.gamepad.html) to get more info about
-    /// the gamepad.
+    /// the gamepad.  The value has the axis' deadzone and curve applied; see
+    /// [`gamepad::set_deadzone()`](../input/gamepad/fn.set_deadzone.html).
     fn gamepad_axis_event(&mut self, _ctx: &mut Context, _axis: Axis, _value: f32, _id: GamepadId) {
     }
 
+    /// A gamepad was plugged in.  `stable_id` stays the same when it's
+    /// unplugged and plugged back in, unlike `id`; see
+    /// [`StableGamepadId`](../input/gamepad/struct.StableGamepadId.html).
+    /// Gamepads connected when the game starts don't get this; see
+    /// [`gamepad::connected_gamepads()`](../input/gamepad/fn.connected_gamepads.html).
+    fn gamepad_connected(
+        &mut self,
+        _ctx: &mut Context,
+        _id: GamepadId,
+        _stable_id: StableGamepadId,
+    ) {
+    }
+
+    /// A gamepad was unplugged.
+    fn gamepad_disconnected(
+        &mut self,
+        _ctx: &mut Context,
+        _id: GamepadId,
+        _stable_id: StableGamepadId,
+    ) {
+    }
+
+    /// A timer, or a step of a sequence, started on the
+    /// [`timer::Scheduler`](../timer/struct.Scheduler.html) fired the
+    /// given tag.  Called just before `update()`.
+    fn timer_event(&mut self, _ctx: &mut Context, _tag: &str) {}
+
     /// Called when the window is shown or hidden.
     fn focus_event(&mut self, _ctx: &mut Context, _gained: bool) {}
 
+    /// Called when the app is suspended, which on Android and iOS means
+    /// it was sent to the background.  `update()` and `draw()` aren't
+    /// called again until it resumes, and the window's surface may be
+    /// destroyed meanwhile, so this is the place to pause the game and
+    /// save anything worth keeping; the OS may kill a suspended app
+    /// without warning.
+    fn suspend_event(&mut self, _ctx: &mut Context) {}
+
+    /// Called when the app comes back after being suspended, just before
+    /// `update()` and `draw()` are called again.  The time spent
+    /// suspended isn't counted by the [`timer`](../timer/index.html).
+    ///
+    /// The graphics context isn't recreated on resume.  On platforms that
+    /// destroy the window's surface and GL context while suspended,
+    /// ggez can't draw again afterwards, and reloading images or meshes
+    /// here won't bring it back.
+    fn resume_event(&mut self, _ctx: &mut Context) {}
+
     /// Called upon a quit event.  If it returns true,
     /// the game does not exit (the quit event is cancelled).
     fn quit_event(&mut self, _ctx: &mut Context) -> bool {
@@ -156,15 +304,166 @@ where
         false
     }
 
+    /// Called when the window's close button is clicked, or the OS asks
+    /// it to close some other way.  Returning true keeps it open, so the
+    /// game can ask "save before quitting?" first and
+    /// [`quit()`](fn.quit.html) itself afterwards.  By default it leaves
+    /// the choice to [`quit_event()`](#method.quit_event).
+    fn close_requested(&mut self, ctx: &mut Context) -> bool {
+        self.quit_event(ctx)
+    }
+
+    /// Called when the window is moved, with the new position of its
+    /// top left corner on the desktop.
+    fn window_moved(&mut self, _ctx: &mut Context, _x: f32, _y: f32) {}
+
+    /// Called when the window is minimized, for pausing the game.
+    /// `winit` only says so on Windows, where the window is resized to
+    /// nothing; `resize_event()` is called too.
+    fn window_minimized(&mut self, _ctx: &mut Context) {}
+
+    /// Called when the window comes back after being minimized.
+    fn window_restored(&mut self, _ctx: &mut Context) {}
+
     /// Called when the user resizes the window, or when it is resized
     /// via [`graphics::set_mode()`](../graphics/fn.set_mode.html).
     fn resize_event(&mut self, _ctx: &mut Context, _width: f32, _height: f32) {}
 
-    /// Something went wrong, causing a `GameError`.
+    /// Called when the window's hidpi scale factor changes, for instance
+    /// when it's dragged to a monitor with a different one.  See
+    /// [`conf::DpiPolicy`](../conf/enum.DpiPolicy.html) for how this
+    /// affects sizes and coordinates.
+    fn scale_factor_changed_event(&mut self, _ctx: &mut Context, _scale_factor: f32) {}
+
+    /// A file was dragged onto the window and dropped there.  When
+    /// several are dropped at once, this is called once for each.
+    fn file_dropped(&mut self, _ctx: &mut Context, _path: &std::path::Path) {}
+
+    /// A file is being dragged over the window, so the game can show it
+    /// would accept it.  Followed by either `file_dropped()` or
+    /// `file_hover_cancelled()`.
+    fn file_hovered(&mut self, _ctx: &mut Context, _path: &std::path::Path) {}
+
+    /// The files being dragged over the window left it without being
+    /// dropped.
+    fn file_hover_cancelled(&mut self, _ctx: &mut Context) {}
+
+    /// A resource file was added, changed or removed, while resources
+    /// are watched with
+    /// [`filesystem::watch_resources()`](../filesystem/fn.watch_resources.html).
+    /// Load it again here to see the change without restarting.
+    fn resource_changed(&mut self, _ctx: &mut Context, _path: &std::path::Path) {}
+
+    /// Sound moved to another output device by itself, because the
+    /// one it was playing on went away or the system's default changed.
+    /// Sounds that were playing carry on on the new device, whose name
+    /// [`audio::output_device()`](../audio/fn.output_device.html) gives.
+    fn audio_device_changed(&mut self, _ctx: &mut Context) {}
+
+    /// Called once when [`run()`](fn.run.html) ends, whether the game
+    /// [quit](fn.quit.html), the window was closed or an error stopped
+    /// it, while graphics, audio and everything else in the `Context` are
+    /// still working.  The place to save the game, flush analytics and
+    /// let go of hardware.  A loop driven by [`step()`](fn.step.html)
+    /// should call it itself when it's done.
+    fn shutdown(&mut self, _ctx: &mut Context) {}
+
+    /// `update()` or `draw()` returned an error, which is given here as it
+    /// was returned.
     /// If this returns true, the error was fatal, so the event loop ends, aborting the game.
     fn on_error(&mut self, _ctx: &mut Context, _origin: ErrorOrigin, _e: E) -> bool {
         true
     }
+
+    /// A callback panicked, while [`run()`](fn.run.html) was
+    /// [catching panics](fn.set_catch_panics.html).  This is the last
+    /// chance to save the player's progress or switch to a crash screen:
+    /// if it returns true, the rest of that frame is skipped and the game
+    /// goes on, and otherwise the panic carries on as if it had never
+    /// been caught.
+    ///
+    /// Whatever the panicking callback was in the middle of is left
+    /// half done, so a game that goes on should be careful what it
+    /// trusts; see [`std::panic::catch_unwind()`](https://doc.rust-lang.org/std/panic/fn.catch_unwind.html).
+    fn on_panic(&mut self, _ctx: &mut Context, _report: &PanicReport) -> bool {
+        false
+    }
+}
+
+/// A panic caught by [`run()`](fn.run.html), given to
+/// [`EventHandler::on_panic()`](trait.EventHandler.html#method.on_panic)
+/// along with how the game was running up to it.
+#[derive(Debug)]
+pub struct PanicReport {
+    /// The panic's message, if it had one.
+    pub message: Option<String>,
+    /// What the panic was called with, as
+    /// [`std::panic::catch_unwind()`](https://doc.rust-lang.org/std/panic/fn.catch_unwind.html)
+    /// returns it.
+    pub payload: Box<dyn Any + Send>,
+    /// How many frames had been run; see
+    /// [`timer::ticks()`](../timer/fn.ticks.html).
+    pub frame: usize,
+    /// The average frames per second; see
+    /// [`timer::fps()`](../timer/fn.fps.html).
+    pub fps: f64,
+    /// How long the last 200 frames took, oldest first.
+    pub frame_times: Vec<Duration>,
+}
+
+impl PanicReport {
+    fn new(ctx: &Context, payload: Box<dyn Any + Send>) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| (*s).to_owned())
+            .or_else(|| payload.downcast_ref::<String>().cloned());
+        PanicReport {
+            message,
+            payload,
+            frame: crate::timer::ticks(ctx),
+            fps: crate::timer::fps(ctx),
+            frame_times: ctx.timer_context.frame_durations().collect(),
+        }
+    }
+}
+
+/// Sets whether [`run()`](fn.run.html) catches panics in the game's
+/// callbacks and hands them to
+/// [`EventHandler::on_panic()`](trait.EventHandler.html#method.on_panic).
+/// Off by default.  The panic message is still printed as usual, by the
+/// panic hook.
+pub fn set_catch_panics(ctx: &mut Context, catch: bool) {
+    ctx.catch_panics = catch;
+}
+
+/// Returns whether [`run()`](fn.run.html) catches panics; see
+/// [`set_catch_panics()`](fn.set_catch_panics.html).
+pub fn catch_panics(ctx: &Context) -> bool {
+    ctx.catch_panics
+}
+
+/// Calls `f` with the context and state, and if it panics while panics
+/// are caught, hands the panic to `on_panic()`.  Returns `None` if it
+/// panicked and the game goes on.
+pub(crate) fn catching_panics<S, E, F, R>(ctx: &mut Context, state: &mut S, f: F) -> Option<R>
+where
+    S: EventHandler<E>,
+    E: std::error::Error,
+    F: FnOnce(&mut Context, &mut S) -> R,
+{
+    if !ctx.catch_panics {
+        return Some(f(ctx, state));
+    }
+    match panic::catch_unwind(AssertUnwindSafe(|| f(ctx, state))) {
+        Ok(result) => Some(result),
+        Err(payload) => {
+            let report = PanicReport::new(ctx, payload);
+            if !state.on_panic(ctx, &report) {
+                panic::resume_unwind(report.payload);
+            }
+            None
+        }
+    }
 }
 
 /// Terminates the [`ggez::event::run()`](fn.run.html) loop by setting
@@ -177,160 +476,607 @@ pub fn quit(ctx: &mut Context) {
 /// Runs the game's main loop, calling event callbacks on the given state
 /// object as events occur.
 ///
-/// It does not try to do any type of framerate limiting.  See the
+/// It only limits the frame rate if asked to, with
+/// [`timer::set_target_fps()`](../timer/fn.set_target_fps.html).  See the
 /// documentation for the [`timer`](../timer/index.html) module for more info.
 pub fn run<S: 'static, E>(mut ctx: Context, event_loop: EventLoop<()>, mut state: S) -> !
 where
     S: EventHandler<E>,
     E: std::error::Error,
 {
-    use crate::input::{keyboard, mouse};
+    // When to run the next frame, while in the background.
+    let mut next_background_frame: Option<Instant> = None;
+
+    event_loop.run(move |event, _, control_flow| {
+        let ctx = &mut ctx;
+        let state = &mut state;
+
+        // However the loop ends, this is the last event, and everything
+        // is still there to clean up.
+        if let Event::LoopDestroyed = event {
+            let _ = catching_panics(ctx, state, |ctx, state| state.shutdown(ctx));
+            return;
+        }
 
-    event_loop.run(move |mut event, _, control_flow| {
         if !ctx.continuing {
             *control_flow = ControlFlow::Exit;
             return;
         }
 
-        *control_flow = ControlFlow::Poll;
+        let frame_due = matches!(event, Event::MainEventsCleared);
+        if catching_panics(ctx, state, |ctx, state| handle_event(ctx, state, event)).is_none() {
+            return;
+        }
+
+        if !in_background(ctx) {
+            next_background_frame = None;
+        }
+        *control_flow = match next_background_frame {
+            Some(at) => ControlFlow::WaitUntil(at),
+            None => ControlFlow::Poll,
+        };
+        if !frame_due {
+            return;
+        }
 
-        let ctx = &mut ctx;
-        let state = &mut state;
+        // Events can wake the loop up early in the background.
+        let frame_start = Instant::now();
+        if next_background_frame.map_or(false, |at| frame_start < at) {
+            return;
+        }
 
-        process_event(ctx, &mut event);
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::Resized(logical_size) => {
-                    // let actual_size = logical_size;
-                    state.resize_event(ctx, logical_size.width as f32, logical_size.height as f32);
-                }
-                WindowEvent::CloseRequested => {
-                    if !state.quit_event(ctx) {
-                        quit(ctx);
+        // If you are writing your own event loop, make sure
+        // you include `timer_context.tick()` and
+        // `ctx.process_event()` calls.  These update ggez's
+        // internal state however necessary.
+        ctx.timer_context.tick();
+
+        let frame = catching_panics(ctx, state, |ctx, state| {
+            run_frame(ctx, state, |state, ctx, origin, e| {
+                state.on_error(ctx, origin, e)
+            })
+        });
+        match frame.unwrap_or(Frame::Drawn) {
+            Frame::Drawn => {
+                next_background_frame = match ctx.background_policy {
+                    BackgroundPolicy::LimitFps(fps) if in_background(ctx) => {
+                        Some(frame_start + crate::timer::fps_as_duration(fps.max(1)))
                     }
+                    _ => None,
+                };
+                if next_background_frame.is_none() {
+                    ctx.timer_context.limit_frame(frame_start);
                 }
-                WindowEvent::Focused(gained) => {
-                    state.focus_event(ctx, gained);
+            }
+            Frame::Paused => {
+                next_background_frame = Some(frame_start + BACKGROUND_PAUSE_WAKEUP);
+            }
+            Frame::Stopped => *control_flow = ControlFlow::Exit,
+        }
+    })
+}
+
+/// Runs one frame of the game by hand, instead of
+/// [`run()`](fn.run.html) running them forever: it feeds `events` through
+/// [`process_event()`](fn.process_event.html) to the matching callbacks,
+/// moves the [`timer`](../timer/index.html) forward by `dt`, and then
+/// calls `update()` and `draw()`, like a frame of `run()` would.  Meant
+/// for integration tests, usually with a hidden window, and for
+/// embedding a game in an editor's preview.
+///
+/// An error from `update()` or `draw()` is returned instead of going to
+/// [`EventHandler::on_error()`](trait.EventHandler.html#method.on_error),
+/// and the frame stops there.  `step()` doesn't stop when the game
+/// [quits](fn.quit.html); check
+/// [`Context.continuing`](../struct.Context.html#structfield.continuing)
+/// for that.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # use ggez::event;
+/// # use std::time::Duration;
+/// # fn t<S: event::EventHandler>(ctx: &mut Context, state: &mut S) -> GameResult {
+/// let frame = Duration::from_secs(1) / 60;
+/// event::step(ctx, state, Vec::new(), frame)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn step<S, E, I>(ctx: &mut Context, state: &mut S, events: I, dt: Duration) -> Result<(), E>
+where
+    S: EventHandler<E>,
+    E: std::error::Error,
+    I: IntoIterator<Item = Event<'static, ()>>,
+{
+    handle_event(ctx, state, Event::NewEvents(StartCause::Poll));
+    for event in events {
+        handle_event(ctx, state, event);
+    }
+    handle_event(ctx, state, Event::MainEventsCleared);
+
+    ctx.timer_context.tick_by(dt);
+    let mut error = None;
+    let _ = run_frame(ctx, state, |_, _, _, e| {
+        error = Some(e);
+        true
+    });
+    error.map_or(Ok(()), Err)
+}
+
+/// How a frame run by `run_frame()` went.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Frame {
+    Drawn,
+    /// Skipped updating and drawing, while in the background or suspended.
+    Paused,
+    /// An error handler said to stop.
+    Stopped,
+}
+
+/// Feeds an event to the context, then to the matching callback.
+/// `MainEventsCleared` only updates the context; the frame itself is
+/// `run_frame()`.
+fn handle_event<S, E>(ctx: &mut Context, state: &mut S, mut event: Event<'_, ()>)
+where
+    S: EventHandler<E>,
+    E: std::error::Error,
+{
+    use crate::input::{keyboard, mouse};
+
+    if filter_event(ctx, &mut event) {
+        return;
+    }
+
+    let was_suspended = ctx.suspended;
+    let was_minimized = ctx.minimized;
+    process_event(ctx, &mut event);
+    match event {
+        Event::WindowEvent { event, .. } => match event {
+            WindowEvent::Resized(physical_size) => {
+                let (width, height) = window_size_in_input_units(ctx, physical_size);
+                state.resize_event(ctx, width, height);
+                match (was_minimized, ctx.minimized) {
+                    (false, true) => state.window_minimized(ctx),
+                    (true, false) => state.window_restored(ctx),
+                    _ => (),
                 }
-                WindowEvent::ReceivedCharacter(ch) => {
-                    state.text_input_event(ctx, ch);
+            }
+            WindowEvent::Moved(position) => {
+                let position = dpi::PhysicalPosition::new(position.x.into(), position.y.into());
+                let (x, y) = position_in_input_units(ctx, position);
+                state.window_moved(ctx, x, y);
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                state.scale_factor_changed_event(ctx, scale_factor as f32);
+            }
+            WindowEvent::CloseRequested => {
+                if !state.close_requested(ctx) {
+                    quit(ctx);
                 }
-                WindowEvent::ModifiersChanged(mods) => {
-                    ctx.keyboard_context.set_modifiers(KeyMods::from(mods))
+            }
+            WindowEvent::Focused(gained) => {
+                state.focus_event(ctx, gained);
+            }
+            WindowEvent::DroppedFile(path) => {
+                state.file_dropped(ctx, &path);
+            }
+            WindowEvent::HoveredFile(path) => {
+                state.file_hovered(ctx, &path);
+            }
+            WindowEvent::HoveredFileCancelled => {
+                state.file_hover_cancelled(ctx);
+            }
+            WindowEvent::ReceivedCharacter(ch) => {
+                state.text_input_event(ctx, ch);
+            }
+            WindowEvent::ModifiersChanged(mods) => {
+                ctx.keyboard_context.set_modifiers(KeyMods::from(mods))
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        scancode,
+                        ..
+                    },
+                ..
+            } => {
+                let repeat = keyboard::is_key_repeated(ctx);
+                if !repeat || ctx.keyboard_context.system_repeats() {
+                    let keymods = ctx.keyboard_context.active_mods();
+                    state.key_down_event(ctx, keycode, scancode, keymods, repeat);
                 }
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: Some(keycode),
-                            ..
-                        },
-                    ..
-                } => {
-                    let repeat = keyboard::is_key_repeated(ctx);
-                    state.key_down_event(ctx, keycode, ctx.keyboard_context.active_mods(), repeat);
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Released,
+                        virtual_keycode: Some(keycode),
+                        scancode,
+                        ..
+                    },
+                ..
+            } => {
+                let keymods = ctx.keyboard_context.active_mods();
+                state.key_up_event(ctx, keycode, scancode, keymods);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(pos) => {
+                        let scale_factor = ctx.gfx_context.window.window().scale_factor();
+                        let dpi::LogicalPosition { x, y } = pos.to_logical::<f32>(scale_factor);
+                        (x, y)
+                    }
+                };
+                state.mouse_wheel_event(ctx, x, y);
+                state.mouse_scroll_event(ctx, scroll_delta(ctx, delta));
+            }
+            WindowEvent::MouseInput {
+                state: element_state,
+                button,
+                ..
+            } => {
+                let position = mouse::position(ctx);
+                match element_state {
+                    ElementState::Pressed => {
+                        state.mouse_button_down_event(ctx, button, position.x, position.y)
+                    }
+                    ElementState::Released => {
+                        state.mouse_button_up_event(ctx, button, position.x, position.y)
+                    }
                 }
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Released,
-                            virtual_keycode: Some(keycode),
-                            ..
-                        },
-                    ..
-                } => {
-                    state.key_up_event(ctx, keycode, ctx.keyboard_context.active_mods());
+            }
+            WindowEvent::CursorMoved { .. } if !mouse::relative_mode(ctx) => {
+                let position = mouse::position(ctx);
+                let delta = mouse::delta(ctx);
+                state.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y);
+            }
+            WindowEvent::Touch(touch) => {
+                let (x, y) = position_in_input_units(ctx, touch.location);
+                state.touch_event(ctx, touch.phase, touch.id, x, y);
+                if let Some(pen) = Pen::from_touch(&touch, mint::Point2 { x, y }) {
+                    state.pen_event(ctx, pen);
                 }
-                WindowEvent::MouseWheel { delta, .. } => {
-                    let (x, y) = match delta {
-                        MouseScrollDelta::LineDelta(x, y) => (x, y),
-                        MouseScrollDelta::PixelDelta(pos) => {
-                            let scale_factor = ctx.gfx_context.window.window().scale_factor();
-                            let dpi::LogicalPosition { x, y } = pos.to_logical::<f32>(scale_factor);
-                            (x, y)
-                        }
-                    };
-                    state.mouse_wheel_event(ctx, x, y);
+                for gesture in ctx.gesture_context.take_gestures() {
+                    state.gesture_event(ctx, gesture);
                 }
-                WindowEvent::MouseInput {
-                    state: element_state,
-                    button,
-                    ..
-                } => {
+            }
+            _x => {
+                // trace!("ignoring window event {:?}", x);
+            }
+        },
+        Event::DeviceEvent { device_id, event } => match event {
+            DeviceEvent::MouseMotion { delta: (dx, dy) } => {
+                if mouse::relative_mode(ctx) {
                     let position = mouse::position(ctx);
-                    match element_state {
-                        ElementState::Pressed => {
-                            state.mouse_button_down_event(ctx, button, position.x, position.y)
-                        }
-                        ElementState::Released => {
-                            state.mouse_button_up_event(ctx, button, position.x, position.y)
-                        }
-                    }
+                    state.mouse_motion_event(ctx, position.x, position.y, dx as f32, dy as f32);
                 }
-                WindowEvent::CursorMoved { .. } => {
-                    let position = mouse::position(ctx);
-                    let delta = mouse::delta(ctx);
-                    state.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y);
+                state.raw_mouse_motion_event(ctx, device_id, dx as f32, dy as f32);
+            }
+            DeviceEvent::Button {
+                button,
+                state: element_state,
+            } => {
+                let pressed = element_state == ElementState::Pressed;
+                state.raw_mouse_button_event(ctx, device_id, button, pressed);
+            }
+            DeviceEvent::Key(KeyboardInput {
+                virtual_keycode,
+                scancode,
+                state: element_state,
+                ..
+            }) => {
+                let pressed = element_state == ElementState::Pressed;
+                state.raw_key_event(ctx, device_id, virtual_keycode, scancode, pressed);
+            }
+            _ => (),
+        },
+        Event::Resumed => {
+            if was_suspended {
+                state.resume_event(ctx);
+            }
+        }
+        Event::Suspended => {
+            if !was_suspended {
+                state.suspend_event(ctx);
+            }
+        }
+        Event::NewEvents(_) => (),
+        Event::UserEvent(_) => (),
+        Event::MainEventsCleared => (),
+        Event::RedrawRequested(_) => (),
+        Event::RedrawEventsCleared => (),
+        Event::LoopDestroyed => (),
+    }
+}
+
+/// Runs a frame of the game, after the timer's ticked: dispatches the
+/// gamepad events and other things that are checked once a frame, then
+/// updates and draws.  `on_error` gets the errors from `update()` and
+/// `draw()`, and returns whether to stop.
+fn run_frame<S, E, F>(ctx: &mut Context, state: &mut S, mut on_error: F) -> Frame
+where
+    S: EventHandler<E>,
+    E: std::error::Error,
+    F: FnMut(&mut S, &mut Context, ErrorOrigin, E) -> bool,
+{
+    // Handle gamepad events if necessary.
+    if ctx.conf.modules.gamepad {
+        while let Some(gilrs::Event { id, event, .. }) = ctx.gamepad_context.next_event() {
+            match event {
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    ctx.gamepad_edges.set_button(GamepadId(id), button, true);
+                    state.gamepad_button_down_event(ctx, button, GamepadId(id));
                 }
-                _x => {
-                    // trace!("ignoring window event {:?}", x);
+                gilrs::EventType::ButtonReleased(button, _) => {
+                    ctx.gamepad_edges.set_button(GamepadId(id), button, false);
+                    state.gamepad_button_up_event(ctx, button, GamepadId(id));
                 }
-            },
-            Event::DeviceEvent { .. } => (),
-            Event::Resumed => (),
-            Event::Suspended => (),
-            Event::NewEvents(_) => (),
-            Event::UserEvent(_) => (),
-            Event::MainEventsCleared => {
-                // If you are writing your own event loop, make sure
-                // you include `timer_context.tick()` and
-                // `ctx.process_event()` calls.  These update ggez's
-                // internal state however necessary.
-                ctx.timer_context.tick();
-
-                // Handle gamepad events if necessary.
-                if ctx.conf.modules.gamepad {
-                    while let Some(gilrs::Event { id, event, .. }) =
-                        ctx.gamepad_context.next_event()
-                    {
-                        match event {
-                            gilrs::EventType::ButtonPressed(button, _) => {
-                                state.gamepad_button_down_event(ctx, button, GamepadId(id));
-                            }
-                            gilrs::EventType::ButtonReleased(button, _) => {
-                                state.gamepad_button_up_event(ctx, button, GamepadId(id));
-                            }
-                            gilrs::EventType::AxisChanged(axis, value, _) => {
-                                state.gamepad_axis_event(ctx, axis, value, GamepadId(id));
-                            }
-                            _ => {}
-                        }
-                    }
+                gilrs::EventType::AxisChanged(axis, value, _) => {
+                    let value = ctx.axis_filters.apply(axis, value);
+                    state.gamepad_axis_event(ctx, axis, value, GamepadId(id));
                 }
-
-                if let Err(e) = state.update(ctx) {
-                    error!("Error on EventHandler::update(): {:?}", e);
-                    eprintln!("Error on EventHandler::update(): {:?}", e);
-                    if state.on_error(ctx, ErrorOrigin::Update, e) {
-                        *control_flow = ControlFlow::Exit;
-                        return;
-                    }
+                gilrs::EventType::Connected => {
+                    let guid = ctx.gamepad_context.gamepad(GamepadId(id)).uuid();
+                    let stable_id = ctx.gamepad_slots.connect(GamepadId(id), guid);
+                    state.gamepad_connected(ctx, GamepadId(id), stable_id);
                 }
-
-                if let Err(e) = state.draw(ctx) {
-                    error!("Error on EventHandler::draw(): {:?}", e);
-                    eprintln!("Error on EventHandler::draw(): {:?}", e);
-                    if state.on_error(ctx, ErrorOrigin::Draw, e) {
-                        *control_flow = ControlFlow::Exit;
-                        return;
+                gilrs::EventType::Disconnected => {
+                    if let Some(stable_id) = ctx.gamepad_slots.disconnect(GamepadId(id)) {
+                        state.gamepad_disconnected(ctx, GamepadId(id), stable_id);
                     }
                 }
+                _ => {}
             }
-            Event::RedrawRequested(_) => (),
-            Event::RedrawEventsCleared => (),
-            Event::LoopDestroyed => (),
         }
-    })
+    }
+
+    for gesture in ctx.gesture_context.take_gestures() {
+        state.gesture_event(ctx, gesture);
+    }
+
+    let now = Instant::now();
+    if let Some((keycode, scancode)) = ctx.keyboard_context.next_repeat(now) {
+        let keymods = ctx.keyboard_context.active_mods();
+        state.key_down_event(ctx, keycode, scancode, keymods, true);
+    }
+
+    for path in crate::filesystem::changed_resources(ctx) {
+        state.resource_changed(ctx, &path);
+    }
+
+    #[cfg(feature = "audio")]
+    {
+        if crate::audio::output_device_changed(ctx) {
+            state.audio_device_changed(ctx);
+        }
+    }
+
+    // There may be nothing to draw on while suspended.
+    if ctx.suspended || (in_background(ctx) && ctx.background_policy == BackgroundPolicy::Pause) {
+        ctx.timer_context.skip_paused_time();
+        return Frame::Paused;
+    }
+
+    ctx.scheduler.advance(crate::timer::delta(ctx));
+    while let Some(tag) = ctx.scheduler.poll() {
+        state.timer_event(ctx, &tag);
+    }
+    ctx.executor.run_pending();
+
+    // Once per frame, or as many times as it takes to keep up
+    // in the fixed update mode.
+    let fixed_rate = crate::timer::fixed_update_rate(ctx);
+    let mut updates = 0;
+    loop {
+        if let Some(rate) = fixed_rate {
+            // Check the cap first, so the update that's left over stays
+            // in the residual for the next frame.
+            if updates == crate::timer::MAX_FIXED_UPDATES_PER_FRAME {
+                ctx.timer_context.drop_update_backlog(rate);
+                break;
+            }
+            if !ctx.timer_context.take_fixed_update(rate) {
+                break;
+            }
+        } else if updates == 1 {
+            break;
+        }
+
+        if let Err(e) = state.update(ctx) {
+            error!("Error on EventHandler::update(): {:?}", e);
+            eprintln!("Error on EventHandler::update(): {:?}", e);
+            if on_error(state, ctx, ErrorOrigin::Update, e) {
+                return Frame::Stopped;
+            }
+        }
+        // Presses and releases are only new to the first
+        // update that sees them.
+        if fixed_rate.is_some() && updates == 0 {
+            end_input_frame(ctx);
+        }
+        updates += 1;
+    }
+
+    if let Err(e) = state.draw(ctx) {
+        error!("Error on EventHandler::draw(): {:?}", e);
+        eprintln!("Error on EventHandler::draw(): {:?}", e);
+        if on_error(state, ctx, ErrorOrigin::Draw, e) {
+            return Frame::Stopped;
+        }
+    }
+    Frame::Drawn
+}
+
+/// Identifies a filter added with
+/// [`add_event_filter()`](fn.add_event_filter.html), to remove it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventFilterId(u64);
+
+/// A filter added with [`add_event_filter()`](fn.add_event_filter.html).
+type EventFilter = Box<dyn FnMut(&mut Context, &mut Event<'_, ()>) -> bool>;
+
+/// The context's event filters, in the order they were added.
+#[derive(Default)]
+pub(crate) struct EventFilters {
+    next_id: u64,
+    filters: Vec<(EventFilterId, EventFilter)>,
+    /// Whether the filters are running, and so out of the list.
+    running: bool,
+    /// Filters removed while they were running.
+    removed: Vec<EventFilterId>,
+}
+
+/// Adds a filter that sees window and device events before ggez and the
+/// `EventHandler` do, and can change them or, by returning true, consume
+/// them, so that neither ever sees them.  A UI layer can claim keyboard
+/// and mouse input this way while one of its widgets has the focus.
+/// Filters run in the order they were added, until one consumes the
+/// event.
+///
+/// Consumed events don't update ggez's own state either: a key a filter
+/// consumes doesn't count as [pressed](../input/keyboard/fn.is_key_pressed.html),
+/// and a consumed `Resized` leaves the graphics at the old size.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # use ggez::event::winit_event::{Event, WindowEvent};
+/// # fn t(ctx: &mut Context) {
+/// // Keeps the game from seeing any typing.
+/// let _ = event::add_event_filter(ctx, |_ctx, event| {
+///     matches!(
+///         event,
+///         Event::WindowEvent {
+///             event: WindowEvent::ReceivedCharacter(_),
+///             ..
+///         }
+///     )
+/// });
+/// # }
+/// ```
+pub fn add_event_filter<F>(ctx: &mut Context, filter: F) -> EventFilterId
+where
+    F: FnMut(&mut Context, &mut Event<'_, ()>) -> bool + 'static,
+{
+    let filters = &mut ctx.event_filters;
+    let id = EventFilterId(filters.next_id);
+    filters.next_id += 1;
+    filters.filters.push((id, Box::new(filter)));
+    id
+}
+
+/// Removes a filter added with [`add_event_filter()`](fn.add_event_filter.html).
+pub fn remove_event_filter(ctx: &mut Context, id: EventFilterId) {
+    let filters = &mut ctx.event_filters;
+    let before = filters.filters.len();
+    filters.filters.retain(|(filter_id, _)| *filter_id != id);
+    if filters.running && filters.filters.len() == before {
+        filters.removed.push(id);
+    }
+}
+
+/// Runs the event filters on a window or device event.  Returns whether
+/// one of them consumed it.
+fn filter_event(ctx: &mut Context, event: &mut Event<'_, ()>) -> bool {
+    match event {
+        Event::WindowEvent { .. } | Event::DeviceEvent { .. } => (),
+        _ => return false,
+    }
+    if ctx.event_filters.filters.is_empty() {
+        return false;
+    }
+
+    // The filters get the context, so they're taken out of it while
+    // they run.
+    let mut filters = std::mem::take(&mut ctx.event_filters.filters);
+    ctx.event_filters.running = true;
+    let consumed = filters.iter_mut().any(|(_, filter)| filter(ctx, event));
+
+    // Puts back the filters that weren't removed, followed by any that
+    // were added meanwhile.
+    let current = &mut ctx.event_filters;
+    current.running = false;
+    let removed = std::mem::take(&mut current.removed);
+    filters.retain(|(id, _)| !removed.contains(id));
+    filters.append(&mut current.filters);
+    current.filters = filters;
+    consumed
+}
+
+/// Returns whether the window is unfocused, minimized or suspended.
+fn in_background(ctx: &Context) -> bool {
+    ctx.suspended || !ctx.focused || ctx.minimized
+}
+
+/// Sets what [`run()`](fn.run.html) does while the window is unfocused,
+/// minimized or suspended, overriding
+/// [`WindowSetup::background_policy`](../conf/struct.WindowSetup.html#structfield.background_policy).
+pub fn set_background_policy(ctx: &mut Context, policy: BackgroundPolicy) {
+    ctx.background_policy = policy;
+}
+
+/// Returns what [`run()`](fn.run.html) does while the window is in the
+/// background.
+pub fn background_policy(ctx: &Context) -> BackgroundPolicy {
+    ctx.background_policy
+}
+
+/// Converts a position in the window from physical pixels to the ones
+/// the `DpiPolicy` says input should be in.
+fn position_in_input_units(ctx: &Context, position: dpi::PhysicalPosition<f64>) -> (f32, f32) {
+    match ctx.gfx_context.dpi_policy {
+        DpiPolicy::Physical => (position.x as f32, position.y as f32),
+        DpiPolicy::Logical => {
+            let scale_factor = ctx.gfx_context.window.window().scale_factor();
+            let position = position.to_logical::<f32>(scale_factor);
+            (position.x, position.y)
+        }
+    }
+}
+
+/// Forgets which keys and buttons were just pressed and released.
+fn end_input_frame(ctx: &mut Context) {
+    ctx.keyboard_context.end_frame();
+    ctx.mouse_context.end_frame();
+    ctx.gamepad_edges.end_frame();
+}
+
+/// Converts a scroll reported by winit into a `ScrollDelta` for
+/// `mouse_scroll_event()`, with pixels in the units the DPI policy says
+/// we hand out.  A mouse wheel scrolled with shift held scrolls sideways,
+/// since few mice have a sideways wheel; macOS does that by itself.
+/// `mouse_wheel_event()` gets the deltas as they are, like it always has.
+fn scroll_delta(ctx: &Context, delta: MouseScrollDelta) -> ScrollDelta {
+    match delta {
+        MouseScrollDelta::LineDelta(x, y) => {
+            if cfg!(not(target_os = "macos"))
+                && x == 0.0
+                && crate::input::keyboard::is_mod_active(ctx, KeyMods::SHIFT)
+            {
+                ScrollDelta::Lines { x: y, y: 0.0 }
+            } else {
+                ScrollDelta::Lines { x, y }
+            }
+        }
+        MouseScrollDelta::PixelDelta(position) => {
+            let (x, y) = position_in_input_units(ctx, position);
+            ScrollDelta::Pixels { x, y }
+        }
+    }
+}
+
+/// Converts a window size reported by winit into the units the DPI
+/// policy says we hand out.
+fn window_size_in_input_units(ctx: &Context, size: dpi::PhysicalSize<u32>) -> (f32, f32) {
+    match ctx.gfx_context.dpi_policy {
+        DpiPolicy::Physical => (size.width as f32, size.height as f32),
+        DpiPolicy::Logical => {
+            let scale_factor = ctx.gfx_context.window.window().scale_factor();
+            let size = size.to_logical::<f32>(scale_factor);
+            (size.width, size.height)
+        }
+    }
 }
 
 /// Feeds an `Event` into the `Context` so it can update any internal
@@ -341,18 +1087,52 @@ pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
     match event {
         winit_event::Event::WindowEvent { event, .. } => match event {
             winit_event::WindowEvent::Resized(physical_size) => {
+                // Windows reports minimizing as a resize to nothing.
+                ctx.minimized = physical_size.width == 0 || physical_size.height == 0;
                 ctx.gfx_context.window.resize(*physical_size);
                 ctx.gfx_context.resize_viewport();
+                if ctx.gfx_context.dpi_policy == DpiPolicy::Logical {
+                    let (width, height) = window_size_in_input_units(ctx, *physical_size);
+                    let rect = crate::graphics::Rect::new(0.0, 0.0, width, height);
+                    if let Err(e) = crate::graphics::set_screen_coordinates(ctx, rect) {
+                        warn!("Could not reset screen coordinates after resize: {}", e);
+                    }
+                }
             }
+            winit_event::WindowEvent::Focused(gained) => ctx.focused = *gained,
             winit_event::WindowEvent::CursorMoved {
-                position: logical_position,
+                position: physical_position,
                 ..
             } => {
-                ctx.mouse_context
-                    .set_last_position(crate::graphics::Point2::new(
-                        logical_position.x as f32,
-                        logical_position.y as f32,
-                    ));
+                let (x, y) = position_in_input_units(ctx, *physical_position);
+                let position = crate::graphics::Point2::new(x, y);
+                match ctx.mouse_context.locked_position() {
+                    // Puts a locked cursor back where it belongs.
+                    Some(locked) if locked != position => {
+                        let _ = crate::input::mouse::set_position(ctx, locked);
+                    }
+                    _ => ctx.mouse_context.set_last_position(position),
+                }
+            }
+            winit_event::WindowEvent::Touch(touch) => {
+                let (x, y) = position_in_input_units(ctx, touch.location);
+                let position = crate::graphics::Point2::new(x, y);
+                ctx.touch_context.update(touch.phase, touch.id, position);
+                if let Some(pen) = crate::input::pen::Pen::from_touch(touch, position.into()) {
+                    ctx.pen_context.update(pen);
+                }
+                if ctx.virtual_pad.is_some() {
+                    let screen_position = crate::graphics::window_to_screen_coords(ctx, position);
+                    if let Some(pad) = &mut ctx.virtual_pad {
+                        pad.touch(touch.phase, touch.id, screen_position.into());
+                    }
+                }
+                ctx.gesture_context.touch(
+                    touch.phase,
+                    touch.id,
+                    position,
+                    std::time::Instant::now(),
+                );
             }
             winit_event::WindowEvent::MouseInput { button, state, .. } => {
                 let pressed = match state {
@@ -368,7 +1148,8 @@ pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
                 input:
                     winit::event::KeyboardInput {
                         state,
-                        virtual_keycode: Some(keycode),
+                        virtual_keycode,
+                        scancode,
                         ..
                     },
                 ..
@@ -377,10 +1158,24 @@ pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
                     winit_event::ElementState::Pressed => true,
                     winit_event::ElementState::Released => false,
                 };
-                ctx.keyboard_context.set_key(*keycode, pressed);
+                ctx.keyboard_context
+                    .set_scancode(*scancode, *virtual_keycode, pressed);
+                if let Some(keycode) = virtual_keycode {
+                    ctx.keyboard_context.set_key(*keycode, pressed);
+                    ctx.keyboard_context.update_repeat(
+                        *keycode,
+                        *scancode,
+                        pressed,
+                        std::time::Instant::now(),
+                    );
+                }
             }
             winit_event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                if !ctx.conf.window_mode.resize_on_scale_factor_change {
+                // With logical sizes, winit's suggested size is exactly
+                // the one that keeps the window the same logical size.
+                if ctx.gfx_context.dpi_policy == DpiPolicy::Physical
+                    && !ctx.conf.window_mode.resize_on_scale_factor_change
+                {
                     // actively set the new_inner_size to be the desired size
                     // to stop winit from resizing our window
                     **new_inner_size = winit::dpi::PhysicalSize::<u32>::from([
@@ -397,6 +1192,33 @@ pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
         } => ctx
             .mouse_context
             .set_last_delta(crate::graphics::Point2::new(*x as f32, *y as f32)),
+        winit_event::Event::NewEvents(_) => {
+            // A new frame's events are coming, so last frame's presses
+            // and releases aren't new anymore.  In the fixed update mode
+            // they're kept until an update has seen them instead.
+            if crate::timer::fixed_update_rate(ctx).is_none() {
+                end_input_frame(ctx);
+            }
+        }
+        winit_event::Event::Suspended => ctx.suspended = true,
+        winit_event::Event::Resumed => {
+            if ctx.suspended {
+                ctx.suspended = false;
+                ctx.timer_context.skip_paused_time();
+            }
+        }
+        winit_event::Event::MainEventsCleared => {
+            // Follows the default output device around, so sound keeps
+            // working when headphones are plugged in or out.
+            #[cfg(feature = "audio")]
+            {
+                if ctx.conf.modules.audio && ctx.audio_context.check_device() {
+                    ctx.audio_device_changed = true;
+                }
+            }
+            ctx.filesystem.check_resources();
+            ctx.gesture_context.update(std::time::Instant::now());
+        }
 
         _ => (),
     };
@@ -6,6 +6,8 @@
 //! program executable, if any,
 //! * The `resources.zip` file in the same
 //! directory as the program executable, if any,
+//! * The `resources.pak` file, made with [`pack::build()`](../pack/fn.build.html),
+//! in the same directory as the program executable, if any,
 //! * The root folder of the  game's "save" directory which is in a
 //! platform-dependent location,
 //! such as `~/.local/share/<gameid>/` on Linux.  The `gameid`
@@ -26,21 +28,34 @@
 //! directory isolation is intended for convenience, not security, so
 //! don't assume it will be secure.
 
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::convert::TryFrom;
 use std::env;
+use std::fs;
 use std::io;
 use std::io::SeekFrom;
 use std::path;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time;
 
 use directories::ProjectDirs;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::conf;
 use crate::vfs::{self, VFS};
 use crate::{Context, GameError, GameResult};
 
 pub use crate::vfs::OpenOptions;
+pub use crate::vfs::PathCase;
 
 const CONFIG_NAME: &str = "/conf.toml";
 
+/// How often resource directories are scanned for changes while
+/// they're watched.
+const WATCH_INTERVAL: time::Duration = time::Duration::from_millis(500);
+
 /// A structure that contains the filesystem state and cache.
 #[derive(Debug)]
 pub struct Filesystem {
@@ -49,6 +64,84 @@ pub struct Filesystem {
     zip_path: path::PathBuf,
     user_config_path: path::PathBuf,
     user_data_path: path::PathBuf,
+    /// The priority of each of the VFS's in `vfs`, highest first.
+    priorities: Vec<i32>,
+    /// Directories mounted with `mount()`, besides the resources one.
+    mounted_paths: Vec<path::PathBuf>,
+    watcher: Option<Watcher>,
+}
+
+/// Keeps track of when the files in some directories were last
+/// modified, to notice when they change.
+#[derive(Debug)]
+struct Watcher {
+    roots: Vec<path::PathBuf>,
+    /// Modification times, keyed by the path of the file in the VFS.
+    files: HashMap<path::PathBuf, time::SystemTime>,
+    last_check: time::Instant,
+    changed: Vec<path::PathBuf>,
+}
+
+impl Watcher {
+    fn new(roots: Vec<path::PathBuf>) -> Self {
+        let mut watcher = Watcher {
+            roots,
+            files: HashMap::new(),
+            last_check: time::Instant::now(),
+            changed: Vec::new(),
+        };
+        watcher.files = watcher.scan();
+        watcher
+    }
+
+    /// Finds every file under the roots, with its modification time.
+    fn scan(&self) -> HashMap<path::PathBuf, time::SystemTime> {
+        fn visit(
+            dir: &path::Path,
+            virtual_dir: &path::Path,
+            files: &mut HashMap<path::PathBuf, time::SystemTime>,
+        ) {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(_) => return,
+            };
+            for entry in entries.flatten() {
+                let virtual_path = virtual_dir.join(entry.file_name());
+                match entry.metadata() {
+                    Ok(m) if m.is_dir() => visit(&entry.path(), &virtual_path, files),
+                    Ok(m) => {
+                        let modified = m.modified().unwrap_or(time::UNIX_EPOCH);
+                        let _ = files.entry(virtual_path).or_insert(modified);
+                    }
+                    Err(_) => (),
+                }
+            }
+        }
+
+        let mut files = HashMap::new();
+        for root in &self.roots {
+            visit(root, path::Path::new("/"), &mut files);
+        }
+        files
+    }
+
+    /// Rescans the roots and notes the files that were added, changed
+    /// or removed since last time.
+    fn check(&mut self) {
+        let files = self.scan();
+        for (path, modified) in &files {
+            if self.files.get(path) != Some(modified) {
+                self.changed.push(path.clone());
+            }
+        }
+        for path in self.files.keys() {
+            if !files.contains_key(path) {
+                self.changed.push(path.clone());
+            }
+        }
+        self.files = files;
+        self.last_check = time::Instant::now();
+    }
 }
 
 /// Represents a file, either in the filesystem, or in the resources zip file,
@@ -89,6 +182,36 @@ impl io::Seek for File {
     }
 }
 
+/// Where a file or directory in the filesystem comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileSource {
+    /// A directory on disk, such as the resources or user data
+    /// directory.
+    Directory(path::PathBuf),
+    /// An archive on disk: a zip, tar or pak file.
+    Archive(path::PathBuf),
+    /// Something without a path on disk, such as files embedded in the
+    /// executable or a [`VFS`](../vfs/trait.VFS.html) of your own.
+    Other,
+}
+
+/// Information about a file or directory, as returned by
+/// [`metadata()`](fn.metadata.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// The length of the file, in bytes.  Undefined for directories.
+    pub len: u64,
+    /// Whether it's a file.
+    pub is_file: bool,
+    /// Whether it's a directory.
+    pub is_dir: bool,
+    /// When it was last modified.  Only known for things in
+    /// directories on disk, not in archives.
+    pub modified: Option<time::SystemTime>,
+    /// Where it comes from.
+    pub source: FileSource,
+}
+
 impl Filesystem {
     /// Create a new `Filesystem` instance, using the given `id` and (on
     /// some platforms) the `author` as a portion of the user
@@ -141,6 +264,19 @@ impl Filesystem {
             }
         }
 
+        // <root>/resources.pak
+        {
+            let mut resources_pak_path = resources_zip_path.clone();
+            let _ = resources_pak_path.set_extension("pak");
+            if resources_pak_path.exists() {
+                trace!("Resources pak file: {:?}", resources_pak_path);
+                let pakfs = vfs::PakFS::new(&resources_pak_path)?;
+                overlay.push_back(Box::new(pakfs));
+            } else {
+                trace!("No resources pak file found");
+            }
+        }
+
         // Per-user data dir,
         // ~/.local/share/whatever/
         {
@@ -159,12 +295,16 @@ impl Filesystem {
             overlay.push_back(Box::new(physfs));
         }
 
+        let priorities = vec![0; overlay.roots().len()];
         let fs = Filesystem {
             vfs: overlay,
+            priorities,
             resources_path,
             zip_path: resources_zip_path,
             user_config_path: user_config_path.to_path_buf(),
             user_data_path: user_data_path.to_path_buf(),
+            mounted_paths: Vec::new(),
+            watcher: None,
         };
 
         Ok(fs)
@@ -176,6 +316,16 @@ impl Filesystem {
         self.vfs.open(path.as_ref()).map(|f| File::VfsFile(f))
     }
 
+    /// Sets how the case of paths is matched.
+    pub(crate) fn set_path_case(&mut self, path_case: PathCase) {
+        self.vfs.set_path_case(path_case);
+    }
+
+    /// Gets the whole contents of a file, memory-mapped if it can be.
+    pub(crate) fn map<P: AsRef<path::Path>>(&self, path: P) -> GameResult<vfs::MappedFile> {
+        self.vfs.map(path.as_ref())
+    }
+
     /// Opens a file in the user directory with the given
     /// [`filesystem::OpenOptions`](struct.OpenOptions.html).
     /// Note that even if you open a file read-write, it can only
@@ -242,6 +392,33 @@ impl Filesystem {
             .unwrap_or(false)
     }
 
+    /// Gets the metadata of a file or directory from the first place
+    /// it's found, along with which place that is.
+    pub(crate) fn metadata<P: AsRef<path::Path>>(&self, path: P) -> GameResult<Metadata> {
+        let resolved = self.vfs.resolve(path.as_ref());
+        let path = &*resolved;
+        for vfs in self.vfs.roots() {
+            if let Ok(metadata) = vfs.metadata(path) {
+                let source = match vfs.to_path_buf() {
+                    Some(root) if root.is_dir() => FileSource::Directory(root),
+                    Some(root) => FileSource::Archive(root),
+                    None => FileSource::Other,
+                };
+                return Ok(Metadata {
+                    len: metadata.len(),
+                    is_file: metadata.is_file(),
+                    is_dir: metadata.is_dir(),
+                    modified: metadata.modified(),
+                    source,
+                });
+            }
+        }
+        Err(GameError::ResourceNotFound(
+            path.to_string_lossy().into_owned(),
+            Vec::new(),
+        ))
+    }
+
     /// Returns a list of all files and directories in the resource directory,
     /// in no particular order.
     ///
@@ -256,6 +433,57 @@ impl Filesystem {
         Ok(Box::new(itr))
     }
 
+    /// Returns every file and directory under the given directory, and
+    /// under the directories in it, sorted.
+    pub(crate) fn walk_dir<P: AsRef<path::Path>>(&self, path: P) -> GameResult<Vec<path::PathBuf>> {
+        let mut found = BTreeSet::new();
+        let mut pending = vec![path.as_ref().to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            for entry in self.vfs.read_dir(&dir)?.flatten() {
+                // A VFS of the game's own may list its paths without
+                // the leading `/`.
+                let entry = path::Path::new("/").join(entry);
+                if found.insert(entry.clone()) && self.is_dir(&entry) {
+                    pending.push(entry);
+                }
+            }
+        }
+        Ok(found.into_iter().collect())
+    }
+
+    /// Returns the files matching the given glob pattern, sorted.
+    pub(crate) fn glob(&self, pattern: &str) -> GameResult<Vec<path::PathBuf>> {
+        let pattern = pattern
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+        // Only walk the directory the pattern is sure to be under.
+        let literal = pattern
+            .iter()
+            .take_while(|part| !part.contains(|c| c == '*' || c == '?'))
+            .count()
+            .min(pattern.len().saturating_sub(1));
+        let base = format!("/{}", pattern[..literal].join("/"));
+        if !self.is_dir(&base) {
+            return Ok(Vec::new());
+        }
+        let matches = self
+            .walk_dir(&base)?
+            .into_iter()
+            .filter(|path| {
+                let parts = path
+                    .components()
+                    .filter_map(|c| match c {
+                        path::Component::Normal(part) => part.to_str(),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                glob_match(&pattern, &parts) && self.is_file(path)
+            })
+            .collect();
+        Ok(matches)
+    }
+
     fn write_to_string(&mut self) -> String {
         use std::fmt::Write;
         let mut s = String::new();
@@ -298,7 +526,94 @@ impl Filesystem {
     pub(crate) fn mount(&mut self, path: &path::Path, readonly: bool) {
         let physfs = vfs::PhysicalFS::new(path, readonly);
         trace!("Mounting new path: {:?}", physfs);
-        self.vfs.push_back(Box::new(physfs));
+        self.push(Box::new(physfs), 0);
+        self.add_mounted_path(path);
+    }
+
+    /// Adds a VFS to the search list, after the ones with the same or
+    /// a higher priority.
+    fn push(&mut self, vfs: Box<dyn VFS>, priority: i32) {
+        let index = self
+            .priorities
+            .iter()
+            .take_while(|&&other| other >= priority)
+            .count();
+        self.vfs.insert(index, vfs);
+        self.priorities.insert(index, priority);
+    }
+
+    /// Keeps track of a mounted directory, to watch it for changes.
+    fn add_mounted_path(&mut self, path: &path::Path) {
+        self.mounted_paths.push(path.to_path_buf());
+        if let Some(watcher) = &mut self.watcher {
+            watcher.roots.push(path.to_path_buf());
+            watcher.files = watcher.scan();
+        }
+    }
+
+    /// Mounts a directory or archive with the given priority.
+    pub(crate) fn mount_at(&mut self, path: &path::Path, priority: i32) -> GameResult {
+        if path.is_dir() {
+            let physfs = vfs::PhysicalFS::new(path, true);
+            trace!("Mounting new path: {:?} at priority {}", physfs, priority);
+            self.push(Box::new(physfs), priority);
+            self.add_mounted_path(path);
+        } else {
+            let archive = open_archive(path)?;
+            trace!("Mounting archive: {:?} at priority {}", archive, priority);
+            self.push(archive, priority);
+        }
+        Ok(())
+    }
+
+    /// Removes everything mounted from the given path.
+    pub(crate) fn unmount(&mut self, path: &path::Path) -> bool {
+        let mut found = false;
+        let mut index = 0;
+        while index < self.priorities.len() {
+            if self.vfs.roots()[index].to_path_buf().as_deref() == Some(path) {
+                let _ = self.vfs.remove(index);
+                let _ = self.priorities.remove(index);
+                found = true;
+            } else {
+                index += 1;
+            }
+        }
+        self.mounted_paths.retain(|mounted| mounted != path);
+        if let Some(watcher) = &mut self.watcher {
+            watcher.roots.retain(|root| root != path);
+            watcher.files = watcher.scan();
+        }
+        found
+    }
+
+    /// Starts or stops watching the resource directories for changes.
+    pub(crate) fn watch_resources(&mut self, watch: bool) {
+        self.watcher = if watch {
+            let mut roots = vec![self.resources_path.clone()];
+            roots.extend(self.mounted_paths.iter().cloned());
+            Some(Watcher::new(roots))
+        } else {
+            None
+        };
+    }
+
+    /// Checks the watched directories for changes, if it's been long
+    /// enough since the last check.
+    pub(crate) fn check_resources(&mut self) {
+        if let Some(watcher) = &mut self.watcher {
+            if watcher.last_check.elapsed() >= WATCH_INTERVAL {
+                watcher.check();
+            }
+        }
+    }
+
+    /// Takes the resources that changed since last time.
+    pub(crate) fn changed_resources(&mut self) -> Vec<path::PathBuf> {
+        self.watcher
+            .as_mut()
+            .map(|watcher| std::mem::take(&mut watcher.changed))
+            .unwrap_or_default()
     }
 
     /// Adds any object that implements Read + Seek as a zip file.
@@ -313,24 +628,70 @@ impl Filesystem {
     ) -> GameResult<()> {
         let zipfs = vfs::ZipFS::from_read(reader)?;
         trace!("Adding zip file from reader");
-        self.vfs.push_back(Box::new(zipfs));
+        self.push(Box::new(zipfs), 0);
         Ok(())
     }
 
-    /// Looks for a file named `/conf.toml` in any resource directory and
-    /// loads it if it finds it.
-    /// If it can't read it for some reason, returns an error.
-    pub(crate) fn read_config(&mut self) -> GameResult<conf::Conf> {
-        let conf_path = path::Path::new(CONFIG_NAME);
-        if self.is_file(conf_path) {
-            let mut file = self.open(conf_path)?;
-            let c = conf::Conf::from_toml_file(&mut file)?;
-            Ok(c)
+    /// Adds a zip, pak, tar or gzipped tar file to the end of the
+    /// search list, telling them apart by their first few bytes.
+    pub(crate) fn mount_archive(&mut self, path: &path::Path) -> GameResult {
+        let archive = open_archive(path)?;
+        trace!("Mounting archive: {:?}", archive);
+        self.push(archive, 0);
+        Ok(())
+    }
+
+    /// Adds an encrypted pak file to the end of the search list.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn mount_encrypted(&mut self, path: &path::Path, key: &[u8; 32]) -> GameResult {
+        let pakfs = vfs::PakFS::new_encrypted(path, key)?;
+        trace!("Mounting encrypted archive: {:?}", pakfs);
+        self.push(Box::new(pakfs), 0);
+        Ok(())
+    }
+
+    /// Adds a custom VFS to the end of the search list.
+    pub(crate) fn mount_vfs(&mut self, vfs: Box<dyn VFS>, readonly: bool) {
+        trace!("Mounting VFS: {:?}", vfs);
+        if readonly {
+            self.push(Box::new(vfs::ReadOnlyFS(vfs)), 0);
         } else {
-            Err(GameError::ConfigError(String::from(
-                "Config file not found",
-            )))
+            self.push(vfs, 0);
+        }
+    }
+
+    /// Adds files embedded in the executable, as pairs of paths and
+    /// contents.
+    pub(crate) fn add_embedded_files<I>(&mut self, files: I)
+    where
+        I: IntoIterator<Item = (&'static str, &'static [u8])>,
+    {
+        let embedded = vfs::EmbeddedFS::new(files);
+        trace!("Adding embedded files: {:?}", embedded);
+        self.push(Box::new(embedded), 0);
+    }
+
+    /// Looks for a file named `/conf.toml`, `/conf.json` or `/conf.ron`
+    /// in any resource directory and loads it over `base` if it finds it.
+    /// If it can't read it for some reason, returns an error.
+    pub(crate) fn read_config_over(&mut self, base: conf::Conf) -> GameResult<conf::Conf> {
+        for format in conf::ConfFormat::all() {
+            let conf_path = path::PathBuf::from(CONFIG_NAME).with_extension(format.extension());
+            if self.is_file(&conf_path) {
+                let mut file = self.open(&conf_path)?;
+                return base.merge(&mut file, format);
+            }
         }
+        Err(GameError::ConfigError(String::from(
+            "Config file not found",
+        )))
+    }
+
+    /// Looks for a `conf` file in any resource directory and loads it
+    /// over the default `Conf` if it finds it.
+    /// If it can't read it for some reason, returns an error.
+    pub(crate) fn read_config(&mut self) -> GameResult<conf::Conf> {
+        self.read_config_over(conf::Conf::default())
     }
 
     /// Takes a `Conf` object and saves it to the user directory,
@@ -350,12 +711,65 @@ impl Filesystem {
     }
 }
 
+/// Opens a zip, pak, or with the `tar` feature tar or gzipped tar
+/// file, telling them apart by their first few bytes.
+fn open_archive(path: &path::Path) -> GameResult<Box<dyn VFS>> {
+    use std::io::Read;
+    // Enough for the "ustar" magic of a tar header, at byte 257.
+    let mut header = Vec::with_capacity(262);
+    let _ = fs::File::open(path)?.take(262).read_to_end(&mut header)?;
+    if header.starts_with(b"PK") {
+        Ok(Box::new(vfs::ZipFS::new(path)?))
+    } else if header.starts_with(crate::pack::MAGIC) {
+        Ok(Box::new(vfs::PakFS::new(path)?))
+    } else if header.starts_with(crate::pack::ENCRYPTED_MAGIC) {
+        Err(GameError::FilesystemError(format!(
+            "{:?} is an encrypted pak file; mount it with mount_encrypted(), which needs the `encryption` feature",
+            path
+        )))
+    } else if header.starts_with(&[0x1f, 0x8b]) || header.get(257..262) == Some(b"ustar") {
+        open_tar(path)
+    } else {
+        Err(GameError::FilesystemError(format!(
+            "{:?} is not an archive ggez recognizes; it should be a zip, tar, gzipped tar or pak file",
+            path
+        )))
+    }
+}
+
+#[cfg(feature = "tar")]
+fn open_tar(path: &path::Path) -> GameResult<Box<dyn VFS>> {
+    Ok(Box::new(vfs::TarFS::new(path)?))
+}
+
+#[cfg(not(feature = "tar"))]
+fn open_tar(path: &path::Path) -> GameResult<Box<dyn VFS>> {
+    Err(GameError::FilesystemError(format!(
+        "{:?} is a tar file, which needs the `tar` feature",
+        path
+    )))
+}
+
 /// Opens the given path and returns the resulting `File`
 /// in read-only mode.
 pub fn open<P: AsRef<path::Path>>(ctx: &mut Context, path: P) -> GameResult<File> {
     ctx.filesystem.open(path)
 }
 
+/// Gets the whole contents of the file at the given path, as a
+/// [`MappedFile`](../vfs/struct.MappedFile.html) that derefs to
+/// `&[u8]`.  With the `mmap` feature, files in resource directories
+/// are memory-mapped, so big asset packs and audio banks can be sliced
+/// up and streamed without being read into memory first; otherwise, and
+/// for files anywhere else, they're read into memory, or shared if
+/// they're embedded in the executable.
+///
+/// A mapped file must not be changed or truncated while it's mapped,
+/// by the game or anything else, or reading it can crash.
+pub fn map<P: AsRef<path::Path>>(ctx: &Context, path: P) -> GameResult<vfs::MappedFile> {
+    ctx.filesystem.map(path)
+}
+
 /// Opens a file in the user directory with the given `filesystem::OpenOptions`.
 /// Note that even if you open a file read-only, it can only access
 /// files in the user directory.
@@ -406,6 +820,37 @@ pub fn is_dir<P: AsRef<path::Path>>(ctx: &Context, path: P) -> bool {
     ctx.filesystem.is_dir(path)
 }
 
+/// Sets how the case of paths is matched when looking up files; see
+/// [`PathCase`](enum.PathCase.html).  Set to
+/// [`ContextBuilder::path_case()`](../struct.ContextBuilder.html#method.path_case)
+/// to begin with.
+pub fn set_path_case(ctx: &mut Context, path_case: PathCase) {
+    ctx.filesystem.set_path_case(path_case)
+}
+
+/// Returns how the case of paths is matched when looking up files.
+pub fn path_case(ctx: &Context) -> PathCase {
+    ctx.filesystem.vfs.path_case()
+}
+
+/// Gets the size, modification time and source of a file or
+/// directory, from the first place it's found.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// // Newest save first.
+/// let mut saves = filesystem::glob(ctx, "/*.sav")?;
+/// saves.sort_by_key(|save| {
+///     let metadata = filesystem::metadata(ctx, save).ok();
+///     std::cmp::Reverse(metadata.and_then(|metadata| metadata.modified))
+/// });
+/// # Ok(()) }
+/// ```
+pub fn metadata<P: AsRef<path::Path>>(ctx: &Context, path: P) -> GameResult<Metadata> {
+    ctx.filesystem.metadata(path)
+}
+
 /// Return the full path to the user data directory
 pub fn user_data_dir(ctx: &Context) -> &path::Path {
     &ctx.filesystem.user_data_path
@@ -433,6 +878,62 @@ pub fn read_dir<P: AsRef<path::Path>>(
     ctx.filesystem.read_dir(path)
 }
 
+/// Returns every file and directory under the given directory, and
+/// under the directories in those, in all the places resources are
+/// searched, sorted by path.  A path that's in more than one place is
+/// only listed once.
+pub fn walk_dir<P: AsRef<path::Path>>(ctx: &Context, path: P) -> GameResult<Vec<path::PathBuf>> {
+    ctx.filesystem.walk_dir(path)
+}
+
+/// Returns the files whose paths match the given glob pattern, in all
+/// the places resources are searched, sorted by path.  In the pattern
+/// `*` matches any part of a file or directory name, `?` any one
+/// character of it, and `**` any number of directories, so
+/// `/maps/**/*.tmx` finds all the `.tmx` files anywhere under `/maps`.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// for level in filesystem::glob(ctx, "/maps/**/*.tmx")? {
+///     println!("Found level {}", level.display());
+/// }
+/// # Ok(()) }
+/// ```
+pub fn glob(ctx: &Context, pattern: &str) -> GameResult<Vec<path::PathBuf>> {
+    ctx.filesystem.glob(pattern)
+}
+
+/// Whether the parts of a path match those of a glob pattern.
+fn glob_match(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (Some(&"**"), _) => {
+            glob_match(&pattern[1..], path) || (!path.is_empty() && glob_match(pattern, &path[1..]))
+        }
+        (Some(part), Some(name)) => {
+            wildcard_match(part.as_bytes(), name.as_bytes())
+                && glob_match(&pattern[1..], &path[1..])
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Whether a file or directory name matches a pattern with `*` and `?`
+/// in it.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (Some(b'*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) => p == n && wildcard_match(&pattern[1..], &name[1..]),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
 /// Prints the contents of all data directories.
 /// Useful for debugging.
 pub fn print_all(ctx: &mut Context) {
@@ -460,8 +961,135 @@ pub fn mount(ctx: &mut Context, path: &path::Path, readonly: bool) {
     ctx.filesystem.mount(path, readonly)
 }
 
-/// Looks for a file named `/conf.toml` in any resource directory and
-/// loads it if it finds it.
+/// Starts or stops watching the resources directory, and any
+/// directories added with [`mount()`](fn.mount.html), for changes to
+/// the files in them.  When one is added, changed or removed
+/// [`EventHandler::resource_changed()`](../event/trait.EventHandler.html#method.resource_changed)
+/// is called with its path, so the game can load it again and show
+/// tweaked sprites and the like without restarting.  Files in zips
+/// and the user directories aren't watched.
+///
+/// The directories are scanned twice a second, which is slow with a
+/// lot of files, so this is meant for development; it's off by default.
+pub fn watch_resources(ctx: &mut Context, watch: bool) {
+    ctx.filesystem.watch_resources(watch)
+}
+
+/// Takes the paths of the resources that changed since the last call,
+/// while they're being watched with
+/// [`watch_resources()`](fn.watch_resources.html).  Only needed with a
+/// custom event loop; [`event::run()`](../event/fn.run.html) hands them
+/// to `EventHandler::resource_changed()` instead.  Any of them in the
+/// [`cache`](../cache/index.html) are invalidated.
+pub fn changed_resources(ctx: &mut Context) -> Vec<path::PathBuf> {
+    let changed = ctx.filesystem.changed_resources();
+    for path in &changed {
+        let _ = ctx.cache.invalidate(path);
+    }
+    changed
+}
+
+/// Mounts a directory or archive, at the given (absolute) path, with
+/// the given priority, for layering DLC and mods over the game's own
+/// resources.  Archives can be any kind
+/// [`mount_archive()`](fn.mount_archive.html) takes, and both are
+/// read-only.
+///
+/// Files are looked for in everything mounted, highest priority first,
+/// so a file in a mod mounted with priority `10` overrides the game's
+/// own.  The resource and user directories and everything mounted
+/// any other way have priority `0`; things with the same priority are
+/// searched in the order they were mounted.  Negative priorities make
+/// fallbacks that are searched after everything else.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let dlc = std::path::Path::new("/games/mygame/dlc/winter.pak");
+/// filesystem::mount_at(ctx, dlc, 10)?;
+/// // ...and when it's turned off again:
+/// filesystem::unmount(ctx, dlc);
+/// # Ok(()) }
+/// ```
+pub fn mount_at(ctx: &mut Context, path: &path::Path, priority: i32) -> GameResult {
+    ctx.filesystem.mount_at(path, priority)
+}
+
+/// Unmounts everything mounted from the given path, however it was
+/// mounted, and returns whether there was anything.  Files already
+/// open, and assets already loaded from them, keep working; it's up to
+/// the game to load them again, and to
+/// [`cache::invalidate()`](../cache/fn.invalidate.html) them if they
+/// were cached.
+pub fn unmount(ctx: &mut Context, path: &path::Path) -> bool {
+    ctx.filesystem.unmount(path)
+}
+
+/// Adds the archive at the given (absolute) path to the list of places
+/// it will search to look for resources.  It can be a zip file, a pak
+/// file made with [`pack::build()`](../pack/fn.build.html), or with the
+/// `tar` feature a tar file, gzipped or not; pak files are the fastest
+/// to load thousands of small files from.
+pub fn mount_archive(ctx: &mut Context, path: &path::Path) -> GameResult {
+    ctx.filesystem.mount_archive(path)
+}
+
+/// Adds an encrypted pak file, made with
+/// [`pack::build_encrypted()`](../pack/fn.build_encrypted.html), to the
+/// list of places it will search to look for resources, decrypting it
+/// with the given key.  See the [`pack`](../pack/index.html) module for
+/// how far that goes in keeping the assets safe.
+#[cfg(feature = "encryption")]
+pub fn mount_encrypted(ctx: &mut Context, path: &path::Path, key: &[u8; 32]) -> GameResult {
+    ctx.filesystem.mount_encrypted(path, key)
+}
+
+/// Adds your own source of files, implementing the
+/// [`vfs::VFS`](../vfs/trait.VFS.html) trait, to the places it will
+/// search to look for resources.
+///
+/// Sources are searched in the order they were added, after the
+/// built-in ones listed in the [module docs](index.html), and the
+/// first one that has a file wins; `read_dir()` lists the files of all
+/// of them.  Files are created, written and deleted in the first
+/// source that allows it, which is always the user directory, so a
+/// custom source is only written to when a path can't be written
+/// there.  With `readonly` ggez never writes to it at all, whatever
+/// the source itself allows.
+pub fn mount_vfs(ctx: &mut Context, vfs: Box<dyn VFS>, readonly: bool) {
+    ctx.filesystem.mount_vfs(vfs, readonly)
+}
+
+/// Adds files embedded in the executable to the places it will search
+/// to look for resources, so a game can be distributed as a single
+/// file without a `resources.zip` next to it.  They're given as pairs
+/// of absolute paths and contents, usually from `include_bytes!()`:
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// static ASSETS: &[(&str, &[u8])] = &[
+///     ("/player.png", include_bytes!("../resources/player.png")),
+///     ("/sounds/pew.ogg", include_bytes!("../resources/pew.ogg")),
+/// ];
+/// # fn t(ctx: &mut Context) {
+/// filesystem::mount_embedded(ctx, ASSETS.iter().copied());
+/// # }
+/// ```
+///
+/// Like zip files, they're searched after the resource and user
+/// directories, so files there override them, and are read-only.
+/// [`ContextBuilder::add_embedded_files()`](../struct.ContextBuilder.html#method.add_embedded_files)
+/// does the same before the `conf.toml` file is looked for.
+pub fn mount_embedded<I>(ctx: &mut Context, files: I)
+where
+    I: IntoIterator<Item = (&'static str, &'static [u8])>,
+{
+    ctx.filesystem.add_embedded_files(files)
+}
+
+/// Looks for a file named `/conf.toml`, `/conf.json` or, with the `ron`
+/// feature, `/conf.ron` in any resource directory and loads it if it
+/// finds it.  Settings it leaves out are the defaults.
 /// If it can't read it for some reason, returns an error.
 pub fn read_config(ctx: &mut Context) -> GameResult<conf::Conf> {
     ctx.filesystem.read_config()
@@ -473,6 +1101,517 @@ pub fn write_config(ctx: &mut Context, conf: &conf::Conf) -> GameResult {
     ctx.filesystem.write_config(conf)
 }
 
+/// The first bytes of every save file.
+const SAVE_MAGIC: &[u8; 8] = b"GGEZSAVE";
+/// The version of the save file header itself, bumped if its layout
+/// ever changes.
+const SAVE_HEADER_VERSION: u8 = 1;
+/// The magic, header version, format, compression flag, and the game's
+/// own version as a little-endian `u32`.
+const SAVE_HEADER_LEN: usize = 15;
+
+/// The serde formats [`save_with()`](fn.save_with.html) can write
+/// save games in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// JSON, which is human-readable, and what [`save()`](fn.save.html)
+    /// uses.
+    Json,
+    /// TOML, which is human-readable too, but can only save structs
+    /// and maps as the outermost value.
+    Toml,
+    /// Bincode, which is small and fast but not human-readable.  Needs
+    /// the `bincode` feature.
+    #[cfg(feature = "bincode")]
+    Bincode,
+}
+
+impl SaveFormat {
+    fn to_byte(self) -> u8 {
+        match self {
+            SaveFormat::Json => 0,
+            SaveFormat::Toml => 1,
+            #[cfg(feature = "bincode")]
+            SaveFormat::Bincode => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<SaveFormat> {
+        match byte {
+            0 => Some(SaveFormat::Json),
+            1 => Some(SaveFormat::Toml),
+            #[cfg(feature = "bincode")]
+            2 => Some(SaveFormat::Bincode),
+            _ => None,
+        }
+    }
+}
+
+/// How [`save_with()`](fn.save_with.html) writes a save game.
+///
+/// ```rust
+/// # use ggez::filesystem::{SaveFormat, SaveOptions};
+/// let options = SaveOptions::default()
+///     .format(SaveFormat::Toml)
+///     .compress(true)
+///     .version(3);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// The format the value is serialized with.
+    pub format: SaveFormat,
+    /// Whether the serialized value is gzipped.
+    pub compress: bool,
+    /// The game's own version number for its save format, so it can
+    /// tell old saves apart with [`save_version()`](fn.save_version.html).
+    pub version: u32,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        SaveOptions {
+            format: SaveFormat::Json,
+            compress: false,
+            version: 0,
+        }
+    }
+}
+
+impl SaveOptions {
+    /// Set the format the value is serialized with.
+    pub fn format(mut self, format: SaveFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set whether the serialized value is gzipped.
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Set the game's own version number for its save format.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+}
+
+/// The file a save slot is kept in, if it's a reasonable name for one.
+fn save_path(dir: &path::Path, slot: &str) -> GameResult<path::PathBuf> {
+    let valid = slot
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ' || c == '.');
+    if slot.is_empty() || slot.starts_with('.') || !valid {
+        return Err(GameError::FilesystemError(format!(
+            "Invalid save slot name: {:?}",
+            slot
+        )));
+    }
+    Ok(dir.join(format!("{}.sav", slot)))
+}
+
+fn write_save<T: Serialize>(
+    dir: &path::Path,
+    slot: &str,
+    value: &T,
+    options: SaveOptions,
+) -> GameResult {
+    use std::io::Write;
+    let path = save_path(dir, slot)?;
+    let serialized = match options.format {
+        SaveFormat::Json => serde_json::to_vec_pretty(value).map_err(|e| e.to_string()),
+        SaveFormat::Toml => toml::to_vec(value).map_err(|e| e.to_string()),
+        #[cfg(feature = "bincode")]
+        SaveFormat::Bincode => bincode::serialize(value).map_err(|e| e.to_string()),
+    };
+    let mut contents = serialized.map_err(|e| {
+        GameError::FilesystemError(format!("Could not serialize save {:?}: {}", slot, e))
+    })?;
+    if options.compress {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&contents)?;
+        contents = encoder.finish()?;
+    }
+
+    // Everything's written to a temporary file first and then renamed
+    // over the old save, so crashing halfway through never leaves a
+    // half-written save behind.
+    fs::create_dir_all(dir)?;
+    let temp_path = path.with_extension("sav.tmp");
+    let write_temp = || -> GameResult {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(SAVE_MAGIC)?;
+        file.write_all(&[
+            SAVE_HEADER_VERSION,
+            options.format.to_byte(),
+            options.compress as u8,
+        ])?;
+        file.write_all(&options.version.to_le_bytes())?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        Ok(())
+    };
+    if let Err(e) = write_temp() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+/// Reads a save file's header, returning its format, whether it's
+/// compressed and the game's version.
+fn read_save_header<R: io::Read>(
+    reader: &mut R,
+    slot: &str,
+) -> GameResult<(SaveFormat, bool, u32)> {
+    let invalid = |reason: &str| {
+        GameError::FilesystemError(format!("Could not load save {:?}: {}", slot, reason))
+    };
+    let mut header = [0; SAVE_HEADER_LEN];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| invalid("not a save file"))?;
+    if &header[..8] != SAVE_MAGIC {
+        return Err(invalid("not a save file"));
+    }
+    if header[8] != SAVE_HEADER_VERSION {
+        return Err(invalid("saved by a newer version of ggez"));
+    }
+    #[cfg(not(feature = "bincode"))]
+    {
+        if header[9] == 2 {
+            return Err(invalid(
+                "saved in bincode, which needs the `bincode` feature",
+            ));
+        }
+    }
+    let format = SaveFormat::from_byte(header[9]).ok_or_else(|| invalid("unknown format"))?;
+    let mut version = [0; 4];
+    version.copy_from_slice(&header[11..]);
+    Ok((format, header[10] != 0, u32::from_le_bytes(version)))
+}
+
+fn read_save<T: DeserializeOwned>(dir: &path::Path, slot: &str) -> GameResult<T> {
+    use std::io::Read;
+    let mut file = io::BufReader::new(fs::File::open(save_path(dir, slot)?)?);
+    let (format, compressed, _) = read_save_header(&mut file, slot)?;
+    let mut contents = Vec::new();
+    if compressed {
+        let _ = flate2::read::GzDecoder::new(file).read_to_end(&mut contents)?;
+    } else {
+        let _ = file.read_to_end(&mut contents)?;
+    }
+    let value = match format {
+        SaveFormat::Json => serde_json::from_slice(&contents).map_err(|e| e.to_string()),
+        SaveFormat::Toml => toml::from_slice(&contents).map_err(|e| e.to_string()),
+        #[cfg(feature = "bincode")]
+        SaveFormat::Bincode => bincode::deserialize(&contents).map_err(|e| e.to_string()),
+    };
+    value.map_err(|e| GameError::FilesystemError(format!("Could not load save {:?}: {}", slot, e)))
+}
+
+/// Saves a value to the given save slot, as JSON, in the
+/// [user data directory](fn.user_data_dir.html).  See
+/// [`save_with()`](fn.save_with.html) for the details.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # use serde_derive::{Deserialize, Serialize};
+/// #[derive(Serialize, Deserialize)]
+/// struct Progress {
+///     level: u32,
+/// }
+///
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// filesystem::save(ctx, "slot1", &Progress { level: 3 })?;
+/// let progress: Progress = filesystem::load(ctx, "slot1")?;
+/// # Ok(()) }
+/// ```
+pub fn save<T: Serialize>(ctx: &Context, slot: &str, value: &T) -> GameResult {
+    save_with(ctx, slot, value, SaveOptions::default())
+}
+
+/// Saves a value to the given save slot, in the
+/// [user data directory](fn.user_data_dir.html), with the given
+/// format, compression and version.
+///
+/// The save is written to `<slot>.sav`, so it also shows up in the
+/// filesystem as `/<slot>.sav`.  Slot names can only have letters,
+/// numbers, spaces, `-`, `_` and `.` in them, and can't start with a
+/// `.`.  The save is written to a temporary file first, which then
+/// replaces the old one, so a crash or a full disk never leaves a
+/// half-written save in its place.
+pub fn save_with<T: Serialize>(
+    ctx: &Context,
+    slot: &str,
+    value: &T,
+    options: SaveOptions,
+) -> GameResult {
+    write_save(&ctx.filesystem.user_data_path, slot, value, options)
+}
+
+/// Loads a value saved with [`save()`](fn.save.html) or
+/// [`save_with()`](fn.save_with.html).  The format and compression
+/// are read from the save itself.
+pub fn load<T: DeserializeOwned>(ctx: &Context, slot: &str) -> GameResult<T> {
+    read_save(&ctx.filesystem.user_data_path, slot)
+}
+
+/// Returns the version a save was saved with, from its
+/// [`SaveOptions`](struct.SaveOptions.html), without loading the
+/// rest of it, so older saves can be loaded as an older type and
+/// migrated.
+pub fn save_version(ctx: &Context, slot: &str) -> GameResult<u32> {
+    let path = save_path(&ctx.filesystem.user_data_path, slot)?;
+    let mut file = fs::File::open(path)?;
+    let (_, _, version) = read_save_header(&mut file, slot)?;
+    Ok(version)
+}
+
+/// How many threads a `Preloader` decodes assets on.
+const PRELOAD_THREADS: usize = 4;
+/// How long `Preloader::update()` spends reading files each frame.
+const PRELOAD_READ_TIME: time::Duration = time::Duration::from_millis(4);
+
+/// A file read by a `Preloader`, on its way to a worker thread to be
+/// decoded.
+type PreloadJob = (path::PathBuf, vfs::MappedFile);
+
+/// What a `Preloader`'s worker thread made of a file.
+enum Preloaded {
+    Image(u16, u16, Vec<u8>),
+    Font(vfs::MappedFile),
+    #[cfg(feature = "audio")]
+    Sound(crate::audio::SoundData),
+    Skipped,
+}
+
+/// Decodes a preloaded file, on a worker thread, as far as it can be
+/// without the `Context`.
+fn decode_preloaded(path: &path::Path, bytes: vfs::MappedFile) -> Result<Preloaded, String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "ttf" | "otf" => {
+            let _ = glyph_brush::ab_glyph::FontRef::try_from_slice(&bytes)
+                .map_err(|e| e.to_string())?;
+            Ok(Preloaded::Font(bytes))
+        }
+        #[cfg(feature = "audio")]
+        "ogg" | "wav" | "flac" | "mp3" | "opus" => Ok(Preloaded::Sound(
+            crate::audio::SoundData::from_bytes(&bytes),
+        )),
+        _ if image::ImageFormat::from_path(path).is_ok() => {
+            let image = image::load_from_memory(&bytes)
+                .map_err(|e| e.to_string())?
+                .to_rgba8();
+            let (width, height) = image.dimensions();
+            match (u16::try_from(width), u16::try_from(height)) {
+                (Ok(width), Ok(height)) => Ok(Preloaded::Image(width, height, image.into_raw())),
+                _ => Err(String::from("Image bigger than u16::MAX")),
+            }
+        }
+        _ => Ok(Preloaded::Skipped),
+    }
+}
+
+/// Loads a list of assets into the [`cache`](../cache/index.html) a
+/// bit at a time, so a loading screen can show how far along it is
+/// instead of freezing.
+///
+/// Call [`update()`](#method.update) once a frame until it's
+/// [`is_done()`](#method.is_done).  Each update reads files for a few
+/// milliseconds and hands them to background threads, which decode
+/// images and check fonts while the game goes on drawing; decoded
+/// images are then uploaded to the GPU by the next update.  Once
+/// they're all in, [`graphics::load_cached_image()`](../graphics/fn.load_cached_image.html),
+/// [`graphics::load_cached_font()`](../graphics/fn.load_cached_font.html)
+/// and [`audio::load_cached_sound_data()`](../audio/fn.load_cached_sound_data.html)
+/// hand them out without loading anything.
+///
+/// Files are read on the main thread, since the filesystem lives in
+/// the `Context`; those in resource directories are memory-mapped, so
+/// it's mostly the decoding that takes time.  Files that are neither
+/// images, fonts nor sounds are skipped, with a warning.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let mut preloader = filesystem::Preloader::new(vec!["/player.png", "/music.ogg"]);
+/// // In `update()`:
+/// let progress = preloader.update(ctx);
+/// // ...and draw a progress bar `progress` of the way full.
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct Preloader {
+    /// Files yet to be read.
+    pending: VecDeque<path::PathBuf>,
+    total: usize,
+    done: usize,
+    jobs: mpsc::Sender<PreloadJob>,
+    results: mpsc::Receiver<(path::PathBuf, Result<Preloaded, String>)>,
+    errors: Vec<(path::PathBuf, GameError)>,
+}
+
+impl Preloader {
+    /// Creates a `Preloader` for the given files.
+    pub fn new<I, P>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<path::PathBuf>,
+    {
+        let pending = paths.into_iter().map(Into::into).collect::<VecDeque<_>>();
+        let (jobs, job_receiver) = mpsc::channel::<PreloadJob>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for _ in 0..PRELOAD_THREADS.min(pending.len()) {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            let _ = thread::spawn(move || loop {
+                // The lock is only held while waiting for a job, so the
+                // others can be decoding in the meantime.
+                let job = job_receiver.lock().map(|receiver| receiver.recv());
+                let (path, bytes) = match job {
+                    Ok(Ok(job)) => job,
+                    _ => break,
+                };
+                let decoded = decode_preloaded(&path, bytes);
+                if result_sender.send((path, decoded)).is_err() {
+                    break;
+                }
+            });
+        }
+        Preloader {
+            total: pending.len(),
+            pending,
+            done: 0,
+            jobs,
+            results,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Creates a `Preloader` for the files listed in a manifest file:
+    /// one path per line, where blank lines and lines starting with
+    /// `#` are ignored, and lines with wildcards are expanded with
+    /// [`glob()`](fn.glob.html).
+    ///
+    /// ```text
+    /// # Everything in the first level.
+    /// /levels/1/**
+    /// /images/player.png
+    /// /sounds/*.ogg
+    /// ```
+    pub fn from_manifest<P: AsRef<path::Path>>(ctx: &mut Context, manifest: P) -> GameResult<Self> {
+        use std::io::Read;
+        let mut contents = String::new();
+        let _ = open(ctx, manifest)?.read_to_string(&mut contents)?;
+        let mut paths = Vec::new();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            } else if line.contains(|c: char| c == '*' || c == '?') {
+                paths.extend(glob(ctx, line)?);
+            } else {
+                paths.push(path::PathBuf::from(line));
+            }
+        }
+        Ok(Preloader::new(paths))
+    }
+
+    /// Reads some more files and loads the ones that have been decoded
+    /// into the cache, returning the progress so far.  Files that fail
+    /// to load are kept in [`errors()`](#method.errors) rather than
+    /// stopping the rest.
+    pub fn update(&mut self, ctx: &mut Context) -> f32 {
+        let started = time::Instant::now();
+        while started.elapsed() < PRELOAD_READ_TIME {
+            let path = match self.pending.pop_front() {
+                Some(path) => path,
+                None => break,
+            };
+            if ctx.cache.contains(&path) {
+                self.done += 1;
+                continue;
+            }
+            match map(ctx, &path) {
+                Ok(bytes) => {
+                    if let Err(mpsc::SendError((path, _))) = self.jobs.send((path, bytes)) {
+                        let error = GameError::ResourceLoadError(String::from(
+                            "Preloader threads have stopped",
+                        ));
+                        self.errors.push((path, error));
+                        self.done += 1;
+                    }
+                }
+                Err(e) => {
+                    self.errors.push((path, e));
+                    self.done += 1;
+                }
+            }
+        }
+
+        while let Ok((path, decoded)) = self.results.try_recv() {
+            self.done += 1;
+            let loaded = decoded
+                .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path.display(), e)))
+                .and_then(|decoded| match decoded {
+                    Preloaded::Image(width, height, rgba) => {
+                        let image = crate::graphics::Image::from_rgba8(ctx, width, height, &rgba)?;
+                        ctx.cache.insert_image(&path, image);
+                        Ok(())
+                    }
+                    Preloaded::Font(bytes) => {
+                        let font = crate::graphics::Font::new_glyph_font_bytes(ctx, &bytes)?;
+                        ctx.cache.insert_font(&path, font, bytes.len());
+                        Ok(())
+                    }
+                    #[cfg(feature = "audio")]
+                    Preloaded::Sound(data) => {
+                        ctx.cache.insert_sound_data(&path, data);
+                        Ok(())
+                    }
+                    Preloaded::Skipped => {
+                        warn!(
+                            "Preloader skipped {:?}, which isn't an asset it knows",
+                            path
+                        );
+                        Ok(())
+                    }
+                });
+            if let Err(e) = loaded {
+                self.errors.push((path, e));
+            }
+        }
+        self.progress()
+    }
+
+    /// How far along it is, from `0.0` to `1.0`.
+    pub fn progress(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+
+    /// Whether everything has been loaded, or failed to.
+    pub fn is_done(&self) -> bool {
+        self.done == self.total
+    }
+
+    /// The files that couldn't be loaded so far, and why.
+    pub fn errors(&self) -> &[(path::PathBuf, GameError)] {
+        &self.errors
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::conf;
@@ -489,11 +1628,14 @@ mod tests {
         ofs.push_front(Box::new(physfs));
         Filesystem {
             vfs: ofs,
+            priorities: vec![0],
 
             resources_path: "".into(),
             zip_path: "".into(),
             user_config_path: "".into(),
             user_data_path: "".into(),
+            mounted_paths: Vec::new(),
+            watcher: None,
         }
     }
 
@@ -562,6 +1704,212 @@ mod tests {
         }
     }
 
+    #[test]
+    fn headless_test_walk_dir_and_glob() {
+        let f = dummy_fs_for_tests();
+        let all = f.walk_dir("/").unwrap();
+        assert!(all.contains(&path::PathBuf::from("/tile.png")));
+        let mut sorted = all.clone();
+        sorted.sort();
+        assert_eq!(all, sorted);
+
+        let dragons = f.glob("/dragon?.png").unwrap();
+        assert_eq!(dragons.len(), 4);
+        assert_eq!(dragons[0], path::PathBuf::from("/dragon1.png"));
+        let oggs = f.glob("**/*.ogg").unwrap();
+        assert!(oggs.contains(&path::PathBuf::from("/pew.ogg")));
+        assert!(oggs.iter().all(|p| p.extension().unwrap() == "ogg"));
+        assert!(f.glob("/nowhere/**/*.png").unwrap().is_empty());
+
+        // Files in a mounted zip are listed and globbed like the rest.
+        let zip_bytes = {
+            let mut zip_archive = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+            let options = zip::write::FileOptions::default();
+            zip_archive.add_directory("maps/", options).unwrap();
+            zip_archive.start_file("maps/town.tmx", options).unwrap();
+            zip_archive.write_all(b"<map/>").unwrap();
+            zip_archive
+                .start_file("maps/caves/deep.tmx", options)
+                .unwrap();
+            zip_archive.write_all(b"<map/>").unwrap();
+            zip_archive.finish().unwrap().into_inner()
+        };
+        let mut f = dummy_fs_for_tests();
+        f.add_zip_file(io::Cursor::new(zip_bytes)).unwrap();
+        let all = f.walk_dir("/").unwrap();
+        for path in &[
+            "/maps",
+            "/maps/caves",
+            "/maps/caves/deep.tmx",
+            "/maps/town.tmx",
+        ] {
+            assert!(all.contains(&path::PathBuf::from(path)), "{}", path);
+        }
+        assert_eq!(
+            f.glob("/maps/**/*.tmx").unwrap(),
+            vec![
+                path::PathBuf::from("/maps/caves/deep.tmx"),
+                path::PathBuf::from("/maps/town.tmx")
+            ]
+        );
+    }
+
+    #[test]
+    fn headless_test_glob_match() {
+        assert!(glob_match(&["maps", "**", "*.tmx"], &["maps", "a.tmx"]));
+        assert!(glob_match(
+            &["maps", "**", "*.tmx"],
+            &["maps", "x", "y", "a.tmx"]
+        ));
+        assert!(!glob_match(&["maps", "**", "*.tmx"], &["maps", "a.png"]));
+        assert!(!glob_match(&["*.tmx"], &["maps", "a.tmx"]));
+        assert!(wildcard_match(b"a*c?e", b"abbbcde"));
+        assert!(!wildcard_match(b"a*c?e", b"abbbce"));
+    }
+
+    #[test]
+    fn headless_test_metadata() {
+        let mut f = dummy_fs_for_tests();
+        let mut resources = path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        resources.push("resources");
+        let metadata = f.metadata("/tile.png").unwrap();
+        let expected = fs::metadata(resources.join("tile.png")).unwrap();
+        assert!(metadata.is_file);
+        assert_eq!(metadata.len, expected.len());
+        assert_eq!(metadata.modified, expected.modified().ok());
+        assert_eq!(metadata.source, FileSource::Directory(resources));
+
+        f.mount_vfs(
+            Box::new(vfs::EmbeddedFS::new(vec![("/embedded.txt", &b"hi"[..])])),
+            true,
+        );
+        let metadata = f.metadata("/embedded.txt").unwrap();
+        assert_eq!(metadata.len, 2);
+        assert_eq!(metadata.modified, None);
+        assert_eq!(metadata.source, FileSource::Other);
+        assert!(f.metadata("/nonexistent.png").is_err());
+    }
+
+    #[test]
+    fn headless_test_mount_priorities() {
+        let mut f = dummy_fs_for_tests();
+        let mut dir = env::temp_dir();
+        dir.push(format!("ggez_mount_test_{}", std::process::id()));
+        let (low, high) = (dir.join("low"), dir.join("high"));
+        fs::create_dir_all(&low).unwrap();
+        fs::create_dir_all(&high).unwrap();
+        fs::write(low.join("tile.png"), b"low").unwrap();
+        fs::write(low.join("only_low.txt"), b"low").unwrap();
+        fs::write(high.join("only_low.txt"), b"high").unwrap();
+
+        let read = |f: &mut Filesystem, name: &str| {
+            let mut contents = Vec::new();
+            let _ = f.open(name).unwrap().read_to_end(&mut contents).unwrap();
+            contents
+        };
+        f.mount_at(&low, -1).unwrap();
+        f.mount_at(&high, 10).unwrap();
+        // Below the resources, so it doesn't override them...
+        assert_ne!(read(&mut f, "/tile.png"), b"low");
+        // ...but above it overrides everything.
+        assert_eq!(read(&mut f, "/only_low.txt"), b"high");
+
+        assert!(f.unmount(&high));
+        assert!(!f.unmount(&high));
+        assert_eq!(read(&mut f, "/only_low.txt"), b"low");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn headless_test_unrecognized_archives() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("ggez_archive_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let junk = dir.join("junk.bin");
+        fs::write(&junk, b"not an archive at all").unwrap();
+        let encrypted = dir.join("encrypted.pak");
+        fs::write(&encrypted, b"GGEZPAKE0123456789ab").unwrap();
+        let empty = dir.join("empty.zip");
+        fs::write(&empty, b"").unwrap();
+
+        for path in &[&junk, &encrypted, &empty] {
+            match open_archive(path) {
+                Err(GameError::FilesystemError(msg)) => {
+                    assert!(!msg.contains("tar file"), "{}", msg)
+                }
+                other => panic!("{:?} opened as {:?}", path, other),
+            }
+        }
+        match open_archive(&encrypted) {
+            Err(GameError::FilesystemError(msg)) => assert!(msg.contains("encrypted")),
+            other => panic!("Encrypted pak opened as {:?}", other),
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn headless_test_save_and_load() {
+        use std::collections::BTreeMap;
+        let mut dir = env::temp_dir();
+        dir.push(format!("ggez_save_test_{}", std::process::id()));
+        let mut value = BTreeMap::new();
+        let _ = value.insert("levels".to_string(), vec![1u32, 2, 3]);
+        let _ = value.insert("keys".to_string(), vec![]);
+
+        let formats = [
+            SaveFormat::Json,
+            SaveFormat::Toml,
+            #[cfg(feature = "bincode")]
+            SaveFormat::Bincode,
+        ];
+        for &format in &formats {
+            for &compress in &[false, true] {
+                let options = SaveOptions::default()
+                    .format(format)
+                    .compress(compress)
+                    .version(7);
+                write_save(&dir, "slot 1", &value, options).unwrap();
+                let loaded: BTreeMap<String, Vec<u32>> = read_save(&dir, "slot 1").unwrap();
+                assert_eq!(loaded, value);
+            }
+        }
+        let mut file = fs::File::open(dir.join("slot 1.sav")).unwrap();
+        let (_, compressed, version) = read_save_header(&mut file, "slot 1").unwrap();
+        assert!(compressed);
+        assert_eq!(version, 7);
+        assert!(!dir.join("slot 1.sav.tmp").exists());
+
+        assert!(write_save(&dir, "../slot", &value, SaveOptions::default()).is_err());
+        assert!(write_save(&dir, "", &value, SaveOptions::default()).is_err());
+        assert!(read_save::<BTreeMap<String, Vec<u32>>>(&dir, "missing").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn headless_test_watcher() {
+        let mut dir = env::temp_dir();
+        dir.push(format!("ggez_watcher_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sprites")).unwrap();
+        fs::write(dir.join("sprites/old.png"), b"old").unwrap();
+
+        let mut watcher = Watcher::new(vec![dir.clone()]);
+        watcher.check();
+        assert!(watcher.changed.is_empty());
+
+        fs::write(dir.join("sprites/new.png"), b"new").unwrap();
+        fs::remove_file(dir.join("sprites/old.png")).unwrap();
+        watcher.check();
+        watcher.changed.sort();
+        assert_eq!(
+            watcher.changed,
+            vec![
+                path::PathBuf::from("/sprites/new.png"),
+                path::PathBuf::from("/sprites/old.png")
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
     #[test]
     fn headless_test_write_config() {
         let mut f = dummy_fs_for_tests();
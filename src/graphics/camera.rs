@@ -0,0 +1,352 @@
+//! A simple 2D camera, for games whose world is bigger than the screen.
+
+use std::time::Duration;
+
+use crate::context::Context;
+use crate::error::GameResult;
+use crate::graphics::{Matrix4, Point2, Rect};
+
+/// A 2D camera with a position, zoom and rotation, looking at
+/// the world through a viewport.
+///
+/// The viewport is the rectangle, in screen coordinates, that the
+/// camera's view is mapped onto; usually this is just
+/// [`screen_coordinates()`](fn.screen_coordinates.html).  The camera's
+/// position is the point of the world that appears in the center of
+/// the viewport.
+///
+/// Use [`set_camera()`](fn.set_camera.html) to make it take effect,
+/// and [`set_screen_coordinates()`](fn.set_screen_coordinates.html) to
+/// go back to drawing in screen space (for UI and such).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    position: Point2,
+    zoom: f32,
+    rotation: f32,
+    viewport: Rect,
+    bounds: Option<Rect>,
+    shake_intensity: f32,
+    shake_duration: f32,
+    shake_remaining: f32,
+    shake_offset: Point2,
+    rng_state: u32,
+}
+
+impl Camera {
+    /// Creates a new camera looking at the center of the given viewport,
+    /// so that until it's moved, drawing looks exactly the same as without it.
+    pub fn new(viewport: Rect) -> Self {
+        Camera {
+            position: Point2::new(viewport.x + viewport.w / 2.0, viewport.y + viewport.h / 2.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport,
+            bounds: None,
+            shake_intensity: 0.0,
+            shake_duration: 0.0,
+            shake_remaining: 0.0,
+            shake_offset: Point2::ZERO,
+            rng_state: 0x9E37_79B9,
+        }
+    }
+
+    /// Creates a camera whose viewport is the current
+    /// [`screen_coordinates()`](fn.screen_coordinates.html).
+    pub fn from_screen(ctx: &Context) -> Self {
+        Camera::new(ctx.gfx_context.screen_rect)
+    }
+
+    /// The world position shown in the center of the viewport.
+    pub fn position(&self) -> mint::Point2<f32> {
+        self.position.into()
+    }
+
+    /// Moves the camera to look at the given world position.
+    ///
+    /// The position is clamped to the camera's bounds, if it has any.
+    pub fn set_position<P>(&mut self, position: P)
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        self.position = Point2::from(position.into());
+        self.clamp_to_bounds();
+    }
+
+    /// Moves the camera by the given offset in world units.
+    pub fn translate<V>(&mut self, offset: V)
+    where
+        V: Into<mint::Vector2<f32>>,
+    {
+        let offset: mint::Vector2<f32> = offset.into();
+        self.position += Point2::new(offset.x, offset.y);
+        self.clamp_to_bounds();
+    }
+
+    /// The zoom factor; 2.0 makes everything appear twice as big.
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets the zoom factor.  Must be greater than zero.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        debug_assert!(zoom > 0.0, "Camera zoom must be positive!");
+        self.zoom = zoom;
+        self.clamp_to_bounds();
+    }
+
+    /// The camera's rotation in radians.
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Sets the camera's rotation in radians.  Rotating the camera
+    /// clockwise makes the world appear to rotate counter-clockwise.
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    /// The rectangle, in screen coordinates, the camera draws into.
+    pub fn viewport(&self) -> Rect {
+        self.viewport
+    }
+
+    /// Sets the rectangle, in screen coordinates, the camera draws into.
+    pub fn set_viewport(&mut self, viewport: Rect) {
+        self.viewport = viewport;
+        self.clamp_to_bounds();
+    }
+
+    /// The region of the world the camera is kept inside of, if any.
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+
+    /// Restricts the camera so it never shows anything outside the given
+    /// region of the world.  If the region is smaller than the camera's
+    /// view, the camera is centered on it instead.
+    ///
+    /// Rotation is not taken into account.
+    pub fn set_bounds(&mut self, bounds: Option<Rect>) {
+        self.bounds = bounds;
+        self.clamp_to_bounds();
+    }
+
+    /// The region of the world currently visible through the viewport,
+    /// ignoring rotation and shake.
+    pub fn visible_rect(&self) -> Rect {
+        let w = self.viewport.w / self.zoom;
+        let h = self.viewport.h / self.zoom;
+        Rect::new(self.position.x - w / 2.0, self.position.y - h / 2.0, w, h)
+    }
+
+    /// Smoothly moves the camera towards `target`.
+    ///
+    /// `smoothing` is how quickly the camera catches up; higher is snappier,
+    /// and the motion is independent of framerate for a given value.
+    /// Call this once per update with the time that update covers.
+    pub fn follow<P>(&mut self, target: P, smoothing: f32, dt: Duration)
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        let target = Point2::from(target.into());
+        let t = 1.0 - (-smoothing * dt.as_secs_f32()).exp();
+        self.position = self.position.lerp(target, t.max(0.0).min(1.0));
+        self.clamp_to_bounds();
+    }
+
+    /// Starts shaking the camera by up to `intensity` world units,
+    /// fading out over `duration`.  Call [`update()`](#method.update)
+    /// every frame to make it actually shake.
+    pub fn shake(&mut self, intensity: f32, duration: Duration) {
+        self.shake_intensity = intensity;
+        self.shake_duration = duration.as_secs_f32();
+        self.shake_remaining = self.shake_duration;
+    }
+
+    /// Returns whether or not the camera is currently shaking.
+    pub fn is_shaking(&self) -> bool {
+        self.shake_remaining > 0.0
+    }
+
+    /// Advances the camera's shake by the given amount of time.
+    pub fn update(&mut self, dt: Duration) {
+        if self.shake_remaining <= 0.0 {
+            self.shake_offset = Point2::ZERO;
+            return;
+        }
+        self.shake_remaining = (self.shake_remaining - dt.as_secs_f32()).max(0.0);
+        let falloff = if self.shake_duration > 0.0 {
+            self.shake_remaining / self.shake_duration
+        } else {
+            0.0
+        };
+        let magnitude = self.shake_intensity * falloff;
+        let x = self.next_random() * 2.0 - 1.0;
+        let y = self.next_random() * 2.0 - 1.0;
+        self.shake_offset = Point2::new(x, y) * magnitude;
+    }
+
+    /// Converts a point in screen coordinates to the world point
+    /// the camera shows there.
+    pub fn screen_to_world<P>(&self, point: P) -> mint::Point2<f32>
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        let point = Point2::from(point.into());
+        let local = (point - self.viewport_center()) / self.zoom;
+        let (sin, cos) = self.rotation.sin_cos();
+        let rotated = Point2::new(local.x * cos - local.y * sin, local.x * sin + local.y * cos);
+        (rotated + self.position + self.shake_offset).into()
+    }
+
+    /// Converts a point in the world to where it appears in screen coordinates.
+    pub fn world_to_screen<P>(&self, point: P) -> mint::Point2<f32>
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        let point = Point2::from(point.into());
+        let local = point - self.position - self.shake_offset;
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let rotated = Point2::new(local.x * cos - local.y * sin, local.x * sin + local.y * cos);
+        (rotated * self.zoom + self.viewport_center()).into()
+    }
+
+    /// Returns the matrix transforming world coordinates into
+    /// screen coordinates.
+    pub fn to_matrix(&self) -> mint::ColumnMatrix4<f32> {
+        self.view_matrix().into()
+    }
+
+    pub(crate) fn view_matrix(&self) -> Matrix4 {
+        let center = self.viewport_center();
+        let eye = self.position + self.shake_offset;
+        Matrix4::from_translation(center.extend(0.0))
+            * Matrix4::from_scale(glam::vec3(self.zoom, self.zoom, 1.0))
+            * Matrix4::from_rotation_z(-self.rotation)
+            * Matrix4::from_translation((-eye).extend(0.0))
+    }
+
+    fn viewport_center(&self) -> Point2 {
+        Point2::new(
+            self.viewport.x + self.viewport.w / 2.0,
+            self.viewport.y + self.viewport.h / 2.0,
+        )
+    }
+
+    fn clamp_to_bounds(&mut self) {
+        if let Some(bounds) = self.bounds {
+            let half_w = self.viewport.w / self.zoom / 2.0;
+            let half_h = self.viewport.h / self.zoom / 2.0;
+            self.position.x = if half_w * 2.0 >= bounds.w {
+                bounds.x + bounds.w / 2.0
+            } else {
+                self.position
+                    .x
+                    .max(bounds.x + half_w)
+                    .min(bounds.x + bounds.w - half_w)
+            };
+            self.position.y = if half_h * 2.0 >= bounds.h {
+                bounds.y + bounds.h / 2.0
+            } else {
+                self.position
+                    .y
+                    .max(bounds.y + half_h)
+                    .min(bounds.y + bounds.h - half_h)
+            };
+        }
+    }
+
+    /// A tiny xorshift generator so shaking doesn't need a `rand` dependency.
+    /// Returns a number in `[0, 1)`.
+    fn next_random(&mut self) -> f32 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+        (x >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/// Makes the given camera the current view, so that everything drawn
+/// afterwards is positioned in world coordinates.
+///
+/// This replaces the current projection, the same way
+/// [`set_screen_coordinates()`](fn.set_screen_coordinates.html) does;
+/// call that to go back to drawing in screen space.  If the camera moves
+/// you need to call this again to see the change.
+pub fn set_camera(ctx: &mut Context, camera: &Camera) -> GameResult {
+    let gfx = &mut ctx.gfx_context;
+    gfx.set_projection_rect(camera.viewport);
+    let projection = gfx.projection() * camera.view_matrix();
+    gfx.set_projection(projection);
+    gfx.set_global_mvp(Matrix4::IDENTITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn assert_points_eq(a: mint::Point2<f32>, b: mint::Point2<f32>) {
+        assert_relative_eq!(a.x, b.x, epsilon = 1e-3);
+        assert_relative_eq!(a.y, b.y, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn headless_test_camera_round_trip() {
+        let mut camera = Camera::new(Rect::new(0.0, 0.0, 800.0, 600.0));
+        camera.set_position([1000.0, -250.0]);
+        camera.set_zoom(2.5);
+        camera.set_rotation(0.7);
+        let world = mint::Point2 { x: 12.0, y: 34.0 };
+        let screen = camera.world_to_screen(world);
+        assert_points_eq(camera.screen_to_world(screen), world);
+
+        let m = Matrix4::from(camera.to_matrix());
+        let p = m.transform_point3(glam::vec3(world.x, world.y, 0.0));
+        assert_points_eq(mint::Point2 { x: p.x, y: p.y }, screen);
+    }
+
+    #[test]
+    fn headless_test_camera_default_is_identity() {
+        let camera = Camera::new(Rect::new(0.0, 0.0, 800.0, 600.0));
+        let p = mint::Point2 { x: 10.0, y: 20.0 };
+        assert_points_eq(camera.world_to_screen(p), p);
+    }
+
+    #[test]
+    fn headless_test_camera_bounds() {
+        let mut camera = Camera::new(Rect::new(0.0, 0.0, 800.0, 600.0));
+        camera.set_bounds(Some(Rect::new(0.0, 0.0, 2000.0, 1000.0)));
+        camera.set_position([-500.0, 5000.0]);
+        assert_points_eq(camera.position(), mint::Point2 { x: 400.0, y: 700.0 });
+
+        // Zoomed out further than the bounds are big, so we center on them.
+        camera.set_zoom(0.25);
+        assert_points_eq(
+            camera.position(),
+            mint::Point2 {
+                x: 1000.0,
+                y: 500.0,
+            },
+        );
+    }
+
+    #[test]
+    fn headless_test_camera_shake_decays() {
+        let mut camera = Camera::new(Rect::new(0.0, 0.0, 800.0, 600.0));
+        camera.shake(10.0, Duration::from_millis(100));
+        assert!(camera.is_shaking());
+        camera.update(Duration::from_millis(50));
+        let p = camera.world_to_screen([400.0, 300.0]);
+        assert!((p.x - 400.0).abs() <= 10.0 && (p.y - 300.0).abs() <= 10.0);
+        camera.update(Duration::from_millis(60));
+        camera.update(Duration::from_millis(10));
+        assert!(!camera.is_shaking());
+        assert_points_eq(
+            camera.world_to_screen([400.0, 300.0]),
+            mint::Point2 { x: 400.0, y: 300.0 },
+        );
+    }
+}
@@ -3,7 +3,7 @@
 use std::path;
 
 use gfx::format::{Format, Swizzle};
-use gfx::handle::RawRenderTargetView;
+use gfx::handle::{RawDepthStencilView, RawRenderTargetView};
 use gfx::memory::{Bind, Usage};
 use gfx::texture::{AaMode, Kind};
 use gfx::Factory;
@@ -24,6 +24,7 @@ where
     Spec: BackendSpec,
 {
     target: RawRenderTargetView<Spec::Resources>,
+    depth: RawDepthStencilView<Spec::Resources>,
     image: Image,
     debug_id: DebugId,
 }
@@ -84,6 +85,7 @@ where
         };
         let kind = Kind::D2(width, height, aa);
         let levels = 1;
+        let depth_format = ctx.gfx_context.depth_format();
         let factory = &mut ctx.gfx_context.factory;
         let texture_create_info = gfx::texture::Info {
             kind,
@@ -107,8 +109,30 @@ where
             layer: None,
         };
         let target = factory.view_texture_as_render_target_raw(&tex, render_desc)?;
+        let depth_create_info = gfx::texture::Info {
+            kind,
+            levels,
+            format: depth_format.0,
+            bind: Bind::DEPTH_STENCIL,
+            usage: Usage::Data,
+        };
+        let depth_tex =
+            factory.create_texture_raw(depth_create_info, Some(depth_format.1), None)?;
+        let depth_desc = gfx::texture::DepthStencilDesc {
+            level: 0,
+            layer: None,
+            flags: gfx::texture::DepthStencilFlags::empty(),
+        };
+        let depth = factory.view_texture_as_depth_stencil_raw(&depth_tex, depth_desc)?;
+        let bytes_per_pixel = usize::from(color_format.0.get_total_bits()) / 8
+            + usize::from(depth_format.0.get_total_bits()) / 8;
+        let bytes = usize::from(width)
+            * usize::from(height)
+            * bytes_per_pixel
+            * usize::from(u8::from(samples));
         Ok(Canvas {
             target,
+            depth,
             image: Image {
                 texture: resource,
                 texture_handle: tex,
@@ -116,6 +140,7 @@ where
                 blend_mode: None,
                 width,
                 height,
+                memory: ctx.gfx_context.stats.track_texture(bytes, true),
                 debug_id,
             },
             debug_id,
@@ -136,6 +161,22 @@ where
         )
     }
 
+    /// Create a new HDR `Canvas`, which stores colors as 16-bit floats
+    /// so they can go above 1.0, e.g. for accumulating light or bloom.
+    /// See [`set_hdr()`](fn.set_hdr.html).
+    ///
+    /// Its contents can't be read back with
+    /// [`to_rgba8()`](#method.to_rgba8); draw it to an ordinary canvas
+    /// first.
+    pub fn new_hdr(
+        ctx: &mut Context,
+        width: u16,
+        height: u16,
+        samples: conf::NumSamples,
+    ) -> GameResult<Canvas> {
+        Canvas::new(ctx, width, height, samples, super::postprocess::HDR_FORMAT)
+    }
+
     /// Gets the backend `Image` that is being rendered to. Note that this will be flipped but otherwise the same, use the [`to_image`](#method.to_image) function for the unflipped version.
     pub fn raw_image(&self) -> &Image {
         &self.image
@@ -152,6 +193,12 @@ where
         &self.target
     }
 
+    /// The depth buffer that goes with the target, used when depth
+    /// testing is on.
+    pub(crate) fn depth(&self) -> &RawDepthStencilView<S::Resources> {
+        &self.depth
+    }
+
     /// Dumps the flipped `Canvas`'s data to a `Vec` of `u8` RBGA8 values.
     pub fn to_rgba8(&self, ctx: &mut Context) -> GameResult<Vec<u8>> {
         let mut pixel_data = self.image.to_rgba8(ctx)?;
@@ -217,10 +264,10 @@ where
     }
 }
 
-impl Drawable for Canvas {
-    fn draw(&self, ctx: &mut Context, param: DrawParam) -> GameResult {
-        self.debug_id.assert(ctx);
-
+impl Canvas {
+    /// Turns the `DrawParam` for drawing the canvas into the one for
+    /// drawing its raw image, which is scaled to unit size and flipped.
+    pub(crate) fn raw_draw_param(&self, param: DrawParam) -> DrawParam {
         // We have to mess with the scale to make everything
         // be its-unit-size-in-pixels.
         let scale_x = param.src.w * f32::from(self.width());
@@ -231,8 +278,15 @@ impl Drawable for Canvas {
                 * Matrix4::from_scale(glam::vec3(scale_x, scale_y, 1.0)),
         );
 
-        let new_param = flip_draw_param_vertical(param);
+        flip_draw_param_vertical(param)
+    }
+}
 
+impl Drawable for Canvas {
+    fn draw(&self, ctx: &mut Context, param: DrawParam) -> GameResult {
+        self.debug_id.assert(ctx);
+        let new_param = self.raw_draw_param(param);
+        ctx.gfx_context.gradient = param.local_gradient();
         image::draw_image_raw(&self.image, ctx, new_param)
     }
     fn dimensions(&self, _: &mut Context) -> Option<Rect> {
@@ -274,10 +328,11 @@ pub fn set_canvas(ctx: &mut Context, target: Option<&Canvas>) {
     match target {
         Some(surface) => {
             surface.debug_id.assert(ctx);
-            ctx.gfx_context.data.out = surface.target.clone();
+            ctx.gfx_context
+                .set_render_target(surface.target.clone(), &surface.depth);
         }
         None => {
-            ctx.gfx_context.data.out = ctx.gfx_context.screen_render_target.clone();
+            ctx.gfx_context.set_screen_as_render_target();
         }
     };
 }
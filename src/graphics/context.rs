@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use gfx::traits::FactoryExt;
@@ -8,9 +9,14 @@ use glyph_brush::{GlyphBrush, GlyphBrushBuilder};
 use ::image as imgcrate;
 use winit::{self, dpi};
 
-use crate::conf::{FullscreenType, WindowMode, WindowSetup};
+use crate::conf::{DpiPolicy, FullscreenType, WindowMode, WindowSetup};
 use crate::context::DebugId;
 use crate::filesystem::Filesystem;
+use crate::graphics::gradient::Gradients;
+use crate::graphics::lighting::Lighting;
+use crate::graphics::postprocess::FinalPass;
+#[cfg(feature = "gpu-profiling")]
+use crate::graphics::GpuProfiler;
 use crate::graphics::*;
 
 use crate::error::GameResult;
@@ -26,15 +32,22 @@ where
 {
     shader_globals: Globals,
     pub(crate) projection: Matrix4,
+    pub(crate) transform: Matrix4,
+    pub(crate) transform_stack: Vec<Matrix4>,
     pub(crate) white_image: ImageGeneric<B>,
     pub(crate) screen_rect: Rect,
+    pub(crate) viewport: Option<Rect>,
     pub(crate) to_rgba8_buffer: gfx::handle::Buffer<B::Resources, u8>,
+    /// Download buffers for `screenshot_async()`, by size in bytes.  A
+    /// buffer is free again once no `PendingScreenshot` holds it.
+    pub(crate) screenshot_buffers: HashMap<usize, Vec<Rc<gfx::handle::Buffer<B::Resources, u8>>>>,
     color_format: gfx::format::Format,
     depth_format: gfx::format::Format,
     srgb: bool,
 
     pub(crate) backend_spec: B,
     pub(crate) window: glutin::WindowedContext<glutin::PossiblyCurrent>,
+    pub(crate) dpi_policy: DpiPolicy,
     pub(crate) multisample_samples: u8,
     pub(crate) device: Box<B::Device>,
     pub(crate) factory: Box<B::Factory>,
@@ -53,10 +66,26 @@ where
     default_shader: ShaderId,
     pub(crate) current_shader: Rc<RefCell<Option<ShaderId>>>,
     pub(crate) shaders: Vec<Box<dyn ShaderHandle<B>>>,
+    pub(crate) polygon_mode: PolygonMode,
+    pub(crate) depth_test: bool,
 
     pub(crate) glyph_brush: Rc<RefCell<GlyphBrush<DrawParam>>>,
+    /// The fonts added to `glyph_brush`, in `FontId` order, since the
+    /// brush doesn't hand them back out.
+    pub(crate) fonts: Rc<RefCell<Vec<glyph_brush::ab_glyph::FontArc>>>,
     pub(crate) glyph_cache: ImageGeneric<B>,
     pub(crate) glyph_state: Rc<RefCell<spritebatch::SpriteBatch>>,
+
+    pub(crate) stats: StatsTracker,
+    #[cfg(feature = "gpu-profiling")]
+    pub(crate) gpu_profiler: GpuProfiler,
+    pub(crate) frames_presented: u64,
+    pub(crate) final_pass: FinalPass<B>,
+    pub(crate) lighting: Lighting<B>,
+    pub(crate) gradients: Gradients<B>,
+    /// The gradient to draw the next thing with, and the matrix that
+    /// takes its instances to its own coordinates.
+    pub(crate) gradient: Option<(Gradient, Matrix4)>,
 }
 
 impl<B> fmt::Debug for GraphicsContextGeneric<B>
@@ -73,12 +102,14 @@ pub(crate) type GraphicsContext = GraphicsContextGeneric<GlBackendSpec>;
 
 impl GraphicsContextGeneric<GlBackendSpec> {
     /// Create a new GraphicsContext
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         filesystem: &mut Filesystem,
         events_loop: &winit::event_loop::EventLoop<()>,
         window_setup: &WindowSetup,
         window_mode: WindowMode,
         backend: GlBackendSpec,
+        fallbacks: &[GlBackendSpec],
         debug_id: DebugId,
     ) -> GameResult<Self> {
         let srgb = window_setup.srgb;
@@ -99,27 +130,40 @@ impl GraphicsContextGeneric<GlBackendSpec> {
         );
 
         // WINDOW SETUP
-        let gl_builder = glutin::ContextBuilder::new()
-            .with_gl(glutin::GlRequest::Specific(
-                backend.api(),
-                backend.version_tuple(),
-            ))
-            .with_gl_profile(glutin::GlProfile::Core)
-            .with_multisampling(match window_setup.samples.into() {
-                // Fix for https://github.com/ggez/ggez/issues/552
-                // 1 isn't multisampling but glutin wants a 0 to disable it
-                1 => 0,
-                n => u16::from(n),
-            })
-            // 24 color bits, 8 alpha bits
-            .with_pixel_format(24, 8)
-            .with_vsync(window_setup.vsync);
-
-        let window_size = dpi::PhysicalSize::<f64>::from((window_mode.width, window_mode.height));
+        let gl_builder = |backend: GlBackendSpec| {
+            let gl_builder = glutin::ContextBuilder::new()
+                .with_gl(glutin::GlRequest::Specific(
+                    backend.api(),
+                    backend.version_tuple(),
+                ))
+                .with_multisampling(match window_setup.samples.into() {
+                    // Fix for https://github.com/ggez/ggez/issues/552
+                    // 1 isn't multisampling but glutin wants a 0 to disable it
+                    1 => 0,
+                    n => u16::from(n),
+                })
+                // 24 color bits, 8 alpha bits
+                .with_pixel_format(24, 8)
+                .with_vsync(window_setup.vsync);
+            // OpenGL ES has no profiles, and some drivers refuse to make
+            // a context when asked for one.
+            if backend.api() == glutin::Api::OpenGl {
+                gl_builder.with_gl_profile(glutin::GlProfile::Core)
+            } else {
+                gl_builder
+            }
+        };
+
+        let window_size = window_size(
+            window_setup.dpi_policy,
+            window_mode.width,
+            window_mode.height,
+        );
         let mut window_builder = winit::window::WindowBuilder::new()
             .with_title(window_setup.title.clone())
             .with_inner_size(window_size)
             .with_resizable(window_mode.resizable)
+            .with_always_on_top(window_mode.always_on_top)
             .with_visible(window_mode.visible);
 
         // We need to disable drag-and-drop on windows for multithreaded stuff like cpal to work.
@@ -137,13 +181,34 @@ impl GraphicsContextGeneric<GlBackendSpec> {
             window_builder
         };
 
-        let (window, device, mut factory, screen_render_target, depth_view) = backend.init(
-            window_builder,
-            gl_builder,
-            events_loop,
-            color_format,
-            depth_format,
-        )?;
+        // Tries each backend in turn, until one works.
+        let mut backend = backend;
+        let mut fallbacks = fallbacks.iter();
+        let (window, device, mut factory, screen_render_target, depth_view) = loop {
+            match backend.init(
+                window_builder.clone(),
+                gl_builder(backend),
+                events_loop,
+                color_format,
+                depth_format,
+            ) {
+                Ok(init) => break init,
+                Err(e) => match fallbacks.next() {
+                    Some(&fallback) => {
+                        let (major, minor) = backend.version_tuple();
+                        warn!(
+                            "Couldn't create a {:?} {}.{} context, falling back: {}",
+                            backend.api(),
+                            major,
+                            minor,
+                            e
+                        );
+                        backend = fallback;
+                    }
+                    None => return Err(e.into()),
+                },
+            }
+        };
 
         // see winit #548 about DPI.
         // We basically ignore it and if it's wrong, that's a winit bug
@@ -192,6 +257,8 @@ impl GraphicsContextGeneric<GlBackendSpec> {
             BlendMode::Lighten,
             BlendMode::Darken,
             BlendMode::Premultiplied,
+            BlendMode::Screen,
+            BlendMode::Erase,
         ];
         let multisample_samples = window_setup.samples.into();
         let (vs_text, fs_text) = backend.shaders();
@@ -239,18 +306,21 @@ impl GraphicsContextGeneric<GlBackendSpec> {
         let texture = white_image.texture.clone();
         let typed_thingy = backend.raw_to_typed_shader_resource(texture);
 
+        let (target_w, target_h, _, _) = screen_render_target.get_dimensions();
         let data = pipe::Data {
             vbuf: quad_vertex_buffer.clone(),
             tex: (typed_thingy, sampler),
             rect_instance_properties: rect_inst_props,
             globals: globals_buffer,
             out: screen_render_target.clone(),
+            depth: gfx::memory::Typed::new(depth_view.clone()),
+            scissor: viewport_scissor(None, (target_w, target_h)),
         };
 
         // Glyph cache stuff.
         let font_vec = glyph_brush::ab_glyph::FontArc::try_from_slice(Font::default_font_bytes())
             .expect("Invalid default font bytes, should never happen");
-        let glyph_brush = GlyphBrushBuilder::using_font(font_vec).build();
+        let glyph_brush = GlyphBrushBuilder::using_font(font_vec.clone()).build();
         let (glyph_cache_width, glyph_cache_height) = glyph_brush.texture_dimensions();
         use std::convert::{TryFrom, TryInto};
         let initial_contents = vec![
@@ -284,15 +354,20 @@ impl GraphicsContextGeneric<GlBackendSpec> {
         let mut gfx = Self {
             shader_globals: globals,
             projection: initial_projection,
+            transform: Matrix4::IDENTITY,
+            transform_stack: Vec::new(),
             white_image,
             screen_rect: Rect::new(left, top, right - left, bottom - top),
+            viewport: None,
             to_rgba8_buffer,
+            screenshot_buffers: HashMap::new(),
             color_format,
             depth_format,
             srgb,
 
             backend_spec: backend,
             window,
+            dpi_policy: window_setup.dpi_policy,
             multisample_samples,
             device: Box::new(device as <GlBackendSpec as BackendSpec>::Device),
             factory: Box::new(factory as <GlBackendSpec as BackendSpec>::Factory),
@@ -310,10 +385,22 @@ impl GraphicsContextGeneric<GlBackendSpec> {
             default_shader: shader.shader_id(),
             current_shader: Rc::new(RefCell::new(None)),
             shaders: vec![draw],
+            polygon_mode: PolygonMode::Fill,
+            depth_test: false,
 
             glyph_brush: Rc::new(RefCell::new(glyph_brush)),
+            fonts: Rc::new(RefCell::new(vec![font_vec])),
             glyph_cache,
             glyph_state,
+
+            stats: StatsTracker::default(),
+            #[cfg(feature = "gpu-profiling")]
+            gpu_profiler: GpuProfiler::default(),
+            frames_presented: 0,
+            final_pass: FinalPass::default(),
+            lighting: Lighting::default(),
+            gradients: Gradients::default(),
+            gradient: None,
         };
         gfx.set_window_mode(window_mode)?;
 
@@ -359,6 +446,36 @@ impl<B> GraphicsContextGeneric<B>
 where
     B: BackendSpec + 'static,
 {
+    /// Returns a download buffer of the given size for
+    /// `screenshot_async()`, reusing a free one if there is one.  When a
+    /// new one has to be made, free ones of other sizes are dropped, so
+    /// they don't pile up as the window is resized.
+    pub(crate) fn screenshot_buffer(
+        &mut self,
+        size: usize,
+    ) -> GameResult<Rc<gfx::handle::Buffer<B::Resources, u8>>> {
+        let free =
+            |buffer: &Rc<gfx::handle::Buffer<B::Resources, u8>>| Rc::strong_count(buffer) == 1;
+        if let Some(buffer) = self
+            .screenshot_buffers
+            .get(&size)
+            .and_then(|buffers| buffers.iter().find(|&buffer| free(buffer)))
+        {
+            return Ok(Rc::clone(buffer));
+        }
+        for buffers in self.screenshot_buffers.values_mut() {
+            buffers.retain(|buffer| !free(buffer));
+        }
+        self.screenshot_buffers
+            .retain(|_, buffers| !buffers.is_empty());
+        let buffer = Rc::new(self.factory.create_download_buffer::<u8>(size)?);
+        self.screenshot_buffers
+            .entry(size)
+            .or_default()
+            .push(Rc::clone(&buffer));
+        Ok(buffer)
+    }
+
     /// Sends the current value of the graphics context's shader globals
     /// to the graphics card.
     pub(crate) fn update_globals(&mut self) -> GameResult {
@@ -367,10 +484,13 @@ where
         Ok(())
     }
 
-    /// Sets the shader MVP matrix to the current projection multiplied by
-    /// the given matrix, and updates the uniform buffer.
+    /// Sets the shader MVP matrix to the current projection and transform
+    /// multiplied by the given matrix, and updates the uniform buffer.
     pub(crate) fn set_global_mvp(&mut self, matrix: Matrix4) -> GameResult {
-        let mvp = self.projection * matrix;
+        let mvp = viewport_matrix(self.viewport, self.target_size())
+            * self.projection
+            * self.transform
+            * matrix;
         self.shader_globals.mvp_matrix = mvp.to_cols_array_2d();
         self.update_globals()
     }
@@ -390,6 +510,13 @@ where
     /// this method from `Drawables` so that the pixel shader gets used
     pub(crate) fn draw(&mut self, slice: Option<&gfx::Slice<B::Resources>>) -> GameResult {
         let slice = slice.unwrap_or(&self.quad_slice);
+        self.data.scissor = viewport_scissor(self.viewport, self.target_size());
+        let instances = slice.instances.map_or(1, |(count, _base)| count as usize);
+        self.stats.record_draw(instances);
+        if let Some((gradient, space)) = self.gradient.take() {
+            let slice = slice.clone();
+            return self.draw_gradient(&slice, gradient, space);
+        }
         let id = (*self.current_shader.borrow()).unwrap_or(self.default_shader);
         let shader_handle = &self.shaders[id];
 
@@ -397,6 +524,12 @@ where
         Ok(())
     }
 
+    /// Returns the size of the current render target, in pixels.
+    pub(crate) fn target_size(&self) -> (u16, u16) {
+        let (w, h, _depth, _aa) = self.data.out.get_dimensions();
+        (w, h)
+    }
+
     /// Sets the blend mode of the active shader
     pub(crate) fn set_blend_mode(&mut self, mode: BlendMode) -> GameResult {
         let id = (*self.current_shader.borrow()).unwrap_or(self.default_shader);
@@ -484,59 +617,53 @@ where
     /// Sets window mode from a WindowMode object.
     pub(crate) fn set_window_mode(&mut self, mode: WindowMode) -> GameResult {
         let window = self.window.window();
+        let policy = self.dpi_policy;
 
         // TODO LATER: find out if single-dimension constraints are possible?
         let min_dimensions = if mode.min_width > 0.0 && mode.min_height > 0.0 {
-            Some(dpi::PhysicalSize {
-                width: f64::from(mode.min_width),
-                height: f64::from(mode.min_height),
-            })
+            Some(window_size(policy, mode.min_width, mode.min_height))
         } else {
             None
         };
         window.set_min_inner_size(min_dimensions);
 
         let max_dimensions = if mode.max_width > 0.0 && mode.max_height > 0.0 {
-            Some(dpi::PhysicalSize {
-                width: f64::from(mode.max_width),
-                height: f64::from(mode.max_height),
-            })
+            Some(window_size(policy, mode.max_width, mode.max_height))
         } else {
             None
         };
         window.set_max_inner_size(max_dimensions);
         window.set_visible(mode.visible);
+        window.set_always_on_top(mode.always_on_top);
 
         match mode.fullscreen_type {
             FullscreenType::Windowed => {
                 window.set_fullscreen(None);
                 window.set_decorations(!mode.borderless);
-                window.set_inner_size(dpi::PhysicalSize {
-                    width: f64::from(mode.width),
-                    height: f64::from(mode.height),
-                });
+                window.set_inner_size(window_size(policy, mode.width, mode.height));
                 window.set_resizable(mode.resizable);
                 window.set_maximized(mode.maximized);
             }
             FullscreenType::True => {
                 if let Some(monitor) = window.current_monitor() {
-                    let v_modes = monitor.video_modes();
-                    // try to find a video mode with a matching resolution
-                    let mut match_found = false;
-                    for v_mode in v_modes {
-                        let size = v_mode.size();
-                        if (size.width, size.height) == (mode.width as u32, mode.height as u32) {
-                            window
-                                .set_fullscreen(Some(winit::window::Fullscreen::Exclusive(v_mode)));
-                            match_found = true;
-                            break;
+                    let v_modes: Vec<_> = monitor.video_modes().collect();
+                    let candidates: Vec<_> = v_modes.iter().map(VideoMode::from).collect();
+                    match best_video_mode(&candidates, &mode) {
+                        Some(i) => window.set_fullscreen(Some(
+                            winit::window::Fullscreen::Exclusive(v_modes[i].clone()),
+                        )),
+                        None if mode.refresh_rate == 0 => {
+                            return Err(GameError::WindowError(format!(
+                                "resolution {}x{} is not supported by this monitor",
+                                mode.width, mode.height
+                            )));
+                        }
+                        None => {
+                            return Err(GameError::WindowError(format!(
+                                "video mode {}x{}@{}Hz is not supported by this monitor",
+                                mode.width, mode.height, mode.refresh_rate
+                            )));
                         }
-                    }
-                    if !match_found {
-                        return Err(GameError::WindowError(format!(
-                            "resolution {}x{} is not supported by this monitor",
-                            mode.width, mode.height
-                        )));
                     }
                 }
             }
@@ -559,6 +686,50 @@ where
     /// so it may cause squirrelliness to
     /// happen with canvases or other things that touch it.
     pub(crate) fn resize_viewport(&mut self) {
+        let was_screen = self.data.out == self.screen_render_target;
+        self.final_pass.detach(&mut self.screen_render_target);
+        self.resize_screen_target();
+        let color_format = self.color_format();
+        if let Err(e) = self.final_pass.update_target(
+            &mut *self.factory,
+            &mut self.screen_render_target,
+            color_format,
+        ) {
+            warn!("Could not resize final pass render target: {}", e);
+        }
+        if was_screen {
+            self.set_screen_as_render_target();
+        }
+    }
+
+    /// Makes the given color and depth views the ones drawn to.
+    pub(crate) fn set_render_target(
+        &mut self,
+        color: gfx::handle::RawRenderTargetView<B::Resources>,
+        depth: &gfx::handle::RawDepthStencilView<B::Resources>,
+    ) {
+        self.data.out = color;
+        self.data.depth = gfx::memory::Typed::new(depth.clone());
+    }
+
+    /// Makes the screen the render target drawn to.
+    pub(crate) fn set_screen_as_render_target(&mut self) {
+        let color = self.screen_render_target.clone();
+        let depth = self.depth_view.clone();
+        self.set_render_target(color, &depth);
+    }
+
+    /// Turns depth testing on or off for every shader.
+    pub(crate) fn set_depth_test(&mut self, depth_test: bool) -> GameResult {
+        for shader in self.shaders.iter_mut() {
+            shader.set_depth_test(&mut *self.factory, depth_test)?;
+        }
+        self.depth_test = depth_test;
+        Ok(())
+    }
+
+    /// Replaces the screen's render target with one of the window's new size.
+    fn resize_screen_target(&mut self) {
         if let Some((cv, dv)) = self.backend_spec.resize_viewport(
             &self.screen_render_target,
             &self.depth_view,
@@ -571,6 +742,15 @@ where
         }
     }
 
+    /// Turns vsync on or off for the existing GL context.
+    ///
+    /// glutin only takes a vsync setting when the context is created,
+    /// and recreating it would throw away every resource we've made, so
+    /// instead we poke the platform's swap interval extension directly.
+    pub(crate) fn set_vsync(&mut self, vsync: bool) -> GameResult {
+        set_swap_interval(&self.window, i32::from(vsync))
+    }
+
     /// Returns the screen color format used by the context.
     pub(crate) fn color_format(&self) -> gfx::format::Format {
         self.color_format
@@ -588,3 +768,172 @@ where
         self.color_format().1 == gfx::format::ChannelType::Srgb
     }
 }
+
+/// Turns a window size from the config into physical or logical pixels
+/// according to the DPI policy.
+fn window_size(policy: DpiPolicy, width: f32, height: f32) -> dpi::Size {
+    let (width, height) = (f64::from(width), f64::from(height));
+    match policy {
+        DpiPolicy::Physical => dpi::PhysicalSize::new(width, height).into(),
+        DpiPolicy::Logical => dpi::LogicalSize::new(width, height).into(),
+    }
+}
+
+/// Looks up a GL-adjacent function pointer by name, returning `None` if
+/// the driver doesn't provide it.
+#[cfg(any(
+    target_os = "windows",
+    target_os = "macos",
+    all(unix, not(target_os = "ios"))
+))]
+fn proc_address(
+    window: &glutin::WindowedContext<glutin::PossiblyCurrent>,
+    name: &str,
+) -> Option<*const std::ffi::c_void> {
+    let ptr = window.get_proc_address(name);
+    if ptr.is_null() {
+        None
+    } else {
+        Some(ptr)
+    }
+}
+
+fn unsupported_vsync() -> GameError {
+    GameError::RenderError(
+        "Changing vsync at runtime isn't supported by this platform's GL driver".to_string(),
+    )
+}
+
+#[cfg(target_os = "windows")]
+#[allow(unsafe_code)]
+fn set_swap_interval(
+    window: &glutin::WindowedContext<glutin::PossiblyCurrent>,
+    interval: i32,
+) -> GameResult {
+    type SwapIntervalExt = extern "system" fn(i32) -> i32;
+    let ptr = proc_address(window, "wglSwapIntervalEXT").ok_or_else(unsupported_vsync)?;
+    // Safety: the pointer came from the driver under this exact name,
+    // and the context it applies to is current.
+    let swap_interval: SwapIntervalExt = unsafe { std::mem::transmute(ptr) };
+    if swap_interval(interval) == 0 {
+        return Err(GameError::RenderError(
+            "wglSwapIntervalEXT failed".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+#[allow(unsafe_code)]
+fn set_swap_interval(
+    window: &glutin::WindowedContext<glutin::PossiblyCurrent>,
+    interval: i32,
+) -> GameResult {
+    type GetCurrentContext = extern "C" fn() -> *mut std::ffi::c_void;
+    type SetParameter = extern "C" fn(*mut std::ffi::c_void, i32, *const i32) -> i32;
+    const K_CGLCP_SWAP_INTERVAL: i32 = 222;
+    let get_ptr = proc_address(window, "CGLGetCurrentContext").ok_or_else(unsupported_vsync)?;
+    let set_ptr = proc_address(window, "CGLSetParameter").ok_or_else(unsupported_vsync)?;
+    // Safety: both pointers are the OpenGL framework's own CGL functions,
+    // and the context they act on is current.
+    let (get_current_context, set_parameter): (GetCurrentContext, SetParameter) =
+        unsafe { (std::mem::transmute(get_ptr), std::mem::transmute(set_ptr)) };
+    if set_parameter(get_current_context(), K_CGLCP_SWAP_INTERVAL, &interval) != 0 {
+        return Err(GameError::RenderError("CGLSetParameter failed".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios")))]
+#[allow(unsafe_code)]
+fn set_swap_interval(
+    window: &glutin::WindowedContext<glutin::PossiblyCurrent>,
+    interval: i32,
+) -> GameResult {
+    use glutin::platform::ContextTraitExt;
+    type EglSwapInterval = extern "C" fn(*const std::ffi::c_void, i32) -> u32;
+    type GlxSwapIntervalMesa = extern "C" fn(u32) -> i32;
+    type GlxSwapIntervalSgi = extern "C" fn(i32) -> i32;
+
+    // Wayland and some X11 setups go through EGL, everything else is GLX.
+    // Safety: every pointer below came from the driver under the name of
+    // the function we cast it to, and the context is current.
+    unsafe {
+        if let Some(display) = window.get_egl_display() {
+            let ptr = proc_address(window, "eglSwapInterval").ok_or_else(unsupported_vsync)?;
+            let swap_interval: EglSwapInterval = std::mem::transmute(ptr);
+            if swap_interval(display, interval) == 0 {
+                return Err(GameError::RenderError("eglSwapInterval failed".to_string()));
+            }
+        } else if let Some(ptr) = proc_address(window, "glXSwapIntervalMESA") {
+            let swap_interval: GlxSwapIntervalMesa = std::mem::transmute(ptr);
+            if swap_interval(interval as u32) != 0 {
+                return Err(GameError::RenderError(
+                    "glXSwapIntervalMESA failed".to_string(),
+                ));
+            }
+        } else if let Some(ptr) = proc_address(window, "glXSwapIntervalSGI") {
+            // The SGI extension refuses an interval of 0, so it can only
+            // turn vsync back on.
+            let swap_interval: GlxSwapIntervalSgi = std::mem::transmute(ptr);
+            if swap_interval(interval) != 0 {
+                return Err(GameError::RenderError(
+                    "glXSwapIntervalSGI failed".to_string(),
+                ));
+            }
+        } else {
+            return Err(unsupported_vsync());
+        }
+    }
+    Ok(())
+}
+
+/// Everywhere else, including iOS, where the swap interval belongs to
+/// the system's display link rather than to GL.
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "macos",
+    all(unix, not(target_os = "ios"))
+)))]
+fn set_swap_interval(
+    _window: &glutin::WindowedContext<glutin::PossiblyCurrent>,
+    _interval: i32,
+) -> GameResult {
+    Err(unsupported_vsync())
+}
+
+/// Returns the matrix that squeezes normalized device coordinates into
+/// the given viewport (in pixels, origin top-left) of a target of the
+/// given size.  GL's own viewport always covers the whole target, so
+/// this is how we fake it.
+pub(crate) fn viewport_matrix(viewport: Option<Rect>, target_size: (u16, u16)) -> Matrix4 {
+    match viewport {
+        None => Matrix4::IDENTITY,
+        Some(r) => {
+            let (tw, th) = (f32::from(target_size.0), f32::from(target_size.1));
+            let sx = r.w / tw;
+            let sy = r.h / th;
+            let tx = (2.0 * r.x + r.w) / tw - 1.0;
+            let ty = 1.0 - (2.0 * r.y + r.h) / th;
+            Matrix4::from_translation(glam::vec3(tx, ty, 0.0))
+                * Matrix4::from_scale(glam::vec3(sx, sy, 1.0))
+        }
+    }
+}
+
+/// Returns the scissor rectangle clipping drawing to the given viewport.
+/// Unlike everything else in ggez, GL's scissor origin is the bottom left.
+pub(crate) fn viewport_scissor(viewport: Option<Rect>, target_size: (u16, u16)) -> gfx::Rect {
+    let (tw, th) = (f32::from(target_size.0), f32::from(target_size.1));
+    let r = viewport.unwrap_or_else(|| Rect::new(0.0, 0.0, tw, th));
+    let left = r.left().max(0.0).min(tw);
+    let right = r.right().max(0.0).min(tw);
+    let top = r.top().max(0.0).min(th);
+    let bottom = r.bottom().max(0.0).min(th);
+    gfx::Rect {
+        x: left.round() as u16,
+        y: (th - bottom).round() as u16,
+        w: (right - left).round() as u16,
+        h: (bottom - top).round() as u16,
+    }
+}
@@ -0,0 +1,297 @@
+//! Debugging aids: per-frame rendering statistics and an
+//! on-screen overlay to show them.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::context::Context;
+use crate::error::GameResult;
+use crate::graphics::*;
+use crate::timer;
+
+/// Rendering statistics, gathered over the course of one frame.
+///
+/// Get the numbers for the last complete frame with
+/// [`stats()`](fn.stats.html).  A frame ends when
+/// [`present()`](fn.present.html) is called.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GraphicsStats {
+    /// The number of draw calls sent to the GPU.
+    pub draw_calls: usize,
+    /// The number of instances drawn; a sprite batch or mesh batch
+    /// counts as one draw call but many instances.
+    pub instances: usize,
+    /// The number of text sections queued for drawing.
+    pub text_sections: usize,
+    /// Approximate GPU memory used by live images, in bytes.
+    pub texture_bytes: usize,
+    /// Approximate GPU memory used by live canvases, in bytes.
+    pub canvas_bytes: usize,
+}
+
+/// Keeps count of how much texture memory is in use; when the last
+/// clone of an image is dropped its memory is subtracted again.
+#[derive(Debug)]
+struct MemoryToken {
+    counter: Arc<AtomicUsize>,
+    bytes: usize,
+}
+
+impl Drop for MemoryToken {
+    fn drop(&mut self) {
+        let _ = self.counter.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+/// Handle tying an image's texture memory to the statistics.  Images
+/// ggez creates for its own use are not tracked.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TextureMemory(Option<Arc<MemoryToken>>);
+
+impl PartialEq for TextureMemory {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The bookkeeping behind [`GraphicsStats`](struct.GraphicsStats.html)
+/// and the debug overlay.
+#[derive(Debug, Default)]
+pub(crate) struct StatsTracker {
+    current: GraphicsStats,
+    last: GraphicsStats,
+    texture_bytes: Arc<AtomicUsize>,
+    canvas_bytes: Arc<AtomicUsize>,
+    pub(crate) overlay_visible: bool,
+}
+
+impl StatsTracker {
+    pub(crate) fn record_draw(&mut self, instances: usize) {
+        self.current.draw_calls += 1;
+        self.current.instances += instances;
+    }
+
+    pub(crate) fn record_text(&mut self) {
+        self.current.text_sections += 1;
+    }
+
+    /// Starts tracking `bytes` of texture memory until the returned
+    /// handle (and all its clones) are dropped.
+    pub(crate) fn track_texture(&self, bytes: usize, canvas: bool) -> TextureMemory {
+        let counter = if canvas {
+            &self.canvas_bytes
+        } else {
+            &self.texture_bytes
+        };
+        let _ = counter.fetch_add(bytes, Ordering::Relaxed);
+        TextureMemory(Some(Arc::new(MemoryToken {
+            counter: Arc::clone(counter),
+            bytes,
+        })))
+    }
+
+    /// Returns the statistics for the frame currently being drawn.
+    pub(crate) fn current(&self) -> GraphicsStats {
+        GraphicsStats {
+            texture_bytes: self.texture_bytes.load(Ordering::Relaxed),
+            canvas_bytes: self.canvas_bytes.load(Ordering::Relaxed),
+            ..self.current
+        }
+    }
+
+    /// Restores the counters for the frame in progress, so drawing
+    /// the overlay doesn't show up in its own numbers.
+    pub(crate) fn restore(&mut self, stats: GraphicsStats) {
+        self.current = stats;
+    }
+
+    pub(crate) fn last(&self) -> GraphicsStats {
+        self.last
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.last = self.current();
+        self.current = GraphicsStats::default();
+    }
+}
+
+/// Returns the rendering statistics of the last complete frame.
+pub fn stats(ctx: &Context) -> GraphicsStats {
+    ctx.gfx_context.stats.last()
+}
+
+/// Shows or hides the debug overlay drawn by
+/// [`debug_overlay()`](fn.debug_overlay.html).
+pub fn set_debug_overlay_visible(ctx: &mut Context, visible: bool) {
+    ctx.gfx_context.stats.overlay_visible = visible;
+}
+
+/// Toggles the debug overlay on or off; handy to bind to a key.
+pub fn toggle_debug_overlay(ctx: &mut Context) {
+    let stats = &mut ctx.gfx_context.stats;
+    stats.overlay_visible = !stats.overlay_visible;
+}
+
+/// Returns whether or not the debug overlay is currently visible.
+pub fn debug_overlay_visible(ctx: &Context) -> bool {
+    ctx.gfx_context.stats.overlay_visible
+}
+
+/// Draws the debug overlay, if it's visible: the framerate, a graph of
+/// recent frame times, and the [`GraphicsStats`](struct.GraphicsStats.html)
+/// of the last frame.
+///
+/// Call this at the end of your `draw()` callback, just before
+/// [`present()`](fn.present.html).  It always draws to the screen in
+/// window pixels, and restores the current canvas, projection, viewport,
+/// shader, blend mode and depth testing afterwards.  Like any text drawing it flushes
+/// text queued with [`queue_text()`](fn.queue_text.html), so draw your own
+/// queued text first.
+pub fn debug_overlay(ctx: &mut Context) -> GameResult {
+    if !ctx.gfx_context.stats.overlay_visible {
+        return Ok(());
+    }
+    let frame_stats = ctx.gfx_context.stats.current();
+
+    let gfx = &mut ctx.gfx_context;
+    let old_projection = gfx.projection;
+    let old_screen_rect = gfx.screen_rect;
+    let old_viewport = gfx.viewport.take();
+    let old_depth_test = gfx.depth_test;
+    gfx.set_depth_test(false)?;
+    let old_target = (gfx.data.out.clone(), gfx.data.depth.clone());
+    gfx.set_screen_as_render_target();
+    let old_shader = gfx.current_shader.borrow_mut().take();
+    let old_blend_mode = gfx.blend_mode();
+
+    let (w, h) = drawable_size(ctx);
+    set_screen_coordinates(ctx, Rect::new(0.0, 0.0, w, h))?;
+    set_blend_mode(ctx, BlendMode::Alpha)?;
+    let result = draw_overlay(ctx);
+
+    let gfx = &mut ctx.gfx_context;
+    gfx.set_blend_mode(old_blend_mode)?;
+    *gfx.current_shader.borrow_mut() = old_shader;
+    gfx.set_depth_test(old_depth_test)?;
+    gfx.data.out = old_target.0;
+    gfx.data.depth = old_target.1;
+    gfx.viewport = old_viewport;
+    gfx.screen_rect = old_screen_rect;
+    gfx.projection = old_projection;
+    gfx.set_global_mvp(Matrix4::IDENTITY)?;
+    gfx.stats.restore(frame_stats);
+    result
+}
+
+/// How many frame times the graph shows.
+const GRAPH_FRAMES: usize = 120;
+/// Frame times at or above this are drawn at the top of the graph, in ms.
+const GRAPH_MAX_MS: f32 = 50.0;
+
+fn draw_overlay(ctx: &mut Context) -> GameResult {
+    const MIB: f32 = 1024.0 * 1024.0;
+    const PADDING: f32 = 8.0;
+    const GRAPH_HEIGHT: f32 = 48.0;
+
+    let stats = ctx.gfx_context.stats.last();
+    let lines = format!(
+        "FPS: {:.1} ({:.2} ms)\n\
+         draw calls: {}  instances: {}\n\
+         textures: {:.1} MiB  canvases: {:.1} MiB\n\
+         text sections: {}",
+        timer::fps(ctx),
+        timer::average_delta(ctx).as_secs_f64() * 1000.0,
+        stats.draw_calls,
+        stats.instances,
+        stats.texture_bytes as f32 / MIB,
+        stats.canvas_bytes as f32 / MIB,
+        stats.text_sections,
+    );
+    let text = Text::new(lines);
+    let text_rect = text.dimensions(ctx);
+    let width = text_rect.w.max(GRAPH_FRAMES as f32 * 2.0) + PADDING * 2.0;
+    let graph_top = text_rect.h + PADDING * 2.0;
+    let height = graph_top + GRAPH_HEIGHT + PADDING;
+
+    let mut mb = MeshBuilder::new();
+    let _ = mb.rectangle(
+        DrawMode::fill(),
+        Rect::new(0.0, 0.0, width, height),
+        Color::new(0.0, 0.0, 0.0, 0.7),
+    )?;
+    let graph_bottom = graph_top + GRAPH_HEIGHT;
+    let graph_width = width - PADDING * 2.0;
+    let target_ms = 1000.0 / 60.0;
+    let target_y = graph_bottom - GRAPH_HEIGHT * target_ms / GRAPH_MAX_MS;
+    let _ = mb.line(
+        &[
+            Point2::new(PADDING, target_y),
+            Point2::new(PADDING + graph_width, target_y),
+        ],
+        1.0,
+        Color::new(0.3, 0.8, 0.3, 0.8),
+    )?;
+    let frames: Vec<_> = ctx.timer_context.frame_durations().collect();
+    let frames = &frames[frames.len().saturating_sub(GRAPH_FRAMES)..];
+    if frames.len() >= 2 {
+        let step = graph_width / (GRAPH_FRAMES - 1) as f32;
+        let start = PADDING + graph_width - step * (frames.len() - 1) as f32;
+        let points: Vec<Point2> = frames
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                let ms = (d.as_secs_f32() * 1000.0).min(GRAPH_MAX_MS);
+                Point2::new(
+                    start + step * i as f32,
+                    graph_bottom - GRAPH_HEIGHT * ms / GRAPH_MAX_MS,
+                )
+            })
+            .collect();
+        let _ = mb.line(&points, 1.0, Color::WHITE)?;
+    }
+    let background = mb.build(ctx)?;
+    draw(ctx, &background, DrawParam::default())?;
+    draw(
+        ctx,
+        &text,
+        DrawParam::default().dest(Point2::new(PADDING, PADDING)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_texture_memory_tracking() {
+        let mut tracker = StatsTracker::default();
+        let image = tracker.track_texture(1024, false);
+        let canvas = tracker.track_texture(4096, true);
+        let image_clone = image.clone();
+        assert_eq!(image, image_clone);
+        assert_ne!(image, canvas);
+        assert_eq!(tracker.current().texture_bytes, 1024);
+        assert_eq!(tracker.current().canvas_bytes, 4096);
+
+        drop(image);
+        assert_eq!(tracker.current().texture_bytes, 1024);
+        drop(image_clone);
+        assert_eq!(tracker.current().texture_bytes, 0);
+        drop(canvas);
+        assert_eq!(tracker.current().canvas_bytes, 0);
+
+        tracker.record_draw(1);
+        tracker.record_draw(100);
+        tracker.record_text();
+        tracker.end_frame();
+        let last = tracker.last();
+        assert_eq!(last.draw_calls, 2);
+        assert_eq!(last.instances, 101);
+        assert_eq!(last.text_sections, 1);
+        assert_eq!(tracker.current(), GraphicsStats::default());
+    }
+}
@@ -5,6 +5,7 @@ use crate::graphics::*;
 /// This can either be a set of individual components, or
 /// a single `Matrix4` transform.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub enum Transform {
     /// Transform made of individual values
     Values {
@@ -19,6 +20,12 @@ pub enum Transform {
         /// By default these operations are done from the top-left corner, so to rotate something
         /// from the center specify `Point2::new(0.5, 0.5)` here.
         offset: mint::Point2<f32>,
+        /// The x/y skew (shear) angles in radians, applied after scaling and
+        /// before rotation.  A positive `x` pushes the bottom of the drawable
+        /// to the right, so a negative one gives the slant of italic text; a
+        /// positive `y` pushes its right side down.
+        #[cfg_attr(feature = "serde-serialize", serde(default = "no_skew"))]
+        skew: mint::Vector2<f32>,
     },
     /// Transform made of an arbitrary matrix.
     ///
@@ -29,6 +36,12 @@ pub enum Transform {
     Matrix(mint::ColumnMatrix4<f32>),
 }
 
+/// The skew of transforms serialized before `skew` existed.
+#[cfg(feature = "serde-serialize")]
+fn no_skew() -> mint::Vector2<f32> {
+    mint::Vector2 { x: 0.0, y: 0.0 }
+}
+
 impl Default for Transform {
     fn default() -> Self {
         Transform::Values {
@@ -36,6 +49,7 @@ impl Default for Transform {
             rotation: 0.0,
             scale: mint::Vector2 { x: 1.0, y: 1.0 },
             offset: mint::Point2 { x: 0.0, y: 0.0 },
+            skew: mint::Vector2 { x: 0.0, y: 0.0 },
         }
     }
 }
@@ -55,6 +69,7 @@ impl Transform {
                 rotation,
                 scale,
                 offset,
+                skew,
             } => {
                 // Calculate a matrix equivalent to doing this:
                 // type Vec3 = na::Vector3<f32>;
@@ -65,15 +80,17 @@ impl Transform {
                 //     na::Matrix4::new_translation(&Vec3::new(-o.x, -o.y, 0.0));
                 // let axis_angle = Vec3::z() * *rotation;
                 // let rotation = na::Matrix4::new_rotation(axis_angle);
+                // let skew = a matrix with tan(skew.x) in row 0 column 1 and tan(skew.y) in row 1 column 0;
                 // let scale = na::Matrix4::new_nonuniform_scaling(&Vec3::new(scale.x, scale.y, 1.0));
-                // translate * rotation * scale * offset_inverse
+                // translate * rotation * skew * scale * offset_inverse
                 //
                 // Doing the bits manually is faster though, or at least was last I checked.
                 let (sinr, cosr) = rotation.sin_cos();
-                let m00 = cosr * scale.x;
-                let m01 = -sinr * scale.y;
-                let m10 = sinr * scale.x;
-                let m11 = cosr * scale.y;
+                let (kx, ky) = (skew.x.tan(), skew.y.tan());
+                let m00 = (cosr - sinr * ky) * scale.x;
+                let m01 = (cosr * kx - sinr) * scale.y;
+                let m10 = (sinr + cosr * ky) * scale.x;
+                let m11 = (sinr * kx + cosr) * scale.y;
                 let m03 = offset.x * (-m00) - offset.y * m01 + dest.x;
                 let m13 = offset.y * (-m11) - offset.x * m10 + dest.y;
                 // Welp, this transpose fixes some bug that makes nothing draw,
@@ -110,6 +127,7 @@ impl Transform {
 ///
 /// As a shortcut, it also implements `From` for a variety of tuple types.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub struct DrawParam {
     /// A portion of the drawable to clip, as a fraction of the whole image.
     /// Defaults to the whole image `(0,0 to 1,1)` if omitted.
@@ -118,6 +136,17 @@ pub struct DrawParam {
     pub color: Color,
     /// Where to put the `Drawable`.
     pub trans: Transform,
+    /// How far in front the `Drawable` is, from `-1.0` at the back to
+    /// `1.0` at the front with the default projection.  Only matters
+    /// while depth testing is on; see
+    /// [`set_depth_test()`](fn.set_depth_test.html).  Default: `0.0`.
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub depth: f32,
+    /// A gradient to tint the `Drawable` with, on top of the color.
+    /// Gradients live in the `Context`, so they can't be serialized.
+    /// Default: `None`.
+    #[cfg_attr(feature = "serde-serialize", serde(skip))]
+    pub gradient: Option<Gradient>,
 }
 
 impl Default for DrawParam {
@@ -126,6 +155,8 @@ impl Default for DrawParam {
             src: Rect::one(),
             color: Color::WHITE,
             trans: Transform::default(),
+            depth: 0.0,
+            gradient: None,
         }
     }
 }
@@ -204,6 +235,35 @@ impl DrawParam {
         }
     }
 
+    /// Set the skew (shear) angles of the drawable, in radians.
+    pub fn skew<V>(mut self, skew_: V) -> Self
+    where
+        V: Into<mint::Vector2<f32>>,
+    {
+        if let Transform::Values { ref mut skew, .. } = self.trans {
+            let p: mint::Vector2<f32> = skew_.into();
+            *skew = p;
+            self
+        } else {
+            panic!("Cannot set values for a DrawParam matrix")
+        }
+    }
+
+    /// Set the depth of the drawable, see
+    /// [`DrawParam::depth`](#structfield.depth).  For a `SpriteBatch` or
+    /// `MeshBatch` this is added to the depth of everything in it.
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Set the gradient to tint the drawable with, see
+    /// [`Gradient`](struct.Gradient.html).
+    pub fn gradient(mut self, gradient: Gradient) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
+
     /// Set the transformation matrix of the drawable.
     pub fn transform<M>(mut self, transform: M) -> Self
     where
@@ -213,8 +273,25 @@ impl DrawParam {
         self
     }
 
+    /// The transform as a single matrix, moved forward by the depth.
+    pub(crate) fn to_depth_matrix(self) -> Matrix4 {
+        let m = Matrix4::from(self.trans.to_bare_matrix());
+        if self.depth == 0.0 {
+            m
+        } else {
+            Matrix4::from_translation(glam::vec3(0.0, 0.0, self.depth)) * m
+        }
+    }
+
+    /// The gradient, with the matrix that takes the drawable back from
+    /// where this puts it to its own coordinates.
+    pub(crate) fn local_gradient(self) -> Option<(Gradient, Matrix4)> {
+        self.gradient
+            .map(|gradient| (gradient, self.to_depth_matrix().inverse()))
+    }
+
     pub(crate) fn to_instance_properties(self, srgb: bool) -> InstanceProperties {
-        let mat: [[f32; 4]; 4] = *self.trans.to_bare_matrix().as_ref();
+        let mat = self.to_depth_matrix().to_cols_array_2d();
         let color: [f32; 4] = if srgb {
             let linear_color: types::LinearColor = self.color.into();
             linear_color.into()
@@ -0,0 +1,481 @@
+//! Ready-made post-processing effects: blur, bloom and vignette.
+//!
+//! An [`Effect`](trait.Effect.html) reads one [`Canvas`](../type.Canvas.html)
+//! and draws the result into another, so to use one on the whole frame,
+//! draw the frame into a canvas and then apply the effect from there to
+//! another canvas (or several, one after another with an
+//! [`EffectChain`](struct.EffectChain.html)) before drawing that to the
+//! screen:
+//!
+//! ```rust,no_run
+//! # use ggez::*;
+//! # use ggez::graphics::effects::{Bloom, Effect, EffectChain, Vignette};
+//! # fn t(ctx: &mut Context) -> GameResult {
+//! let scene = graphics::Canvas::with_window_size(ctx)?;
+//! let output = graphics::Canvas::with_window_size(ctx)?;
+//! let mut effects = EffectChain::new()
+//!     .with(Bloom::new(0.8, 1.0))
+//!     .with(Vignette::new(0.5));
+//!
+//! // Every frame:
+//! graphics::set_canvas(ctx, Some(&scene));
+//! // ...draw stuff...
+//! graphics::set_canvas(ctx, None);
+//! effects.apply(ctx, &scene, &output)?;
+//! graphics::draw(ctx, &output, graphics::DrawParam::new())?;
+//! # Ok(()) }
+//! ```
+//!
+//! Each effect compiles its shaders the first time it's applied, so
+//! make them once and keep them around rather than making new ones
+//! every frame.
+
+use std::fmt;
+
+use gfx::format::SurfaceType;
+
+use crate::context::Context;
+use crate::error::{GameError, GameResult};
+use crate::graphics::image::draw_image_raw;
+use crate::graphics::postprocess::HDR_FORMAT;
+use crate::graphics::types::LinearColor;
+use crate::graphics::{
+    self, BackendSpec, BlendMode, Canvas, Color, DrawParam, Matrix4, Rect, Shader,
+};
+
+gfx_defines! {
+    /// Uniforms for the blur shader.
+    constant BlurParams {
+        direction: [f32; 2] = "u_Direction",
+        sigma: f32 = "u_Sigma",
+        radius: f32 = "u_Radius",
+    }
+
+    /// Uniforms for the bloom threshold shader.
+    constant ThresholdParams {
+        threshold: f32 = "u_Threshold",
+    }
+
+    /// Uniforms for the vignette shader.
+    constant VignetteParams {
+        color: [f32; 4] = "u_VignetteColor",
+        radius: f32 = "u_Radius",
+        softness: f32 = "u_Softness",
+        strength: f32 = "u_Strength",
+    }
+}
+
+/// The furthest a blur samples from each pixel, in pixels.
+const MAX_BLUR_RADIUS: f32 = 64.0;
+
+/// A post-processing effect.
+pub trait Effect: fmt::Debug {
+    /// Draws `source` into `dest` with the effect applied, replacing
+    /// what was in `dest`.  They must be different canvases, and are
+    /// usually the same size; if not, `source` is stretched to fit.
+    ///
+    /// The current transform stack is ignored, and the current canvas,
+    /// screen coordinates and blend mode are left as they were.
+    fn apply(&mut self, ctx: &mut Context, source: &Canvas, dest: &Canvas) -> GameResult;
+}
+
+/// Compiles one of the effect shaders from its GLSL 1.50 and GLSL ES
+/// 3.00 sources.  They only ever draw with `BlendMode::Replace`.
+fn make_shader<C>(
+    ctx: &mut Context,
+    name: &str,
+    consts: C,
+    sources: (&[u8], &[u8]),
+) -> GameResult<Shader<C>>
+where
+    C: 'static + gfx::traits::Pod + gfx::pso::buffer::Structure<gfx::shade::ConstFormat> + Copy,
+{
+    let (vs, _) = ctx.gfx_context.backend_spec.shaders();
+    let fs = match ctx.gfx_context.backend_spec.api() {
+        glutin::Api::OpenGlEs => sources.1,
+        _ => sources.0,
+    };
+    Shader::from_u8(ctx, vs, fs, consts, name, Some(&[BlendMode::Replace]))
+}
+
+/// Makes a canvas for intermediate results, the same size as `like`
+/// and HDR if it is.
+fn make_canvas(ctx: &mut Context, like: &Canvas) -> GameResult<Canvas> {
+    let format =
+        if like.raw_image().texture_handle.get_info().format == SurfaceType::R16_G16_B16_A16 {
+            HDR_FORMAT
+        } else {
+            graphics::get_window_color_format(ctx)
+        };
+    Canvas::new(
+        ctx,
+        like.width(),
+        like.height(),
+        crate::conf::NumSamples::One,
+        format,
+    )
+}
+
+/// Makes sure `canvas` is a canvas like `like`, making a new one if not.
+fn ensure_canvas(ctx: &mut Context, canvas: &mut Option<Canvas>, like: &Canvas) -> GameResult {
+    let fits = canvas.as_ref().map_or(false, |c| {
+        c.width() == like.width() && c.height() == like.height()
+    });
+    if !fits {
+        *canvas = Some(make_canvas(ctx, like)?);
+    }
+    Ok(())
+}
+
+/// Draws `source` stretched over all of `dest` with the given shader (or
+/// the current one), blend mode and tint, then puts everything back.
+fn draw_pass<C>(
+    ctx: &mut Context,
+    shader: Option<&Shader<C>>,
+    source: &Canvas,
+    dest: &Canvas,
+    mode: BlendMode,
+    tint: Color,
+) -> GameResult
+where
+    C: gfx::pso::buffer::Structure<gfx::shade::ConstFormat>,
+{
+    let _lock = shader.map(|s| graphics::use_shader(ctx, s));
+    let gfx = &mut ctx.gfx_context;
+    let old_depth_test = gfx.depth_test;
+    gfx.set_depth_test(false)?;
+    let old_out = (gfx.data.out.clone(), gfx.data.depth.clone());
+    gfx.set_render_target(dest.target().clone(), dest.depth());
+    let old_rect = gfx.screen_rect;
+    let old_projection = gfx.projection;
+    let old_viewport = gfx.viewport.take();
+    let old_transform = std::mem::replace(&mut gfx.transform, Matrix4::IDENTITY);
+    let old_mode = gfx.blend_mode();
+
+    let (w, h) = (f32::from(dest.width()), f32::from(dest.height()));
+    gfx.set_projection_rect(Rect::new(0.0, 0.0, w, h));
+    let result = gfx
+        .set_global_mvp(Matrix4::IDENTITY)
+        .and_then(|_| gfx.set_blend_mode(mode));
+    let result = result.and_then(|_| {
+        // Draw the raw image so the source's own blend mode, which
+        // the effect shader may not have, doesn't get in the way.
+        let mut image = source.raw_image().clone();
+        image.blend_mode = None;
+        let param = DrawParam::new().color(tint).scale([
+            w / f32::from(source.width()),
+            h / f32::from(source.height()),
+        ]);
+        draw_image_raw(&image, ctx, source.raw_draw_param(param))
+    });
+
+    let gfx = &mut ctx.gfx_context;
+    gfx.data.out = old_out.0;
+    gfx.data.depth = old_out.1;
+    gfx.set_depth_test(old_depth_test)?;
+    gfx.viewport = old_viewport;
+    gfx.transform = old_transform;
+    gfx.set_projection_rect(old_rect);
+    gfx.set_projection(old_projection);
+    gfx.set_blend_mode(old_mode)?;
+    gfx.set_global_mvp(Matrix4::IDENTITY)?;
+    result
+}
+
+/// A separable gaussian blur.
+#[derive(Debug)]
+pub struct Blur {
+    /// The standard deviation of the blur, in pixels; roughly how far
+    /// each pixel gets smeared.
+    pub sigma: f32,
+    shader: Option<Shader<BlurParams>>,
+    temp: Option<Canvas>,
+}
+
+impl Blur {
+    /// Creates a blur with the given standard deviation in pixels.
+    pub fn new(sigma: f32) -> Self {
+        Blur {
+            sigma,
+            shader: None,
+            temp: None,
+        }
+    }
+
+    fn blur_pass(
+        &mut self,
+        ctx: &mut Context,
+        source: &Canvas,
+        dest: &Canvas,
+        direction: [f32; 2],
+    ) -> GameResult {
+        let params = BlurParams {
+            direction,
+            sigma: self.sigma.max(0.01),
+            radius: (self.sigma * 3.0).ceil().max(0.0).min(MAX_BLUR_RADIUS),
+        };
+        if self.shader.is_none() {
+            self.shader = Some(make_shader(
+                ctx,
+                "BlurParams",
+                params,
+                (
+                    include_bytes!("shader/blur_150.glslf"),
+                    include_bytes!("shader/blur_es300.glslf"),
+                ),
+            )?);
+        }
+        let shader = self.shader.as_ref().expect("created above");
+        shader.send(ctx, params)?;
+        draw_pass(
+            ctx,
+            Some(shader),
+            source,
+            dest,
+            BlendMode::Replace,
+            Color::WHITE,
+        )
+    }
+}
+
+impl Effect for Blur {
+    fn apply(&mut self, ctx: &mut Context, source: &Canvas, dest: &Canvas) -> GameResult {
+        let mut temp = self.temp.take();
+        ensure_canvas(ctx, &mut temp, dest)?;
+        let temp = temp.expect("created above");
+        let horizontal = [1.0 / f32::from(source.width()), 0.0];
+        let vertical = [0.0, 1.0 / f32::from(temp.height())];
+        let result = self
+            .blur_pass(ctx, source, &temp, horizontal)
+            .and_then(|_| self.blur_pass(ctx, &temp, dest, vertical));
+        self.temp = Some(temp);
+        result
+    }
+}
+
+/// Makes bright parts of the image glow, by blurring everything brighter
+/// than a threshold and adding it back on top.
+///
+/// This works best on [HDR](../fn.set_hdr.html) canvases, where bright
+/// things can be a lot brighter than white.
+#[derive(Debug)]
+pub struct Bloom {
+    /// How bright, from 0 to 1 (or more on HDR canvases), a pixel's
+    /// brightest channel must be before it starts to glow.
+    pub threshold: f32,
+    /// How strong the glow is.
+    pub intensity: f32,
+    /// The blur used to spread the glow; change its `sigma` to make the
+    /// glow wider or tighter.
+    pub blur: Blur,
+    shader: Option<Shader<ThresholdParams>>,
+    bright: Option<Canvas>,
+    glow: Option<Canvas>,
+}
+
+impl Bloom {
+    /// Creates a bloom effect with the given threshold and intensity, and
+    /// a blur of 8 pixels.
+    pub fn new(threshold: f32, intensity: f32) -> Self {
+        Bloom {
+            threshold,
+            intensity,
+            blur: Blur::new(8.0),
+            shader: None,
+            bright: None,
+            glow: None,
+        }
+    }
+}
+
+impl Effect for Bloom {
+    fn apply(&mut self, ctx: &mut Context, source: &Canvas, dest: &Canvas) -> GameResult {
+        let params = ThresholdParams {
+            threshold: self.threshold,
+        };
+        if self.shader.is_none() {
+            self.shader = Some(make_shader(
+                ctx,
+                "ThresholdParams",
+                params,
+                (
+                    include_bytes!("shader/threshold_150.glslf"),
+                    include_bytes!("shader/threshold_es300.glslf"),
+                ),
+            )?);
+        }
+        ensure_canvas(ctx, &mut self.bright, dest)?;
+        ensure_canvas(ctx, &mut self.glow, dest)?;
+        let (shader, bright, glow) = match (&self.shader, &self.bright, &self.glow) {
+            (Some(shader), Some(bright), Some(glow)) => (shader, bright, glow),
+            _ => {
+                return Err(GameError::RenderError(
+                    "Bloom effect resources missing".to_string(),
+                ))
+            }
+        };
+
+        shader.send(ctx, params)?;
+        draw_pass(
+            ctx,
+            Some(shader),
+            source,
+            bright,
+            BlendMode::Replace,
+            Color::WHITE,
+        )?;
+        self.blur.apply(ctx, bright, glow)?;
+
+        draw_pass::<BlurParams>(ctx, None, source, dest, BlendMode::Replace, Color::WHITE)?;
+        let k = self.intensity.max(0.0);
+        let tint = if ctx.gfx_context.is_srgb() {
+            // The tint gets converted to linear, so undo that.
+            Color::from(LinearColor {
+                r: k,
+                g: k,
+                b: k,
+                a: 1.0,
+            })
+        } else {
+            Color::new(k, k, k, 1.0)
+        };
+        draw_pass::<BlurParams>(ctx, None, glow, dest, BlendMode::Add, tint)
+    }
+}
+
+/// Darkens (or tints) the edges of the image.
+#[derive(Debug)]
+pub struct Vignette {
+    /// How strong the effect is at the corners, from 0 to 1.
+    pub strength: f32,
+    /// How far out from the center the effect starts, from 0 (the
+    /// center) to 1 (the corners).
+    pub radius: f32,
+    /// How gradually the effect fades in past `radius`.
+    pub softness: f32,
+    /// The color the edges fade to.
+    pub color: Color,
+    shader: Option<Shader<VignetteParams>>,
+}
+
+impl Vignette {
+    /// Creates a black vignette with the given strength, starting
+    /// halfway out from the center.
+    pub fn new(strength: f32) -> Self {
+        Vignette {
+            strength,
+            radius: 0.5,
+            softness: 0.5,
+            color: Color::BLACK,
+            shader: None,
+        }
+    }
+}
+
+impl Effect for Vignette {
+    fn apply(&mut self, ctx: &mut Context, source: &Canvas, dest: &Canvas) -> GameResult {
+        let color: [f32; 4] = if ctx.gfx_context.is_srgb() {
+            LinearColor::from(self.color).into()
+        } else {
+            self.color.into()
+        };
+        let params = VignetteParams {
+            color,
+            radius: self.radius,
+            softness: self.softness.max(0.0001),
+            strength: self.strength,
+        };
+        if self.shader.is_none() {
+            self.shader = Some(make_shader(
+                ctx,
+                "VignetteParams",
+                params,
+                (
+                    include_bytes!("shader/vignette_150.glslf"),
+                    include_bytes!("shader/vignette_es300.glslf"),
+                ),
+            )?);
+        }
+        let shader = self.shader.as_ref().expect("created above");
+        shader.send(ctx, params)?;
+        draw_pass(
+            ctx,
+            Some(shader),
+            source,
+            dest,
+            BlendMode::Replace,
+            Color::WHITE,
+        )
+    }
+}
+
+/// Several effects applied one after the other.
+#[derive(Debug, Default)]
+pub struct EffectChain {
+    effects: Vec<Box<dyn Effect>>,
+    buffers: [Option<Canvas>; 2],
+}
+
+impl EffectChain {
+    /// Creates an empty chain, which just copies its source.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an effect to the end of the chain.
+    pub fn with<E: Effect + 'static>(mut self, effect: E) -> Self {
+        self.push(effect);
+        self
+    }
+
+    /// Adds an effect to the end of the chain.
+    pub fn push<E: Effect + 'static>(&mut self, effect: E) {
+        self.effects.push(Box::new(effect));
+    }
+
+    /// The number of effects in the chain.
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    /// Whether the chain has no effects.
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+}
+
+impl Effect for EffectChain {
+    fn apply(&mut self, ctx: &mut Context, source: &Canvas, dest: &Canvas) -> GameResult {
+        let count = self.effects.len();
+        if count == 0 {
+            return draw_pass::<BlurParams>(
+                ctx,
+                None,
+                source,
+                dest,
+                BlendMode::Replace,
+                Color::WHITE,
+            );
+        }
+        for buffer in self.buffers.iter_mut().take(count - 1) {
+            ensure_canvas(ctx, buffer, dest)?;
+        }
+
+        // Ping-pong between the two buffers, finishing in `dest`.
+        let buffers = &self.buffers;
+        for (i, effect) in self.effects.iter_mut().enumerate() {
+            let from = if i == 0 {
+                source
+            } else {
+                buffers[(i - 1) % 2].as_ref().expect("created above")
+            };
+            let to = if i == count - 1 {
+                dest
+            } else {
+                buffers[i % 2].as_ref().expect("created above")
+            };
+            effect.apply(ctx, from, to)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//! Linear and radial color gradients for tinting drawables.
+
+use std::collections::HashMap;
+
+use gfx::traits::FactoryExt;
+
+use crate::context::Context;
+use crate::error::{GameError, GameResult};
+use crate::graphics::context::GraphicsContextGeneric;
+use crate::graphics::*;
+
+/// How many texels wide the ramp texture of a gradient is.
+const RAMP_WIDTH: u16 = 256;
+
+gfx_defines! {
+    /// Uniforms for the gradient shader.
+    constant GradientParams {
+        space: [[f32; 4]; 4] = "u_GradientSpace",
+        shape: [f32; 4] = "u_GradientShape",
+        kind: f32 = "u_GradientKind",
+    }
+}
+
+pub(crate) mod gradient_pipe {
+    use super::GradientParams;
+    use crate::graphics::{Globals, InstanceProperties, Vertex};
+
+    gfx_pipeline_inner! {
+        vbuf: gfx::VertexBuffer<Vertex>,
+        tex: gfx::TextureSampler<[f32; 4]>,
+        ramp: gfx::TextureSampler<[f32; 4]>,
+        globals: gfx::ConstantBuffer<Globals>,
+        gradient: gfx::ConstantBuffer<GradientParams>,
+        rect_instance_properties: gfx::InstanceBuffer<InstanceProperties>,
+        out: gfx::RawRenderTarget,
+        depth: gfx::DepthTarget<gfx::format::DepthStencil>,
+        scissor: gfx::Scissor,
+    }
+
+    pub fn new(
+        color_format: gfx::format::Format,
+        blend: gfx::state::Blend,
+        depth: gfx::state::Depth,
+    ) -> Init<'static> {
+        Init {
+            vbuf: (),
+            tex: "t_Texture",
+            ramp: "t_Ramp",
+            globals: "Globals",
+            gradient: "Gradient",
+            rect_instance_properties: (),
+            out: (
+                "Target0",
+                color_format,
+                gfx::state::ColorMask::all(),
+                Some(blend),
+            ),
+            depth,
+            scissor: (),
+        }
+    }
+}
+
+/// The shape of a [`Gradient`](struct.Gradient.html), in the
+/// coordinates of the drawable it's drawn on, before the `DrawParam`'s
+/// transform: pixels from the top-left corner for images and canvases,
+/// the mesh's own coordinates for meshes, and so on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GradientShape {
+    /// The colors run along the line from `start` to `end`, and stay
+    /// the same across it.  Before `start` and past `end` they're the
+    /// first and last stop's colors.
+    Linear {
+        /// Where the gradient's position is `0.0`.
+        start: mint::Point2<f32>,
+        /// Where the gradient's position is `1.0`.
+        end: mint::Point2<f32>,
+    },
+    /// The colors run outward in circles from `center`.
+    Radial {
+        /// Where the gradient's position is `0.0`.
+        center: mint::Point2<f32>,
+        /// How far from the center the gradient's position is `1.0`.
+        radius: f32,
+    },
+}
+
+impl GradientShape {
+    fn to_params(self) -> ([f32; 4], f32) {
+        match self {
+            GradientShape::Linear { start, end } => ([start.x, start.y, end.x, end.y], 0.0),
+            GradientShape::Radial { center, radius } => ([center.x, center.y, radius, 0.0], 1.0),
+        }
+    }
+}
+
+/// A color gradient with any number of stops, which can tint a drawable
+/// in place of a flat color by setting it as the
+/// [`DrawParam::gradient`](struct.DrawParam.html#structfield.gradient).
+/// The gradient's color is multiplied with the drawable's own colors
+/// and the `DrawParam`'s color.
+///
+/// Like a [`Font`](struct.Font.html), this is a cheap handle to data
+/// stored in the `Context`: the colors are baked into a small ramp
+/// texture when the gradient is created, and kept until the `Context`
+/// goes away.  Gradients with the same colors share a texture, so making
+/// the same one again every frame costs the baking but no more memory;
+/// still, make the gradients you need once up front where you can.  The
+/// shape isn't part of the texture, so [`with_shape()`](#method.with_shape)
+/// can move the gradient around freely.
+///
+/// Gradients apply to images, canvases, meshes, text and sprite and
+/// mesh batches.  While drawing with a gradient the shader set with
+/// [`use_shader()`](fn.use_shader.html) is not used.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # use ggez::graphics::*;
+/// # fn t(ctx: &mut Context, bar: &Mesh) -> GameResult {
+/// let fade = Gradient::linear(
+///     ctx,
+///     [0.0, 0.0],
+///     [200.0, 0.0],
+///     &[(0.0, Color::GREEN), (0.5, Color::YELLOW), (1.0, Color::RED)],
+/// )?;
+/// graphics::draw(ctx, bar, DrawParam::new().gradient(fade))?;
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Gradient {
+    ramp: usize,
+    shape: GradientShape,
+}
+
+impl Gradient {
+    /// Creates a gradient of the given shape from a list of color stops,
+    /// each with its position along the gradient from `0.0` to `1.0`.
+    /// The stops must be in order; colors in between are blended
+    /// smoothly.  If a gradient with the same colors was made before,
+    /// its ramp texture is used again.
+    pub fn new(
+        ctx: &mut Context,
+        shape: GradientShape,
+        stops: &[(f32, Color)],
+    ) -> GameResult<Self> {
+        let rgba = ramp_pixels(stops)?;
+        if let Some(&ramp) = ctx.gfx_context.gradients.cache.get(&rgba) {
+            return Ok(Gradient { ramp, shape });
+        }
+        let mut ramp = Image::from_rgba8(ctx, RAMP_WIDTH, 1, &rgba)?;
+        ramp.sampler_info =
+            texture::SamplerInfo::new(texture::FilterMethod::Bilinear, texture::WrapMode::Clamp);
+        let gradients = &mut ctx.gfx_context.gradients;
+        gradients.ramps.push(ramp);
+        let ramp = gradients.ramps.len() - 1;
+        let _ = gradients.cache.insert(rgba, ramp);
+        Ok(Gradient { ramp, shape })
+    }
+
+    /// Creates a linear gradient running from `start` to `end`.
+    pub fn linear<P>(
+        ctx: &mut Context,
+        start: P,
+        end: P,
+        stops: &[(f32, Color)],
+    ) -> GameResult<Self>
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        let shape = GradientShape::Linear {
+            start: start.into(),
+            end: end.into(),
+        };
+        Gradient::new(ctx, shape, stops)
+    }
+
+    /// Creates a radial gradient running outward from `center`.
+    pub fn radial<P>(
+        ctx: &mut Context,
+        center: P,
+        radius: f32,
+        stops: &[(f32, Color)],
+    ) -> GameResult<Self>
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        let shape = GradientShape::Radial {
+            center: center.into(),
+            radius,
+        };
+        Gradient::new(ctx, shape, stops)
+    }
+
+    /// The gradient's shape.
+    pub fn shape(&self) -> GradientShape {
+        self.shape
+    }
+
+    /// Returns the same colors with a different shape.
+    pub fn with_shape(mut self, shape: GradientShape) -> Self {
+        self.shape = shape;
+        self
+    }
+}
+
+/// Bakes the color stops into the pixels of a ramp texture.
+fn ramp_pixels(stops: &[(f32, Color)]) -> GameResult<Vec<u8>> {
+    if stops.is_empty() {
+        return Err(GameError::RenderError(
+            "A gradient needs at least one color stop".to_string(),
+        ));
+    }
+    if stops.windows(2).any(|pair| pair[0].0 > pair[1].0) {
+        return Err(GameError::RenderError(
+            "The stops of a gradient must be in order".to_string(),
+        ));
+    }
+    let last = f32::from(RAMP_WIDTH - 1);
+    let mut rgba = Vec::with_capacity(usize::from(RAMP_WIDTH) * 4);
+    for i in 0..RAMP_WIDTH {
+        let t = f32::from(i) / last;
+        let next = stops.iter().position(|&(position, _)| position > t);
+        let color = match next {
+            None => stops[stops.len() - 1].1,
+            Some(0) => stops[0].1,
+            Some(n) => {
+                let (from, from_color) = stops[n - 1];
+                let (to, to_color) = stops[n];
+                from_color.lerp(to_color, (t - from) / (to - from))
+            }
+        };
+        let (r, g, b, a) = color.to_rgba();
+        rgba.extend_from_slice(&[r, g, b, a]);
+    }
+    Ok(rgba)
+}
+
+/// The gradient ramps and the pipelines for drawing with them, kept in
+/// the graphics context.
+pub(crate) struct Gradients<B: BackendSpec> {
+    ramps: Vec<ImageGeneric<B>>,
+    /// The index in `ramps` of the texture with each set of pixels.
+    cache: HashMap<Vec<u8>, usize>,
+    psos: HashMap<(BlendMode, bool), gfx::PipelineState<B::Resources, gradient_pipe::Meta>>,
+    params: Option<gfx::handle::Buffer<B::Resources, GradientParams>>,
+}
+
+impl<B: BackendSpec> Default for Gradients<B> {
+    fn default() -> Self {
+        Gradients {
+            ramps: Vec::new(),
+            cache: HashMap::new(),
+            psos: HashMap::new(),
+            params: None,
+        }
+    }
+}
+
+impl<B> GraphicsContextGeneric<B>
+where
+    B: BackendSpec + 'static,
+{
+    /// Draws like `draw()`, but tinted by the given gradient.  `space`
+    /// takes the positions of the instances to the drawable's own
+    /// coordinates, where the gradient's shape is.
+    pub(crate) fn draw_gradient(
+        &mut self,
+        slice: &gfx::Slice<B::Resources>,
+        gradient: Gradient,
+        space: Matrix4,
+    ) -> GameResult {
+        let ramp = self.gradients.ramps.get(gradient.ramp).ok_or_else(|| {
+            GameError::RenderError("The gradient belongs to another Context".to_string())
+        })?;
+        let ramp_sampler = self
+            .samplers
+            .get_or_insert(ramp.sampler_info, self.factory.as_mut());
+        let ramp_texture = self
+            .backend_spec
+            .raw_to_typed_shader_resource(ramp.texture.clone());
+
+        let blend_mode = self.blend_mode();
+        let key = (blend_mode, self.depth_test);
+        if !self.gradients.psos.contains_key(&key) {
+            let (vs, fs): (&[u8], &[u8]) = match self.backend_spec.api() {
+                glutin::Api::OpenGlEs => (
+                    include_bytes!("shader/gradient_es300.glslv"),
+                    include_bytes!("shader/gradient_es300.glslf"),
+                ),
+                _ => (
+                    include_bytes!("shader/gradient_150.glslv"),
+                    include_bytes!("shader/gradient_150.glslf"),
+                ),
+            };
+            let set = self.factory.create_shader_set(vs, fs)?;
+            let rasterizer = gfx::state::Rasterizer {
+                samples: if self.multisample_samples > 1 {
+                    Some(gfx::state::MultiSample)
+                } else {
+                    None
+                },
+                ..gfx::state::Rasterizer::new_fill()
+            };
+            let depth = if self.depth_test {
+                DEPTH_TEST
+            } else {
+                gfx::preset::depth::PASS_TEST
+            };
+            let pso = self.factory.create_pipeline_state(
+                &set,
+                gfx::Primitive::TriangleList,
+                rasterizer,
+                gradient_pipe::new(self.color_format(), blend_mode.into(), depth),
+            )?;
+            let _ = self.gradients.psos.insert(key, pso);
+        }
+        if self.gradients.params.is_none() {
+            self.gradients.params = Some(self.factory.create_constant_buffer(1));
+        }
+        let params = self.gradients.params.clone().expect("created above");
+        let (shape, kind) = gradient.shape.to_params();
+        self.encoder.update_constant_buffer(
+            &params,
+            &GradientParams {
+                space: space.to_cols_array_2d(),
+                shape,
+                kind,
+            },
+        );
+
+        let data = gradient_pipe::Data {
+            vbuf: self.data.vbuf.clone(),
+            tex: self.data.tex.clone(),
+            ramp: (ramp_texture, ramp_sampler),
+            globals: self.data.globals.clone(),
+            gradient: params,
+            rect_instance_properties: self.data.rect_instance_properties.clone(),
+            out: self.data.out.clone(),
+            depth: self.data.depth.clone(),
+            scissor: self.data.scissor,
+        };
+        self.encoder.draw(slice, &self.gradients.psos[&key], &data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_gradient_ramp() {
+        let stops = [(0.25, Color::BLACK), (0.75, Color::WHITE)];
+        let rgba = ramp_pixels(&stops).unwrap();
+        assert_eq!(rgba.len(), usize::from(RAMP_WIDTH) * 4);
+        // Before the first stop and after the last one the colors stay put.
+        assert_eq!(&rgba[..4], &[0, 0, 0, 255]);
+        assert_eq!(&rgba[rgba.len() - 4..], &[255, 255, 255, 255]);
+        // Halfway between the stops is halfway between the colors.
+        let middle = usize::from(RAMP_WIDTH / 2) * 4;
+        assert!((i32::from(rgba[middle]) - 128).abs() <= 2);
+
+        assert!(ramp_pixels(&[]).is_err());
+        assert!(ramp_pixels(&[(1.0, Color::BLACK), (0.0, Color::WHITE)]).is_err());
+    }
+}
@@ -26,6 +26,7 @@ where
     pub(crate) blend_mode: Option<BlendMode>,
     pub(crate) width: u16,
     pub(crate) height: u16,
+    pub(crate) memory: TextureMemory,
 
     pub(crate) debug_id: DebugId,
 }
@@ -112,6 +113,7 @@ where
             blend_mode: None,
             width,
             height,
+            memory: TextureMemory::default(),
             debug_id,
         })
     }
@@ -179,7 +181,7 @@ impl Image {
     ) -> GameResult<Self> {
         let debug_id = DebugId::get(context);
         let color_format = context.gfx_context.color_format();
-        Self::make_raw(
+        let mut image = Self::make_raw(
             &mut *context.gfx_context.factory,
             &context.gfx_context.default_sampler_info,
             width,
@@ -187,7 +189,9 @@ impl Image {
             rgba,
             color_format,
             debug_id,
-        )
+        )?;
+        image.memory = context.gfx_context.stats.track_texture(rgba.len(), false);
+        Ok(image)
     }
 
     /// Dumps the `Image`'s data to a `Vec` of `u8` RGBA values.
@@ -311,6 +315,23 @@ impl Image {
     }
 }
 
+/// Loads the image at the given path, or hands out the one already
+/// loaded from there, so an image used in many places is only loaded
+/// and uploaded to the GPU once.  See the [`cache`](../cache/index.html)
+/// module.
+pub fn load_cached_image<P: AsRef<path::Path>>(
+    context: &mut Context,
+    path: P,
+) -> GameResult<Image> {
+    let path = path.as_ref();
+    if let Some(image) = context.cache.image(path) {
+        return Ok(image);
+    }
+    let image = Image::new(context, path)?;
+    context.cache.insert_image(path, image.clone());
+    Ok(image)
+}
+
 impl fmt::Debug for Image {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -345,6 +366,7 @@ impl Drawable for Image {
             ),
         };
 
+        ctx.gfx_context.gradient = param.local_gradient();
         draw_image_raw(self, ctx, new_param)
     }
 
@@ -0,0 +1,304 @@
+//! Instanced drawing with user-defined per-instance data.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+
+use gfx::format::Format;
+use gfx::memory::Typed;
+use gfx::pso::buffer::{ElemStride, Element, Structure};
+use gfx::traits::{FactoryExt, Pod};
+
+use crate::context::{Context, DebugId};
+use crate::error::{GameError, GameResult};
+use crate::graphics::context::viewport_scissor;
+use crate::graphics::*;
+
+pub(crate) mod instanced_pipe {
+    use crate::graphics::{Globals, Vertex};
+    use gfx::format::Format;
+    use gfx::pso::buffer::{ElemStride, Element};
+
+    gfx_pipeline_inner! {
+        vbuf: gfx::VertexBuffer<Vertex>,
+        instances: gfx::RawVertexBuffer,
+        tex: gfx::TextureSampler<[f32; 4]>,
+        globals: gfx::ConstantBuffer<Globals>,
+        out: gfx::RawRenderTarget,
+        depth: gfx::DepthTarget<gfx::format::DepthStencil>,
+        scissor: gfx::Scissor,
+    }
+
+    pub fn new<'a>(
+        instances: &'a [(&'a str, Element<Format>)],
+        stride: ElemStride,
+        color_format: Format,
+        blend: gfx::state::Blend,
+        depth: gfx::state::Depth,
+    ) -> Init<'a> {
+        Init {
+            vbuf: (),
+            instances: (instances, stride, 1),
+            tex: "t_Texture",
+            globals: "Globals",
+            out: (
+                "Target0",
+                color_format,
+                gfx::state::ColorMask::all(),
+                Some(blend),
+            ),
+            depth,
+            scissor: (),
+        }
+    }
+}
+
+type Resources = gfx_device_gl::Resources;
+
+/// A `Mesh` drawn many times at once with hardware instancing, where
+/// each instance is described by your own type `T` and drawn by your
+/// own shader, rather than by a `DrawParam` like in a
+/// [`MeshBatch`](struct.MeshBatch.html).  This is for drawing lots of
+/// grass blades, crowds, particles and such where every instance needs
+/// data the standard shader knows nothing about.
+///
+/// `T` is normally declared as a `vertex` in gfx's `gfx_defines!`
+/// macro, which names each field's attribute in the shader:
+///
+/// ```text
+/// gfx_defines! {
+///     vertex Blade {
+///         root: [f32; 2] = "a_Root",
+///         sway: f32 = "a_Sway",
+///     }
+/// }
+/// ```
+///
+/// The vertex shader gets the mesh's vertices as usual in `a_Pos`,
+/// `a_Uv` and `a_VertColor`, the instance's fields in their attributes,
+/// and the transform from the `DrawParam` given to
+/// [`draw()`](#method.draw) as `u_MVP` in the `Globals` uniform block.
+/// The mesh's texture is `t_Texture`, and the color goes out through
+/// `Target0`.  Have a look at the built-in shaders in
+/// `src/graphics/shader` for a starting point.  Since the shader does
+/// all the work, `use_shader()`, the `DrawParam`'s color and its source
+/// rect don't apply here.
+pub struct Instanced<T>
+where
+    T: Pod + Structure<Format>,
+{
+    mesh: Mesh,
+    program: gfx::handle::Program<Resources>,
+    /// The shader's per-instance attributes, as found in `T`.
+    attributes: Vec<(String, Element<Format>)>,
+    psos: HashMap<(BlendMode, bool), gfx::PipelineState<Resources, instanced_pipe::Meta>>,
+    instances: Vec<T>,
+    buffer: Option<gfx::handle::Buffer<Resources, T>>,
+    dirty: bool,
+    blend_mode: Option<BlendMode>,
+    debug_id: DebugId,
+}
+
+impl<T> Instanced<T>
+where
+    T: Pod + Structure<Format>,
+{
+    /// Creates an instanced mesh drawn with the given GLSL vertex and
+    /// fragment shader source.  Every input of the vertex shader must
+    /// be either one of the mesh's vertex attributes or a field of `T`.
+    pub fn new(
+        ctx: &mut Context,
+        mesh: Mesh,
+        vertex_shader: &[u8],
+        fragment_shader: &[u8],
+    ) -> GameResult<Self> {
+        let stride = mem::size_of::<T>();
+        if stride > usize::from(ElemStride::max_value()) {
+            return Err(GameError::RenderError(format!(
+                "Instance data can be at most {} bytes, but is {}",
+                ElemStride::max_value(),
+                stride
+            )));
+        }
+        let program = ctx
+            .gfx_context
+            .factory
+            .link_program(vertex_shader, fragment_shader)?;
+        let attributes = program
+            .get_info()
+            .vertex_attributes
+            .iter()
+            .filter(|at| Vertex::query(&at.name).is_none())
+            .filter_map(|at| T::query(&at.name).map(|element| (at.name.clone(), element)))
+            .collect();
+        Ok(Instanced {
+            mesh,
+            program,
+            attributes,
+            psos: HashMap::new(),
+            instances: Vec::new(),
+            buffer: None,
+            dirty: true,
+            blend_mode: None,
+            debug_id: DebugId::get(ctx),
+        })
+    }
+
+    /// Adds an instance.
+    pub fn push(&mut self, instance: T) {
+        self.instances.push(instance);
+        self.dirty = true;
+    }
+
+    /// Removes all instances.
+    pub fn clear(&mut self) {
+        self.instances.clear();
+        self.dirty = true;
+    }
+
+    /// The instances.
+    pub fn instances(&self) -> &[T] {
+        &self.instances
+    }
+
+    /// The instances, for changing them.  They're sent to the graphics
+    /// card again on the next draw.
+    pub fn instances_mut(&mut self) -> &mut Vec<T> {
+        self.dirty = true;
+        &mut self.instances
+    }
+
+    /// The mesh drawn for each instance.
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    /// Sets the blend mode to draw with, or `None` to use the one set
+    /// with [`set_blend_mode()`](fn.set_blend_mode.html).
+    pub fn set_blend_mode(&mut self, mode: Option<BlendMode>) {
+        self.blend_mode = mode;
+    }
+
+    /// Gets the blend mode to draw with, if one was set.
+    pub fn blend_mode(&self) -> Option<BlendMode> {
+        self.blend_mode
+    }
+
+    /// Immediately sends the instances to the graphics card.  Drawing
+    /// does this automatically when they've changed.
+    pub fn flush(&mut self, ctx: &mut Context) -> GameResult {
+        if self.instances.is_empty() {
+            self.dirty = false;
+            return Ok(());
+        }
+        let gfx = &mut ctx.gfx_context;
+        let needs_new_buffer = self
+            .buffer
+            .as_ref()
+            .map_or(true, |buffer| buffer.len() < self.instances.len());
+        if needs_new_buffer {
+            self.buffer = Some(gfx.factory.create_buffer(
+                self.instances.len(),
+                gfx::buffer::Role::Vertex,
+                gfx::memory::Usage::Dynamic,
+                gfx::memory::Bind::TRANSFER_DST,
+            )?);
+        }
+        let buffer = self.buffer.as_ref().expect("created above");
+        gfx.encoder.update_buffer(buffer, &self.instances, 0)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Draws every instance, with the transform and depth of the given
+    /// `DrawParam` applied to them all.
+    pub fn draw(&mut self, ctx: &mut Context, param: DrawParam) -> GameResult {
+        self.debug_id.assert(ctx);
+        if self.dirty {
+            self.flush(ctx)?;
+        }
+        let buffer = match self.buffer {
+            Some(ref buffer) if !self.instances.is_empty() => buffer.raw().clone(),
+            _ => return Ok(()),
+        };
+
+        let gfx = &mut ctx.gfx_context;
+        let blend_mode = self.blend_mode.unwrap_or_else(|| gfx.blend_mode());
+        let key = (blend_mode, gfx.depth_test);
+        if !self.psos.contains_key(&key) {
+            let attributes: Vec<(&str, Element<Format>)> = self
+                .attributes
+                .iter()
+                .map(|(name, element)| (name.as_str(), *element))
+                .collect();
+            let depth = if gfx.depth_test {
+                DEPTH_TEST
+            } else {
+                gfx::preset::depth::PASS_TEST
+            };
+            let init = instanced_pipe::new(
+                &attributes,
+                mem::size_of::<T>() as ElemStride,
+                gfx.color_format(),
+                blend_mode.into(),
+                depth,
+            );
+            let rasterizer = gfx::state::Rasterizer {
+                samples: if gfx.multisample_samples > 1 {
+                    Some(gfx::state::MultiSample)
+                } else {
+                    None
+                },
+                ..gfx::state::Rasterizer::new_fill()
+            };
+            let pso = gfx
+                .factory
+                .create_pipeline_from_program(
+                    &self.program,
+                    gfx::Primitive::TriangleList,
+                    rasterizer,
+                    init,
+                )
+                .map_err(gfx::PipelineStateError::<String>::from)?;
+            let _ = self.psos.insert(key, pso);
+        }
+
+        gfx.set_global_mvp(param.to_depth_matrix())?;
+        let image = self.mesh.image();
+        let sampler = gfx
+            .samplers
+            .get_or_insert(image.sampler_info, gfx.factory.as_mut());
+        let data = instanced_pipe::Data {
+            vbuf: self.mesh.get_vertex_buffer(),
+            instances: buffer,
+            tex: (
+                gfx.backend_spec
+                    .raw_to_typed_shader_resource(image.texture.clone()),
+                sampler,
+            ),
+            globals: gfx.data.globals.clone(),
+            out: gfx.data.out.clone(),
+            depth: gfx.data.depth.clone(),
+            scissor: viewport_scissor(gfx.viewport, gfx.target_size()),
+        };
+        let mut slice = self.mesh.get_slice().clone();
+        slice.instances = Some((self.instances.len() as u32, 0));
+        gfx.stats.record_draw(self.instances.len());
+        gfx.encoder.draw(&slice, &self.psos[&key], &data);
+        gfx.set_global_mvp(Matrix4::IDENTITY)
+    }
+}
+
+impl<T> fmt::Debug for Instanced<T>
+where
+    T: Pod + Structure<Format>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "<Instanced: {} instances, {:p}>",
+            self.instances.len(),
+            self
+        )
+    }
+}
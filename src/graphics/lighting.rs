@@ -0,0 +1,404 @@
+//! Simple dynamic lighting for sprites with normal maps.
+
+use std::collections::HashMap;
+
+use gfx::traits::FactoryExt;
+
+use crate::context::{Context, DebugId};
+use crate::error::{GameError, GameResult};
+use crate::graphics::context::viewport_scissor;
+use crate::graphics::types::LinearColor;
+use crate::graphics::*;
+
+/// The most [`PointLight`](struct.PointLight.html)s that can light a
+/// [`LitSprite`](struct.LitSprite.html) at once.
+pub const MAX_LIGHTS: usize = 8;
+
+gfx_defines! {
+    /// Uniforms for the lit sprite shader.  Arrays of vectors can't be
+    /// constant buffer members, so each light gets its own pair.
+    constant LightParams {
+        ambient: [f32; 4] = "u_Ambient",
+        position0: [f32; 4] = "u_LightPos0",
+        position1: [f32; 4] = "u_LightPos1",
+        position2: [f32; 4] = "u_LightPos2",
+        position3: [f32; 4] = "u_LightPos3",
+        position4: [f32; 4] = "u_LightPos4",
+        position5: [f32; 4] = "u_LightPos5",
+        position6: [f32; 4] = "u_LightPos6",
+        position7: [f32; 4] = "u_LightPos7",
+        color0: [f32; 4] = "u_LightColor0",
+        color1: [f32; 4] = "u_LightColor1",
+        color2: [f32; 4] = "u_LightColor2",
+        color3: [f32; 4] = "u_LightColor3",
+        color4: [f32; 4] = "u_LightColor4",
+        color5: [f32; 4] = "u_LightColor5",
+        color6: [f32; 4] = "u_LightColor6",
+        color7: [f32; 4] = "u_LightColor7",
+        count: f32 = "u_LightCount",
+    }
+}
+
+pub(crate) mod lit_pipe {
+    use super::LightParams;
+    use crate::graphics::{Globals, InstanceProperties, Vertex};
+
+    gfx_pipeline_inner! {
+        vbuf: gfx::VertexBuffer<Vertex>,
+        tex: gfx::TextureSampler<[f32; 4]>,
+        normal_map: gfx::TextureSampler<[f32; 4]>,
+        globals: gfx::ConstantBuffer<Globals>,
+        lights: gfx::ConstantBuffer<LightParams>,
+        rect_instance_properties: gfx::InstanceBuffer<InstanceProperties>,
+        out: gfx::RawRenderTarget,
+        scissor: gfx::Scissor,
+    }
+
+    pub fn new(color_format: gfx::format::Format, blend: gfx::state::Blend) -> Init<'static> {
+        Init {
+            vbuf: (),
+            tex: "t_Texture",
+            normal_map: "t_NormalMap",
+            globals: "Globals",
+            lights: "Lights",
+            rect_instance_properties: (),
+            out: (
+                "Target0",
+                color_format,
+                gfx::state::ColorMask::all(),
+                Some(blend),
+            ),
+            scissor: (),
+        }
+    }
+}
+
+/// A point light for [`LitSprite`](struct.LitSprite.html)s, set with
+/// [`set_lighting()`](fn.set_lighting.html).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLight {
+    /// Where the light is, in screen coordinates.
+    pub position: mint::Point2<f32>,
+    /// How far the light floats above the sprites, in the same units.
+    /// Low lights graze the sprites and bring out their bumps, high
+    /// ones light them more evenly.
+    pub height: f32,
+    /// How far the light reaches; it fades out smoothly to nothing at
+    /// this distance.
+    pub radius: f32,
+    /// The light's color.
+    pub color: Color,
+    /// What the color is multiplied by.
+    pub intensity: f32,
+}
+
+impl PointLight {
+    /// Creates a white light of the given radius at the given position.
+    /// The height defaults to a fifth of the radius and the intensity
+    /// to 1.0.
+    pub fn new<P>(position: P, radius: f32) -> Self
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        PointLight {
+            position: position.into(),
+            height: radius / 5.0,
+            radius,
+            color: Color::WHITE,
+            intensity: 1.0,
+        }
+    }
+
+    /// Set the height.
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Set the color.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the intensity.
+    pub fn intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+/// The lights and the pipelines for drawing lit sprites, kept in the
+/// graphics context.
+pub(crate) struct Lighting<B: BackendSpec> {
+    ambient: Color,
+    lights: Vec<PointLight>,
+    psos: HashMap<BlendMode, gfx::PipelineState<B::Resources, lit_pipe::Meta>>,
+    params: Option<gfx::handle::Buffer<B::Resources, LightParams>>,
+}
+
+impl<B: BackendSpec> Default for Lighting<B> {
+    fn default() -> Self {
+        Lighting {
+            ambient: Color::WHITE,
+            lights: Vec::new(),
+            psos: HashMap::new(),
+            params: None,
+        }
+    }
+}
+
+impl<B: BackendSpec> Lighting<B> {
+    fn params(&self, srgb: bool) -> LightParams {
+        let convert = |color: Color| -> [f32; 4] {
+            if srgb {
+                LinearColor::from(color).into()
+            } else {
+                color.into()
+            }
+        };
+        let mut positions = [[0.0; 4]; MAX_LIGHTS];
+        let mut colors = [[0.0; 4]; MAX_LIGHTS];
+        for (i, light) in self.lights.iter().enumerate() {
+            positions[i] = [
+                light.position.x,
+                light.position.y,
+                light.height,
+                light.radius,
+            ];
+            let [r, g, b, a] = convert(light.color);
+            let k = light.intensity;
+            colors[i] = [r * k, g * k, b * k, a];
+        }
+        LightParams {
+            ambient: convert(self.ambient),
+            position0: positions[0],
+            position1: positions[1],
+            position2: positions[2],
+            position3: positions[3],
+            position4: positions[4],
+            position5: positions[5],
+            position6: positions[6],
+            position7: positions[7],
+            color0: colors[0],
+            color1: colors[1],
+            color2: colors[2],
+            color3: colors[3],
+            color4: colors[4],
+            color5: colors[5],
+            color6: colors[6],
+            color7: colors[7],
+            count: self.lights.len() as f32,
+        }
+    }
+}
+
+/// Sets the ambient light and the point lights used to draw every
+/// [`LitSprite`](struct.LitSprite.html), replacing the previous ones.
+/// There can be at most [`MAX_LIGHTS`](constant.MAX_LIGHTS.html) point
+/// lights.
+///
+/// The ambient light is added to every pixel regardless of its normal.
+/// It starts out white, so lit sprites look just like ordinary ones
+/// until you turn it down.
+pub fn set_lighting(ctx: &mut Context, ambient: Color, lights: &[PointLight]) -> GameResult {
+    if lights.len() > MAX_LIGHTS {
+        return Err(GameError::RenderError(format!(
+            "Tried to set {} lights, but at most {} are supported",
+            lights.len(),
+            MAX_LIGHTS
+        )));
+    }
+    let lighting = &mut ctx.gfx_context.lighting;
+    lighting.ambient = ambient;
+    lighting.lights = lights.to_vec();
+    Ok(())
+}
+
+/// Returns the current ambient light and point lights.
+pub fn lighting(ctx: &Context) -> (Color, &[PointLight]) {
+    let lighting = &ctx.gfx_context.lighting;
+    (lighting.ambient, &lighting.lights)
+}
+
+/// A sprite drawn with a normal map, so that it's shaded by the
+/// lights set with [`set_lighting()`](fn.set_lighting.html).
+///
+/// The normal map is an image the same size as the sprite, with each
+/// pixel's normal stored the usual way: red is right, green is up and
+/// blue is out of the screen, each mapped from `-1..1` to `0..255`.
+/// It's sampled with the sprite's filter mode and source rect, and
+/// turns along with the sprite when it's rotated or flipped.
+///
+/// Custom shaders set with [`use_shader()`](fn.use_shader.html) don't
+/// apply to lit sprites.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LitSprite {
+    image: Image,
+    normal_map: Image,
+    blend_mode: Option<BlendMode>,
+}
+
+impl LitSprite {
+    /// Creates a lit sprite from an image and its normal map, which must
+    /// be the same size.
+    pub fn new(ctx: &mut Context, image: Image, normal_map: &Image) -> GameResult<Self> {
+        if image.dimensions() != normal_map.dimensions() {
+            return Err(GameError::RenderError(format!(
+                "The normal map is {}x{}, but the sprite is {}x{}",
+                normal_map.width(),
+                normal_map.height(),
+                image.width(),
+                image.height()
+            )));
+        }
+        // Images are sRGB if the screen is, but normals aren't colors and
+        // mustn't be gamma decoded, so upload them again as plain data.
+        let rgba = normal_map.to_rgba8(ctx)?;
+        let linear = gfx::format::Format(
+            gfx::format::SurfaceType::R8_G8_B8_A8,
+            gfx::format::ChannelType::Unorm,
+        );
+        let debug_id = DebugId::get(ctx);
+        let mut normal_map = Image::make_raw(
+            &mut *ctx.gfx_context.factory,
+            &image.sampler_info,
+            normal_map.width(),
+            normal_map.height(),
+            &rgba,
+            linear,
+            debug_id,
+        )?;
+        normal_map.memory = ctx.gfx_context.stats.track_texture(rgba.len(), false);
+        Ok(LitSprite {
+            image,
+            normal_map,
+            blend_mode: None,
+        })
+    }
+
+    /// Loads a lit sprite and its normal map from the given paths.
+    pub fn from_paths<P, Q>(ctx: &mut Context, image: P, normal_map: Q) -> GameResult<Self>
+    where
+        P: AsRef<std::path::Path>,
+        Q: AsRef<std::path::Path>,
+    {
+        let image = Image::new(ctx, image)?;
+        let normal_map = Image::new(ctx, normal_map)?;
+        LitSprite::new(ctx, image, &normal_map)
+    }
+
+    /// The sprite's image.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Sets the filter mode used for both the image and the normal map.
+    pub fn set_filter(&mut self, mode: FilterMode) {
+        self.image.set_filter(mode);
+        self.normal_map.set_filter(mode);
+    }
+}
+
+impl Drawable for LitSprite {
+    fn draw(&self, ctx: &mut Context, param: DrawParam) -> GameResult {
+        self.image.debug_id.assert(ctx);
+
+        let scale_x = param.src.w * f32::from(self.image.width);
+        let scale_y = param.src.h * f32::from(self.image.height);
+        let new_param = match param.trans {
+            Transform::Values { scale, .. } => param.scale(mint::Vector2 {
+                x: scale.x * scale_x,
+                y: scale.y * scale_y,
+            }),
+            Transform::Matrix(m) => param.transform(
+                Matrix4::from(m) * Matrix4::from_scale(glam::vec3(scale_x, scale_y, 1.0)),
+            ),
+        };
+
+        let gfx = &mut ctx.gfx_context;
+        gfx.update_instance_properties(new_param)?;
+        let blend_mode = self.blend_mode.unwrap_or_else(|| gfx.blend_mode());
+        let srgb = gfx.is_srgb();
+        let color_format = gfx.color_format();
+        let multisample = gfx.multisample_samples > 1;
+        let scissor = viewport_scissor(gfx.viewport, gfx.target_size());
+
+        let lighting = &mut gfx.lighting;
+        if !lighting.psos.contains_key(&blend_mode) {
+            let (vs, fs): (&[u8], &[u8]) = match gfx.backend_spec.api() {
+                glutin::Api::OpenGlEs => (
+                    include_bytes!("shader/lit_es300.glslv"),
+                    include_bytes!("shader/lit_es300.glslf"),
+                ),
+                _ => (
+                    include_bytes!("shader/lit_150.glslv"),
+                    include_bytes!("shader/lit_150.glslf"),
+                ),
+            };
+            let set = gfx.factory.create_shader_set(vs, fs)?;
+            let rasterizer = gfx::state::Rasterizer {
+                samples: if multisample {
+                    Some(gfx::state::MultiSample)
+                } else {
+                    None
+                },
+                ..gfx::state::Rasterizer::new_fill()
+            };
+            let pso = gfx.factory.create_pipeline_state(
+                &set,
+                gfx::Primitive::TriangleList,
+                rasterizer,
+                lit_pipe::new(color_format, blend_mode.into()),
+            )?;
+            let _ = lighting.psos.insert(blend_mode, pso);
+        }
+        if lighting.params.is_none() {
+            lighting.params = Some(gfx.factory.create_constant_buffer(1));
+        }
+        let params = lighting.params.clone().expect("created above");
+        gfx.encoder
+            .update_constant_buffer(&params, &lighting.params(srgb));
+
+        let tex_sampler = gfx
+            .samplers
+            .get_or_insert(self.image.sampler_info, gfx.factory.as_mut());
+        let normal_sampler = gfx
+            .samplers
+            .get_or_insert(self.normal_map.sampler_info, gfx.factory.as_mut());
+        let data = lit_pipe::Data {
+            vbuf: gfx.quad_vertex_buffer.clone(),
+            tex: (
+                gfx.backend_spec
+                    .raw_to_typed_shader_resource(self.image.texture.clone()),
+                tex_sampler,
+            ),
+            normal_map: (
+                gfx.backend_spec
+                    .raw_to_typed_shader_resource(self.normal_map.texture.clone()),
+                normal_sampler,
+            ),
+            globals: gfx.data.globals.clone(),
+            lights: params,
+            rect_instance_properties: gfx.data.rect_instance_properties.clone(),
+            out: gfx.data.out.clone(),
+            scissor,
+        };
+        gfx.stats.record_draw(1);
+        let pso = &lighting.psos[&blend_mode];
+        gfx.encoder.draw(&gfx.quad_slice, pso, &data);
+        Ok(())
+    }
+
+    fn dimensions(&self, _: &mut Context) -> Option<Rect> {
+        Some(self.image.dimensions())
+    }
+
+    fn set_blend_mode(&mut self, mode: Option<BlendMode>) {
+        self.blend_mode = mode;
+    }
+
+    fn blend_mode(&self) -> Option<BlendMode> {
+        self.blend_mode
+    }
+}
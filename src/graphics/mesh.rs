@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::rc::Rc;
 
 use crate::context::DebugId;
 use crate::error::GameError;
@@ -72,6 +74,8 @@ pub use self::t::{FillOptions, FillRule, LineCap, LineJoin, StrokeOptions};
 pub struct MeshBuilder {
     buffer: t::geometry_builder::VertexBuffers<Vertex, u32>,
     image: Option<Image>,
+    feather: f32,
+    hit_testable: bool,
 }
 
 impl Default for MeshBuilder {
@@ -79,6 +83,8 @@ impl Default for MeshBuilder {
         Self {
             buffer: t::VertexBuffers::new(),
             image: None,
+            feather: 0.0,
+            hit_testable: false,
         }
     }
 }
@@ -90,6 +96,10 @@ impl MeshBuilder {
     }
 
     /// Create a new mesh for a line of one or more connected segments.
+    ///
+    /// This uses lyon's default miter joins and butt caps; for other
+    /// styles use [`polyline()`](#method.polyline) with e.g.
+    /// `DrawMode::stroke(width).with_line_join(LineJoin::Round)`.
     pub fn line<P>(&mut self, points: &[P], width: f32, color: Color) -> GameResult<&mut Self>
     where
         P: Into<mint::Point2<f32>> + Clone,
@@ -411,6 +421,34 @@ impl MeshBuilder {
         Ok(self)
     }
 
+    /// Smooths the edges of everything in the mesh by fading them out
+    /// over the given width, in the mesh's own units (so pixels, unless
+    /// it's drawn scaled).  This makes shapes look smooth even without
+    /// multisampling; `1.0` is usually about right, and `0.0` turns it
+    /// off again.
+    ///
+    /// It works by adding a thin strip of triangles around the outside
+    /// edges of the geometry when the mesh is built, so shapes get
+    /// bigger by the width on every side.  Edges are found by looking
+    /// for triangle sides with nothing on the other side, so it works
+    /// best on what `MeshBuilder` tessellates itself; raw geometry whose
+    /// triangles don't share their corners exactly will get strips in
+    /// between them as well.
+    pub fn antialias(&mut self, feather: f32) -> GameResult<&mut Self> {
+        self.feather = feather;
+        Ok(self)
+    }
+
+    /// Sets whether the built mesh keeps a copy of its triangles, so
+    /// [`Mesh::contains_point()`](struct.Mesh.html#method.contains_point)
+    /// can test points against its exact shape instead of just its
+    /// bounding rect.  Off by default, since most meshes are never
+    /// hit-tested and the copy costs an allocation for every mesh built.
+    pub fn hit_testable(&mut self, hit_testable: bool) -> GameResult<&mut Self> {
+        self.hit_testable = hit_testable;
+        Ok(self)
+    }
+
     /// Takes an `Image` to apply to the mesh.
     pub fn texture(&mut self, texture: Image) -> GameResult<&mut Self> {
         self.image = Some(texture);
@@ -456,12 +494,108 @@ impl MeshBuilder {
     /// Note that this returns a `GameResult<Mesh>`, since the build can fail,
     /// for example when trying to build an empty `MeshBuilder`.
     pub fn build(&self, ctx: &mut Context) -> GameResult<Mesh> {
-        Mesh::from_raw(
-            ctx,
-            &self.buffer.vertices,
-            &self.buffer.indices,
-            self.image.clone(),
-        )
+        let feathered;
+        let buffer = if self.feather > 0.0 {
+            let mut buffer = self.buffer.clone();
+            add_feathered_edges(&mut buffer, self.feather);
+            feathered = buffer;
+            &feathered
+        } else {
+            &self.buffer
+        };
+        let mut mesh = Mesh::from_raw(ctx, &buffer.vertices, &buffer.indices, self.image.clone())?;
+        if self.hit_testable {
+            mesh.triangles = Some(Rc::new(triangles_for_vertices(
+                &buffer.vertices,
+                &buffer.indices,
+            )));
+        }
+        Ok(mesh)
+    }
+}
+
+/// Adds a strip around the outside edges of the triangles in `buffer`
+/// that fades from their color to transparent over `width`.
+fn add_feathered_edges(buffer: &mut t::VertexBuffers<Vertex, u32>, width: f32) {
+    // Vertices are told apart by position rather than index, since the
+    // tessellators sometimes put several vertices in the same place.
+    type Key = (u32, u32);
+    let key = |v: &Vertex| (v.pos[0].to_bits(), v.pos[1].to_bits());
+    let vertices = &buffer.vertices;
+    let pos = |i: u32| Point2::from(vertices[i as usize].pos);
+
+    // An edge is on the outside if only one triangle uses it.  For each
+    // one, keep its vertices and the normal pointing away from the
+    // triangle.
+    let mut edges: HashMap<(Key, Key), Vec<(u32, u32, Point2)>> = HashMap::new();
+    for tri in buffer.indices.chunks_exact(3) {
+        let (a, b, c) = (pos(tri[0]), pos(tri[1]), pos(tri[2]));
+        let (ab, ac) = (b - a, c - a);
+        if (ab.x * ac.y - ab.y * ac.x).abs() <= f32::EPSILON {
+            continue;
+        }
+        for &(i, j, k) in &[
+            (tri[0], tri[1], tri[2]),
+            (tri[1], tri[2], tri[0]),
+            (tri[2], tri[0], tri[1]),
+        ] {
+            let side = pos(j) - pos(i);
+            let mut normal = Point2::new(-side.y, side.x).normalize();
+            if normal.dot(pos(k) - pos(i)) > 0.0 {
+                normal = -normal;
+            }
+            let (ki, kj) = (key(&vertices[i as usize]), key(&vertices[j as usize]));
+            let edge = if ki < kj { (ki, kj) } else { (kj, ki) };
+            edges
+                .entry(edge)
+                .or_insert_with(Vec::new)
+                .push((i, j, normal));
+        }
+    }
+    let outside: Vec<(u32, u32, Point2)> = edges
+        .values()
+        .filter(|users| users.len() == 1)
+        .map(|users| users[0])
+        .collect();
+
+    // Each corner of the outside gets one transparent vertex, pushed out
+    // along the average of its edges' normals.
+    let mut corners: HashMap<Key, (u32, Vec<Point2>)> = HashMap::new();
+    for &(i, j, normal) in &outside {
+        for &v in &[i, j] {
+            corners
+                .entry(key(&vertices[v as usize]))
+                .or_insert_with(|| (v, Vec::new()))
+                .1
+                .push(normal);
+        }
+    }
+    let mut outer: HashMap<Key, u32> = HashMap::new();
+    for (corner, (v, normals)) in corners {
+        let sum = normals.iter().fold(Point2::ZERO, |sum, n| sum + *n);
+        let direction = if sum.length_squared() > f32::EPSILON {
+            sum.normalize()
+        } else {
+            normals[0]
+        };
+        // Go out far enough for the strip to keep its width along each
+        // edge, within reason, like a miter join.
+        let cos = normals
+            .iter()
+            .map(|n| n.dot(direction))
+            .fold(1.0, f32::min)
+            .max(0.25);
+        let mut vertex = buffer.vertices[v as usize];
+        vertex.pos = (Point2::from(vertex.pos) + direction * (width / cos)).into();
+        vertex.color[3] = 0.0;
+        let _ = outer.insert(corner, buffer.vertices.len() as u32);
+        buffer.vertices.push(vertex);
+    }
+
+    for (i, j, _) in outside {
+        let oi = outer[&key(&buffer.vertices[i as usize])];
+        let oj = outer[&key(&buffer.vertices[j as usize])];
+        buffer.indices.extend_from_slice(&[i, j, oj, i, oj, oi]);
     }
 }
 
@@ -514,6 +648,9 @@ pub struct Mesh {
     image: Image,
     debug_id: DebugId,
     rect: Rect,
+    /// A CPU-side copy of the triangles' corners, for hit-testing, if
+    /// it was asked for with `MeshBuilder::hit_testable()`.
+    triangles: Option<Rc<Vec<[Point2; 3]>>>,
 }
 
 impl Mesh {
@@ -706,6 +843,7 @@ impl Mesh {
             image: texture.unwrap_or_else(|| ctx.gfx_context.white_image.clone()),
             debug_id: DebugId::get(ctx),
             rect,
+            triangles: None,
         })
     }
 
@@ -735,6 +873,46 @@ impl Mesh {
             .create_vertex_buffer_with_slice(verts, indices);
         self.buffer = vbuf;
         self.slice = slice;
+        if let Some(rect) = bbox_for_vertices(verts) {
+            self.rect = rect;
+        }
+        if self.triangles.is_some() {
+            self.triangles = Some(Rc::new(triangles_for_vertices(verts, indices)));
+        }
+    }
+
+    /// Returns the smallest `Rect` containing every vertex of the mesh,
+    /// in the mesh's own coordinates.
+    pub fn bounding_rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Checks whether the given point, in the mesh's own coordinates, is
+    /// inside any of the mesh's triangles.  Points exactly on an edge
+    /// count as inside.
+    ///
+    /// To test a point on the screen against a mesh drawn with a
+    /// `DrawParam`, transform the point by the inverse of the param's
+    /// transform first.  Only meshes built with
+    /// [`MeshBuilder::hit_testable()`](struct.MeshBuilder.html#method.hit_testable)
+    /// keep their triangles for this, checking each one in turn, so it's
+    /// fine for clickable shapes but slow for huge meshes; for any other
+    /// mesh this just checks its [bounding rect](#method.bounding_rect).
+    /// Note that strokes and
+    /// [`antialias()`](struct.MeshBuilder.html#method.antialias)ed edges
+    /// are made of triangles too, so they count.
+    pub fn contains_point<P>(&self, point: P) -> bool
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        let point = Point2::from(point.into());
+        if !self.rect.contains(point) {
+            return false;
+        }
+        match &self.triangles {
+            Some(triangles) => triangles.iter().any(|tri| triangle_contains(tri, point)),
+            None => true,
+        }
     }
 
     /// Returns a slice for this mesh that could be used for manual draw call submission
@@ -746,6 +924,11 @@ impl Mesh {
     pub fn get_vertex_buffer(&self) -> gfx::handle::Buffer<gfx_device_gl::Resources, Vertex> {
         self.buffer.clone()
     }
+
+    /// The texture the mesh is drawn with.
+    pub(crate) fn image(&self) -> &Image {
+        &self.image
+    }
 }
 
 impl Drawable for Mesh {
@@ -774,6 +957,7 @@ impl Drawable for Mesh {
             None
         };
 
+        gfx.gradient = param.local_gradient();
         gfx.draw(Some(&self.slice))?;
         if let Some(mode) = previous_mode {
             gfx.set_blend_mode(mode)?;
@@ -987,7 +1171,7 @@ impl MeshBatch {
 
             // In the batch we multiply the transform for each item in the batch
             // with the transform given in the `DrawParam` here.
-            let batch_transform = Matrix4::from(param.trans.to_bare_matrix());
+            let batch_transform = param.to_depth_matrix();
             gfx.set_global_mvp(batch_transform)?;
 
             // HACK this code has to restore the old instance buffer after drawing,
@@ -1005,6 +1189,8 @@ impl MeshBatch {
             let typed_thingy = gfx.backend_spec.raw_to_typed_shader_resource(texture);
             gfx.data.tex = (typed_thingy, sampler);
 
+            // The instances are already in the batch's own coordinates.
+            gfx.gradient = param.gradient.map(|g| (g, Matrix4::IDENTITY));
             gfx.draw(Some(&slice))?;
 
             gfx.data.rect_instance_properties = old_instance_buffer;
@@ -1032,6 +1218,33 @@ impl MeshBatch {
     }
 }
 
+/// Gets the corners of each triangle, skipping any with out of bounds
+/// indices.
+fn triangles_for_vertices(verts: &[Vertex], indices: &[u32]) -> Vec<[Point2; 3]> {
+    let pos = |i: u32| verts.get(i as usize).map(|v| Point2::from(v.pos));
+    indices
+        .chunks_exact(3)
+        .filter_map(|tri| Some([pos(tri[0])?, pos(tri[1])?, pos(tri[2])?]))
+        .collect()
+}
+
+/// Whether the point is inside the triangle or on its edge, whichever
+/// way round the triangle is wound.
+fn triangle_contains(tri: &[Point2; 3], point: Point2) -> bool {
+    let side = |a: Point2, b: Point2| {
+        let (ab, ap) = (b - a, point - a);
+        ab.x * ap.y - ab.y * ap.x
+    };
+    let (d1, d2, d3) = (
+        side(tri[0], tri[1]),
+        side(tri[1], tri[2]),
+        side(tri[2], tri[0]),
+    );
+    let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_negative && has_positive)
+}
+
 fn bbox_for_vertices(verts: &[Vertex]) -> Option<Rect> {
     if verts.is_empty() {
         return None;
@@ -1056,3 +1269,59 @@ fn bbox_for_vertices(verts: &[Vertex]) -> Option<Rect> {
         y: y_min,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_feathered_edges() {
+        let color = [1.0, 1.0, 1.0, 1.0];
+        let vertex = |x, y| Vertex {
+            pos: [x, y],
+            uv: [0.0, 0.0],
+            color,
+        };
+        let mut buffer = t::VertexBuffers::new();
+        buffer.vertices = vec![
+            vertex(0.0, 0.0),
+            vertex(10.0, 0.0),
+            vertex(10.0, 10.0),
+            vertex(0.0, 10.0),
+        ];
+        buffer.indices = vec![0, 1, 2, 0, 2, 3];
+        add_feathered_edges(&mut buffer, 1.0);
+
+        // One transparent vertex per corner, and two triangles per
+        // outside edge; the diagonal is shared so it gets none.
+        assert_eq!(buffer.vertices.len(), 8);
+        assert_eq!(buffer.indices.len(), 6 + 4 * 6);
+        let outer: Vec<[f32; 2]> = buffer.vertices[4..]
+            .iter()
+            .map(|v| {
+                assert!(v.color[3].abs() < f32::EPSILON);
+                [v.pos[0].round(), v.pos[1].round()]
+            })
+            .collect();
+        for corner in &[[-1.0, -1.0], [11.0, -1.0], [11.0, 11.0], [-1.0, 11.0]] {
+            assert!(outer.contains(corner), "{:?} missing", corner);
+        }
+    }
+
+    #[test]
+    fn headless_test_triangle_contains() {
+        let clockwise = [
+            Point2::new(0.0, 0.0),
+            Point2::new(10.0, 0.0),
+            Point2::new(0.0, 10.0),
+        ];
+        let counterclockwise = [clockwise[0], clockwise[2], clockwise[1]];
+        for tri in &[clockwise, counterclockwise] {
+            assert!(triangle_contains(tri, Point2::new(2.0, 2.0)));
+            assert!(triangle_contains(tri, Point2::new(5.0, 5.0)));
+            assert!(triangle_contains(tri, Point2::new(0.0, 0.0)));
+            assert!(!triangle_contains(tri, Point2::new(6.0, 6.0)));
+            assert!(!triangle_contains(tri, Point2::new(-1.0, 2.0)));
+        }
+    }
+}
@@ -20,6 +20,7 @@ use std::collections::HashMap;
 use std::convert::From;
 use std::fmt;
 use std::path::Path;
+use std::rc::Rc;
 use std::u16;
 
 use gfx::texture;
@@ -33,23 +34,44 @@ use crate::context::Context;
 use crate::GameError;
 use crate::GameResult;
 
+pub(crate) mod camera;
 pub(crate) mod canvas;
 pub(crate) mod context;
+pub(crate) mod debug;
 pub(crate) mod drawparam;
+pub(crate) mod gradient;
 pub(crate) mod image;
+pub(crate) mod instanced;
+pub(crate) mod lighting;
 pub(crate) mod mesh;
+pub(crate) mod postprocess;
+#[cfg(feature = "gpu-profiling")]
+pub(crate) mod profiling;
+pub(crate) mod recorder;
 pub(crate) mod shader;
 pub(crate) mod text;
 pub(crate) mod types;
 
 pub use mint;
 
+pub mod effects;
 pub mod spritebatch;
 
+pub use crate::graphics::camera::*;
 pub use crate::graphics::canvas::*;
+pub use crate::graphics::debug::*;
 pub use crate::graphics::drawparam::*;
+pub use crate::graphics::gradient::{Gradient, GradientShape};
 pub use crate::graphics::image::*;
+pub use crate::graphics::instanced::Instanced;
+pub use crate::graphics::lighting::{lighting, set_lighting, LitSprite, PointLight, MAX_LIGHTS};
 pub use crate::graphics::mesh::*;
+pub use crate::graphics::postprocess::{
+    has_color_lut, hdr, set_color_lut, set_hdr, HdrSettings, Tonemapper,
+};
+#[cfg(feature = "gpu-profiling")]
+pub use crate::graphics::profiling::*;
+pub use crate::graphics::recorder::*;
 pub use crate::graphics::shader::*;
 pub use crate::graphics::text::*;
 pub use crate::graphics::types::*;
@@ -211,6 +233,16 @@ impl From<conf::Backend> for GlBackendSpec {
     }
 }
 
+impl From<GlBackendSpec> for conf::Backend {
+    fn from(spec: GlBackendSpec) -> Self {
+        let GlBackendSpec { major, minor, api } = spec;
+        match api {
+            glutin::Api::OpenGl => conf::Backend::OpenGL { major, minor },
+            _ => conf::Backend::OpenGLES { major, minor },
+        }
+    }
+}
+
 impl BackendSpec for GlBackendSpec {
     type Resources = gfx_device_gl::Resources;
     type Factory = gfx_device_gl::Factory;
@@ -348,6 +380,8 @@ pub(crate) mod pipe {
         globals: gfx::ConstantBuffer<Globals>,
         rect_instance_properties: gfx::InstanceBuffer<InstanceProperties>,
         out: gfx::RawRenderTarget,
+        depth: gfx::DepthTarget<gfx::format::DepthStencil>,
+        scissor: gfx::Scissor,
     }
 
     pub fn new() -> Init<'static> {
@@ -365,10 +399,19 @@ pub(crate) mod pipe {
                 gfx::state::ColorMask::all(),
                 Some(gfx::preset::blend::ALPHA),
             ),
+            depth: gfx::preset::depth::PASS_TEST,
+            scissor: (),
         }
     }
 }
 
+/// The depth test used with [`set_depth_test()`](fn.set_depth_test.html):
+/// things with a greater depth are drawn in front.
+pub(crate) const DEPTH_TEST: gfx::state::Depth = gfx::state::Depth {
+    fun: gfx::state::Comparison::GreaterEqual,
+    write: true,
+};
+
 impl fmt::Display for InstanceProperties {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let matrix = Matrix4::from_cols_array_2d(&[self.col1, self.col2, self.col3, self.col4]);
@@ -455,6 +498,7 @@ pub fn clear(ctx: &mut Context, color: Color) {
     let linear_color: types::LinearColor = color.into();
     let c: [f32; 4] = linear_color.into();
     gfx.encoder.clear_raw(&gfx.data.out, c.into());
+    gfx.encoder.clear_depth(&gfx.data.depth, 0.0);
 }
 
 /// Draws the given `Drawable` object to the screen by calling its
@@ -475,31 +519,42 @@ where
 /// Unsets any active canvas.
 pub fn present(ctx: &mut Context) -> GameResult<()> {
     let gfx = &mut ctx.gfx_context;
-    gfx.data.out = gfx.screen_render_target.clone();
+    let mut final_pass = std::mem::take(&mut gfx.final_pass);
+    let result = final_pass.apply(gfx);
+    gfx.final_pass = final_pass;
+    result?;
+    gfx.set_screen_as_render_target();
     // We might want to give the user more control over when the
     // encoder gets flushed eventually, if we want them to be able
     // to do their own gfx drawing.  HOWEVER, the whole pipeline type
     // thing is a bigger hurdle, so this is fine for now.
     gfx.encoder.flush(&mut *gfx.device);
+    gfx.frames_presented += 1;
     gfx.window.swap_buffers()?;
     gfx.device.cleanup();
+    gfx.stats.end_frame();
+    #[cfg(feature = "gpu-profiling")]
+    gfx.gpu_profiler.end_frame(&mut gfx.device);
     Ok(())
 }
 
 /// Take a screenshot by outputting the current render surface
 /// (screen or selected canvas) to an `Image`.
 pub fn screenshot(ctx: &mut Context) -> GameResult<Image> {
+    let (w, h, _depth, _aa) = ctx.gfx_context.data.out.get_dimensions();
+    screenshot_region(ctx, Rect::new(0.0, 0.0, f32::from(w), f32::from(h)))
+}
+
+/// Like [`screenshot()`](fn.screenshot.html), but only captures the
+/// given region of the current render surface, in pixels with the
+/// origin at the top-left.  The region is clipped to the surface.
+pub fn screenshot_region(ctx: &mut Context, region: Rect) -> GameResult<Image> {
     use gfx::memory::Typed;
     use gfx::traits::FactoryExt;
 
+    let image_info = readback_info(&ctx.gfx_context, region)?;
+    let (w, h) = (image_info.width, image_info.height);
     let gfx = &mut ctx.gfx_context;
-    let (w, h, _depth, aa) = gfx.data.out.get_dimensions();
-    if aa != gfx_core::texture::AaMode::Single {
-        // Details see https://github.com/ggez/ggez/issues/751
-        return Err(GameError::RenderError("Can't take screenshots of anti aliased textures.\n(since neither copying or resolving them is supported right now)".to_string()));
-    }
-
-    let surface_format = gfx.color_format();
 
     let dl_buffer = &mut gfx.to_rgba8_buffer;
     // check if it's big enough and recreate it if not
@@ -508,17 +563,6 @@ pub fn screenshot(ctx: &mut Context) -> GameResult<Image> {
         *dl_buffer = gfx.factory.create_download_buffer::<u8>(size_needed)?;
     }
 
-    let image_info = gfx::texture::ImageInfoCommon {
-        xoffset: 0,
-        yoffset: 0,
-        zoffset: 0,
-        width: w,
-        height: h,
-        depth: 0,
-        format: surface_format,
-        mipmap: 0,
-    };
-
     let encoder = &mut gfx.encoder;
 
     encoder.copy_texture_to_buffer_raw(
@@ -538,6 +582,139 @@ pub fn screenshot(ctx: &mut Context) -> GameResult<Image> {
     Ok(image)
 }
 
+/// Starts reading back the current render surface (or the given region
+/// of it, as with [`screenshot_region()`](fn.screenshot_region.html))
+/// without waiting for the GPU to finish drawing it.
+///
+/// A synchronous [`screenshot()`](fn.screenshot.html) has to stall until
+/// everything drawn so far is done.  This instead queues the copy with
+/// the rest of the frame, so if you collect the result after the next
+/// [`present()`](fn.present.html) (see
+/// [`PendingScreenshot::is_ready()`](struct.PendingScreenshot.html#method.is_ready))
+/// the data is usually already there.
+pub fn screenshot_async(ctx: &mut Context, region: Option<Rect>) -> GameResult<PendingScreenshot> {
+    use gfx::memory::Typed;
+
+    let region = region.unwrap_or_else(|| {
+        let (w, h, _depth, _aa) = ctx.gfx_context.data.out.get_dimensions();
+        Rect::new(0.0, 0.0, f32::from(w), f32::from(h))
+    });
+    let image_info = readback_info(&ctx.gfx_context, region)?;
+    let (width, height) = (image_info.width, image_info.height);
+    let gfx = &mut ctx.gfx_context;
+
+    // Each pending screenshot needs its own buffer, since several can be
+    // in flight at once.
+    let buffer = gfx.screenshot_buffer(usize::from(width) * usize::from(height) * 4)?;
+    gfx.encoder.copy_texture_to_buffer_raw(
+        gfx.data.out.get_texture(),
+        None,
+        image_info,
+        buffer.raw(),
+        0,
+    )?;
+
+    Ok(PendingScreenshot {
+        buffer,
+        width,
+        height,
+        frame: gfx.frames_presented,
+    })
+}
+
+/// A screenshot requested with [`screenshot_async()`](fn.screenshot_async.html)
+/// whose pixels are on their way back from the GPU.
+#[derive(Debug)]
+pub struct PendingScreenshot {
+    buffer: Rc<gfx::handle::Buffer<gfx_device_gl::Resources, u8>>,
+    width: u16,
+    height: u16,
+    frame: u64,
+}
+
+impl PendingScreenshot {
+    /// Returns true once the frame the screenshot was taken from has been
+    /// presented, after which reading it shouldn't stall.
+    pub fn is_ready(&self, ctx: &Context) -> bool {
+        self.is_ready_in(&ctx.gfx_context)
+    }
+
+    /// The width of the screenshot in pixels.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// The height of the screenshot in pixels.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Returns the screenshot as RGBA8 pixel data, top row first.
+    ///
+    /// If it isn't [ready](#method.is_ready) yet this flushes the pending
+    /// drawing and waits for it, same as a synchronous screenshot.
+    pub fn to_rgba8(&self, ctx: &mut Context) -> GameResult<Vec<u8>> {
+        let gfx = &mut ctx.gfx_context;
+        if !self.is_ready_in(gfx) {
+            gfx.encoder.flush(&mut *gfx.device);
+        }
+        let mut data = gfx.factory.read_mapping(&*self.buffer)?.to_vec();
+        flip_pixel_data(&mut data, self.width as usize, self.height as usize);
+        Ok(data)
+    }
+
+    /// Returns the screenshot as an `Image`.  See
+    /// [`to_rgba8()`](#method.to_rgba8) about waiting.
+    pub fn to_image(&self, ctx: &mut Context) -> GameResult<Image> {
+        let data = self.to_rgba8(ctx)?;
+        Image::from_rgba8(ctx, self.width, self.height, &data)
+    }
+
+    fn is_ready_in(&self, gfx: &context::GraphicsContext) -> bool {
+        gfx.frames_presented > self.frame
+    }
+}
+
+/// Works out what part of the current render target to copy for a
+/// screenshot of the given region.
+fn readback_info(
+    gfx: &context::GraphicsContext,
+    region: Rect,
+) -> GameResult<gfx::texture::RawImageInfo> {
+    let (w, h, _depth, aa) = gfx.data.out.get_dimensions();
+    if aa != gfx_core::texture::AaMode::Single {
+        // Details see https://github.com/ggez/ggez/issues/751
+        return Err(GameError::RenderError("Can't take screenshots of anti aliased textures.\n(since neither copying or resolving them is supported right now)".to_string()));
+    }
+    if gfx.final_pass.is_hdr() && gfx.data.out == gfx.screen_render_target {
+        return Err(GameError::RenderError(
+            "Can't take screenshots of the screen while HDR is on".to_string(),
+        ));
+    }
+
+    let clamp = |v: f32, max: u16| v.round().max(0.0).min(f32::from(max)) as u16;
+    let (left, right) = (clamp(region.x, w), clamp(region.right(), w));
+    let (top, bottom) = (clamp(region.y, h), clamp(region.bottom(), h));
+    if right <= left || bottom <= top {
+        return Err(GameError::RenderError(format!(
+            "Screenshot region {:?} doesn't overlap the {}x{} render target",
+            region, w, h
+        )));
+    }
+
+    Ok(gfx::texture::ImageInfoCommon {
+        xoffset: left,
+        // GL's origin is the bottom left.
+        yoffset: h - bottom,
+        zoffset: 0,
+        width: right - left,
+        height: bottom - top,
+        depth: 0,
+        format: gfx.color_format(),
+        mipmap: 0,
+    })
+}
+
 /// Fast non-allocating function for flipping pixel data in an image vertically
 fn flip_pixel_data(rgba: &mut Vec<u8>, width: usize, height: usize) {
     // cast the buffer into u32 so we can easily access the pixels themselves
@@ -588,6 +765,16 @@ pub fn default_filter(ctx: &Context) -> FilterMode {
     gfx.default_sampler_info.filter.into()
 }
 
+/// Returns the backend the graphics context was made with: the one
+/// [`Conf::backend`](../conf/struct.Conf.html#structfield.backend) asked
+/// for, or whichever of the
+/// [fallbacks](../conf/struct.Conf.html#structfield.backend_fallbacks)
+/// worked first.  Handy for picking shaders written for the right GLSL
+/// version, `150` for OpenGL and `300 es` for OpenGL ES.
+pub fn backend(ctx: &Context) -> conf::Backend {
+    ctx.gfx_context.backend_spec.into()
+}
+
 /// Returns a string that tells a little about the obtained rendering mode.
 /// It is supposed to be human-readable and will change; do not try to parse
 /// information out of it!
@@ -616,6 +803,72 @@ pub fn screen_coordinates(ctx: &Context) -> Rect {
     ctx.gfx_context.screen_rect
 }
 
+/// Converts a position in the window, in physical pixels with the origin
+/// at the top-left (which is what [`mouse::position()`](../input/mouse/fn.position.html)
+/// and the mouse events give you), into the coordinate system things are
+/// drawn in.  With [`DpiPolicy::Logical`](../conf/enum.DpiPolicy.html#variant.Logical)
+/// the position is in logical pixels instead, same as the mouse.
+///
+/// This takes into account the window's hidpi scale factor, the current
+/// [`screen_coordinates()`](fn.screen_coordinates.html), any custom
+/// projection or [`Camera`](struct.Camera.html), and the current
+/// [`viewport()`](fn.viewport.html).
+pub fn window_to_screen_coords<P>(ctx: &Context, point: P) -> mint::Point2<f32>
+where
+    P: Into<mint::Point2<f32>>,
+{
+    let point = Point2::from(point.into());
+    let (w, h) = drawable_size(ctx);
+    let (input_w, input_h) = input_size(ctx);
+    let ndc = glam::vec3(
+        2.0 * point.x / input_w - 1.0,
+        1.0 - 2.0 * point.y / input_h,
+        0.0,
+    );
+    let inverse = window_projection(ctx, w, h).inverse();
+    let p = inverse.transform_point3(ndc);
+    mint::Point2 { x: p.x, y: p.y }
+}
+
+/// Converts a position in the coordinate system things are drawn in into
+/// a position in the window, in physical (or, with
+/// [`DpiPolicy::Logical`](../conf/enum.DpiPolicy.html#variant.Logical),
+/// logical) pixels with the origin at the top-left.  The inverse of
+/// [`window_to_screen_coords()`](fn.window_to_screen_coords.html).
+pub fn screen_to_window_coords<P>(ctx: &Context, point: P) -> mint::Point2<f32>
+where
+    P: Into<mint::Point2<f32>>,
+{
+    let point = Point2::from(point.into());
+    let (w, h) = drawable_size(ctx);
+    let (input_w, input_h) = input_size(ctx);
+    let ndc = window_projection(ctx, w, h).transform_point3(point.extend(0.0));
+    mint::Point2 {
+        x: (ndc.x + 1.0) / 2.0 * input_w,
+        y: (1.0 - ndc.y) / 2.0 * input_h,
+    }
+}
+
+/// The window's size in whatever units mouse positions are reported in.
+fn input_size(ctx: &Context) -> (f32, f32) {
+    let (w, h) = drawable_size(ctx);
+    match ctx.gfx_context.dpi_policy {
+        conf::DpiPolicy::Physical => (w, h),
+        conf::DpiPolicy::Logical => {
+            let scale_factor = scale_factor(ctx);
+            (w / scale_factor, h / scale_factor)
+        }
+    }
+}
+
+/// The full matrix taking screen coordinates to normalized device
+/// coordinates of a window of the given size.
+fn window_projection(ctx: &Context, w: f32, h: f32) -> Matrix4 {
+    let gfx = &ctx.gfx_context;
+    let window_size = (w as u16, h as u16);
+    context::viewport_matrix(gfx.viewport, window_size) * gfx.projection()
+}
+
 /// Sets the default filter mode used to scale images.
 ///
 /// This does not apply retroactively to already created images.
@@ -646,6 +899,138 @@ pub fn set_screen_coordinates(context: &mut Context, rect: Rect) -> GameResult {
     //gfx.update_globals()
 }
 
+/// Restricts drawing to the given region of the current render target,
+/// in pixels, with the origin at the top-left.
+///
+/// The whole of the current projection (as set by
+/// [`set_screen_coordinates()`](fn.set_screen_coordinates.html) or
+/// [`set_camera()`](fn.set_camera.html)) is squeezed into the viewport,
+/// so you probably want to set screen coordinates with the same aspect
+/// ratio as the viewport.  This makes split-screen easy: draw the scene
+/// once per player, each time with a different viewport and camera.
+///
+/// The viewport is relative to whatever render target is current, so
+/// set it again after calling [`set_canvas()`](fn.set_canvas.html).
+/// [`clear()`](fn.clear.html) is not affected by it and always clears
+/// the whole target.
+pub fn set_viewport(ctx: &mut Context, rect: Rect) -> GameResult {
+    let gfx = &mut ctx.gfx_context;
+    gfx.viewport = Some(rect);
+    gfx.set_global_mvp(Matrix4::IDENTITY)
+}
+
+/// Resets the viewport so drawing covers the whole render target again.
+pub fn reset_viewport(ctx: &mut Context) -> GameResult {
+    let gfx = &mut ctx.gfx_context;
+    gfx.viewport = None;
+    gfx.set_global_mvp(Matrix4::IDENTITY)
+}
+
+/// Returns the current viewport, in pixels.  If none has been
+/// set this is the size of the whole render target.
+pub fn viewport(ctx: &Context) -> Rect {
+    let gfx = &ctx.gfx_context;
+    gfx.viewport.unwrap_or_else(|| {
+        let (w, h) = gfx.target_size();
+        Rect::new(0.0, 0.0, f32::from(w), f32::from(h))
+    })
+}
+
+/// Sets the viewport to `rect`, calls `f`, and then restores whatever
+/// viewport was in use before, even if `f` returns an error.
+///
+/// See [`set_viewport()`](fn.set_viewport.html) for details.
+pub fn with_viewport<F, T>(ctx: &mut Context, rect: Rect, f: F) -> GameResult<T>
+where
+    F: FnOnce(&mut Context) -> GameResult<T>,
+{
+    let previous = ctx.gfx_context.viewport;
+    set_viewport(ctx, rect)?;
+    let result = f(ctx);
+    let gfx = &mut ctx.gfx_context;
+    gfx.viewport = previous;
+    gfx.set_global_mvp(Matrix4::IDENTITY)?;
+    result
+}
+
+/// Pushes a transform onto the transform stack, so that everything drawn
+/// until the matching [`pop_transform()`](fn.pop_transform.html) is
+/// transformed by it, after its own `DrawParam`.  Transforms pushed on
+/// top of others are applied first, so a child entity can push its
+/// position relative to its parent and draw as though the parent were
+/// at the origin:
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # use ggez::graphics::*;
+/// # fn t(ctx: &mut Context, body: &Image, arm: &Image) -> GameResult {
+/// let body_transform = glam::Mat4::from_translation(glam::vec3(300.0, 200.0, 0.0));
+/// graphics::push_transform(ctx, body_transform)?;
+/// graphics::draw(ctx, body, DrawParam::new())?;
+/// // The arm is drawn 20 pixels right of the body.
+/// graphics::push_transform(ctx, glam::Mat4::from_translation(glam::vec3(20.0, 0.0, 0.0)))?;
+/// graphics::draw(ctx, arm, DrawParam::new().rotation(0.5))?;
+/// graphics::pop_transform(ctx)?;
+/// graphics::pop_transform(ctx)?;
+/// # Ok(()) }
+/// ```
+///
+/// The transform is applied on top of the screen coordinates or
+/// [`Camera`](struct.Camera.html), and stays in place when either of
+/// them changes.
+pub fn push_transform<M>(ctx: &mut Context, transform: M) -> GameResult
+where
+    M: Into<mint::ColumnMatrix4<f32>>,
+{
+    let transform = Matrix4::from(transform.into());
+    let gfx = &mut ctx.gfx_context;
+    gfx.transform_stack.push(gfx.transform);
+    gfx.transform = gfx.transform * transform;
+    gfx.set_global_mvp(Matrix4::IDENTITY)
+}
+
+/// Pops the last transform pushed with
+/// [`push_transform()`](fn.push_transform.html), going back to the one
+/// before it.  Returns an error if the stack is empty.
+pub fn pop_transform(ctx: &mut Context) -> GameResult {
+    let gfx = &mut ctx.gfx_context;
+    match gfx.transform_stack.pop() {
+        Some(transform) => {
+            gfx.transform = transform;
+            gfx.set_global_mvp(Matrix4::IDENTITY)
+        }
+        None => Err(GameError::RenderError(
+            "pop_transform() called without a matching push_transform()".to_string(),
+        )),
+    }
+}
+
+/// Pushes a transform, calls `f`, and then pops the transform again,
+/// even if `f` returns an error.
+///
+/// See [`push_transform()`](fn.push_transform.html) for details.
+pub fn with_transform<M, F, T>(ctx: &mut Context, transform: M, f: F) -> GameResult<T>
+where
+    M: Into<mint::ColumnMatrix4<f32>>,
+    F: FnOnce(&mut Context) -> GameResult<T>,
+{
+    push_transform(ctx, transform)?;
+    let result = f(ctx);
+    pop_transform(ctx)?;
+    result
+}
+
+/// Returns the combined transform of everything on the transform stack;
+/// the identity matrix if it's empty.
+pub fn transform(ctx: &Context) -> mint::ColumnMatrix4<f32> {
+    ctx.gfx_context.transform.into()
+}
+
+/// Returns how many transforms are on the transform stack.
+pub fn transform_stack_depth(ctx: &Context) -> usize {
+    ctx.gfx_context.transform_stack.len()
+}
+
 /// Premultiplies the given transformation matrix with the current projection matrix
 ///
 /// You must call [`apply_transformations(ctx)`](fn.apply_transformations.html)
@@ -688,6 +1073,54 @@ pub fn set_blend_mode(ctx: &mut Context, mode: BlendMode) -> GameResult {
     ctx.gfx_context.set_blend_mode(mode)
 }
 
+/// Turns depth testing on or off.  With it on, everything drawn is
+/// sorted by its [`DrawParam::depth`](struct.DrawParam.html#structfield.depth)
+/// rather than by the order it's drawn in: things with a greater depth
+/// cover things with a lesser one, whatever image, batch or mesh they
+/// come from.  This makes y-sorting isometric scenes as simple as
+/// setting each sprite's depth from its y position.
+///
+/// [`clear()`](fn.clear.html) resets the depth of the current render
+/// target.  Depth is written for every pixel drawn, even see-through
+/// ones, so sprites with soft or partly transparent edges can hide
+/// what's behind them; draw those in order, after the rest, with depth
+/// testing off.  [`LitSprite`](struct.LitSprite.html)s and the
+/// [`effects`](effects/index.html) ignore depth.
+///
+/// Like [`set_polygon_mode()`](fn.set_polygon_mode.html), the first
+/// time this is turned on every shader's pipelines for it get built,
+/// which may cause a short hitch.
+pub fn set_depth_test(ctx: &mut Context, depth_test: bool) -> GameResult {
+    ctx.gfx_context.set_depth_test(depth_test)
+}
+
+/// Returns whether depth testing is on; see
+/// [`set_depth_test()`](fn.set_depth_test.html).
+pub fn depth_test(ctx: &Context) -> bool {
+    ctx.gfx_context.depth_test
+}
+
+/// Sets whether triangles are filled in or drawn as wireframes, for
+/// every shader including ones created later.  This is a debugging aid
+/// for looking at mesh tessellation and batching; images and text are
+/// drawn as quads, so they show up as pairs of triangles.
+///
+/// The first time wireframe mode is turned on the wireframe pipelines
+/// for all shaders get built, which may cause a short hitch.
+pub fn set_polygon_mode(ctx: &mut Context, mode: PolygonMode) -> GameResult {
+    let gfx = &mut ctx.gfx_context;
+    for shader in gfx.shaders.iter_mut() {
+        shader.set_polygon_mode(&mut *gfx.factory, mode)?;
+    }
+    gfx.polygon_mode = mode;
+    Ok(())
+}
+
+/// Returns the current [`PolygonMode`](enum.PolygonMode.html).
+pub fn polygon_mode(ctx: &Context) -> PolygonMode {
+    ctx.gfx_context.polygon_mode
+}
+
 /// Sets the window mode, such as the size and other properties.
 ///
 /// Setting the window mode may have side effects, such as clearing
@@ -731,6 +1164,65 @@ pub fn set_resizable(context: &mut Context, resizable: bool) -> GameResult {
     set_mode(context, window_mode)
 }
 
+/// Sets whether the window has decorations (title bar and borders)
+/// in windowed mode.  This is the inverse of
+/// [`WindowMode::borderless`](../conf/struct.WindowMode.html#structfield.borderless).
+pub fn set_window_decorations(context: &mut Context, decorations: bool) -> GameResult {
+    let window_mode = context.conf.window_mode.borderless(!decorations);
+    set_mode(context, window_mode)
+}
+
+/// Sets whether the window stays above other windows.
+pub fn set_always_on_top(context: &mut Context, always_on_top: bool) -> GameResult {
+    let window_mode = context.conf.window_mode.always_on_top(always_on_top);
+    set_mode(context, window_mode)
+}
+
+/// Maximizes the window, or restores it to its previous size.
+/// Only has an effect in windowed mode.
+pub fn set_maximized(context: &mut Context, maximized: bool) -> GameResult {
+    let window_mode = context.conf.window_mode.maximized(maximized);
+    set_mode(context, window_mode)
+}
+
+/// Shows or hides the window.
+pub fn set_window_visible(context: &mut Context, visible: bool) -> GameResult {
+    let window_mode = context.conf.window_mode.visible(visible);
+    set_mode(context, window_mode)
+}
+
+/// Sets the minimum window size for windowed mode; pass zeroes to
+/// remove the limit.
+pub fn set_min_window_size(context: &mut Context, width: f32, height: f32) -> GameResult {
+    let window_mode = context.conf.window_mode.min_dimensions(width, height);
+    set_mode(context, window_mode)
+}
+
+/// Sets the maximum window size for windowed mode; pass zeroes to
+/// remove the limit.
+pub fn set_max_window_size(context: &mut Context, width: f32, height: f32) -> GameResult {
+    let window_mode = context.conf.window_mode.max_dimensions(width, height);
+    set_mode(context, window_mode)
+}
+
+/// Turns vsync on or off without restarting, so it can be offered as
+/// a setting in game.  The new value is stored in
+/// [`conf.window_setup.vsync`](../conf/struct.WindowSetup.html#structfield.vsync).
+///
+/// Returns an error if the GL driver doesn't let us change the swap
+/// interval after creating the context, in which case the old setting
+/// stays in effect.
+pub fn set_vsync(context: &mut Context, vsync: bool) -> GameResult {
+    context.gfx_context.set_vsync(vsync)?;
+    context.conf.window_setup.vsync = vsync;
+    Ok(())
+}
+
+/// Returns whether vsync is currently enabled.
+pub fn vsync(context: &Context) -> bool {
+    context.conf.window_setup.vsync
+}
+
 /// Sets the window icon.
 pub fn set_window_icon<P: AsRef<Path>>(context: &mut Context, path: Option<P>) -> GameResult<()> {
     let icon = match path {
@@ -744,6 +1236,27 @@ pub fn set_window_icon<P: AsRef<Path>>(context: &mut Context, path: Option<P>) -
     Ok(())
 }
 
+/// Sets the window icon from an [`Image`](struct.Image.html), such as
+/// one generated at runtime or drawn into a [`Canvas`](struct.Canvas.html).
+/// `None` removes the icon.
+pub fn set_window_icon_from_image(context: &mut Context, image: Option<&Image>) -> GameResult {
+    let icon = match image {
+        Some(image) => {
+            let rgba = image.to_rgba8(context)?;
+            let icon = winit::window::Icon::from_rgba(
+                rgba,
+                u32::from(image.width()),
+                u32::from(image.height()),
+            )
+            .map_err(|e| GameError::RenderError(format!("Could not create icon: {:?}", e)))?;
+            Some(icon)
+        }
+        None => None,
+    };
+    context.gfx_context.window.window().set_window_icon(icon);
+    Ok(())
+}
+
 /// Sets the window title.
 pub fn set_window_title(context: &Context, title: &str) {
     context.gfx_context.window.window().set_title(title);
@@ -781,6 +1294,64 @@ pub fn supported_resolutions(
     monitor.video_modes().map(|v_mode| v_mode.size())
 }
 
+/// A display mode a monitor supports in true fullscreen, as returned by
+/// [`video_modes()`](fn.video_modes.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    /// Width in physical pixels.
+    pub width: u32,
+    /// Height in physical pixels.
+    pub height: u32,
+    /// Refresh rate in Hz.
+    pub refresh_rate: u16,
+    /// Bits per pixel.
+    pub bit_depth: u16,
+}
+
+impl From<&winit::monitor::VideoMode> for VideoMode {
+    fn from(v_mode: &winit::monitor::VideoMode) -> Self {
+        let size = v_mode.size();
+        VideoMode {
+            width: size.width,
+            height: size.height,
+            refresh_rate: v_mode.refresh_rate(),
+            bit_depth: v_mode.bit_depth(),
+        }
+    }
+}
+
+/// Returns every video mode the current monitor supports, for building
+/// a resolution and refresh rate menu.  Pass the chosen one to
+/// [`set_mode()`](fn.set_mode.html) via
+/// [`WindowMode::dimensions()`](../conf/struct.WindowMode.html#method.dimensions)
+/// and [`WindowMode::refresh_rate()`](../conf/struct.WindowMode.html#method.refresh_rate)
+/// along with [`FullscreenType::True`](../conf/enum.FullscreenType.html#variant.True).
+///
+/// Returns an empty list if the current monitor can't be determined.
+pub fn video_modes(ctx: &Context) -> Vec<VideoMode> {
+    match ctx.gfx_context.window.window().current_monitor() {
+        Some(monitor) => monitor
+            .video_modes()
+            .map(|v_mode| VideoMode::from(&v_mode))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Picks the video mode that best fits the given window mode: the
+/// resolution must match exactly, as must the refresh rate unless it's
+/// 0, in which case the fastest is chosen.  Ties go to the deepest
+/// color.
+pub(crate) fn best_video_mode(modes: &[VideoMode], mode: &WindowMode) -> Option<usize> {
+    modes
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| (m.width, m.height) == (mode.width as u32, mode.height as u32))
+        .filter(|(_, m)| mode.refresh_rate == 0 || m.refresh_rate == mode.refresh_rate)
+        .max_by_key(|(_, m)| (m.refresh_rate, m.bit_depth))
+        .map(|(i, _)| i)
+}
+
 /// Returns the size of the window in pixels as (width, height),
 /// including borders, titlebar, etc.
 /// Returns zeros if the window doesn't exist.
@@ -791,6 +1362,12 @@ pub fn size(context: &Context) -> (f32, f32) {
     (physical_size.width as f32, physical_size.height as f32)
 }
 
+/// Returns the window's hidpi scale factor: how many physical pixels
+/// make up one logical pixel.
+pub fn scale_factor(context: &Context) -> f32 {
+    context.gfx_context.window.window().scale_factor() as f32
+}
+
 /// Returns the size of the window's underlying drawable in physical pixels as (width, height).
 /// Returns zeros if window doesn't exist.
 pub fn drawable_size(context: &Context) -> (f32, f32) {
@@ -815,7 +1392,7 @@ pub fn clear_font_cache(ctx: &mut Context) {
     use std::rc::Rc;
     let font_vec =
         glyph_brush::ab_glyph::FontArc::try_from_slice(Font::default_font_bytes()).unwrap();
-    let glyph_brush = GlyphBrushBuilder::using_font(font_vec).build();
+    let glyph_brush = GlyphBrushBuilder::using_font(font_vec.clone()).build();
     let (glyph_cache_width, glyph_cache_height) = glyph_brush.texture_dimensions();
     let initial_contents = vec![255; 4 * glyph_cache_width as usize * glyph_cache_height as usize];
     let glyph_cache = Image::from_rgba8(
@@ -829,6 +1406,7 @@ pub fn clear_font_cache(ctx: &mut Context) {
         glyph_cache.clone(),
     )));
     ctx.gfx_context.glyph_brush = Rc::new(RefCell::new(glyph_brush));
+    *ctx.gfx_context.fonts.borrow_mut() = vec![font_vec];
     ctx.gfx_context.glyph_cache = glyph_cache;
     ctx.gfx_context.glyph_state = glyph_state;
 }
@@ -891,7 +1469,8 @@ pub fn transform_rect(rect: Rect, param: DrawParam) -> Rect {
             offset,
             dest,
             rotation,
-        } => {
+            skew,
+        } if skew == [0.0, 0.0].into() => {
             // first apply the offset
             let mut r = Rect {
                 w: rect.w,
@@ -913,16 +1492,63 @@ pub fn transform_rect(rect: Rect, param: DrawParam) -> Rect {
 
             r
         }
+        Transform::Values {
+            scale,
+            offset,
+            dest,
+            rotation,
+            skew,
+        } => {
+            // Skewed rects aren't rects anymore, so take the bounding box
+            // of the transformed corners.
+            let m = Matrix4::from(
+                Transform::Values {
+                    dest,
+                    rotation,
+                    scale: [param.src.w * scale.x, param.src.h * scale.y].into(),
+                    offset: [offset.x * rect.w, offset.y * rect.h].into(),
+                    skew,
+                }
+                .to_bare_matrix(),
+            );
+            let corners = [
+                glam::vec3(rect.left(), rect.top(), 0.0),
+                glam::vec3(rect.right(), rect.top(), 0.0),
+                glam::vec3(rect.left(), rect.bottom(), 0.0),
+                glam::vec3(rect.right(), rect.bottom(), 0.0),
+            ];
+            let (mut min, mut max) = (glam::Vec2::splat(f32::MAX), glam::Vec2::splat(f32::MIN));
+            for corner in &corners {
+                let p = m.transform_point3(*corner).truncate();
+                min = min.min(p);
+                max = max.max(p);
+            }
+            Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+        }
         Transform::Matrix(_m) => todo!("Fix me"),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::graphics::{transform_rect, DrawParam, Rect};
+    use crate::conf::WindowMode;
+    use crate::graphics::context::{viewport_matrix, viewport_scissor};
+    use crate::graphics::{best_video_mode, transform_rect, DrawParam, Rect, VideoMode};
     use approx::assert_relative_eq;
     use std::f32::consts::PI;
 
+    #[test]
+    fn headless_test_transform_rect_skew() {
+        let r = Rect::new(0.0, 0.0, 1.0, 1.0);
+        let param = DrawParam::new().skew([PI * 0.25, 0.0]);
+        let real = transform_rect(r, param);
+        assert_relative_eq!(real, Rect::new(0.0, 0.0, 2.0, 1.0), epsilon = 1e-5);
+
+        let param = DrawParam::new().skew([0.0, PI * 0.25]).dest([10.0, 0.0]);
+        let real = transform_rect(r, param);
+        assert_relative_eq!(real, Rect::new(10.0, 0.0, 1.0, 2.0), epsilon = 1e-5);
+    }
+
     #[test]
     fn headless_test_transform_rect() {
         {
@@ -1073,4 +1699,59 @@ mod tests {
             assert_relative_eq!(real, expected);
         }
     }
+
+    #[test]
+    fn headless_test_viewport_matrix() {
+        // The right half of an 800x600 target.
+        let viewport = Rect::new(400.0, 0.0, 400.0, 600.0);
+        let m = viewport_matrix(Some(viewport), (800, 600));
+        let top_left = m.transform_point3(glam::vec3(-1.0, 1.0, 0.0));
+        let bottom_right = m.transform_point3(glam::vec3(1.0, -1.0, 0.0));
+        assert_relative_eq!(top_left.x, 0.0);
+        assert_relative_eq!(top_left.y, 1.0);
+        assert_relative_eq!(bottom_right.x, 1.0);
+        assert_relative_eq!(bottom_right.y, -1.0);
+
+        let identity = viewport_matrix(None, (800, 600));
+        assert_eq!(identity, glam::Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn headless_test_viewport_scissor() {
+        // The top half; GL's scissor origin is the bottom left.
+        let viewport = Rect::new(0.0, 0.0, 800.0, 300.0);
+        let r = viewport_scissor(Some(viewport), (800, 600));
+        assert_eq!((r.x, r.y, r.w, r.h), (0, 300, 800, 300));
+
+        // Anything off the target gets clipped.
+        let viewport = Rect::new(-100.0, 500.0, 300.0, 300.0);
+        let r = viewport_scissor(Some(viewport), (800, 600));
+        assert_eq!((r.x, r.y, r.w, r.h), (0, 0, 200, 100));
+
+        let r = viewport_scissor(None, (800, 600));
+        assert_eq!((r.x, r.y, r.w, r.h), (0, 0, 800, 600));
+    }
+
+    #[test]
+    fn headless_test_best_video_mode() {
+        let mode = |width, height, refresh_rate, bit_depth| VideoMode {
+            width,
+            height,
+            refresh_rate,
+            bit_depth,
+        };
+        let modes = [
+            mode(1920, 1080, 60, 32),
+            mode(1920, 1080, 144, 24),
+            mode(1920, 1080, 144, 32),
+            mode(1280, 720, 240, 32),
+        ];
+
+        let any_rate = WindowMode::default().dimensions(1920.0, 1080.0);
+        assert_eq!(best_video_mode(&modes, &any_rate), Some(2));
+        assert_eq!(best_video_mode(&modes, &any_rate.refresh_rate(60)), Some(0));
+        assert_eq!(best_video_mode(&modes, &any_rate.refresh_rate(75)), None);
+        let missing = WindowMode::default().dimensions(1024.0, 768.0);
+        assert_eq!(best_video_mode(&modes, &missing), None);
+    }
 }
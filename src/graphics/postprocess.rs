@@ -0,0 +1,401 @@
+//! The final pass: when HDR, color grading or the like is turned on,
+//! everything meant for the screen is drawn into an offscreen scene
+//! target instead, and `present()` copies that to the real screen
+//! through a shader that applies the effects.
+
+use gfx::handle::{RawRenderTargetView, RawShaderResourceView};
+use gfx::traits::FactoryExt;
+use gfx::Factory;
+
+use crate::context::Context;
+use crate::error::{GameError, GameResult};
+use crate::graphics::context::GraphicsContextGeneric;
+use crate::graphics::*;
+
+gfx_defines! {
+    /// Uniforms for the final pass shader.
+    constant FinalPassParams {
+        lut_size: f32 = "u_LutSize",
+        srgb: f32 = "u_Srgb",
+        exposure: f32 = "u_Exposure",
+        tonemapper: f32 = "u_Tonemapper",
+    }
+}
+
+pub(crate) mod final_pipe {
+    use super::FinalPassParams;
+    use crate::graphics::Vertex;
+
+    gfx_pipeline_inner! {
+        vbuf: gfx::VertexBuffer<Vertex>,
+        scene: gfx::TextureSampler<[f32; 4]>,
+        lut: gfx::TextureSampler<[f32; 4]>,
+        params: gfx::ConstantBuffer<FinalPassParams>,
+        out: gfx::RawRenderTarget,
+    }
+
+    pub fn new(color_format: gfx::format::Format) -> Init<'static> {
+        Init {
+            vbuf: (),
+            scene: "t_Scene",
+            lut: "t_Lut",
+            params: "FinalPassParams",
+            out: ("Target0", color_format, gfx::state::ColorMask::all(), None),
+        }
+    }
+}
+
+/// The texture format of HDR canvases and the HDR scene target.
+pub(crate) const HDR_FORMAT: gfx::format::Format = gfx::format::Format(
+    gfx::format::SurfaceType::R16_G16_B16_A16,
+    gfx::format::ChannelType::Float,
+);
+
+/// How [HDR](fn.set_hdr.html) colors are squeezed into the range the
+/// screen can show.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// Just clip anything brighter than white.
+    Clamp,
+    /// `c / (1 + c)`; gentle, but tends to look washed out.
+    Reinhard,
+    /// An approximation of the ACES filmic curve, with more contrast
+    /// and saturation.
+    Aces,
+}
+
+impl Tonemapper {
+    fn shader_value(self) -> f32 {
+        match self {
+            Tonemapper::Clamp => 0.0,
+            Tonemapper::Reinhard => 1.0,
+            Tonemapper::Aces => 2.0,
+        }
+    }
+}
+
+/// Settings for [`set_hdr()`](fn.set_hdr.html).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HdrSettings {
+    /// The curve mapping HDR colors onto the screen.
+    pub tonemapper: Tonemapper,
+    /// What colors are multiplied by before tonemapping; raise it to
+    /// brighten the scene, lower it to bring over-bright areas back.
+    pub exposure: f32,
+}
+
+impl Default for HdrSettings {
+    fn default() -> Self {
+        HdrSettings {
+            tonemapper: Tonemapper::Aces,
+            exposure: 1.0,
+        }
+    }
+}
+
+impl HdrSettings {
+    /// Set the tonemapper.
+    pub fn tonemapper(mut self, tonemapper: Tonemapper) -> Self {
+        self.tonemapper = tonemapper;
+        self
+    }
+
+    /// Set the exposure.
+    pub fn exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+}
+
+/// The offscreen target standing in for the screen.
+struct SceneTarget<B: BackendSpec> {
+    resource: RawShaderResourceView<B::Resources>,
+    format: gfx::format::Format,
+    /// The real screen target, which this replaces while it exists.
+    window_target: RawRenderTargetView<B::Resources>,
+}
+
+/// Final pass state kept in the graphics context.
+pub(crate) struct FinalPass<B: BackendSpec> {
+    scene: Option<SceneTarget<B>>,
+    lut: Option<ImageGeneric<B>>,
+    hdr: Option<HdrSettings>,
+    pso: Option<gfx::PipelineState<B::Resources, final_pipe::Meta>>,
+    params: Option<gfx::handle::Buffer<B::Resources, FinalPassParams>>,
+}
+
+impl<B: BackendSpec> Default for FinalPass<B> {
+    fn default() -> Self {
+        FinalPass {
+            scene: None,
+            lut: None,
+            hdr: None,
+            pso: None,
+            params: None,
+        }
+    }
+}
+
+impl<B: BackendSpec> FinalPass<B> {
+    /// Whether anything needs the final pass at all.
+    pub(crate) fn is_active(&self) -> bool {
+        self.lut.is_some() || self.hdr.is_some()
+    }
+
+    /// Whether the scene target is an HDR one.
+    pub(crate) fn is_hdr(&self) -> bool {
+        self.hdr.is_some()
+    }
+
+    /// Puts the real screen target back and drops the scene target,
+    /// e.g. before the window is resized.
+    pub(crate) fn detach(&mut self, screen: &mut RawRenderTargetView<B::Resources>) {
+        if let Some(scene) = self.scene.take() {
+            *screen = scene.window_target;
+        }
+    }
+
+    /// Creates the scene target and swaps it in for the screen if the
+    /// final pass is active, or undoes that if it isn't anymore.
+    pub(crate) fn update_target(
+        &mut self,
+        factory: &mut B::Factory,
+        screen: &mut RawRenderTargetView<B::Resources>,
+        color_format: gfx::format::Format,
+    ) -> GameResult {
+        if !self.is_active() {
+            self.detach(screen);
+            return Ok(());
+        }
+        let format = if self.hdr.is_some() {
+            HDR_FORMAT
+        } else {
+            color_format
+        };
+        match self.scene {
+            Some(ref scene) if scene.format == format => return Ok(()),
+            Some(_) => self.detach(screen),
+            None => (),
+        }
+
+        let (width, height, _, _) = screen.get_dimensions();
+        let (target, resource) = create_scene_target::<B>(factory, width, height, format)?;
+        let window_target = std::mem::replace(screen, target);
+        self.scene = Some(SceneTarget {
+            resource,
+            format,
+            window_target,
+        });
+        Ok(())
+    }
+
+    /// Draws the scene to the real screen, if there is a scene.
+    pub(crate) fn apply(&mut self, gfx: &mut GraphicsContextGeneric<B>) -> GameResult
+    where
+        B: 'static,
+    {
+        match self.scene {
+            Some(ref scene) => {
+                let out = scene.window_target.clone();
+                self.draw_scene(gfx, out)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Draws the scene through the final pass to `out`, which is the real
+    /// screen but for tests, since that can't be read back.
+    pub(crate) fn draw_scene(
+        &mut self,
+        gfx: &mut GraphicsContextGeneric<B>,
+        out: RawRenderTargetView<B::Resources>,
+    ) -> GameResult
+    where
+        B: 'static,
+    {
+        let scene = match self.scene {
+            Some(ref scene) => scene,
+            None => return Ok(()),
+        };
+        let color_format = gfx.color_format();
+
+        if self.pso.is_none() {
+            let (vs, fs): (&[u8], &[u8]) = match gfx.backend_spec.api() {
+                glutin::Api::OpenGlEs => (
+                    include_bytes!("shader/final_es300.glslv"),
+                    include_bytes!("shader/final_es300.glslf"),
+                ),
+                _ => (
+                    include_bytes!("shader/final_150.glslv"),
+                    include_bytes!("shader/final_150.glslf"),
+                ),
+            };
+            let pso = gfx
+                .factory
+                .create_pipeline_simple(vs, fs, final_pipe::new(color_format))?;
+            self.pso = Some(pso);
+        }
+        if self.params.is_none() {
+            self.params = Some(gfx.factory.create_constant_buffer(1));
+        }
+
+        if let (Some(pso), Some(params)) = (&self.pso, &self.params) {
+            let linear = texture::SamplerInfo::new(
+                texture::FilterMethod::Bilinear,
+                texture::WrapMode::Clamp,
+            );
+            let sampler = gfx.samplers.get_or_insert(linear, &mut *gfx.factory);
+            let (lut_view, lut_size) = match self.lut {
+                Some(ref lut) => (lut.texture.clone(), f32::from(lut.height)),
+                None => (gfx.white_image.texture.clone(), 0.0),
+            };
+
+            // Without HDR the scene is already in range, and clamping it
+            // changes nothing.
+            let hdr = self.hdr.unwrap_or(HdrSettings {
+                tonemapper: Tonemapper::Clamp,
+                exposure: 1.0,
+            });
+            let srgb = color_format.1 == gfx::format::ChannelType::Srgb;
+            gfx.encoder.update_constant_buffer(
+                params,
+                &FinalPassParams {
+                    lut_size,
+                    srgb: if srgb { 1.0 } else { 0.0 },
+                    exposure: hdr.exposure,
+                    tonemapper: hdr.tonemapper.shader_value(),
+                },
+            );
+            let data = final_pipe::Data {
+                vbuf: gfx.quad_vertex_buffer.clone(),
+                scene: (
+                    gfx.backend_spec
+                        .raw_to_typed_shader_resource(scene.resource.clone()),
+                    sampler.clone(),
+                ),
+                lut: (
+                    gfx.backend_spec.raw_to_typed_shader_resource(lut_view),
+                    sampler,
+                ),
+                params: params.clone(),
+                out,
+            };
+            gfx.encoder.draw(&gfx.quad_slice, pso, &data);
+        }
+        Ok(())
+    }
+}
+
+fn create_scene_target<B: BackendSpec>(
+    factory: &mut B::Factory,
+    width: u16,
+    height: u16,
+    color_format: gfx::format::Format,
+) -> GameResult<(
+    RawRenderTargetView<B::Resources>,
+    RawShaderResourceView<B::Resources>,
+)> {
+    use gfx::memory::{Bind, Usage};
+    use gfx::texture::{AaMode, Kind};
+
+    let info = gfx::texture::Info {
+        kind: Kind::D2(width, height, AaMode::Single),
+        levels: 1,
+        format: color_format.0,
+        bind: Bind::SHADER_RESOURCE | Bind::RENDER_TARGET | Bind::TRANSFER_SRC,
+        usage: Usage::Data,
+    };
+    let tex = factory.create_texture_raw(info, Some(color_format.1), None)?;
+    let resource_desc = gfx::texture::ResourceDesc {
+        channel: color_format.1,
+        layer: None,
+        min: 0,
+        max: 0,
+        swizzle: gfx::format::Swizzle::new(),
+    };
+    let resource = factory.view_texture_as_shader_resource_raw(&tex, resource_desc)?;
+    let render_desc = gfx::texture::RenderDesc {
+        channel: color_format.1,
+        level: 0,
+        layer: None,
+    };
+    let target = factory.view_texture_as_render_target_raw(&tex, render_desc)?;
+    Ok((target, resource))
+}
+
+/// Sets a color lookup table to grade everything drawn to the screen
+/// with, or `None` to turn grading off.  Handy for day/night tints and
+/// film looks.
+///
+/// The LUT is a standard "strip" image: for a LUT of size `N` it's
+/// `N * N` pixels wide and `N` tall, made of `N` squares side by side
+/// with blue increasing from one square to the next, red increasing
+/// left to right and green increasing top to bottom within each.  An
+/// unmodified 16 or 32 sized strip leaves colors as they are, so the
+/// usual workflow is to color grade a screenshot with one pasted into
+/// it in an image editor, then cut the strip back out.
+///
+/// Grading happens in [`present()`](fn.present.html), so screenshots
+/// taken before then show the ungraded frame.
+pub fn set_color_lut(ctx: &mut Context, lut: Option<&Image>) -> GameResult {
+    if let Some(lut) = lut {
+        let n = lut.height();
+        if n < 2 || u32::from(lut.width()) != u32::from(n) * u32::from(n) {
+            return Err(GameError::RenderError(format!(
+                "A color LUT must be N*N pixels wide and N tall, not {}x{}",
+                lut.width(),
+                lut.height()
+            )));
+        }
+    }
+
+    ctx.gfx_context.final_pass.lut = lut.cloned();
+    update_final_pass_target(ctx)
+}
+
+/// Brings the screen target up to date after the final pass settings
+/// change.
+fn update_final_pass_target(ctx: &mut Context) -> GameResult {
+    let gfx = &mut ctx.gfx_context;
+    let was_screen = gfx.data.out == gfx.screen_render_target;
+    let color_format = gfx.color_format();
+    gfx.final_pass.update_target(
+        &mut *gfx.factory,
+        &mut gfx.screen_render_target,
+        color_format,
+    )?;
+    if was_screen {
+        gfx.set_screen_as_render_target();
+    }
+    Ok(())
+}
+
+/// Turns on HDR rendering with the given settings, or turns it off
+/// with `None`.
+///
+/// With HDR on, everything drawn to the screen goes into a 16-bit
+/// floating point target instead, so colors brighter than white (such
+/// as `Color::new(4.0, 3.0, 2.0, 1.0)`, or lots of additive blending)
+/// add up instead of being clipped.  [`present()`](fn.present.html)
+/// then applies the exposure and tonemapper to bring them into the
+/// range the screen can show, before any
+/// [color LUT](fn.set_color_lut.html).  Use
+/// [`Canvas::new_hdr()`](type.Canvas.html#method.new_hdr) for
+/// canvases that keep over-bright colors too, e.g. for bloom.
+///
+/// Screenshots can't be taken of the screen while HDR is on; draw to
+/// an ordinary canvas for that.
+pub fn set_hdr(ctx: &mut Context, settings: Option<HdrSettings>) -> GameResult {
+    ctx.gfx_context.final_pass.hdr = settings;
+    update_final_pass_target(ctx)
+}
+
+/// Returns the current HDR settings, or `None` if HDR is off.
+pub fn hdr(ctx: &Context) -> Option<HdrSettings> {
+    ctx.gfx_context.final_pass.hdr
+}
+
+/// Returns whether a color LUT is set.
+pub fn has_color_lut(ctx: &Context) -> bool {
+    ctx.gfx_context.final_pass.lut.is_some()
+}
@@ -0,0 +1,169 @@
+//! GPU timing scopes, with the `gpu-profiling` feature.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use gfx_gl as gl;
+
+use crate::context::Context;
+use crate::error::GameResult;
+
+/// How long a named [`gpu_scope()`](fn.gpu_scope.html) took on the GPU.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuScopeTiming {
+    /// The name given to `gpu_scope()`.
+    pub name: String,
+    /// The time between the GPU starting the scope's first command
+    /// and finishing its last one.
+    pub duration: Duration,
+}
+
+#[derive(Debug)]
+struct PendingScope {
+    name: String,
+    start: gl::types::GLuint,
+    end: gl::types::GLuint,
+}
+
+/// How many frames of queries we keep in flight before giving up on
+/// the oldest; results usually come back within a frame or two.
+const MAX_PENDING_FRAMES: usize = 4;
+
+/// Issues GL timestamp queries around profiling scopes and collects
+/// their results a few frames later, without stalling the pipeline.
+#[derive(Debug, Default)]
+pub(crate) struct GpuProfiler {
+    supported: Option<bool>,
+    current: Vec<PendingScope>,
+    pending: VecDeque<Vec<PendingScope>>,
+    free_queries: Vec<gl::types::GLuint>,
+    results: Vec<GpuScopeTiming>,
+}
+
+#[allow(unsafe_code)]
+impl GpuProfiler {
+    /// Timestamp queries need GL 3.3 or `ARB_timer_query`, which
+    /// not every driver ggez runs on has.
+    fn is_supported(&mut self, device: &mut gfx_device_gl::Device) -> bool {
+        if let Some(supported) = self.supported {
+            return supported;
+        }
+        let mut supported = false;
+        unsafe {
+            device.with_gl(|gl| {
+                supported = gl.GenQueries.is_loaded()
+                    && gl.QueryCounter.is_loaded()
+                    && gl.GetQueryObjectiv.is_loaded()
+                    && gl.GetQueryObjectui64v.is_loaded();
+            });
+        }
+        self.supported = Some(supported);
+        supported
+    }
+
+    /// Records a timestamp once the GPU has finished everything
+    /// submitted so far, and returns the query that will hold it.
+    fn timestamp(&mut self, device: &mut gfx_device_gl::Device) -> gl::types::GLuint {
+        let mut query = self.free_queries.pop().unwrap_or(0);
+        unsafe {
+            device.with_gl(|gl| {
+                if query == 0 {
+                    gl.GenQueries(1, &mut query);
+                }
+                gl.QueryCounter(query, gl::TIMESTAMP);
+            });
+        }
+        query
+    }
+
+    /// Moves this frame's scopes into the queue and collects the
+    /// results of any earlier frames the GPU has finished with.
+    pub(crate) fn end_frame(&mut self, device: &mut gfx_device_gl::Device) {
+        if self.current.is_empty() && self.pending.is_empty() {
+            return;
+        }
+        let frame = std::mem::replace(&mut self.current, Vec::new());
+        self.pending.push_back(frame);
+
+        while let Some(frame) = self.pending.front() {
+            let last_end = frame.last().map(|s| s.end);
+            let mut available = 1;
+            if let Some(query) = last_end {
+                unsafe {
+                    device.with_gl(|gl| {
+                        gl.GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available)
+                    });
+                }
+            }
+            let too_old = self.pending.len() > MAX_PENDING_FRAMES;
+            if available == 0 && !too_old {
+                break;
+            }
+            let frame = self
+                .pending
+                .pop_front()
+                .expect("Checked above; should never happen");
+            if available != 0 {
+                self.results.clear();
+            }
+            for scope in frame {
+                if available != 0 {
+                    let (mut start, mut end) = (0u64, 0u64);
+                    unsafe {
+                        device.with_gl(|gl| {
+                            gl.GetQueryObjectui64v(scope.start, gl::QUERY_RESULT, &mut start);
+                            gl.GetQueryObjectui64v(scope.end, gl::QUERY_RESULT, &mut end);
+                        });
+                    }
+                    self.results.push(GpuScopeTiming {
+                        name: scope.name,
+                        duration: Duration::from_nanos(end.saturating_sub(start)),
+                    });
+                }
+                self.free_queries.push(scope.start);
+                self.free_queries.push(scope.end);
+            }
+        }
+    }
+}
+
+/// Measures how long the drawing done in `f` takes on the GPU.
+///
+/// GPU work happens asynchronously, so the result isn't known right
+/// away; timings show up in [`gpu_timings()`](fn.gpu_timings.html) a
+/// frame or two later.  Scopes may not be nested.
+///
+/// This has to flush all pending drawing to the GPU before and after
+/// `f`, so it has a small cost of its own.  If the driver doesn't
+/// support timer queries (they need OpenGL 3.3), `f` is still called
+/// but nothing is measured.
+pub fn gpu_scope<F, T>(ctx: &mut Context, name: &str, f: F) -> GameResult<T>
+where
+    F: FnOnce(&mut Context) -> GameResult<T>,
+{
+    let gfx = &mut ctx.gfx_context;
+    if !gfx.gpu_profiler.is_supported(&mut gfx.device) {
+        return f(ctx);
+    }
+    gfx.encoder.flush(&mut *gfx.device);
+    let start = gfx.gpu_profiler.timestamp(&mut gfx.device);
+
+    let result = f(ctx);
+
+    let gfx = &mut ctx.gfx_context;
+    gfx.encoder.flush(&mut *gfx.device);
+    let end = gfx.gpu_profiler.timestamp(&mut gfx.device);
+    gfx.gpu_profiler.current.push(PendingScope {
+        name: name.to_owned(),
+        start,
+        end,
+    });
+    result
+}
+
+/// Returns the GPU timings of every [`gpu_scope()`](fn.gpu_scope.html)
+/// in the most recent frame whose results are available, in the order
+/// the scopes ran.
+pub fn gpu_timings(ctx: &Context) -> &[GpuScopeTiming] {
+    &ctx.gfx_context.gpu_profiler.results
+}
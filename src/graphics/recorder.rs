@@ -0,0 +1,206 @@
+//! Capturing the screen every frame, for making trailers and gifs.
+
+use std::collections::VecDeque;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use crate::context::Context;
+use crate::error::{GameError, GameResult};
+use crate::filesystem;
+use crate::graphics::{self, PendingScreenshot};
+
+/// Where a [`Recorder`](struct.Recorder.html) puts the frames.
+#[derive(Debug)]
+enum Sink {
+    /// Numbered PNGs in a directory of the ggez filesystem.
+    ImageSequence(PathBuf),
+    /// Raw RGBA frames piped into an `ffmpeg` process.
+    Ffmpeg {
+        output: PathBuf,
+        fps: u32,
+        process: Option<Child>,
+        size: Option<(u16, u16)>,
+    },
+}
+
+/// Records what's drawn to the screen, one frame per call to
+/// [`capture()`](#method.capture), either as a numbered PNG sequence or
+/// by piping the frames into `ffmpeg`.
+///
+/// Frames are read back asynchronously (see
+/// [`screenshot_async()`](fn.screenshot_async.html)), so each one is
+/// written out a frame or so after it was captured rather than stalling
+/// the game while it's drawn.  Call [`finish()`](#method.finish) when
+/// you're done to write out the rest.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let mut recorder = graphics::Recorder::image_sequence("/recording");
+/// // ...then every frame, right before presenting:
+/// recorder.capture(ctx)?;
+/// graphics::present(ctx)?;
+/// // ...and once you've got enough:
+/// recorder.finish(ctx)?;
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct Recorder {
+    sink: Sink,
+    pending: VecDeque<PendingScreenshot>,
+    frames_written: usize,
+}
+
+impl Recorder {
+    /// Writes frames as `frame_000000.png`, `frame_000001.png` and so on
+    /// into the given directory in the user data directory of the ggez
+    /// [`filesystem`](../filesystem/index.html).
+    pub fn image_sequence<P: AsRef<Path>>(directory: P) -> Self {
+        Recorder {
+            sink: Sink::ImageSequence(directory.as_ref().to_path_buf()),
+            pending: VecDeque::new(),
+            frames_written: 0,
+        }
+    }
+
+    /// Pipes frames into an `ffmpeg` process, which must be on the
+    /// `PATH`, encoding them at the given framerate to the given output
+    /// file.  The output path is a normal path on disk, not one in the
+    /// ggez filesystem, and its extension decides the format, e.g.
+    /// `.mp4` or `.gif`.
+    ///
+    /// `ffmpeg` is started when the first frame arrives, since it needs
+    /// to know the frame size; the window shouldn't be resized while
+    /// recording.
+    pub fn ffmpeg<P: AsRef<Path>>(output: P, fps: u32) -> Self {
+        Recorder {
+            sink: Sink::Ffmpeg {
+                output: output.as_ref().to_path_buf(),
+                fps,
+                process: None,
+                size: None,
+            },
+            pending: VecDeque::new(),
+            frames_written: 0,
+        }
+    }
+
+    /// Captures whatever has been drawn to the screen this frame.  Call
+    /// this just before [`present()`](fn.present.html); afterwards the
+    /// screen's contents are gone.
+    ///
+    /// Also writes out any earlier frames that have arrived by now.
+    pub fn capture(&mut self, ctx: &mut Context) -> GameResult {
+        // Always record the screen, even if a canvas is set.
+        let screen = ctx.gfx_context.screen_render_target.clone();
+        let old_target = std::mem::replace(&mut ctx.gfx_context.data.out, screen);
+        let pending = graphics::screenshot_async(ctx, None);
+        ctx.gfx_context.data.out = old_target;
+        self.pending.push_back(pending?);
+
+        while self.pending.front().map_or(false, |p| p.is_ready(ctx)) {
+            self.write_next(ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Writes out all remaining frames, waiting for them if need be, and
+    /// closes `ffmpeg` if it's being used.  Returns how many frames were
+    /// written in total.
+    pub fn finish(mut self, ctx: &mut Context) -> GameResult<usize> {
+        while !self.pending.is_empty() {
+            self.write_next(ctx)?;
+        }
+        if let Sink::Ffmpeg {
+            process: Some(ref mut process),
+            ..
+        } = self.sink
+        {
+            // Closing stdin tells ffmpeg the video is over.
+            drop(process.stdin.take());
+            let status = process.wait()?;
+            if !status.success() {
+                return Err(GameError::VideoError(format!(
+                    "ffmpeg exited with {}",
+                    status
+                )));
+            }
+        }
+        Ok(self.frames_written)
+    }
+
+    /// The number of frames written out so far.
+    pub fn frames_written(&self) -> usize {
+        self.frames_written
+    }
+
+    /// The number of frames captured but not yet written out.
+    pub fn frames_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn write_next(&mut self, ctx: &mut Context) -> GameResult {
+        let frame = match self.pending.pop_front() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        let (width, height) = (frame.width(), frame.height());
+        let data = frame.to_rgba8(ctx)?;
+
+        match self.sink {
+            Sink::ImageSequence(ref directory) => {
+                if self.frames_written == 0 {
+                    filesystem::create_dir(ctx, directory)?;
+                }
+                let path = directory.join(format!("frame_{:06}.png", self.frames_written));
+                let file = filesystem::create(ctx, path)?;
+                let writer = &mut BufWriter::new(file);
+                ::image::png::PngEncoder::new(writer).encode(
+                    &data,
+                    u32::from(width),
+                    u32::from(height),
+                    ::image::ColorType::Rgba8,
+                )?;
+            }
+            Sink::Ffmpeg {
+                ref output,
+                fps,
+                ref mut process,
+                ref mut size,
+            } => {
+                if process.is_none() {
+                    *process = Some(spawn_ffmpeg(output, fps, width, height)?);
+                    *size = Some((width, height));
+                }
+                if *size != Some((width, height)) {
+                    return Err(GameError::VideoError(
+                        "The window size changed while recording to ffmpeg".to_string(),
+                    ));
+                }
+                let stdin = process
+                    .as_mut()
+                    .and_then(|p| p.stdin.as_mut())
+                    .ok_or_else(|| GameError::VideoError("ffmpeg isn't running".to_string()))?;
+                stdin.write_all(&data)?;
+            }
+        }
+        self.frames_written += 1;
+        Ok(())
+    }
+}
+
+fn spawn_ffmpeg(output: &Path, fps: u32, width: u16, height: u16) -> GameResult<Child> {
+    Command::new("ffmpeg")
+        .args(&["-y", "-loglevel", "error"])
+        .args(&["-f", "rawvideo", "-pix_fmt", "rgba"])
+        .arg("-s")
+        .arg(format!("{}x{}", width, height))
+        .arg("-r")
+        .arg(fps.to_string())
+        .args(&["-i", "-"])
+        .arg(output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| GameError::VideoError(format!("Could not start ffmpeg: {}", e)))
+}
@@ -13,12 +13,13 @@ use gfx::shade::*;
 use gfx::state::*;
 use gfx::traits::{FactoryExt, Pod};
 use gfx::*;
+use gfx_core::shade::CreateShaderError;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::io::prelude::*;
 use std::marker::PhantomData;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use crate::context::DebugId;
@@ -42,7 +43,12 @@ unsafe impl Pod for EmptyConst {}
 /// An enum for specifying default and custom blend modes
 ///
 /// If you want to know what these actually do take a look at the implementation of `From<BlendMode> for Blend`
+///
+/// Modes like overlay or soft light, which depend on the destination
+/// color in ways blending can't express, have to be done in a shader,
+/// drawing a `Canvas` of the background.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub enum BlendMode {
     /// When combining two fragments, add their values together, saturating
     /// at 1.0
@@ -75,6 +81,16 @@ pub enum BlendMode {
     /// containing semi-transparent imagery.
     /// For an explanation on this see: https://github.com/ggez/ggez/issues/694#issuecomment-853724926
     Premultiplied,
+    /// When combining two fragments, invert both, multiply them together
+    /// and invert the result, which always gives a lighter color.  Like
+    /// `Multiply` it ignores the source alpha, so transparent parts
+    /// should be black.  Good for glows and light shafts.
+    Screen,
+    /// When combining two fragments, remove the destination where the
+    /// source is opaque, leaving it transparent; the source color is
+    /// ignored.  Useful for punching holes in a `Canvas`, e.g. for fog
+    /// of war.
+    Erase,
 }
 
 impl From<BlendMode> for Blend {
@@ -155,6 +171,30 @@ impl From<BlendMode> for Blend {
                     destination: Factor::One,
                 },
             },
+            BlendMode::Screen => Blend {
+                color: BlendChannel {
+                    equation: Equation::Add,
+                    source: Factor::OneMinus(BlendValue::DestColor),
+                    destination: Factor::One,
+                },
+                alpha: BlendChannel {
+                    equation: Equation::Add,
+                    source: Factor::OneMinus(BlendValue::DestAlpha),
+                    destination: Factor::One,
+                },
+            },
+            BlendMode::Erase => Blend {
+                color: BlendChannel {
+                    equation: Equation::Add,
+                    source: Factor::Zero,
+                    destination: Factor::OneMinus(BlendValue::SourceAlpha),
+                },
+                alpha: BlendChannel {
+                    equation: Equation::Add,
+                    source: Factor::Zero,
+                    destination: Factor::OneMinus(BlendValue::SourceAlpha),
+                },
+            },
         }
     }
 }
@@ -232,49 +272,316 @@ pub type Shader<C> = ShaderGeneric<graphics::GlBackendSpec, C>;
 
 type ShaderHandlePtr<Spec> = Box<dyn ShaderHandle<Spec>>;
 
-#[allow(clippy::too_many_arguments)]
-pub(crate) fn create_shader<C, S, Spec>(
-    vertex_source: &[u8],
-    pixel_source: &[u8],
-    consts: C,
-    name: S,
-    encoder: &mut Encoder<Spec::Resources, Spec::CommandBuffer>,
-    factory: &mut Spec::Factory,
-    multisample_samples: u8,
-    blend_modes: Option<&[BlendMode]>,
-    color_format: format::Format,
-    debug_id: DebugId,
-) -> GameResult<(ShaderGeneric<Spec, C>, ShaderHandlePtr<Spec>)>
-where
-    C: 'static + Pod + Structure<ConstFormat> + Clone + Copy,
-    S: Into<String>,
-    Spec: graphics::BackendSpec + 'static,
-{
-    let buffer = factory.create_constant_buffer(1);
+/// The stage of the shader pipeline a [`ShaderCompileError`](struct.ShaderCompileError.html)
+/// came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    /// The vertex shader failed to compile.
+    Vertex,
+    /// The pixel (fragment) shader failed to compile.
+    Pixel,
+    /// Both stages compiled but the program failed to link.
+    Link,
+}
 
-    encoder.update_buffer(&buffer, &[consts], 0)?;
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Pixel => write!(f, "pixel"),
+            ShaderStage::Link => write!(f, "link"),
+        }
+    }
+}
 
-    let default_mode = vec![BlendMode::Alpha];
-    let blend_modes = blend_modes.unwrap_or(&default_mode[..]);
+/// A single diagnostic message pulled out of the driver's shader log.
+///
+/// Line and column numbers are 1-based, as the drivers report them.
+/// Either may be `None` if the driver didn't provide it or we couldn't
+/// make sense of the log format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    /// The line in the shader source the message refers to.
+    pub line: Option<u32>,
+    /// The column in the shader source the message refers to.
+    pub column: Option<u32>,
+    /// The message itself, with the location prefix stripped.
+    pub message: String,
+    /// The offending line of shader source, if we could find it.
+    pub source_line: Option<String>,
+}
+
+/// A structured error describing why a shader failed to compile or link.
+///
+/// GL drivers all format their logs a little differently, so we try
+/// to parse the common ones (Mesa, NVIDIA, AMD/ANGLE and Apple) into
+/// [`ShaderDiagnostic`](struct.ShaderDiagnostic.html)s.  The raw log is
+/// always kept in `log` in case parsing misses something.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderCompileError {
+    /// Which stage failed.
+    pub stage: ShaderStage,
+    /// The file the failing source was loaded from, if it came from
+    /// [`Shader::new()`](type.Shader.html#method.new).
+    pub file: Option<PathBuf>,
+    /// The diagnostics we managed to parse out of the log.
+    pub diagnostics: Vec<ShaderDiagnostic>,
+    /// The complete, unparsed log from the driver.
+    pub log: String,
+}
+
+impl ShaderCompileError {
+    /// Parses a driver log for the given stage, looking up
+    /// offending lines in `source`.
+    pub(crate) fn parse(stage: ShaderStage, log: &str, source: Option<&[u8]>) -> Self {
+        let source = source.map(String::from_utf8_lossy);
+        let mut diagnostics: Vec<ShaderDiagnostic> = log
+            .lines()
+            .filter_map(parse_log_line)
+            .map(|(line, column, message)| {
+                let source_line = match (line, source.as_ref()) {
+                    (Some(l), Some(src)) if l > 0 => src
+                        .lines()
+                        .nth(l as usize - 1)
+                        .map(|s| s.trim_end().to_owned()),
+                    _ => None,
+                };
+                ShaderDiagnostic {
+                    line,
+                    column,
+                    message,
+                    source_line,
+                }
+            })
+            .collect();
+        if diagnostics.is_empty() && !log.trim().is_empty() {
+            diagnostics.push(ShaderDiagnostic {
+                line: None,
+                column: None,
+                message: log.trim().to_owned(),
+                source_line: None,
+            });
+        }
+        ShaderCompileError {
+            stage,
+            file: None,
+            diagnostics,
+            log: log.to_owned(),
+        }
+    }
+
+    /// Turns a `gfx` program error into a `GameError`, producing a
+    /// `ShaderCompileError` wherever the driver gave us a log to parse.
+    pub(crate) fn from_program_error(
+        e: ProgramError,
+        vertex_source: &[u8],
+        pixel_source: &[u8],
+    ) -> GameError {
+        let parsed = match e {
+            ProgramError::Vertex(CreateShaderError::CompilationFailed(ref log)) => {
+                ShaderCompileError::parse(ShaderStage::Vertex, log, Some(vertex_source))
+            }
+            ProgramError::Pixel(CreateShaderError::CompilationFailed(ref log)) => {
+                ShaderCompileError::parse(ShaderStage::Pixel, log, Some(pixel_source))
+            }
+            ProgramError::Link(ref err) => {
+                ShaderCompileError::parse(ShaderStage::Link, &err.to_string(), None)
+            }
+            _ => return GameError::ShaderProgramError(e),
+        };
+        GameError::ShaderCompileError(parsed)
+    }
+
+    /// Sets the file name reported alongside each diagnostic.
+    pub(crate) fn with_file<P: AsRef<Path>>(mut self, file: P) -> Self {
+        self.file = Some(file.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for ShaderCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error compiling shader ({} stage)", self.stage)?;
+        for d in &self.diagnostics {
+            writeln!(f)?;
+            if let Some(ref file) = self.file {
+                write!(f, "{}:", file.display())?;
+            }
+            match (d.line, d.column) {
+                (Some(l), Some(c)) => write!(f, "{}:{}: ", l, c)?,
+                (Some(l), None) => write!(f, "{}: ", l)?,
+                _ => (),
+            }
+            write!(f, "{}", d.message)?;
+            if let Some(ref src) = d.source_line {
+                write!(f, "\n    {}", src.trim())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderCompileError {}
+
+/// Splits a leading run of ASCII digits off `s`.
+fn split_number(s: &str) -> Option<(u32, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        return None;
+    }
+    s[..end].parse().ok().map(|n| (n, &s[end..]))
+}
+
+/// Strips a leading `error:`, `warning C1234:` or similar severity tag.
+fn strip_severity(s: &str) -> &str {
+    let s = s.trim_start();
+    let lower = s.to_ascii_lowercase();
+    for tag in &["error", "warning"] {
+        if lower.starts_with(tag) {
+            if let Some(idx) = s.find(':') {
+                // Only strip if the colon terminates the tag and an optional code,
+                // not some colon further into the message.
+                if !s[tag.len()..idx].trim().contains(' ') {
+                    return s[idx + 1..].trim_start();
+                }
+            }
+        }
+    }
+    s
+}
+
+/// Parses one line of a driver log into `(line, column, message)`.
+///
+/// Understands the following location formats, where the first number
+/// is the source string index and is ignored:
+///
+/// * Mesa: `0:12(5): error: message`
+/// * NVIDIA: `0(12) : error C0000: message`
+/// * AMD/ANGLE/Apple: `ERROR: 0:12: message`
+fn parse_log_line(line: &str) -> Option<(Option<u32>, Option<u32>, String)> {
+    let mut rest = line.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    let upper = rest.to_ascii_uppercase();
+    for prefix in &["ERROR:", "WARNING:"] {
+        if upper.starts_with(prefix) {
+            rest = rest[prefix.len()..].trim_start();
+            break;
+        }
+    }
+
+    let (_source_index, after) = split_number(rest)?;
+    let (lineno, column, after) = if let Some(after) = after.strip_prefix(':') {
+        let (lineno, after) = split_number(after)?;
+        if let Some(after) = after.strip_prefix('(') {
+            let (column, after) = split_number(after)?;
+            (lineno, Some(column), after.strip_prefix(')')?)
+        } else {
+            (lineno, None, after)
+        }
+    } else if let Some(after) = after.strip_prefix('(') {
+        let (lineno, after) = split_number(after)?;
+        (lineno, None, after.strip_prefix(')')?)
+    } else {
+        return None;
+    };
+    let after = after.trim_start().strip_prefix(':')?;
+    let message = strip_severity(after).trim().to_owned();
+    Some((Some(lineno), column, message))
+}
+
+/// The way triangles are rasterized; see
+/// [`set_polygon_mode()`](fn.set_polygon_mode.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum PolygonMode {
+    /// Fill in triangles, as normal.
+    Fill,
+    /// Only draw the edges of triangles, for inspecting geometry.
+    Wireframe,
+}
+
+impl Default for PolygonMode {
+    fn default() -> Self {
+        PolygonMode::Fill
+    }
+}
 
-    let mut psos = PsoSet::new(blend_modes.len());
-    let name: String = name.into();
-    for mode in blend_modes {
+impl From<PolygonMode> for RasterMethod {
+    fn from(mode: PolygonMode) -> RasterMethod {
+        match mode {
+            PolygonMode::Fill => RasterMethod::Fill,
+            // Wide lines aren't supported in core profiles, so just use 1.
+            PolygonMode::Wireframe => RasterMethod::Line(1),
+        }
+    }
+}
+
+/// Which of a shader program's sets of pipeline states to draw with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct PsoVariant {
+    polygon_mode: PolygonMode,
+    depth_test: bool,
+}
+
+impl Default for PsoVariant {
+    fn default() -> Self {
+        PsoVariant {
+            polygon_mode: PolygonMode::Fill,
+            depth_test: false,
+        }
+    }
+}
+
+/// Everything needed to build a shader program's pipeline states,
+/// kept around so we can build more of them later on.
+struct PsoSource {
+    vertex: Vec<u8>,
+    pixel: Vec<u8>,
+    name: String,
+    blend_modes: Vec<BlendMode>,
+    color_format: format::Format,
+    multisample_samples: u8,
+}
+
+/// Builds one PSO for each of the source's blend modes, all using
+/// the given polygon mode and depth test.
+fn build_psos<Spec, C>(
+    factory: &mut Spec::Factory,
+    source: &PsoSource,
+    variant: PsoVariant,
+) -> GameResult<PsoSet<Spec, C>>
+where
+    C: Structure<ConstFormat>,
+    Spec: graphics::BackendSpec,
+{
+    let mut psos = PsoSet::new(source.blend_modes.len());
+    for mode in &source.blend_modes {
         let init = ConstInit::<C>(
             graphics::pipe::Init {
                 out: (
                     "Target0",
-                    color_format,
+                    source.color_format,
                     ColorMask::all(),
                     Some((*mode).into()),
                 ),
+                depth: if variant.depth_test {
+                    graphics::DEPTH_TEST
+                } else {
+                    gfx::preset::depth::PASS_TEST
+                },
                 ..graphics::pipe::new()
             },
-            name.clone(),
+            source.name.clone(),
             PhantomData,
         );
-        let set = factory.create_shader_set(vertex_source, pixel_source)?;
-        let sample = if multisample_samples > 1 {
+        let set = factory
+            .create_shader_set(&source.vertex, &source.pixel)
+            .map_err(|e| {
+                ShaderCompileError::from_program_error(e, &source.vertex, &source.pixel)
+            })?;
+        let sample = if source.multisample_samples > 1 {
             Some(MultiSample)
         } else {
             None
@@ -282,7 +589,7 @@ where
         let rasterizer = Rasterizer {
             front_face: FrontFace::CounterClockwise,
             cull_face: CullFace::Nothing,
-            method: RasterMethod::Fill,
+            method: variant.polygon_mode.into(),
             offset: None,
             samples: sample,
         };
@@ -290,10 +597,51 @@ where
         let pso = factory.create_pipeline_state(&set, Primitive::TriangleList, rasterizer, init)?;
         psos.insert_mode(*mode, pso);
     }
+    Ok(psos)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_shader<C, S, Spec>(
+    vertex_source: &[u8],
+    pixel_source: &[u8],
+    consts: C,
+    name: S,
+    encoder: &mut Encoder<Spec::Resources, Spec::CommandBuffer>,
+    factory: &mut Spec::Factory,
+    multisample_samples: u8,
+    blend_modes: Option<&[BlendMode]>,
+    color_format: format::Format,
+    debug_id: DebugId,
+) -> GameResult<(ShaderGeneric<Spec, C>, ShaderHandlePtr<Spec>)>
+where
+    C: 'static + Pod + Structure<ConstFormat> + Clone + Copy,
+    S: Into<String>,
+    Spec: graphics::BackendSpec + 'static,
+{
+    let buffer = factory.create_constant_buffer(1);
+
+    encoder.update_buffer(&buffer, &[consts], 0)?;
+
+    let default_mode = vec![BlendMode::Alpha];
+    let blend_modes = blend_modes.unwrap_or(&default_mode[..]);
+
+    let source = PsoSource {
+        vertex: vertex_source.to_vec(),
+        pixel: pixel_source.to_vec(),
+        name: name.into(),
+        blend_modes: blend_modes.to_vec(),
+        color_format,
+        multisample_samples,
+    };
+    let variant = PsoVariant::default();
+    let mut psos = HashMap::new();
+    let _ = psos.insert(variant, build_psos(factory, &source, variant)?);
 
     let program = ShaderProgram {
         buffer: buffer.clone(),
         psos,
+        source,
+        variant,
         active_blend_mode: blend_modes[0],
     };
     let draw: ShaderHandlePtr<Spec> = Box::new(program);
@@ -330,13 +678,13 @@ where
     ) -> GameResult<Shader<C>> {
         let vertex_source = {
             let mut buf = Vec::new();
-            let mut reader = ctx.filesystem.open(vertex_path)?;
+            let mut reader = ctx.filesystem.open(&vertex_path)?;
             let _ = reader.read_to_end(&mut buf)?;
             buf
         };
         let pixel_source = {
             let mut buf = Vec::new();
-            let mut reader = ctx.filesystem.open(pixel_path)?;
+            let mut reader = ctx.filesystem.open(&pixel_path)?;
             let _ = reader.read_to_end(&mut buf)?;
             buf
         };
@@ -348,6 +696,16 @@ where
             name,
             blend_modes,
         )
+        .map_err(|e| match e {
+            GameError::ShaderCompileError(err) => {
+                let file = match err.stage {
+                    ShaderStage::Pixel => pixel_path,
+                    _ => vertex_path,
+                };
+                GameError::ShaderCompileError(err.with_file(file))
+            }
+            e => e,
+        })
     }
 
     /// Create a new `Shader` directly from GLSL source code.
@@ -366,7 +724,7 @@ where
     ) -> GameResult<Shader<C>> {
         let debug_id = DebugId::get(ctx);
         let color_format = ctx.gfx_context.color_format();
-        let (mut shader, draw) = create_shader(
+        let (mut shader, mut draw) = create_shader(
             vertex_source,
             pixel_source,
             consts,
@@ -378,8 +736,11 @@ where
             color_format,
             debug_id,
         )?;
-        shader.id = ctx.gfx_context.shaders.len();
-        ctx.gfx_context.shaders.push(draw);
+        let gfx = &mut ctx.gfx_context;
+        draw.set_polygon_mode(&mut *gfx.factory, gfx.polygon_mode)?;
+        draw.set_depth_test(&mut *gfx.factory, gfx.depth_test)?;
+        shader.id = gfx.shaders.len();
+        gfx.shaders.push(draw);
 
         Ok(shader)
     }
@@ -416,7 +777,11 @@ where
 
 struct ShaderProgram<Spec: graphics::BackendSpec, C: Structure<ConstFormat>> {
     buffer: Buffer<Spec::Resources, C>,
-    psos: PsoSet<Spec, C>,
+    /// Only the default variant is built up front; the others are built
+    /// the first time they're needed.
+    psos: HashMap<PsoVariant, PsoSet<Spec, C>>,
+    source: PsoSource,
+    variant: PsoVariant,
     active_blend_mode: BlendMode,
 }
 
@@ -446,6 +811,35 @@ pub trait ShaderHandle<Spec: graphics::BackendSpec>: fmt::Debug {
 
     /// Gets the shader program's current blend mode
     fn blend_mode(&self) -> BlendMode;
+
+    /// Sets the shader program's polygon mode, building the
+    /// pipeline states for it if necessary
+    fn set_polygon_mode(&mut self, factory: &mut Spec::Factory, mode: PolygonMode) -> GameResult;
+
+    /// Turns depth testing on or off for the shader program, building
+    /// the pipeline states for it if necessary
+    fn set_depth_test(&mut self, factory: &mut Spec::Factory, depth_test: bool) -> GameResult;
+}
+
+impl<Spec, C> ShaderProgram<Spec, C>
+where
+    Spec: graphics::BackendSpec,
+    C: Structure<ConstFormat>,
+{
+    fn set_variant(&mut self, factory: &mut Spec::Factory, variant: PsoVariant) -> GameResult {
+        if !self.psos.contains_key(&variant) {
+            let psos = build_psos(factory, &self.source, variant)?;
+            let _ = self.psos.insert(variant, psos);
+        }
+        self.variant = variant;
+        Ok(())
+    }
+
+    fn psos(&self) -> GameResult<&PsoSet<Spec, C>> {
+        self.psos.get(&self.variant).ok_or_else(|| {
+            GameError::RenderError("Pipeline states for the shader were never built".into())
+        })
+    }
 }
 
 impl<Spec, C> ShaderHandle<Spec> for ShaderProgram<Spec, C>
@@ -459,13 +853,13 @@ where
         slice: &Slice<Spec::Resources>,
         data: &graphics::pipe::Data<Spec::Resources>,
     ) -> GameResult {
-        let pso = self.psos.mode(self.active_blend_mode)?;
+        let pso = self.psos()?.mode(self.active_blend_mode)?;
         encoder.draw(slice, pso, &ConstData(data, &self.buffer));
         Ok(())
     }
 
     fn set_blend_mode(&mut self, mode: BlendMode) -> GameResult {
-        let _ = self.psos.mode(mode)?;
+        let _ = self.psos()?.mode(mode)?;
         self.active_blend_mode = mode;
         Ok(())
     }
@@ -473,6 +867,22 @@ where
     fn blend_mode(&self) -> BlendMode {
         self.active_blend_mode
     }
+
+    fn set_polygon_mode(&mut self, factory: &mut Spec::Factory, mode: PolygonMode) -> GameResult {
+        let variant = PsoVariant {
+            polygon_mode: mode,
+            ..self.variant
+        };
+        self.set_variant(factory, variant)
+    }
+
+    fn set_depth_test(&mut self, factory: &mut Spec::Factory, depth_test: bool) -> GameResult {
+        let variant = PsoVariant {
+            depth_test,
+            ..self.variant
+        };
+        self.set_variant(factory, variant)
+    }
 }
 
 /// A lock for RAII shader regions. The shader automatically gets cleared once
@@ -625,3 +1035,58 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &[u8] =
+        b"#version 150 core\nin vec2 a_Pos;\nvoid main() {\n    gl_Position = a_Pos;\n}\n";
+
+    #[test]
+    fn headless_test_parse_mesa_log() {
+        let log =
+            "0:4(17): error: value of type vec2 cannot be assigned to variable of type vec4\n";
+        let err = ShaderCompileError::parse(ShaderStage::Vertex, log, Some(SOURCE));
+        assert_eq!(err.diagnostics.len(), 1);
+        let d = &err.diagnostics[0];
+        assert_eq!(d.line, Some(4));
+        assert_eq!(d.column, Some(17));
+        assert_eq!(
+            d.message,
+            "value of type vec2 cannot be assigned to variable of type vec4"
+        );
+        assert_eq!(d.source_line.as_deref(), Some("    gl_Position = a_Pos;"));
+    }
+
+    #[test]
+    fn headless_test_parse_nvidia_log() {
+        let log = "0(4) : error C7011: implicit cast from \"vec2\" to \"vec4\"\n";
+        let err = ShaderCompileError::parse(ShaderStage::Pixel, log, Some(SOURCE));
+        assert_eq!(err.diagnostics.len(), 1);
+        let d = &err.diagnostics[0];
+        assert_eq!(d.line, Some(4));
+        assert_eq!(d.column, None);
+        assert_eq!(d.message, "implicit cast from \"vec2\" to \"vec4\"");
+    }
+
+    #[test]
+    fn headless_test_parse_amd_log() {
+        let log = "ERROR: 0:2: 'a_Pos' : redefinition\nERROR: 1 compilation errors.  No code generated.\n";
+        let err = ShaderCompileError::parse(ShaderStage::Vertex, log, Some(SOURCE));
+        assert_eq!(err.diagnostics.len(), 1);
+        let d = &err.diagnostics[0];
+        assert_eq!(d.line, Some(2));
+        assert_eq!(d.message, "'a_Pos' : redefinition");
+        assert_eq!(d.source_line.as_deref(), Some("in vec2 a_Pos;"));
+    }
+
+    #[test]
+    fn headless_test_parse_unknown_log() {
+        let log = "something went horribly wrong";
+        let err = ShaderCompileError::parse(ShaderStage::Link, log, None);
+        assert_eq!(err.diagnostics.len(), 1);
+        assert_eq!(err.diagnostics[0].line, None);
+        assert_eq!(err.diagnostics[0].message, log);
+    }
+}
@@ -19,7 +19,7 @@ use crate::error;
 use crate::error::GameResult;
 use crate::graphics::shader::BlendMode;
 use crate::graphics::types::FilterMode;
-use crate::graphics::{self, transform_rect, BackendSpec, DrawParam, Matrix4, Rect, Transform};
+use crate::graphics::{self, transform_rect, BackendSpec, DrawParam, Rect, Transform};
 use gfx::Factory;
 
 /// A `SpriteBatch` draws a number of copies of the same image, using a single draw call.
@@ -188,9 +188,7 @@ impl graphics::Drawable for SpriteBatch {
 
         let mut slice = gfx.quad_slice.clone();
         slice.instances = Some((u32::try_from(self.sprites.len()).unwrap(), 0));
-        // It's a little silly converting this mint matrix back to a glam
-        // one but it's simpler than the alternative.
-        let m = Matrix4::from(new_param.trans.to_bare_matrix());
+        let m = new_param.to_depth_matrix();
         gfx.set_global_mvp(m)?;
         let previous_mode: Option<BlendMode> = if let Some(mode) = self.blend_mode {
             let current_mode = gfx.blend_mode();
@@ -203,6 +201,8 @@ impl graphics::Drawable for SpriteBatch {
         } else {
             None
         };
+        // The sprites are already in the batch's own coordinates.
+        gfx.gradient = param.gradient.map(|g| (g, graphics::Matrix4::IDENTITY));
         gfx.draw(Some(&slice))?;
         if let Some(mode) = previous_mode {
             gfx.set_blend_mode(mode)?;
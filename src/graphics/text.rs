@@ -32,6 +32,7 @@ pub struct Font {
 #[derive(Clone, Debug)]
 pub struct FontCache {
     glyph_brush: Rc<RefCell<GlyphBrush<DrawParam>>>,
+    fonts: Rc<RefCell<Vec<glyph_brush::ab_glyph::FontArc>>>,
 }
 
 impl FontCache {
@@ -39,6 +40,135 @@ impl FontCache {
     pub fn dimensions(&self, text: &Text) -> Rect {
         text.calculate_dimensions(&mut self.glyph_brush.borrow_mut())
     }
+
+    /// Returns a [`TextMeasurer`](struct.TextMeasurer.html) that knows
+    /// all fonts loaded so far.
+    pub fn measurer(&self) -> TextMeasurer {
+        TextMeasurer {
+            fonts: self.fonts.borrow().clone(),
+        }
+    }
+}
+
+/// Measures and lays out text using only the font data, without the
+/// `Context` or anything on the graphics card.
+///
+/// Unlike [`FontCache`](struct.FontCache.html) this is `Send` and
+/// `Sync`, so it can be handed to a worker thread or used in tests and
+/// tools that never open a window.  Get one that knows the fonts loaded
+/// into a `Context` with [`text_measurer()`](fn.text_measurer.html), or
+/// start with just the default font using `TextMeasurer::default()`.
+/// Fonts loaded into the `Context` afterwards aren't known to an
+/// existing measurer, and text using a font the measurer doesn't know
+/// is measured with the default font instead.
+///
+/// Results aren't cached in the `Text`, so measuring the same text
+/// repeatedly lays it out every time.
+#[derive(Clone, Debug)]
+pub struct TextMeasurer {
+    fonts: Vec<glyph_brush::ab_glyph::FontArc>,
+}
+
+impl Default for TextMeasurer {
+    fn default() -> Self {
+        let font = glyph_brush::ab_glyph::FontArc::try_from_slice(Font::default_font_bytes())
+            .expect("default font is valid");
+        TextMeasurer { fonts: vec![font] }
+    }
+}
+
+impl TextMeasurer {
+    /// Adds a TrueType font from the given bytes.  The returned `Font`
+    /// only refers to this font when measuring with this measurer,
+    /// unless the fonts were added in the same order as they were
+    /// loaded into the `Context`.
+    pub fn add_font_bytes(&mut self, bytes: &[u8]) -> GameResult<Font> {
+        let font = glyph_brush::ab_glyph::FontArc::try_from_vec(bytes.to_vec())
+            .map_err(|e| GameError::FontError(e.to_string()))?;
+        self.fonts.push(font);
+        Ok(Font {
+            font_id: FontId(self.fonts.len() - 1),
+        })
+    }
+
+    /// Lays out the text, replacing any font it doesn't know with the
+    /// default one.
+    fn glyphs(
+        &self,
+        text: &Text,
+    ) -> (Vec<glyph_brush::SectionGlyph>, glyph_brush::SectionGeometry) {
+        let mut section = text.generate_varied_section(Point2::new(0.0, 0.0), None);
+        for fragment in &mut section.text {
+            if fragment.font_id.0 >= self.fonts.len() {
+                fragment.font_id = FontId::default();
+            }
+        }
+        let geometry = glyph_brush::SectionGeometry {
+            screen_position: section.screen_position,
+            bounds: section.bounds,
+        };
+        let glyphs = section
+            .layout
+            .calculate_glyphs(&self.fonts, &geometry, &section.text);
+        (glyphs, geometry)
+    }
+
+    /// Returns the width and height of the formatted and wrapped text,
+    /// the same as [`Text::dimensions()`](struct.Text.html#method.dimensions)
+    /// with the same fonts.
+    pub fn dimensions(&self, text: &Text) -> Rect {
+        use glyph_brush::ab_glyph::{Font as _, ScaleFont as _};
+        let (glyphs, geometry) = self.glyphs(text);
+        let bounds = glyphs.iter().fold(None, |bounds: Option<Rect>, sg| {
+            let font = self.fonts[sg.font_id.0].as_scaled(sg.glyph.scale);
+            let pos = sg.glyph.position;
+            let left = pos.x - font.h_side_bearing(sg.glyph.id);
+            let top = pos.y - font.ascent();
+            let glyph_bounds = Rect::new(
+                left,
+                top,
+                pos.x + font.h_advance(sg.glyph.id) - left,
+                pos.y - font.descent() - top,
+            );
+            Some(bounds.map_or(glyph_bounds, |b| b.union(glyph_bounds)))
+        });
+        match bounds {
+            Some(bounds) => {
+                // Cap to the layout's bounds, like glyph_brush does.
+                let max = text.layout.bounds_rect(&geometry);
+                let left = bounds.left().max(max.min.x);
+                let top = bounds.top().max(max.min.y);
+                let right = bounds.right().min(max.max.x);
+                let bottom = bounds.bottom().min(max.max.y);
+                Rect::new(0.0, 0.0, (right - left).ceil(), (bottom - top).ceil())
+            }
+            None => Rect::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Returns the width of the formatted and wrapped text.
+    pub fn width(&self, text: &Text) -> f32 {
+        self.dimensions(text).w
+    }
+
+    /// Returns the height of the formatted and wrapped text.
+    pub fn height(&self, text: &Text) -> f32 {
+        self.dimensions(text).h
+    }
+
+    /// Returns the positions of the glyphs of the formatted and wrapped
+    /// text, the same as
+    /// [`Text::glyph_positions()`](struct.Text.html#method.glyph_positions).
+    pub fn glyph_positions(&self, text: &Text) -> Vec<mint::Point2<f32>> {
+        self.glyphs(text)
+            .0
+            .iter()
+            .map(|sg| mint::Point2 {
+                x: sg.glyph.position.x,
+                y: sg.glyph.position.y,
+            })
+            .collect()
+    }
 }
 
 /// A piece of text with optional color, font and font scale information.
@@ -455,6 +585,7 @@ impl Font {
         // Take a Cow here to avoid this clone where unnecessary?
         // Nah, let's not complicate things more than necessary.
         let font = glyph_brush::ab_glyph::FontArc::try_from_vec(bytes.to_vec()).unwrap();
+        context.gfx_context.fonts.borrow_mut().push(font.clone());
         let font_id = context.gfx_context.glyph_brush.borrow_mut().add_font(font);
 
         Ok(Font { font_id })
@@ -475,13 +606,38 @@ impl Default for Font {
     }
 }
 
+/// Loads the font at the given path, or hands out the one already
+/// loaded from there.  Since fonts can't be unloaded, this is the way
+/// to load them in most cases.  See the [`cache`](../cache/index.html)
+/// module.
+pub fn load_cached_font<P: AsRef<path::Path>>(context: &mut Context, path: P) -> GameResult<Font> {
+    let path = path.as_ref();
+    if let Some(font) = context.cache.font(path) {
+        return Ok(font);
+    }
+    let mut stream = crate::filesystem::open(context, path)?;
+    let mut buf = Vec::new();
+    let _ = stream.read_to_end(&mut buf)?;
+    let font = Font::new_glyph_font_bytes(context, &buf)?;
+    context.cache.insert_font(path, font, buf.len());
+    Ok(font)
+}
+
 /// Obtains the font cache.
 pub fn font_cache(context: &Context) -> FontCache {
     FontCache {
         glyph_brush: context.gfx_context.glyph_brush.clone(),
+        fonts: context.gfx_context.fonts.clone(),
     }
 }
 
+/// Obtains a [`TextMeasurer`](struct.TextMeasurer.html) for measuring
+/// text with the fonts loaded so far, without needing the `Context`
+/// afterwards.
+pub fn text_measurer(context: &Context) -> TextMeasurer {
+    font_cache(context).measurer()
+}
+
 /// Queues the `Text` to be drawn by [`draw_queued_text()`](fn.draw_queued_text.html).
 /// `relative_dest` is relative to the [`DrawParam::dest`](struct.DrawParam.html#structfield.dest)
 /// passed to `draw_queued()`. Note, any `Text` drawn via [`graphics::draw()`](fn.draw.html)
@@ -492,6 +648,7 @@ where
 {
     let p = Point2::from(relative_dest.into());
     let varied_section = batch.generate_varied_section(p, color);
+    context.gfx_context.stats.record_text();
     context
         .gfx_context
         .glyph_brush
@@ -507,6 +664,7 @@ where
     S: Into<Cow<'a, Section<'a>>>,
     G: GlyphPositioner,
 {
+    context.gfx_context.stats.record_text();
     let brush = &mut context.gfx_context.glyph_brush.borrow_mut();
     match custom_layout {
         Some(layout) => brush.queue_custom_layout(section, layout),
@@ -1,7 +1,7 @@
 use std::f32;
 use std::u32;
 
-use crate::graphics::{FillOptions, StrokeOptions};
+use crate::graphics::{FillOptions, LineCap, LineJoin, StrokeOptions};
 
 /// A 2 dimensional point representing a location
 pub(crate) type Point2 = glam::Vec2;
@@ -188,6 +188,93 @@ impl Rect {
         let h = f32::max(self.bottom(), other.bottom()) - y;
         Rect { x, y, w, h }
     }
+
+    /// Returns the smallest `Rect` that includes both `Rect`s; the same as
+    /// [`combine_with()`](#method.combine_with).
+    pub fn union(self, other: Rect) -> Rect {
+        self.combine_with(other)
+    }
+
+    /// Returns the area shared by both `Rect`s, or `None` if they don't
+    /// overlap.  `Rect`s that only touch give a `Rect` with no width or
+    /// height, like [`overlaps()`](#method.overlaps) counts them as
+    /// overlapping.
+    pub fn intersection(self, other: Rect) -> Option<Rect> {
+        if !self.overlaps(&other) {
+            return None;
+        }
+        let x = f32::max(self.x, other.x);
+        let y = f32::max(self.y, other.y);
+        let w = f32::min(self.right(), other.right()) - x;
+        let h = f32::min(self.bottom(), other.bottom()) - y;
+        Some(Rect { x, y, w, h })
+    }
+
+    /// Checks whether the `Rect` entirely contains another `Rect`
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.left() >= self.left()
+            && other.right() <= self.right()
+            && other.top() >= self.top()
+            && other.bottom() <= self.bottom()
+    }
+
+    /// Returns the `Rect` grown by the given amount on every side, or
+    /// shrunk if it's negative.  It won't shrink past zero size.
+    pub fn expanded(self, by: f32) -> Rect {
+        let w = f32::max(self.w + by * 2.0, 0.0);
+        let h = f32::max(self.h + by * 2.0, 0.0);
+        Rect {
+            x: self.x + (self.w - w) / 2.0,
+            y: self.y + (self.h - h) / 2.0,
+            w,
+            h,
+        }
+    }
+
+    /// Returns the `Rect` moved as little as possible to lie inside
+    /// another, e.g. to keep a camera from showing past the edges of
+    /// the level.  If it's bigger than the other `Rect` along an axis,
+    /// it's centered on it along that axis instead.
+    pub fn clamped_inside(self, other: Rect) -> Rect {
+        fn clamp(pos: f32, size: f32, min: f32, max_size: f32) -> f32 {
+            if size > max_size {
+                min + (max_size - size) / 2.0
+            } else {
+                f32::min(f32::max(pos, min), min + max_size - size)
+            }
+        }
+        Rect {
+            x: clamp(self.x, self.w, other.x, other.w),
+            y: clamp(self.y, self.h, other.y, other.h),
+            ..self
+        }
+    }
+
+    /// Returns the `Rect` scaled to the biggest size that fits inside
+    /// `into` while keeping its aspect ratio, centered on it.  This is
+    /// the letterboxed way of scaling a game to a window.
+    pub fn fit(self, into: Rect) -> Rect {
+        let scale = f32::min(into.w / self.w, into.h / self.h);
+        self.scaled_to_center(into, scale)
+    }
+
+    /// Returns the `Rect` scaled to the smallest size that covers all of
+    /// `into` while keeping its aspect ratio, centered on it.  Whatever
+    /// hangs over the edges gets cut off.
+    pub fn fill(self, into: Rect) -> Rect {
+        let scale = f32::max(into.w / self.w, into.h / self.h);
+        self.scaled_to_center(into, scale)
+    }
+
+    fn scaled_to_center(self, into: Rect, scale: f32) -> Rect {
+        let (w, h) = (self.w * scale, self.h * scale);
+        Rect {
+            x: into.x + (into.w - w) / 2.0,
+            y: into.y + (into.h - h) / 2.0,
+            w,
+            h,
+        }
+    }
 }
 
 impl approx::AbsDiffEq for Rect {
@@ -380,6 +467,165 @@ impl Color {
 
         u32::from_be_bytes([0, r, g, b])
     }
+
+    /// Create a new opaque `Color` from a hue in degrees, and a
+    /// saturation and value in the range `[0.0-1.0]`.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+        let chroma = value * saturation;
+        Color::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    /// Return the `Color`'s hue in degrees (in the range `[0.0-360.0)`),
+    /// saturation and value, ignoring alpha.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (max, min) = self.max_min();
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (self.hue(), saturation, max)
+    }
+
+    /// Create a new opaque `Color` from a hue in degrees, and a
+    /// saturation and lightness in the range `[0.0-1.0]`.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Color::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    /// Return the `Color`'s hue in degrees (in the range `[0.0-360.0)`),
+    /// saturation and lightness, ignoring alpha.
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (max, min) = self.max_min();
+        let lightness = (max + min) / 2.0;
+        let saturation = if max > min {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        } else {
+            0.0
+        };
+        (self.hue(), saturation, lightness)
+    }
+
+    /// Return the `Color` with its hue turned by the given number of
+    /// degrees, keeping its saturation, lightness and alpha.
+    pub fn rotate_hue(self, degrees: f32) -> Color {
+        let (hue, saturation, lightness) = self.to_hsl();
+        Color {
+            a: self.a,
+            ..Color::from_hsl(hue + degrees, saturation, lightness)
+        }
+    }
+
+    /// Create a new `Color` from coordinates in the
+    /// [OKLab](https://bottosson.github.io/posts/oklab/) color space,
+    /// in which equal steps look roughly equally different.  Colors
+    /// outside of what sRGB can show are clamped.
+    pub fn from_oklab(l: f32, a: f32, b: f32, alpha: f32) -> Color {
+        let l_ = (l + 0.396_337_8 * a + 0.215_803_76 * b).powi(3);
+        let m_ = (l - 0.105_561_35 * a - 0.063_854_17 * b).powi(3);
+        let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+        let clamp = |c: f32| c.max(0.0).min(1.0);
+        LinearColor {
+            r: clamp(4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_),
+            g: clamp(-1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_),
+            b: clamp(-0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_),
+            a: alpha,
+        }
+        .into()
+    }
+
+    /// Return the `Color`'s `L`, `a` and `b` coordinates in the
+    /// [OKLab](https://bottosson.github.io/posts/oklab/) color space,
+    /// ignoring alpha.
+    pub fn to_oklab(self) -> (f32, f32, f32) {
+        let c = LinearColor::from(self);
+        let l = (0.412_221_46 * c.r + 0.536_332_55 * c.g + 0.051_445_995 * c.b).cbrt();
+        let m = (0.211_903_5 * c.r + 0.680_699_5 * c.g + 0.107_396_96 * c.b).cbrt();
+        let s = (0.088_302_46 * c.r + 0.281_718_85 * c.g + 0.629_978_7 * c.b).cbrt();
+        (
+            0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+            1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+            0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+        )
+    }
+
+    /// Blend between this `Color` and another by `t`, from `0.0` for
+    /// this one to `1.0` for the other, component by component.
+    pub fn lerp(self, other: Color, t: f32) -> Color {
+        Color {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+
+    /// Like [`lerp()`](#method.lerp), but blends in the OKLab color
+    /// space, which avoids the muddy greys and uneven brightness you
+    /// get halfway between some colors.  Good for tweening palettes
+    /// and gradients.
+    pub fn lerp_oklab(self, other: Color, t: f32) -> Color {
+        let (l1, a1, b1) = self.to_oklab();
+        let (l2, a2, b2) = other.to_oklab();
+        Color::from_oklab(
+            l1 + (l2 - l1) * t,
+            a1 + (a2 - a1) * t,
+            b1 + (b2 - b1) * t,
+            self.a + (other.a - self.a) * t,
+        )
+    }
+
+    /// Return the `Color`'s relative luminance as defined by WCAG,
+    /// from `0.0` for black to `1.0` for white, ignoring alpha.
+    pub fn luminance(self) -> f32 {
+        let c = LinearColor::from(self);
+        0.2126 * c.r + 0.7152 * c.g + 0.0722 * c.b
+    }
+
+    /// Return the WCAG contrast ratio between this `Color` and another,
+    /// from `1.0` for no contrast to `21.0` for black on white.  Text
+    /// should usually have a ratio of at least 4.5 with its background.
+    pub fn contrast_ratio(self, other: Color) -> f32 {
+        let (l1, l2) = (self.luminance(), other.luminance());
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
+    /// Builds an opaque color from a hue, a chroma and the amount to
+    /// add to every component.
+    fn from_hue_chroma(hue: f32, chroma: f32, m: f32) -> Color {
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        Color::new(r + m, g + m, b + m, 1.0)
+    }
+
+    fn max_min(self) -> (f32, f32) {
+        (
+            self.r.max(self.g).max(self.b),
+            self.r.min(self.g).min(self.b),
+        )
+    }
+
+    /// The hue in degrees shared by HSV and HSL.
+    fn hue(self) -> f32 {
+        let (max, min) = self.max_min();
+        let delta = max - min;
+        if delta <= 0.0 {
+            return 0.0;
+        }
+        let sector = if self.r >= self.g && self.r >= self.b {
+            ((self.g - self.b) / delta).rem_euclid(6.0)
+        } else if self.g >= self.b {
+            (self.b - self.r) / delta + 2.0
+        } else {
+            (self.r - self.g) / delta + 4.0
+        };
+        sector * 60.0
+    }
 }
 
 impl From<(u8, u8, u8, u8)> for Color {
@@ -501,7 +747,7 @@ impl From<LinearColor> for Color {
             if component <= 0.003_130_8 {
                 component * 12.92
             } else {
-                (1.0 + a) * component.powf(1.0 / 2.4)
+                (1.0 + a) * component.powf(1.0 / 2.4) - a
             }
         }
         Color {
@@ -539,10 +785,40 @@ impl DrawMode {
     pub fn fill() -> DrawMode {
         DrawMode::Fill(FillOptions::default())
     }
+
+    /// Sets how the corners of a stroke are drawn; `LineJoin::Round` or
+    /// `LineJoin::Bevel` avoid the long spikes miter joins make at sharp
+    /// corners of thick lines.  Does nothing when filling.
+    pub fn with_line_join(self, join: LineJoin) -> DrawMode {
+        match self {
+            DrawMode::Stroke(options) => DrawMode::Stroke(options.with_line_join(join)),
+            fill => fill,
+        }
+    }
+
+    /// Sets how both ends of a stroke are drawn.  Does nothing when
+    /// filling.
+    pub fn with_line_cap(self, cap: LineCap) -> DrawMode {
+        match self {
+            DrawMode::Stroke(options) => DrawMode::Stroke(options.with_line_cap(cap)),
+            fill => fill,
+        }
+    }
+
+    /// Sets how long miter joins can get, relative to the line width,
+    /// before they're cut off to a bevel.  Must be at least 1.0; the
+    /// default is 4.0.  Does nothing when filling.
+    pub fn with_miter_limit(self, limit: f32) -> DrawMode {
+        match self {
+            DrawMode::Stroke(options) => DrawMode::Stroke(options.with_miter_limit(limit)),
+            fill => fill,
+        }
+    }
 }
 
 /// Specifies what blending method to use when scaling up/down images.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub enum FilterMode {
     /// Use linear interpolation (ie, smooth)
     Linear,
@@ -610,6 +886,106 @@ mod tests {
         assert_eq!(puce1, puce4);
     }
 
+    fn assert_color_eq(c1: Color, c2: Color) {
+        assert_relative_eq!(c1.r, c2.r, epsilon = 0.0001);
+        assert_relative_eq!(c1.g, c2.g, epsilon = 0.0001);
+        assert_relative_eq!(c1.b, c2.b, epsilon = 0.0001);
+        assert_relative_eq!(c1.a, c2.a, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn headless_test_linear_color_round_trip() {
+        // Above the knee of the sRGB curve in both directions.
+        let linear = LinearColor::from(Color::new(0.5, 0.5, 0.5, 1.0));
+        assert_relative_eq!(linear.r, 0.214_041_14, epsilon = 0.0001);
+        let srgb = Color::from(LinearColor {
+            r: 0.5,
+            g: 0.2,
+            b: 1.0,
+            a: 0.5,
+        });
+        assert_color_eq(srgb, Color::new(0.735_357, 0.484_529, 1.0, 0.5));
+        // Below it, where the curve is linear.
+        let srgb = Color::from(LinearColor {
+            r: 0.001,
+            g: 0.0,
+            b: 0.003,
+            a: 1.0,
+        });
+        assert_color_eq(srgb, Color::new(0.01292, 0.0, 0.03876, 1.0));
+
+        for &color in &[
+            Color::new(0.5, 0.25, 0.75, 1.0),
+            Color::new(0.02, 0.04, 0.9, 0.3),
+            Color::from_rgb_u32(0x00CC_8899),
+            Color::WHITE,
+            Color::BLACK,
+        ] {
+            assert_color_eq(Color::from(LinearColor::from(color)), color);
+        }
+    }
+
+    #[test]
+    fn headless_test_color_spaces() {
+        let puce = Color::from_rgb_u32(0x00CC_8899);
+        let (h, s, v) = puce.to_hsv();
+        assert_relative_eq!(h, 345.0, epsilon = 0.01);
+        assert_color_eq(Color::from_hsv(h, s, v), puce);
+        let (h, s, l) = puce.to_hsl();
+        assert_relative_eq!(h, 345.0, epsilon = 0.01);
+        assert_color_eq(Color::from_hsl(h, s, l), puce);
+
+        assert_color_eq(Color::from_hsv(120.0, 1.0, 1.0), Color::GREEN);
+        assert_color_eq(Color::from_hsl(-120.0, 1.0, 0.5), Color::BLUE);
+        let rotated = Color::new(1.0, 0.0, 0.0, 0.5).rotate_hue(120.0);
+        assert_color_eq(rotated, Color::new(0.0, 1.0, 0.0, 0.5));
+
+        let (l, a, b) = Color::WHITE.to_oklab();
+        assert_relative_eq!(l, 1.0, epsilon = 0.0001);
+        assert_relative_eq!(a, 0.0, epsilon = 0.0001);
+        assert_relative_eq!(b, 0.0, epsilon = 0.0001);
+        let (l, a, b) = puce.to_oklab();
+        assert_color_eq(Color::from_oklab(l, a, b, 1.0), puce);
+
+        assert_color_eq(
+            Color::BLACK.lerp(Color::WHITE, 0.5),
+            Color::new(0.5, 0.5, 0.5, 1.0),
+        );
+        assert_color_eq(Color::RED.lerp_oklab(Color::BLUE, 0.0), Color::RED);
+        assert_color_eq(Color::RED.lerp_oklab(Color::BLUE, 1.0), Color::BLUE);
+
+        assert_relative_eq!(Color::WHITE.luminance(), 1.0, epsilon = 0.0001);
+        assert_relative_eq!(
+            Color::BLACK.contrast_ratio(Color::WHITE),
+            21.0,
+            epsilon = 0.0001
+        );
+        assert_relative_eq!(
+            Color::WHITE.contrast_ratio(Color::BLACK),
+            21.0,
+            epsilon = 0.0001
+        );
+    }
+
+    #[test]
+    fn headless_test_draw_mode_stroke_style() {
+        let mode = DrawMode::stroke(4.0)
+            .with_line_join(LineJoin::Round)
+            .with_line_cap(LineCap::Square)
+            .with_miter_limit(2.0);
+        if let DrawMode::Stroke(options) = mode {
+            assert_eq!(options.line_join, LineJoin::Round);
+            assert_eq!(options.start_cap, LineCap::Square);
+            assert_eq!(options.end_cap, LineCap::Square);
+            assert_relative_eq!(options.miter_limit, 2.0);
+            assert_relative_eq!(options.line_width, 4.0);
+        } else {
+            panic!("Expected a stroke");
+        }
+        let fill = DrawMode::fill().with_line_join(LineJoin::Round);
+        assert!(matches!(fill, DrawMode::Fill(_)));
+    }
+
     #[test]
     fn headless_test_rect_scaling() {
         let r1 = Rect::new(0.0, 0.0, 128.0, 128.0);
@@ -727,6 +1103,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn headless_test_rect_set_operations() {
+        let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let b = Rect::new(5.0, 5.0, 10.0, 10.0);
+        assert_relative_eq!(a.intersection(b).unwrap(), Rect::new(5.0, 5.0, 5.0, 5.0));
+        assert_relative_eq!(a.union(b), Rect::new(0.0, 0.0, 15.0, 15.0));
+        assert!(a.intersection(Rect::new(20.0, 0.0, 1.0, 1.0)).is_none());
+        let touching = a.intersection(Rect::new(10.0, 0.0, 5.0, 5.0)).unwrap();
+        assert_relative_eq!(touching, Rect::new(10.0, 0.0, 0.0, 5.0));
+
+        assert!(a.contains_rect(&Rect::new(1.0, 1.0, 8.0, 8.0)));
+        assert!(a.contains_rect(&a));
+        assert!(!a.contains_rect(&b));
+
+        assert_relative_eq!(a.expanded(2.0), Rect::new(-2.0, -2.0, 14.0, 14.0));
+        assert_relative_eq!(a.expanded(-10.0), Rect::new(5.0, 5.0, 0.0, 0.0));
+
+        let level = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let camera = Rect::new(-5.0, 45.0, 20.0, 20.0);
+        assert_relative_eq!(
+            camera.clamped_inside(level),
+            Rect::new(0.0, 30.0, 20.0, 20.0)
+        );
+        let wide = Rect::new(30.0, 10.0, 200.0, 20.0);
+        assert_relative_eq!(
+            wide.clamped_inside(level),
+            Rect::new(-50.0, 10.0, 200.0, 20.0)
+        );
+
+        let game = Rect::new(0.0, 0.0, 320.0, 180.0);
+        let window = Rect::new(0.0, 0.0, 800.0, 600.0);
+        assert_relative_eq!(game.fit(window), Rect::new(0.0, 75.0, 800.0, 450.0));
+        assert_relative_eq!(
+            game.fill(window),
+            Rect::new(-133.33333, 0.0, 1066.6666, 600.0),
+            epsilon = 0.001
+        );
+    }
+
     #[test]
     fn headless_test_rect_rotate() {
         {
@@ -805,4 +1220,50 @@ mod tests {
             assert_relative_eq!(r, expected);
         }
     }
+
+    #[cfg(feature = "serde-serialize")]
+    #[test]
+    fn headless_test_serde_round_trip() {
+        use crate::graphics::{BlendMode, DrawParam, PolygonMode};
+
+        let param = DrawParam::new()
+            .dest([10.0, 20.0])
+            .rotation(1.5)
+            .color(Color::new(1.0, 0.5, 0.25, 1.0))
+            .depth(0.5);
+        let json = serde_json::to_string(&param).unwrap();
+        assert_eq!(serde_json::from_str::<DrawParam>(&json).unwrap(), param);
+
+        let matrix = DrawParam::new().transform(glam::Mat4::from_scale(glam::Vec3::splat(2.0)));
+        let json = serde_json::to_string(&matrix).unwrap();
+        assert_eq!(serde_json::from_str::<DrawParam>(&json).unwrap(), matrix);
+
+        // Params saved before `depth` and `skew` existed still load.
+        let mut value = serde_json::to_value(&param).unwrap();
+        let _ = value.as_object_mut().unwrap().remove("depth");
+        let _ = value["trans"]["Values"]
+            .as_object_mut()
+            .unwrap()
+            .remove("skew");
+        assert_eq!(
+            serde_json::from_value::<DrawParam>(value).unwrap(),
+            param.depth(0.0)
+        );
+
+        let json = serde_json::to_string(&BlendMode::Multiply).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BlendMode>(&json).unwrap(),
+            BlendMode::Multiply
+        );
+        let json = serde_json::to_string(&PolygonMode::Wireframe).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PolygonMode>(&json).unwrap(),
+            PolygonMode::Wireframe
+        );
+        let json = serde_json::to_string(&FilterMode::Nearest).unwrap();
+        assert!(matches!(
+            serde_json::from_str::<FilterMode>(&json).unwrap(),
+            FilterMode::Nearest
+        ));
+    }
 }
@@ -4,17 +4,36 @@
 //! gets fleshed out.  The `gilrs` crate needs help to add better
 //! cross-platform support.  Why not give it a hand?
 use gilrs::ConnectedGamepadsIterator;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
-pub use gilrs::{self, Event, Gamepad, Gilrs};
+pub use gilrs::{self, Axis, Button, Event, Gamepad, Gilrs};
 
 /// A unique identifier for a particular GamePad
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GamepadId(pub(crate) gilrs::GamepadId);
 
+/// Identifies a gamepad across being unplugged and plugged back in, by
+/// its model's SDL-style GUID and a slot that tells apart several of the
+/// same model.  A gamepad plugged back in gets the lowest slot free for
+/// its model, so it's the same as before unless another of its model was
+/// plugged in meanwhile.  Can be saved with serde, to remember which
+/// player had which controller.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StableGamepadId {
+    /// The GUID of the gamepad's model, as used by SDL_GameControllerDB.
+    pub guid: [u8; 16],
+    /// Which of the connected gamepads with this GUID it is, from `0`.
+    pub slot: u8,
+}
+
 use crate::context::Context;
 use crate::error::GameResult;
 
+/// Where the [`ContextBuilder`](../../struct.ContextBuilder.html) looks for
+/// extra controller mappings in the resource directories.
+pub(crate) const MAPPINGS_NAME: &str = "/gamecontrollerdb.txt";
+
 /// Trait object defining a gamepad/joystick context.
 pub trait GamepadContext {
     /// Returns a gamepad event.
@@ -39,8 +58,10 @@ impl fmt::Debug for GilrsGamepadContext {
 }
 
 impl GilrsGamepadContext {
-    pub(crate) fn new() -> GameResult<Self> {
-        let gilrs = Gilrs::new()?;
+    /// Creates the context with the given SDL_GameControllerDB-style
+    /// mappings on top of the ones built into `gilrs`.
+    pub(crate) fn new(mappings: &str) -> GameResult<Self> {
+        let gilrs = gilrs::GilrsBuilder::new().add_mappings(mappings).build()?;
         Ok(GilrsGamepadContext { gilrs })
     }
 }
@@ -107,6 +128,334 @@ impl GamepadContext for NullGamepadContext {
     }
 }
 
+/// How an axis' value is shaped once its deadzone is taken out, for
+/// finer control near the middle of a stick.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AxisCurve {
+    /// The value is left as it is.  The default.
+    Linear,
+    /// The value's magnitude is raised to the given power; `2.0` or
+    /// `3.0` make small movements finer.
+    Power(f32),
+    /// The value's magnitude, from `0.0` to `1.0`, is passed through
+    /// the given function, which should return `0.0` to `1.0` too.
+    Custom(fn(f32) -> f32),
+}
+
+/// The deadzone and curve of one axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct AxisFilter {
+    deadzone: f32,
+    curve: AxisCurve,
+}
+
+impl Default for AxisFilter {
+    fn default() -> Self {
+        AxisFilter {
+            deadzone: 0.0,
+            curve: AxisCurve::Linear,
+        }
+    }
+}
+
+impl AxisFilter {
+    fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs();
+        if magnitude <= self.deadzone {
+            return 0.0;
+        }
+        // Rescaled so it still goes all the way from 0 to 1 outside the
+        // deadzone, rather than jumping straight to the deadzone's edge.
+        let magnitude = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        let magnitude = match self.curve {
+            AxisCurve::Linear => magnitude,
+            AxisCurve::Power(power) => magnitude.powf(power),
+            AxisCurve::Custom(curve) => curve(magnitude),
+        };
+        magnitude.copysign(value)
+    }
+}
+
+/// The deadzones and curves set for each axis, applied to the values
+/// of all gamepads.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AxisFilters {
+    filters: HashMap<Axis, AxisFilter>,
+}
+
+impl AxisFilters {
+    pub(crate) fn apply(&self, axis: Axis, value: f32) -> f32 {
+        self.filters
+            .get(&axis)
+            .map_or(value, |filter| filter.apply(value))
+    }
+}
+
+/// Sets the deadzone of an axis on all gamepads: values closer to the
+/// middle than this, from `0.0` to `1.0`, come out as `0.0`, so a
+/// stick that doesn't quite center doesn't drift.  The rest of the
+/// range is stretched out to still go from `0.0` to `1.0`.
+///
+/// Applies to [`axis()`](fn.axis.html) and to the values given to
+/// [`EventHandler::gamepad_axis_event()`](../../event/trait.EventHandler.html#method.gamepad_axis_event).
+/// Defaults to `0.0`, leaving only `gilrs`' own small deadzone.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # use ggez::input::gamepad::{self, Axis, AxisCurve};
+/// # fn t(ctx: &mut Context) {
+/// for &axis in &[Axis::LeftStickX, Axis::LeftStickY] {
+///     gamepad::set_deadzone(ctx, axis, 0.15);
+///     gamepad::set_axis_curve(ctx, axis, AxisCurve::Power(2.0));
+/// }
+/// # }
+/// ```
+pub fn set_deadzone(ctx: &mut Context, axis: Axis, deadzone: f32) {
+    let filter = ctx.axis_filters.filters.entry(axis).or_default();
+    filter.deadzone = deadzone.max(0.0).min(1.0);
+}
+
+/// Sets the response curve of an axis on all gamepads, which shapes
+/// its values after the deadzone is taken out; see
+/// [`set_deadzone()`](fn.set_deadzone.html).  Defaults to
+/// `AxisCurve::Linear`.
+pub fn set_axis_curve(ctx: &mut Context, axis: Axis, curve: AxisCurve) {
+    let filter = ctx.axis_filters.filters.entry(axis).or_default();
+    filter.curve = curve;
+}
+
+/// Returns the value of an axis on a gamepad, with its deadzone and
+/// curve applied.  Use [`gamepad()`](fn.gamepad.html) for the raw value.
+pub fn axis(ctx: &Context, id: GamepadId, axis: Axis) -> f32 {
+    let value = ctx.gamepad_context.gamepad(id).value(axis);
+    ctx.axis_filters.apply(axis, value)
+}
+
+/// The kinds of controllers whose buttons are labelled differently.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ControllerType {
+    /// Xbox and other XInput controllers, with A, B, X and Y buttons.
+    Xbox,
+    /// PlayStation controllers, with cross, circle, square and triangle.
+    PlayStation,
+    /// Nintendo controllers, with A on the right and B at the bottom.
+    Nintendo,
+    /// Anything else; buttons are named after where they are.
+    Generic,
+}
+
+impl ControllerType {
+    /// Guesses the type of a controller from its name.
+    fn from_name(name: &str) -> Self {
+        let name = name.to_lowercase();
+        let any = |words: &[&str]| words.iter().any(|word| name.contains(word));
+        if any(&[
+            "playstation",
+            "dualshock",
+            "dualsense",
+            "ps3",
+            "ps4",
+            "ps5",
+            "sony",
+        ]) {
+            ControllerType::PlayStation
+        } else if any(&["nintendo", "switch", "joy-con", "wii u", "gamecube"]) {
+            ControllerType::Nintendo
+        } else if any(&["xbox", "x-box", "xinput"]) {
+            ControllerType::Xbox
+        } else {
+            ControllerType::Generic
+        }
+    }
+
+    /// Returns what a button is labelled on this type of controller, such
+    /// as `"A"` or `"Cross"` for `Button::South`.  `gilrs` names buttons
+    /// after where they are, so the same button gets the label that's
+    /// printed there.
+    pub fn button_name(self, button: Button) -> &'static str {
+        use self::ControllerType::*;
+        match (self, button) {
+            (Xbox, Button::South) => "A",
+            (Xbox, Button::East) => "B",
+            (Xbox, Button::West) => "X",
+            (Xbox, Button::North) => "Y",
+            (Xbox, Button::LeftTrigger) => "LB",
+            (Xbox, Button::LeftTrigger2) => "LT",
+            (Xbox, Button::RightTrigger) => "RB",
+            (Xbox, Button::RightTrigger2) => "RT",
+            (Xbox, Button::Select) => "View",
+            (Xbox, Button::Start) => "Menu",
+            (Xbox, Button::Mode) => "Xbox",
+            (Xbox, Button::LeftThumb) => "LS",
+            (Xbox, Button::RightThumb) => "RS",
+            (PlayStation, Button::South) => "Cross",
+            (PlayStation, Button::East) => "Circle",
+            (PlayStation, Button::West) => "Square",
+            (PlayStation, Button::North) => "Triangle",
+            (PlayStation, Button::LeftTrigger) => "L1",
+            (PlayStation, Button::LeftTrigger2) => "L2",
+            (PlayStation, Button::RightTrigger) => "R1",
+            (PlayStation, Button::RightTrigger2) => "R2",
+            (PlayStation, Button::Select) => "Share",
+            (PlayStation, Button::Start) => "Options",
+            (PlayStation, Button::Mode) => "PS",
+            (PlayStation, Button::LeftThumb) => "L3",
+            (PlayStation, Button::RightThumb) => "R3",
+            (Nintendo, Button::South) => "B",
+            (Nintendo, Button::East) => "A",
+            (Nintendo, Button::West) => "Y",
+            (Nintendo, Button::North) => "X",
+            (Nintendo, Button::LeftTrigger) => "L",
+            (Nintendo, Button::LeftTrigger2) => "ZL",
+            (Nintendo, Button::RightTrigger) => "R",
+            (Nintendo, Button::RightTrigger2) => "ZR",
+            (Nintendo, Button::Select) => "Minus",
+            (Nintendo, Button::Start) => "Plus",
+            (Nintendo, Button::Mode) => "Home",
+            (Nintendo, Button::LeftThumb) => "Left Stick",
+            (Nintendo, Button::RightThumb) => "Right Stick",
+            (_, Button::South) => "South",
+            (_, Button::East) => "East",
+            (_, Button::West) => "West",
+            (_, Button::North) => "North",
+            (_, Button::LeftTrigger) => "Left Bumper",
+            (_, Button::LeftTrigger2) => "Left Trigger",
+            (_, Button::RightTrigger) => "Right Bumper",
+            (_, Button::RightTrigger2) => "Right Trigger",
+            (_, Button::Select) => "Select",
+            (_, Button::Start) => "Start",
+            (_, Button::Mode) => "Mode",
+            (_, Button::LeftThumb) => "Left Stick",
+            (_, Button::RightThumb) => "Right Stick",
+            (_, Button::DPadUp) => "D-Pad Up",
+            (_, Button::DPadDown) => "D-Pad Down",
+            (_, Button::DPadLeft) => "D-Pad Left",
+            (_, Button::DPadRight) => "D-Pad Right",
+            (_, Button::C) => "C",
+            (_, Button::Z) => "Z",
+            (_, Button::Unknown) => "Unknown",
+        }
+    }
+}
+
+/// The gamepad buttons pressed and released since the frame started.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ButtonEdges {
+    pressed: HashSet<(GamepadId, Button)>,
+    released: HashSet<(GamepadId, Button)>,
+}
+
+impl ButtonEdges {
+    pub(crate) fn set_button(&mut self, id: GamepadId, button: Button, pressed: bool) {
+        if pressed {
+            let _ = self.pressed.insert((id, button));
+        } else {
+            let _ = self.released.insert((id, button));
+        }
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
+
+/// The stable ids of the connected gamepads.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GamepadSlots {
+    ids: Vec<(GamepadId, StableGamepadId)>,
+}
+
+impl GamepadSlots {
+    /// Gives a newly connected gamepad the lowest free slot for its GUID,
+    /// or returns the one it has if it's already known.
+    pub(crate) fn connect(&mut self, id: GamepadId, guid: [u8; 16]) -> StableGamepadId {
+        if let Some(&(_, stable)) = self.ids.iter().find(|(known, _)| *known == id) {
+            return stable;
+        }
+        let stable = StableGamepadId {
+            guid,
+            slot: free_slot(self.ids.iter().map(|(_, stable)| stable), guid),
+        };
+        self.ids.push((id, stable));
+        stable
+    }
+
+    /// Frees a disconnected gamepad's slot, returning its stable id.
+    pub(crate) fn disconnect(&mut self, id: GamepadId) -> Option<StableGamepadId> {
+        let i = self.ids.iter().position(|(known, _)| *known == id)?;
+        Some(self.ids.remove(i).1)
+    }
+}
+
+/// Returns the lowest slot not taken by a gamepad with the given GUID.
+fn free_slot<'a, I>(taken: I, guid: [u8; 16]) -> u8
+where
+    I: Iterator<Item = &'a StableGamepadId> + Clone,
+{
+    (0..=u8::MAX)
+        .find(|&slot| {
+            !taken
+                .clone()
+                .any(|stable| stable.guid == guid && stable.slot == slot)
+        })
+        .unwrap_or(u8::MAX)
+}
+
+/// Returns the stable id of a connected gamepad; see
+/// [`StableGamepadId`](struct.StableGamepadId.html).
+pub fn stable_id(ctx: &Context, id: GamepadId) -> Option<StableGamepadId> {
+    ctx.gamepad_slots
+        .ids
+        .iter()
+        .find(|(known, _)| *known == id)
+        .map(|&(_, stable)| stable)
+}
+
+/// Finds the connected gamepad with the given stable id, such as to give
+/// a player back their controller after it was plugged back in.
+pub fn find_gamepad(ctx: &Context, stable: StableGamepadId) -> Option<GamepadId> {
+    ctx.gamepad_slots
+        .ids
+        .iter()
+        .find(|(_, known)| *known == stable)
+        .map(|&(id, _)| id)
+}
+
+/// Returns the connected gamepads and their stable ids, in the order
+/// they were connected.
+pub fn connected_gamepads(ctx: &Context) -> Vec<(GamepadId, StableGamepadId)> {
+    ctx.gamepad_slots.ids.clone()
+}
+
+/// Returns whether a button on a gamepad was pressed since the last
+/// frame.  Only kept up to date by
+/// [`event::run()`](../../event/fn.run.html), which reads the gamepad
+/// events.
+pub fn button_just_pressed(ctx: &Context, id: GamepadId, button: Button) -> bool {
+    ctx.gamepad_edges.pressed.contains(&(id, button))
+}
+
+/// Returns whether a button on a gamepad was released since the last
+/// frame.  Only kept up to date by
+/// [`event::run()`](../../event/fn.run.html).
+pub fn button_just_released(ctx: &Context, id: GamepadId, button: Button) -> bool {
+    ctx.gamepad_edges.released.contains(&(id, button))
+}
+
+/// Guesses what type of controller a gamepad is from its name, so binding
+/// menus can show the right button labels or glyphs for it.
+pub fn controller_type(ctx: &Context, id: GamepadId) -> ControllerType {
+    ControllerType::from_name(ctx.gamepad_context.gamepad(id).name())
+}
+
+/// Returns what a button is labelled on the given gamepad, such as `"A"`
+/// on an Xbox controller or `"Cross"` on a PlayStation one for
+/// `Button::South`.  See [`ControllerType`](enum.ControllerType.html).
+pub fn button_name(ctx: &Context, id: GamepadId, button: Button) -> &'static str {
+    controller_type(ctx, id).button_name(button)
+}
+
 /// Returns the `Gamepad` associated with an `id`.
 pub fn gamepad(ctx: &Context, id: GamepadId) -> Gamepad {
     ctx.gamepad_context.gamepad(id)
@@ -130,11 +479,6 @@ pub fn list_gamepads() {
     unimplemented!()
 }
 
-/// Returns the state of the given axis on a gamepad.
-pub fn axis() {
-    unimplemented!()
-}
-
 /// Returns the state of the given button on a gamepad.
 pub fn button_pressed() {
     unimplemented!()
@@ -147,6 +491,74 @@ mod tests {
 
     #[test]
     fn gilrs_init() {
-        assert!(GilrsGamepadContext::new().is_ok());
+        assert!(GilrsGamepadContext::new("").is_ok());
+    }
+
+    #[test]
+    fn free_slots() {
+        let pad = |guid, slot| StableGamepadId {
+            guid: [guid; 16],
+            slot,
+        };
+        let taken = vec![pad(1, 0), pad(1, 2), pad(2, 1)];
+        assert_eq!(free_slot(taken.iter(), [1; 16]), 1);
+        assert_eq!(free_slot(taken.iter(), [2; 16]), 0);
+        assert_eq!(free_slot(taken.iter(), [3; 16]), 0);
+    }
+
+    #[test]
+    fn controller_types() {
+        assert_eq!(
+            ControllerType::from_name("Xbox One Controller"),
+            ControllerType::Xbox
+        );
+        assert_eq!(
+            ControllerType::from_name("PS4 Controller"),
+            ControllerType::PlayStation
+        );
+        assert_eq!(
+            ControllerType::from_name("Nintendo Switch Pro Controller"),
+            ControllerType::Nintendo
+        );
+        assert_eq!(
+            ControllerType::from_name("Logitech Dual Action"),
+            ControllerType::Generic
+        );
+        assert_eq!(
+            ControllerType::PlayStation.button_name(Button::South),
+            "Cross"
+        );
+        assert_eq!(ControllerType::Nintendo.button_name(Button::East), "A");
+        assert_eq!(ControllerType::Xbox.button_name(Button::DPadUp), "D-Pad Up");
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn axis_filters() {
+        let mut filters = AxisFilters::default();
+        assert_eq!(filters.apply(Axis::LeftStickX, 0.05), 0.05);
+
+        let _ = filters.filters.insert(
+            Axis::LeftStickX,
+            AxisFilter {
+                deadzone: 0.2,
+                curve: AxisCurve::Linear,
+            },
+        );
+        assert_eq!(filters.apply(Axis::LeftStickX, 0.1), 0.0);
+        assert_eq!(filters.apply(Axis::LeftStickX, -0.2), 0.0);
+        assert!((filters.apply(Axis::LeftStickX, 0.6) - 0.5).abs() < 1e-6);
+        assert!((filters.apply(Axis::LeftStickX, -0.6) + 0.5).abs() < 1e-6);
+        assert_eq!(filters.apply(Axis::LeftStickX, 1.0), 1.0);
+        assert_eq!(filters.apply(Axis::LeftStickY, 0.1), 0.1);
+
+        let _ = filters.filters.insert(
+            Axis::LeftStickY,
+            AxisFilter {
+                deadzone: 0.0,
+                curve: AxisCurve::Power(2.0),
+            },
+        );
+        assert!((filters.apply(Axis::LeftStickY, -0.5) + 0.25).abs() < 1e-6);
     }
 }
@@ -0,0 +1,312 @@
+//! Recognizes taps, long presses, pinches and swipes from touches.
+//!
+//! Gestures come in through
+//! [`EventHandler::gesture_event()`](../../event/trait.EventHandler.html#method.gesture_event),
+//! after the [touch events](../touch/index.html) they're made of.
+//! Distances are in the same pixels as touch positions.
+
+use crate::graphics::Point2;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use winit::event::TouchPhase;
+
+/// How far a finger can move and still tap or long-press.
+const TAP_SLOP: f32 = 10.0;
+/// How long a tap can last at most.
+const TAP_TIME: Duration = Duration::from_millis(300);
+/// How long a finger has to be held still to long-press.
+const LONG_PRESS_TIME: Duration = Duration::from_millis(500);
+/// How far a swipe has to go, at least.
+const SWIPE_DISTANCE: f32 = 50.0;
+/// How fast a swipe has to be, at least, in pixels per second.
+const SWIPE_SPEED: f32 = 300.0;
+
+/// A gesture made from one or two touches.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gesture {
+    /// A finger touched the screen and let go again quickly, without
+    /// moving.
+    Tap {
+        /// Where it touched.
+        position: mint::Point2<f32>,
+    },
+    /// A finger has been held still on the screen for a while.  Sent
+    /// once, while it's still down.
+    LongPress {
+        /// Where it's held.
+        position: mint::Point2<f32>,
+    },
+    /// Two fingers on the screen moved.  Sent every time either of
+    /// them moves.
+    Pinch {
+        /// The point halfway between the fingers.
+        center: mint::Point2<f32>,
+        /// How much the distance between the fingers changed since the
+        /// last pinch, as a ratio: above `1.0` they're spreading apart,
+        /// below it they're closing in.
+        scale: f32,
+        /// How far `center` moved since the last pinch.
+        delta: mint::Vector2<f32>,
+    },
+    /// A finger was flicked across the screen and let go.
+    Swipe {
+        /// Where it touched the screen.
+        start: mint::Point2<f32>,
+        /// Where it let go.
+        end: mint::Point2<f32>,
+        /// How fast it went, in pixels per second.
+        velocity: mint::Vector2<f32>,
+    },
+}
+
+/// One finger on the screen, as far as gestures are concerned.
+#[derive(Copy, Clone, Debug)]
+struct Track {
+    start: Point2,
+    start_time: Instant,
+    position: Point2,
+    /// Whether it's gone further than `TAP_SLOP` from where it started.
+    moved: bool,
+    long_pressed: bool,
+}
+
+/// Stores the touches gestures are being recognized from.
+#[derive(Clone, Debug, Default)]
+pub struct GestureContext {
+    touches: BTreeMap<u64, Track>,
+    /// Set when a second finger comes down, until they're all lifted,
+    /// so pinching doesn't end in taps or swipes.
+    multi_touch: bool,
+    /// Gestures recognized but not yet handed to the game.
+    pending: Vec<Gesture>,
+}
+
+impl GestureContext {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The two fingers of a pinch, if there are exactly two.
+    fn pinch(&self) -> Option<(Point2, Point2)> {
+        let mut touches = self.touches.values();
+        match (touches.next(), touches.next(), touches.next()) {
+            (Some(first), Some(second), None) => Some((first.position, second.position)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn touch(&mut self, phase: TouchPhase, id: u64, position: Point2, now: Instant) {
+        match phase {
+            TouchPhase::Started => {
+                let track = Track {
+                    start: position,
+                    start_time: now,
+                    position,
+                    moved: false,
+                    long_pressed: false,
+                };
+                let _ = self.touches.insert(id, track);
+                if self.touches.len() > 1 {
+                    self.multi_touch = true;
+                }
+            }
+            TouchPhase::Moved => {
+                let before = self.pinch();
+                if let Some(track) = self.touches.get_mut(&id) {
+                    track.position = position;
+                    if (position - track.start).length() > TAP_SLOP {
+                        track.moved = true;
+                    }
+                }
+                if let (Some(before), Some(after)) = (before, self.pinch()) {
+                    let distance_before = (before.1 - before.0).length();
+                    let distance_after = (after.1 - after.0).length();
+                    if distance_before > 0.0 {
+                        let center_before = (before.0 + before.1) / 2.0;
+                        let center_after = (after.0 + after.1) / 2.0;
+                        self.pending.push(Gesture::Pinch {
+                            center: center_after.into(),
+                            scale: distance_after / distance_before,
+                            delta: (center_after - center_before).into(),
+                        });
+                    }
+                }
+            }
+            TouchPhase::Ended => {
+                if let Some(track) = self.touches.remove(&id) {
+                    if !self.multi_touch {
+                        self.lifted(track, position, now);
+                    }
+                }
+            }
+            TouchPhase::Cancelled => {
+                let _ = self.touches.remove(&id);
+            }
+        }
+        if self.touches.is_empty() {
+            self.multi_touch = false;
+        }
+    }
+
+    /// Recognizes taps and swipes when a lone finger is lifted.
+    fn lifted(&mut self, track: Track, position: Point2, now: Instant) {
+        let elapsed = now.duration_since(track.start_time);
+        let distance = position - track.start;
+        if !track.moved && !track.long_pressed && elapsed <= TAP_TIME {
+            self.pending.push(Gesture::Tap {
+                position: position.into(),
+            });
+        } else if distance.length() >= SWIPE_DISTANCE {
+            let velocity = distance / elapsed.as_secs_f32().max(0.001);
+            if velocity.length() >= SWIPE_SPEED {
+                self.pending.push(Gesture::Swipe {
+                    start: track.start.into(),
+                    end: position.into(),
+                    velocity: velocity.into(),
+                });
+            }
+        }
+    }
+
+    /// Recognizes long presses, which happen without any touch events.
+    pub(crate) fn update(&mut self, now: Instant) {
+        if self.multi_touch {
+            return;
+        }
+        for track in self.touches.values_mut() {
+            let held = now.duration_since(track.start_time);
+            if !track.moved && !track.long_pressed && held >= LONG_PRESS_TIME {
+                track.long_pressed = true;
+                self.pending.push(Gesture::LongPress {
+                    position: track.position.into(),
+                });
+            }
+        }
+    }
+
+    /// Takes the gestures recognized since last time.
+    pub(crate) fn take_gestures(&mut self) -> Vec<Gesture> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn tap_and_long_press() {
+        let mut gestures = GestureContext::new();
+        let start = Instant::now();
+        gestures.touch(TouchPhase::Started, 1, Point2::new(10.0, 10.0), start);
+        gestures.touch(
+            TouchPhase::Moved,
+            1,
+            Point2::new(12.0, 10.0),
+            start + ms(50),
+        );
+        gestures.touch(
+            TouchPhase::Ended,
+            1,
+            Point2::new(12.0, 10.0),
+            start + ms(100),
+        );
+        assert_eq!(
+            gestures.take_gestures(),
+            vec![Gesture::Tap {
+                position: Point2::new(12.0, 10.0).into()
+            }]
+        );
+
+        gestures.touch(TouchPhase::Started, 2, Point2::new(10.0, 10.0), start);
+        gestures.update(start + ms(400));
+        assert!(gestures.take_gestures().is_empty());
+        gestures.update(start + ms(600));
+        gestures.update(start + ms(700));
+        gestures.touch(
+            TouchPhase::Ended,
+            2,
+            Point2::new(10.0, 10.0),
+            start + ms(800),
+        );
+        assert_eq!(
+            gestures.take_gestures(),
+            vec![Gesture::LongPress {
+                position: Point2::new(10.0, 10.0).into()
+            }]
+        );
+    }
+
+    #[test]
+    fn swipe() {
+        let mut gestures = GestureContext::new();
+        let start = Instant::now();
+        gestures.touch(TouchPhase::Started, 1, Point2::new(0.0, 0.0), start);
+        gestures.touch(
+            TouchPhase::Moved,
+            1,
+            Point2::new(100.0, 0.0),
+            start + ms(50),
+        );
+        gestures.touch(
+            TouchPhase::Ended,
+            1,
+            Point2::new(200.0, 0.0),
+            start + ms(100),
+        );
+        match gestures.take_gestures().as_slice() {
+            [Gesture::Swipe { velocity, .. }] => {
+                assert!((velocity.x - 2000.0).abs() < 1.0);
+                assert!(velocity.y.abs() < f32::EPSILON);
+            }
+            other => panic!("Expected a swipe, got {:?}", other),
+        }
+
+        // Too slow to be a swipe.
+        gestures.touch(TouchPhase::Started, 1, Point2::new(0.0, 0.0), start);
+        gestures.touch(
+            TouchPhase::Ended,
+            1,
+            Point2::new(200.0, 0.0),
+            start + ms(2000),
+        );
+        assert!(gestures.take_gestures().is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn pinch() {
+        let mut gestures = GestureContext::new();
+        let start = Instant::now();
+        gestures.touch(TouchPhase::Started, 1, Point2::new(0.0, 0.0), start);
+        gestures.touch(TouchPhase::Started, 2, Point2::new(100.0, 0.0), start);
+        gestures.touch(
+            TouchPhase::Moved,
+            2,
+            Point2::new(200.0, 0.0),
+            start + ms(50),
+        );
+        assert_eq!(
+            gestures.take_gestures(),
+            vec![Gesture::Pinch {
+                center: Point2::new(100.0, 0.0).into(),
+                scale: 2.0,
+                delta: mint::Vector2 { x: 50.0, y: 0.0 },
+            }]
+        );
+
+        // Lifting the fingers after a pinch isn't a tap or swipe.
+        gestures.touch(TouchPhase::Ended, 1, Point2::new(0.0, 0.0), start + ms(60));
+        gestures.touch(
+            TouchPhase::Ended,
+            2,
+            Point2::new(200.0, 0.0),
+            start + ms(70),
+        );
+        assert!(gestures.take_gestures().is_empty());
+    }
+}
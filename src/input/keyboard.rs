@@ -3,7 +3,7 @@
 //! Example:
 //!
 //! ```rust, compile
-//! use ggez::event::{self, EventHandler, KeyCode, KeyMods};
+//! use ggez::event::{self, EventHandler, KeyCode, KeyMods, ScanCode};
 //! use ggez::{graphics, timer};
 //! use ggez::input::keyboard;
 //! use ggez::{Context, GameResult};
@@ -46,7 +46,7 @@
 //!         Ok(())
 //!     }
 //!
-//!     fn key_down_event(&mut self, ctx: &mut Context, key: KeyCode, mods: KeyMods, _: bool) {
+//!     fn key_down_event(&mut self, ctx: &mut Context, key: KeyCode, _: ScanCode, mods: KeyMods, _: bool) {
 //!         match key {
 //!             // Quit if Shift+Ctrl+Q is pressed.
 //!             KeyCode::Q => {
@@ -67,8 +67,13 @@
 
 use crate::context::Context;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 use winit::event::ModifiersState;
+/// A key's hardware scancode, which names where the key is on the
+/// keyboard rather than what's printed on it, so it stays the same
+/// whatever the keyboard layout.  The numbers differ between platforms.
+pub use winit::event::ScanCode;
 /// A key code.
 pub use winit::event::VirtualKeyCode as KeyCode;
 
@@ -109,6 +114,31 @@ impl From<ModifiersState> for KeyMods {
     }
 }
 
+/// How held keys repeat in
+/// [`EventHandler::key_down_event()`](../../event/trait.EventHandler.html#method.key_down_event);
+/// see [`set_key_repeat()`](fn.set_key_repeat.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyRepeat {
+    /// Keys repeat as the system's settings say.  The default.
+    System,
+    /// Keys don't repeat; `key_down_event()` is called once per press.
+    Disabled,
+    /// The last key pressed repeats after being held for `delay`, then
+    /// every `interval`, whatever the system's settings.
+    Custom {
+        /// How long the key has to be held before it starts repeating.
+        delay: Duration,
+        /// How often it repeats after that.
+        interval: Duration,
+    },
+}
+
+impl Default for KeyRepeat {
+    fn default() -> Self {
+        KeyRepeat::System
+    }
+}
+
 /// Tracks held down keyboard keys, active keyboard modifiers,
 /// and figures out if the system is sending repeat keystrokes.
 #[derive(Clone, Debug)]
@@ -118,10 +148,22 @@ pub struct KeyboardContext {
     /// We COULD use a `Vec<bool>` but turning Rust enums to and from
     /// integers is unsafe and a set really is what we want anyway.
     pressed_keys_set: HashSet<KeyCode>,
+    /// Keys pressed and released since the frame started.
+    just_pressed: HashSet<KeyCode>,
+    just_released: HashSet<KeyCode>,
+    pressed_scancodes: HashSet<ScanCode>,
+    /// The key code each scancode last came with, which depends on the
+    /// keyboard layout.
+    scancode_keys: HashMap<ScanCode, KeyCode>,
 
     // These two are necessary for tracking key-repeat.
     last_pressed: Option<KeyCode>,
     current_pressed: Option<KeyCode>,
+
+    key_repeat: KeyRepeat,
+    /// The key that repeats with `KeyRepeat::Custom`, and when it next
+    /// does.
+    repeating: Option<(KeyCode, ScanCode, Instant)>,
 }
 
 impl KeyboardContext {
@@ -130,24 +172,100 @@ impl KeyboardContext {
             active_modifiers: KeyMods::empty(),
             // We just use 256 as a number Big Enough For Keyboard Keys to try to avoid resizing.
             pressed_keys_set: HashSet::with_capacity(256),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+            pressed_scancodes: HashSet::with_capacity(256),
+            scancode_keys: HashMap::new(),
             last_pressed: None,
             current_pressed: None,
+            key_repeat: KeyRepeat::System,
+            repeating: None,
         }
     }
 
     pub(crate) fn set_key(&mut self, key: KeyCode, pressed: bool) {
         if pressed {
-            let _ = self.pressed_keys_set.insert(key);
+            if self.pressed_keys_set.insert(key) {
+                let _ = self.just_pressed.insert(key);
+            }
             self.last_pressed = self.current_pressed;
             self.current_pressed = Some(key);
         } else {
-            let _ = self.pressed_keys_set.remove(&key);
+            if self.pressed_keys_set.remove(&key) {
+                let _ = self.just_released.insert(key);
+            }
             self.current_pressed = None;
         }
 
         self.set_key_modifier(key, pressed);
     }
 
+    /// Keeps track of the key to repeat with `KeyRepeat::Custom`: the
+    /// last one pressed, until it's released.
+    pub(crate) fn update_repeat(
+        &mut self,
+        key: KeyCode,
+        scancode: ScanCode,
+        pressed: bool,
+        now: Instant,
+    ) {
+        let delay = match self.key_repeat {
+            KeyRepeat::Custom { delay, .. } => delay,
+            _ => return,
+        };
+        match self.repeating {
+            // The system's own repeats of the key already repeating.
+            Some((repeating, _, _)) if pressed && repeating == key => (),
+            _ if pressed => self.repeating = Some((key, scancode, now + delay)),
+            Some((repeating, _, _)) if repeating == key => self.repeating = None,
+            _ => (),
+        }
+    }
+
+    /// Returns the key to repeat, if it's time to with
+    /// `KeyRepeat::Custom`.  Repeats that were missed, such as during a
+    /// slow frame, are skipped rather than all sent at once.
+    pub(crate) fn next_repeat(&mut self, now: Instant) -> Option<(KeyCode, ScanCode)> {
+        let interval = match self.key_repeat {
+            KeyRepeat::Custom { interval, .. } => interval,
+            _ => return None,
+        };
+        let (key, scancode, at) = self.repeating?;
+        if now < at {
+            return None;
+        }
+        let next = if at + interval > now {
+            at + interval
+        } else {
+            now + interval
+        };
+        self.repeating = Some((key, scancode, next));
+        Some((key, scancode))
+    }
+
+    /// Whether a key repeat the system sent should be passed on.
+    pub(crate) fn system_repeats(&self) -> bool {
+        self.key_repeat == KeyRepeat::System
+    }
+
+    /// Forgets which keys were just pressed and released, when a new
+    /// frame starts.
+    pub(crate) fn end_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+
+    pub(crate) fn set_scancode(&mut self, scancode: ScanCode, key: Option<KeyCode>, pressed: bool) {
+        if pressed {
+            let _ = self.pressed_scancodes.insert(scancode);
+        } else {
+            let _ = self.pressed_scancodes.remove(&scancode);
+        }
+        if let Some(key) = key {
+            let _ = self.scancode_keys.insert(scancode, key);
+        }
+    }
+
     /// Take a modifier key code and alter our state.
     ///
     /// Double check that this edge handling is necessary;
@@ -213,6 +331,63 @@ pub fn is_key_pressed(ctx: &Context, key: KeyCode) -> bool {
     ctx.keyboard_context.is_key_pressed(key)
 }
 
+/// Checks if a key was pressed since the last frame, rather than held
+/// down from before; key repeats don't count.  For things that should
+/// happen once per press.
+pub fn is_key_just_pressed(ctx: &Context, key: KeyCode) -> bool {
+    ctx.keyboard_context.just_pressed.contains(&key)
+}
+
+/// Checks if a key was released since the last frame.
+pub fn is_key_just_released(ctx: &Context, key: KeyCode) -> bool {
+    ctx.keyboard_context.just_released.contains(&key)
+}
+
+/// Checks if the key at a scancode is currently pressed down, whatever
+/// the keyboard layout makes it type.  Use these for keys picked for
+/// where they are, like WASD, so they end up in the same place on
+/// AZERTY keyboards.
+pub fn is_scancode_pressed(ctx: &Context, scancode: ScanCode) -> bool {
+    ctx.keyboard_context.pressed_scancodes.contains(&scancode)
+}
+
+/// Returns the key code the current keyboard layout gives the key at a
+/// scancode, for showing bindings to players.  `winit` can't ask the
+/// layout, so this is learned from key presses, and is `None` for keys
+/// that haven't been pressed yet.
+pub fn scancode_key(ctx: &Context, scancode: ScanCode) -> Option<KeyCode> {
+    ctx.keyboard_context.scancode_keys.get(&scancode).copied()
+}
+
+/// Returns a label for the key at a scancode, for binding menus: its key
+/// code under the current layout, such as `"Z"`, once it's known, and
+/// otherwise something like `"Key 44"`.  See [`scancode_key()`](fn.scancode_key.html).
+pub fn scancode_label(ctx: &Context, scancode: ScanCode) -> String {
+    match scancode_key(ctx, scancode) {
+        Some(key) => format!("{:?}", key),
+        None => format!("Key {}", scancode),
+    }
+}
+
+/// Sets how held keys repeat in
+/// [`EventHandler::key_down_event()`](../../event/trait.EventHandler.html#method.key_down_event),
+/// such as turning it off for games, or giving an in-game text field the
+/// same backspace repeat on every system.
+///
+/// Characters typed into
+/// [`EventHandler::text_input_event()`](../../event/trait.EventHandler.html#method.text_input_event)
+/// always repeat as the system's settings say, since the system turns
+/// keys into characters.
+pub fn set_key_repeat(ctx: &mut Context, key_repeat: KeyRepeat) {
+    ctx.keyboard_context.key_repeat = key_repeat;
+    ctx.keyboard_context.repeating = None;
+}
+
+/// Returns how held keys repeat; see [`set_key_repeat()`](fn.set_key_repeat.html).
+pub fn key_repeat(ctx: &Context) -> KeyRepeat {
+    ctx.keyboard_context.key_repeat
+}
+
 /// Checks if the last keystroke sent by the system is repeated,
 /// like when a key is held down for a period of time.
 pub fn is_key_repeated(ctx: &Context) -> bool {
@@ -362,4 +537,58 @@ mod tests {
         keyboard.set_key(KeyCode::B, true);
         assert!(keyboard.is_key_repeated());
     }
+
+    #[test]
+    fn just_pressed_keys_tracking() {
+        let mut keyboard = KeyboardContext::new();
+        keyboard.set_key(KeyCode::A, true);
+        assert!(keyboard.just_pressed.contains(&KeyCode::A));
+        keyboard.end_frame();
+        keyboard.set_key(KeyCode::A, true);
+        assert!(!keyboard.just_pressed.contains(&KeyCode::A));
+        keyboard.set_key(KeyCode::A, false);
+        assert!(keyboard.just_released.contains(&KeyCode::A));
+        keyboard.end_frame();
+        assert!(keyboard.just_released.is_empty());
+    }
+
+    #[test]
+    fn custom_key_repeat() {
+        let mut keyboard = KeyboardContext::new();
+        keyboard.key_repeat = KeyRepeat::Custom {
+            delay: Duration::from_millis(500),
+            interval: Duration::from_millis(100),
+        };
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        keyboard.update_repeat(KeyCode::Back, 14, true, start);
+        assert_eq!(keyboard.next_repeat(at(400)), None);
+        assert_eq!(keyboard.next_repeat(at(500)), Some((KeyCode::Back, 14)));
+        assert_eq!(keyboard.next_repeat(at(550)), None);
+        // The system's own repeat doesn't restart the delay.
+        keyboard.update_repeat(KeyCode::Back, 14, true, at(560));
+        assert_eq!(keyboard.next_repeat(at(600)), Some((KeyCode::Back, 14)));
+        // Missed repeats don't pile up.
+        assert_eq!(keyboard.next_repeat(at(2000)), Some((KeyCode::Back, 14)));
+        assert_eq!(keyboard.next_repeat(at(2050)), None);
+
+        keyboard.update_repeat(KeyCode::A, 30, true, at(2060));
+        keyboard.update_repeat(KeyCode::Back, 14, false, at(2070));
+        assert_eq!(keyboard.next_repeat(at(2600)), Some((KeyCode::A, 30)));
+        keyboard.update_repeat(KeyCode::A, 30, false, at(2610));
+        assert_eq!(keyboard.next_repeat(at(5000)), None);
+    }
+
+    #[test]
+    fn scancode_tracking() {
+        let mut keyboard = KeyboardContext::new();
+        // The key in the W spot on an AZERTY keyboard.
+        keyboard.set_scancode(17, Some(KeyCode::Z), true);
+        assert!(keyboard.pressed_scancodes.contains(&17));
+        assert_eq!(keyboard.scancode_keys.get(&17), Some(&KeyCode::Z));
+        keyboard.set_scancode(17, None, false);
+        assert!(!keyboard.pressed_scancodes.contains(&17));
+        assert_eq!(keyboard.scancode_keys.get(&17), Some(&KeyCode::Z));
+    }
 }
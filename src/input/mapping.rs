@@ -0,0 +1,379 @@
+//! Named actions and axes, bound to keys, mouse buttons and gamepad
+//! inputs, so a game can ask whether "jump" is pressed instead of
+//! checking every key and button that might mean jump.
+//!
+//! Each action can have any number of bindings and is pressed when any
+//! of them is.  An axis has bindings pushing it each way and goes from
+//! `-1.0` to `1.0`.  Gamepad inputs count on any connected gamepad, and
+//! on the [on-screen gamepad](../virtual_pad/index.html) if it's on.
+//!
+//! The bindings live in an [`InputMap`](struct.InputMap.html), which,
+//! with the `serde-serialize` feature, can be serialized with serde, so players' rebindings can be saved with
+//! [`filesystem::save()`](../../filesystem/fn.save.html) and loaded
+//! back with [`set_input_map()`](fn.set_input_map.html).
+//!
+//! ```rust,no_run
+//! # use ggez::*;
+//! # use ggez::input::gamepad::{Axis, gilrs::Button};
+//! # use ggez::input::keyboard::KeyCode;
+//! # use ggez::input::mapping::{self, AxisDirection, Input};
+//! # fn t(ctx: &mut Context) {
+//! let map = mapping::input_map_mut(ctx);
+//! map.bind("jump", Input::Key(KeyCode::Space));
+//! map.bind("jump", Input::GamepadButton(Button::South));
+//! map.bind_axis("move", Input::Key(KeyCode::A), Input::Key(KeyCode::D));
+//! map.bind_axis(
+//!     "move",
+//!     Input::GamepadAxis(Axis::LeftStickX, AxisDirection::Negative),
+//!     Input::GamepadAxis(Axis::LeftStickX, AxisDirection::Positive),
+//! );
+//!
+//! if mapping::action_pressed(ctx, "jump") {
+//!     // ...
+//! }
+//! let speed = mapping::axis(ctx, "move") * 200.0;
+//! # }
+//! ```
+
+use crate::context::Context;
+use crate::input::gamepad::{self, gilrs::Button, Axis};
+use crate::input::keyboard::{self, KeyCode, ScanCode};
+use crate::input::mouse::{self, MouseButton};
+use crate::input::virtual_pad;
+use std::collections::BTreeMap;
+
+/// How far a gamepad axis has to be pushed to count as pressed, when
+/// it's bound to an action.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// The gamepad buttons [`pressed_inputs()`](fn.pressed_inputs.html) looks at.
+const GAMEPAD_BUTTONS: [Button; 19] = [
+    Button::South,
+    Button::East,
+    Button::North,
+    Button::West,
+    Button::C,
+    Button::Z,
+    Button::LeftTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+    Button::Mode,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+];
+
+/// The gamepad axes [`pressed_inputs()`](fn.pressed_inputs.html) looks at.
+const GAMEPAD_AXES: [Axis; 8] = [
+    Axis::LeftStickX,
+    Axis::LeftStickY,
+    Axis::RightStickX,
+    Axis::RightStickY,
+    Axis::LeftZ,
+    Axis::RightZ,
+    Axis::DPadX,
+    Axis::DPadY,
+];
+
+/// Which way a gamepad axis is pushed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum AxisDirection {
+    /// Left or down, below `0.0`.
+    Negative,
+    /// Right or up, above `0.0`.
+    Positive,
+}
+
+/// A physical input that can be bound to actions and axes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Input {
+    /// A key on the keyboard.
+    Key(KeyCode),
+    /// The key at a scancode, wherever the keyboard layout puts it.
+    ScanCode(ScanCode),
+    /// A mouse button.
+    MouseButton(MouseButton),
+    /// A button on a gamepad.
+    GamepadButton(Button),
+    /// A gamepad axis pushed one way, such as a stick or trigger.
+    GamepadAxis(Axis, AxisDirection),
+}
+
+/// The bindings pushing an axis each way.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct AxisBindings {
+    /// Inputs pushing the axis toward `-1.0`.
+    pub negative: Vec<Input>,
+    /// Inputs pushing the axis toward `1.0`.
+    pub positive: Vec<Input>,
+}
+
+/// A set of named actions and axes and the inputs bound to them.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Input>>,
+    axes: BTreeMap<String, AxisBindings>,
+}
+
+impl InputMap {
+    /// Creates an empty `InputMap`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds an input to an action, alongside whatever it's already
+    /// bound to.  Does nothing if it's already bound to that action.
+    pub fn bind(&mut self, action: &str, input: Input) {
+        let bindings = self.actions.entry(action.to_owned()).or_default();
+        if !bindings.contains(&input) {
+            bindings.push(input);
+        }
+    }
+
+    /// Unbinds an input from an action.
+    pub fn unbind(&mut self, action: &str, input: Input) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|&bound| bound != input);
+        }
+    }
+
+    /// Replaces one of an action's bindings with another, keeping its
+    /// place in the list, or adds the new one if the old one wasn't
+    /// bound.  For rebinding menus.
+    pub fn rebind(&mut self, action: &str, old: Input, new: Input) {
+        let bindings = self.actions.entry(action.to_owned()).or_default();
+        bindings.retain(|&bound| bound != new);
+        match bindings.iter().position(|&bound| bound == old) {
+            Some(i) => bindings[i] = new,
+            None => bindings.push(new),
+        }
+    }
+
+    /// Removes all of an action's bindings.
+    pub fn clear(&mut self, action: &str) {
+        let _ = self.actions.remove(action);
+    }
+
+    /// Returns the inputs bound to an action.
+    pub fn bindings(&self, action: &str) -> &[Input] {
+        self.actions.get(action).map_or(&[][..], Vec::as_slice)
+    }
+
+    /// Returns the names of all the actions with bindings.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+
+    /// Returns the actions an input is bound to, so a rebinding menu
+    /// can warn about conflicts.
+    pub fn actions_bound_to(&self, input: Input) -> Vec<&str> {
+        self.actions
+            .iter()
+            .filter(|(_, bindings)| bindings.contains(&input))
+            .map(|(action, _)| action.as_str())
+            .collect()
+    }
+
+    /// Binds a pair of inputs to an axis, one pushing it each way,
+    /// alongside whatever it's already bound to.
+    pub fn bind_axis(&mut self, axis: &str, negative: Input, positive: Input) {
+        let bindings = self.axes.entry(axis.to_owned()).or_default();
+        if !bindings.negative.contains(&negative) {
+            bindings.negative.push(negative);
+        }
+        if !bindings.positive.contains(&positive) {
+            bindings.positive.push(positive);
+        }
+    }
+
+    /// Removes all of an axis' bindings.
+    pub fn clear_axis(&mut self, axis: &str) {
+        let _ = self.axes.remove(axis);
+    }
+
+    /// Returns the inputs bound to an axis, if it has any.
+    pub fn axis_bindings(&self, axis: &str) -> Option<&AxisBindings> {
+        self.axes.get(axis)
+    }
+
+    /// Returns mutable access to an axis' bindings, creating it if it
+    /// doesn't exist yet.
+    pub fn axis_bindings_mut(&mut self, axis: &str) -> &mut AxisBindings {
+        self.axes.entry(axis.to_owned()).or_default()
+    }
+
+    /// Returns the names of all the axes with bindings.
+    pub fn axes(&self) -> impl Iterator<Item = &str> {
+        self.axes.keys().map(String::as_str)
+    }
+}
+
+/// How far an input is pressed, from `0.0` to `1.0`.
+fn strength(ctx: &Context, input: Input) -> f32 {
+    let pressed = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+    match input {
+        Input::Key(key) => pressed(keyboard::is_key_pressed(ctx, key)),
+        Input::ScanCode(scancode) => pressed(keyboard::is_scancode_pressed(ctx, scancode)),
+        Input::MouseButton(button) => pressed(mouse::button_pressed(ctx, button)),
+        Input::GamepadButton(button) => pressed(
+            virtual_pad::button_pressed(ctx, button)
+                || ctx.conf.modules.gamepad
+                    && gamepad::gamepads(ctx).any(|(_, pad)| pad.is_pressed(button)),
+        ),
+        Input::GamepadAxis(axis, direction) => {
+            let sign = match direction {
+                AxisDirection::Negative => -1.0,
+                AxisDirection::Positive => 1.0,
+            };
+            let on_screen = (virtual_pad::axis(ctx, axis) * sign).max(0.0);
+            if !ctx.conf.modules.gamepad {
+                return on_screen;
+            }
+            gamepad::gamepads(ctx)
+                .map(|(id, _)| gamepad::axis(ctx, id, axis) * sign)
+                .fold(on_screen, f32::max)
+        }
+    }
+}
+
+fn is_pressed(ctx: &Context, input: Input) -> bool {
+    strength(ctx, input) >= AXIS_PRESS_THRESHOLD
+}
+
+/// Returns the bindings in use.
+pub fn input_map(ctx: &Context) -> &InputMap {
+    &ctx.input_map
+}
+
+/// Returns the bindings in use, to change them.
+pub fn input_map_mut(ctx: &mut Context) -> &mut InputMap {
+    &mut ctx.input_map
+}
+
+/// Replaces all the bindings in use, such as with ones loaded from a
+/// save file.
+pub fn set_input_map(ctx: &mut Context, map: InputMap) {
+    ctx.input_map = map;
+}
+
+/// Returns whether any of the inputs bound to an action are pressed.
+/// Gamepad axes count once they're pushed halfway.
+pub fn action_pressed(ctx: &Context, action: &str) -> bool {
+    ctx.input_map
+        .bindings(action)
+        .iter()
+        .any(|&input| is_pressed(ctx, input))
+}
+
+/// Returns the value of an axis, from `-1.0` to `1.0`: how far its
+/// strongest positive input is pressed, minus its strongest negative
+/// one.  Keys and buttons count as all the way; gamepad axes count as
+/// far as they're pushed, after their deadzones and curves.
+pub fn axis(ctx: &Context, axis: &str) -> f32 {
+    let bindings = match ctx.input_map.axis_bindings(axis) {
+        Some(bindings) => bindings,
+        None => return 0.0,
+    };
+    let strongest = |inputs: &[Input]| {
+        inputs
+            .iter()
+            .map(|&input| strength(ctx, input))
+            .fold(0.0, f32::max)
+    };
+    strongest(&bindings.positive) - strongest(&bindings.negative)
+}
+
+/// Returns every bindable input that's pressed right now.  A rebinding
+/// menu can wait for this to be non-empty and bind what it finds.
+pub fn pressed_inputs(ctx: &Context) -> Vec<Input> {
+    let mut inputs: Vec<Input> = keyboard::pressed_keys(ctx)
+        .iter()
+        .map(|&key| Input::Key(key))
+        .collect();
+    inputs.extend(ctx.mouse_context.pressed_buttons().map(Input::MouseButton));
+    if ctx.conf.modules.gamepad {
+        let mut gamepad_inputs = Vec::new();
+        for (id, pad) in gamepad::gamepads(ctx) {
+            for &button in &GAMEPAD_BUTTONS {
+                if pad.is_pressed(button) {
+                    gamepad_inputs.push(Input::GamepadButton(button));
+                }
+            }
+            for &axis in &GAMEPAD_AXES {
+                let value = gamepad::axis(ctx, id, axis);
+                if value.abs() >= AXIS_PRESS_THRESHOLD {
+                    let direction = if value < 0.0 {
+                        AxisDirection::Negative
+                    } else {
+                        AxisDirection::Positive
+                    };
+                    gamepad_inputs.push(Input::GamepadAxis(axis, direction));
+                }
+            }
+        }
+        for input in gamepad_inputs {
+            if !inputs.contains(&input) {
+                inputs.push(input);
+            }
+        }
+    }
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_and_rebind() {
+        let mut map = InputMap::new();
+        map.bind("jump", Input::Key(KeyCode::Space));
+        map.bind("jump", Input::Key(KeyCode::Space));
+        map.bind("jump", Input::GamepadButton(Button::South));
+        assert_eq!(
+            map.bindings("jump"),
+            &[
+                Input::Key(KeyCode::Space),
+                Input::GamepadButton(Button::South)
+            ]
+        );
+
+        map.rebind("jump", Input::Key(KeyCode::Space), Input::Key(KeyCode::W));
+        assert_eq!(
+            map.bindings("jump"),
+            &[Input::Key(KeyCode::W), Input::GamepadButton(Button::South)]
+        );
+        assert_eq!(map.actions_bound_to(Input::Key(KeyCode::W)), vec!["jump"]);
+
+        map.unbind("jump", Input::Key(KeyCode::W));
+        assert_eq!(map.bindings("jump"), &[Input::GamepadButton(Button::South)]);
+        map.clear("jump");
+        assert!(map.bindings("jump").is_empty());
+    }
+
+    #[cfg(feature = "serde-serialize")]
+    #[test]
+    fn headless_test_serialize() {
+        let mut map = InputMap::new();
+        map.bind("fire", Input::MouseButton(MouseButton::Left));
+        map.bind_axis("move", Input::Key(KeyCode::A), Input::Key(KeyCode::D));
+        map.bind_axis(
+            "move",
+            Input::GamepadAxis(Axis::LeftStickX, AxisDirection::Negative),
+            Input::GamepadAxis(Axis::LeftStickX, AxisDirection::Positive),
+        );
+        let json = serde_json::to_string(&map).unwrap();
+        let loaded: InputMap = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, map);
+    }
+}
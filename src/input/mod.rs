@@ -1,4 +1,10 @@
-//! Input handling modules for keyboard, mouse and gamepad.
+//! Input handling modules for keyboard, mouse, gamepad, touch and pens, and
+//! mapping them to named actions.
 pub mod gamepad;
+pub mod gestures;
 pub mod keyboard;
+pub mod mapping;
 pub mod mouse;
+pub mod pen;
+pub mod touch;
+pub mod virtual_pad;
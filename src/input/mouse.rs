@@ -1,24 +1,76 @@
 //! Mouse utility functions.
 
+use crate::conf::DpiPolicy;
 use crate::context::Context;
 use crate::error::GameError;
 use crate::error::GameResult;
 use crate::graphics;
 use crate::graphics::Point2;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use winit::dpi;
 pub use winit::event::MouseButton;
 pub use winit::window::CursorIcon;
 
+/// How the mouse cursor is held by the window; see
+/// [`set_cursor_grab_mode()`](fn.set_cursor_grab_mode.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CursorGrabMode {
+    /// The cursor moves freely, in and out of the window.
+    None,
+    /// The cursor moves around inside the window but can't leave it,
+    /// which is what edge scrolling wants.
+    Confined,
+    /// The cursor stays where it was when it got locked; only the
+    /// mouse's motion is reported.
+    Locked,
+}
+
+impl Default for CursorGrabMode {
+    fn default() -> Self {
+        CursorGrabMode::None
+    }
+}
+
+/// How far the mouse wheel or a trackpad scrolled.  `y` is positive
+/// away from the user and negative toward them, and `x` is positive
+/// to the right and negative to the left.  See
+/// [`EventHandler::mouse_scroll_event()`](../../event/trait.EventHandler.html#method.mouse_scroll_event).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScrollDelta {
+    /// Lines, or notches of a mouse wheel.
+    Lines {
+        /// Horizontal lines.
+        x: f32,
+        /// Vertical lines.
+        y: f32,
+    },
+    /// Pixels, from trackpads and other smooth scrolling devices, in the
+    /// same units as the mouse position.
+    Pixels {
+        /// Horizontal pixels.
+        x: f32,
+        /// Vertical pixels.
+        y: f32,
+    },
+}
+
 /// Stores state information for the mouse.
 #[derive(Clone, Debug)]
 pub struct MouseContext {
     last_position: Point2,
     last_delta: Point2,
     buttons_pressed: HashMap<MouseButton, bool>,
+    /// Buttons pressed and released since the frame started.
+    just_pressed: HashSet<MouseButton>,
+    just_released: HashSet<MouseButton>,
     cursor_type: CursorIcon,
-    cursor_grabbed: bool,
+    cursor_grab_mode: CursorGrabMode,
+    /// Where the cursor is held while it's locked.
+    locked_position: Point2,
     cursor_hidden: bool,
+    /// How the cursor was grabbed and whether it was hidden before
+    /// relative mode was turned on, while it's on.
+    relative_mode: Option<(CursorGrabMode, bool)>,
 }
 
 impl MouseContext {
@@ -28,8 +80,12 @@ impl MouseContext {
             last_delta: Point2::ZERO,
             cursor_type: CursorIcon::Default,
             buttons_pressed: HashMap::new(),
-            cursor_grabbed: false,
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+            cursor_grab_mode: CursorGrabMode::None,
+            locked_position: Point2::ZERO,
             cursor_hidden: false,
+            relative_mode: None,
         }
     }
 
@@ -42,12 +98,40 @@ impl MouseContext {
     }
 
     pub(crate) fn set_button(&mut self, button: MouseButton, pressed: bool) {
-        let _ = self.buttons_pressed.insert(button, pressed);
+        let was_pressed = self.buttons_pressed.insert(button, pressed) == Some(true);
+        if pressed && !was_pressed {
+            let _ = self.just_pressed.insert(button);
+        } else if !pressed && was_pressed {
+            let _ = self.just_released.insert(button);
+        }
+    }
+
+    /// Forgets which buttons were just pressed and released, when a new
+    /// frame starts.
+    pub(crate) fn end_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
     }
 
     fn button_pressed(&self, button: MouseButton) -> bool {
         *(self.buttons_pressed.get(&button).unwrap_or(&false))
     }
+
+    pub(crate) fn pressed_buttons(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.buttons_pressed
+            .iter()
+            .filter(|(_, &pressed)| pressed)
+            .map(|(&button, _)| button)
+    }
+
+    /// Where the cursor should be put back to, if it's locked.
+    pub(crate) fn locked_position(&self) -> Option<Point2> {
+        if self.cursor_grab_mode == CursorGrabMode::Locked {
+            Some(self.locked_position)
+        } else {
+            None
+        }
+    }
 }
 
 impl Default for MouseContext {
@@ -67,17 +151,58 @@ pub fn set_cursor_type(ctx: &mut Context, cursor_type: CursorIcon) {
     graphics::window(ctx).set_cursor_icon(cursor_type);
 }
 
-/// Get whether or not the mouse is grabbed (confined to the window)
+/// Get whether or not the mouse is grabbed (confined to the window or locked)
 pub fn cursor_grabbed(ctx: &Context) -> bool {
-    ctx.mouse_context.cursor_grabbed
+    ctx.mouse_context.cursor_grab_mode != CursorGrabMode::None
 }
 
-/// Set whether or not the mouse is grabbed (confined to the window)
+/// Set whether or not the mouse is grabbed (confined to the window).
+/// Shorthand for [`set_cursor_grab_mode()`](fn.set_cursor_grab_mode.html)
+/// with `CursorGrabMode::Confined` or `CursorGrabMode::None`.
 pub fn set_cursor_grabbed(ctx: &mut Context, grabbed: bool) -> GameResult<()> {
-    ctx.mouse_context.cursor_grabbed = grabbed;
-    graphics::window(ctx)
-        .set_cursor_grab(grabbed)
-        .map_err(|e| GameError::WindowError(e.to_string()))
+    let mode = if grabbed {
+        CursorGrabMode::Confined
+    } else {
+        CursorGrabMode::None
+    };
+    set_cursor_grab_mode(ctx, mode)
+}
+
+/// Returns how the mouse cursor is currently held by the window.
+pub fn cursor_grab_mode(ctx: &Context) -> CursorGrabMode {
+    ctx.mouse_context.cursor_grab_mode
+}
+
+/// Confines the mouse cursor to the window, locks it in place, or lets it
+/// go again.  Keeps the cursor from wandering onto another monitor
+/// mid-game.
+///
+/// Returns a `GameError::WindowError` and leaves the cursor free if the
+/// platform can't do it; locking also needs to be able to move the cursor,
+/// which Wayland doesn't allow, for instance.
+pub fn set_cursor_grab_mode(ctx: &mut Context, mode: CursorGrabMode) -> GameResult<()> {
+    let grab = mode != CursorGrabMode::None;
+    if let Err(e) = graphics::window(ctx).set_cursor_grab(grab) {
+        ctx.mouse_context.cursor_grab_mode = CursorGrabMode::None;
+        return Err(GameError::WindowError(format!(
+            "Couldn't grab the mouse cursor: {}",
+            e
+        )));
+    }
+    if mode == CursorGrabMode::Locked {
+        let here = ctx.mouse_context.last_position;
+        if let Err(e) = set_position(ctx, here) {
+            ctx.mouse_context.cursor_grab_mode = CursorGrabMode::None;
+            let _ = graphics::window(ctx).set_cursor_grab(false);
+            return Err(GameError::WindowError(format!(
+                "Couldn't lock the mouse cursor: {}",
+                e
+            )));
+        }
+        ctx.mouse_context.locked_position = here;
+    }
+    ctx.mouse_context.cursor_grab_mode = mode;
+    Ok(())
 }
 
 /// Set whether or not the mouse is hidden (invisible)
@@ -98,7 +223,8 @@ pub fn position(ctx: &Context) -> mint::Point2<f32> {
     ctx.mouse_context.last_position.into()
 }
 
-/// Set the current position of the mouse cursor, in pixels.
+/// Set the current position of the mouse cursor, in pixels (physical or
+/// logical according to the [`DpiPolicy`](../../conf/enum.DpiPolicy.html)).
 /// Uses strictly window-only coordinates.
 pub fn set_position<P>(ctx: &mut Context, point: P) -> GameResult<()>
 where
@@ -106,11 +232,13 @@ where
 {
     let mintpoint = point.into();
     ctx.mouse_context.last_position = Point2::from(mintpoint);
+    let (x, y) = (f64::from(mintpoint.x), f64::from(mintpoint.y));
+    let position: dpi::Position = match ctx.gfx_context.dpi_policy {
+        DpiPolicy::Physical => dpi::PhysicalPosition { x, y }.into(),
+        DpiPolicy::Logical => dpi::LogicalPosition { x, y }.into(),
+    };
     graphics::window(ctx)
-        .set_cursor_position(dpi::LogicalPosition {
-            x: f64::from(mintpoint.x),
-            y: f64::from(mintpoint.y),
-        })
+        .set_cursor_position(position)
         .map_err(|_| GameError::WindowError("Couldn't set mouse cursor position!".to_owned()))
 }
 
@@ -119,7 +247,54 @@ pub fn delta(ctx: &Context) -> mint::Point2<f32> {
     ctx.mouse_context.last_delta.into()
 }
 
+/// Turns relative mouse mode on or off.  While it's on, the cursor is
+/// hidden and locked, and
+/// [`EventHandler::mouse_motion_event()`](../../event/trait.EventHandler.html#method.mouse_motion_event)
+/// gets the raw motion of the mouse itself as `dx` and `dy`, which
+/// doesn't stop at the edges of the window and isn't scaled by the
+/// system's mouse acceleration or DPI.  Good for aiming and dragging
+/// cameras around.  The position it gets stays where the cursor was.
+///
+/// Turning it off shows and releases the cursor again, unless it was
+/// hidden or grabbed before.
+pub fn set_relative_mode(ctx: &mut Context, relative: bool) -> GameResult<()> {
+    match (ctx.mouse_context.relative_mode, relative) {
+        (None, true) => {
+            let previous = (
+                ctx.mouse_context.cursor_grab_mode,
+                ctx.mouse_context.cursor_hidden,
+            );
+            set_cursor_grab_mode(ctx, CursorGrabMode::Locked)?;
+            set_cursor_hidden(ctx, true);
+            ctx.mouse_context.relative_mode = Some(previous);
+        }
+        (Some((grab_mode, hidden)), false) => {
+            ctx.mouse_context.relative_mode = None;
+            set_cursor_hidden(ctx, hidden);
+            set_cursor_grab_mode(ctx, grab_mode)?;
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Returns whether relative mouse mode is on; see
+/// [`set_relative_mode()`](fn.set_relative_mode.html).
+pub fn relative_mode(ctx: &Context) -> bool {
+    ctx.mouse_context.relative_mode.is_some()
+}
+
 /// Returns whether or not the given mouse button is pressed.
 pub fn button_pressed(ctx: &Context, button: MouseButton) -> bool {
     ctx.mouse_context.button_pressed(button)
 }
+
+/// Returns whether the given mouse button was pressed since the last frame.
+pub fn button_just_pressed(ctx: &Context, button: MouseButton) -> bool {
+    ctx.mouse_context.just_pressed.contains(&button)
+}
+
+/// Returns whether the given mouse button was released since the last frame.
+pub fn button_just_released(ctx: &Context, button: MouseButton) -> bool {
+    ctx.mouse_context.just_released.contains(&button)
+}
@@ -0,0 +1,128 @@
+//! Pen and tablet utility functions.
+//!
+//! Pens come in through `winit` as touches that also say how hard
+//! they're pressed, so every touch that reports its pressure shows up
+//! here too, as well as through
+//! [`EventHandler::pen_event()`](../../event/trait.EventHandler.html#method.pen_event).
+//! That includes fingers on touchscreens that can feel pressure.
+//!
+//! `winit` reports how far a pen is tilted only on iOS, and doesn't
+//! report its azimuth, its buttons or whether it's the eraser end yet.
+
+use crate::context::Context;
+use std::collections::BTreeMap;
+use std::f32::consts::FRAC_PI_2;
+use winit::event::Force;
+pub use winit::event::TouchPhase;
+
+/// A pen, or anything else that reports pressure, on the screen or tablet.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pen {
+    /// Identifies the pen until it's lifted.
+    pub id: u64,
+    /// Whether it just touched down, moved or was lifted.
+    pub phase: TouchPhase,
+    /// Where it is, in the same pixels as the mouse position.
+    pub position: mint::Point2<f32>,
+    /// How hard it's pressed, from `0.0` to `1.0`.
+    pub pressure: f32,
+    /// How far it leans away from upright, in radians, from `0.0`
+    /// straight up to `PI / 2` lying flat, if the platform says.
+    pub tilt: Option<f32>,
+}
+
+impl Pen {
+    /// Reads a pen out of a touch, if the touch has a pressure.
+    pub(crate) fn from_touch(
+        touch: &winit::event::Touch,
+        position: mint::Point2<f32>,
+    ) -> Option<Self> {
+        let force = touch.force?;
+        let tilt = match force {
+            Force::Calibrated {
+                altitude_angle: Some(altitude),
+                ..
+            } => Some(FRAC_PI_2 - altitude as f32),
+            _ => None,
+        };
+        Some(Pen {
+            id: touch.id,
+            phase: touch.phase,
+            position,
+            pressure: (force.normalized() as f32).max(0.0).min(1.0),
+            tilt,
+        })
+    }
+}
+
+/// Stores state information for pens.
+#[derive(Clone, Debug, Default)]
+pub struct PenContext {
+    /// Each pen on the screen, by id.
+    pens: BTreeMap<u64, Pen>,
+}
+
+impl PenContext {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn update(&mut self, pen: Pen) {
+        match pen.phase {
+            TouchPhase::Started | TouchPhase::Moved => {
+                let _ = self.pens.insert(pen.id, pen);
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let _ = self.pens.remove(&pen.id);
+            }
+        }
+    }
+}
+
+/// Returns the pens on the screen right now, oldest id first.
+pub fn pens(ctx: &Context) -> Vec<Pen> {
+    ctx.pen_context.pens.values().copied().collect()
+}
+
+/// Returns the given pen, if it's still on the screen.
+pub fn pen(ctx: &Context, id: u64) -> Option<Pen> {
+    ctx.pen_context.pens.get(&id).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::event::{DeviceId, Touch};
+
+    #[test]
+    #[allow(clippy::float_cmp, unsafe_code)]
+    fn pens_from_touches() {
+        let position = mint::Point2 { x: 1.0, y: 2.0 };
+        let mut touch = Touch {
+            device_id: unsafe { DeviceId::dummy() },
+            phase: TouchPhase::Started,
+            location: winit::dpi::PhysicalPosition::new(1.0, 2.0),
+            force: None,
+            id: 4,
+        };
+        assert_eq!(Pen::from_touch(&touch, position), None);
+
+        touch.force = Some(Force::Calibrated {
+            force: 1.0,
+            max_possible_force: 4.0,
+            altitude_angle: Some(f64::from(FRAC_PI_2)),
+        });
+        let pen = Pen::from_touch(&touch, position).unwrap();
+        assert_eq!(pen.pressure, 0.25);
+        assert_eq!(pen.tilt, Some(0.0));
+
+        let mut pens = PenContext::new();
+        pens.update(pen);
+        assert_eq!(pens.pens.len(), 1);
+        pens.update(Pen {
+            phase: TouchPhase::Ended,
+            ..pen
+        });
+        assert!(pens.pens.is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+//! Touchscreen utility functions.
+//!
+//! Every finger on the screen is a separate touch, with its own id
+//! that stays the same from the moment it touches the screen until
+//! it's lifted.  Touches also come in as events through
+//! [`EventHandler::touch_event()`](../../event/trait.EventHandler.html#method.touch_event).
+
+use crate::context::Context;
+use crate::graphics::Point2;
+use std::collections::BTreeMap;
+pub use winit::event::TouchPhase;
+
+/// A finger on the screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Touch {
+    /// Identifies the touch until it ends.
+    pub id: u64,
+    /// Where it is, in the same pixels as the mouse position.
+    pub position: mint::Point2<f32>,
+}
+
+/// Stores state information for touches.
+#[derive(Clone, Debug, Default)]
+pub struct TouchContext {
+    /// The position of each active touch, by id.
+    touches: BTreeMap<u64, Point2>,
+}
+
+impl TouchContext {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn update(&mut self, phase: TouchPhase, id: u64, position: Point2) {
+        match phase {
+            TouchPhase::Started | TouchPhase::Moved => {
+                let _ = self.touches.insert(id, position);
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let _ = self.touches.remove(&id);
+            }
+        }
+    }
+}
+
+/// Returns the touches on the screen right now, oldest id first.
+pub fn touches(ctx: &Context) -> Vec<Touch> {
+    ctx.touch_context
+        .touches
+        .iter()
+        .map(|(&id, &position)| Touch {
+            id,
+            position: position.into(),
+        })
+        .collect()
+}
+
+/// Returns the position of the given touch, if it's still on the screen.
+pub fn position(ctx: &Context, id: u64) -> Option<mint::Point2<f32>> {
+    ctx.touch_context
+        .touches
+        .get(&id)
+        .map(|&position| position.into())
+}
+
+/// Returns whether anything is touching the screen.
+pub fn is_touching(ctx: &Context) -> bool {
+    !ctx.touch_context.touches.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touches_start_move_and_end() {
+        let mut touch = TouchContext::new();
+        touch.update(TouchPhase::Started, 3, Point2::new(1.0, 2.0));
+        touch.update(TouchPhase::Started, 7, Point2::new(5.0, 5.0));
+        touch.update(TouchPhase::Moved, 3, Point2::new(2.0, 3.0));
+        assert_eq!(touch.touches.len(), 2);
+        assert_eq!(touch.touches[&3], Point2::new(2.0, 3.0));
+
+        touch.update(TouchPhase::Ended, 3, Point2::new(2.0, 3.0));
+        touch.update(TouchPhase::Cancelled, 7, Point2::new(5.0, 5.0));
+        assert!(touch.touches.is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! An on-screen gamepad for touchscreens, drawn with ggez's own meshes.
+//!
+//! Build a [`VirtualPad`](struct.VirtualPad.html) out of sticks and
+//! buttons, turn it on with [`set_virtual_pad()`](fn.set_virtual_pad.html)
+//! and [`draw()`](fn.draw.html) it on top of the game each frame.  Its
+//! sticks and buttons count as gamepad axes and buttons in
+//! [`input::mapping`](../mapping/index.html), so actions and axes bound
+//! to a gamepad work on touchscreens as they are, and can also be read
+//! directly with [`axis()`](fn.axis.html) and
+//! [`button_pressed()`](fn.button_pressed.html).
+//!
+//! Positions and sizes are in screen coordinates, like everything else
+//! that's drawn.
+//!
+//! ```rust,no_run
+//! # use ggez::*;
+//! # use ggez::input::gamepad::{Axis, Button};
+//! # use ggez::input::virtual_pad::{self, VirtualPad};
+//! # fn t(ctx: &mut Context) -> GameResult {
+//! let pad = VirtualPad::new()
+//!     .stick([120.0, 480.0], 80.0, Axis::LeftStickX, Axis::LeftStickY)
+//!     .button([700.0, 500.0], 40.0, Button::South);
+//! virtual_pad::set_virtual_pad(ctx, Some(pad));
+//! // Then, at the end of `draw()`:
+//! virtual_pad::draw(ctx)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::context::Context;
+use crate::error::GameResult;
+use crate::graphics::{self, Color, DrawMode, DrawParam, MeshBuilder, Point2};
+use crate::input::gamepad::{Axis, Button};
+use winit::event::TouchPhase;
+
+/// How much a pressed button or held stick is brightened when drawn.
+const PRESSED_ALPHA: f32 = 0.6;
+/// How much an idle button or stick is drawn with.
+const IDLE_ALPHA: f32 = 0.3;
+
+/// An on-screen analog stick, reported as two gamepad axes.
+#[derive(Clone, Debug, PartialEq)]
+struct Stick {
+    center: Point2,
+    radius: f32,
+    x_axis: Axis,
+    y_axis: Axis,
+    /// The touch holding it, if any.
+    touch: Option<u64>,
+    /// How far it's pushed, from `-1.0` to `1.0` each way, with y up
+    /// like gamepad sticks.
+    value: Point2,
+}
+
+/// An on-screen button, reported as a gamepad button.
+#[derive(Clone, Debug, PartialEq)]
+struct PadButton {
+    center: Point2,
+    radius: f32,
+    button: Button,
+    /// The touches pressing it.
+    touches: Vec<u64>,
+}
+
+/// A set of on-screen sticks and buttons.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VirtualPad {
+    sticks: Vec<Stick>,
+    buttons: Vec<PadButton>,
+    color: Color,
+}
+
+impl Default for VirtualPad {
+    fn default() -> Self {
+        VirtualPad {
+            sticks: Vec::new(),
+            buttons: Vec::new(),
+            color: Color::WHITE,
+        }
+    }
+}
+
+impl VirtualPad {
+    /// Creates a `VirtualPad` with no sticks or buttons.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a stick centered at the given point, that reports how far
+    /// it's dragged from there as the given axes; dragging it `radius`
+    /// away pushes it all the way.
+    pub fn stick<P>(mut self, center: P, radius: f32, x_axis: Axis, y_axis: Axis) -> Self
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        self.sticks.push(Stick {
+            center: Point2::from(center.into()),
+            radius,
+            x_axis,
+            y_axis,
+            touch: None,
+            value: Point2::ZERO,
+        });
+        self
+    }
+
+    /// Adds a round button with the given center and radius, that
+    /// reports as the given gamepad button.
+    pub fn button<P>(mut self, center: P, radius: f32, button: Button) -> Self
+    where
+        P: Into<mint::Point2<f32>>,
+    {
+        self.buttons.push(PadButton {
+            center: Point2::from(center.into()),
+            radius,
+            button,
+            touches: Vec::new(),
+        });
+        self
+    }
+
+    /// Sets the color the sticks and buttons are drawn in; they're drawn
+    /// translucent, and more opaque while they're held.  Defaults to white.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Updates the sticks and buttons from a touch, in screen coordinates.
+    pub(crate) fn touch(&mut self, phase: TouchPhase, id: u64, position: Point2) {
+        match phase {
+            TouchPhase::Started => {
+                if let Some(stick) = self
+                    .sticks
+                    .iter_mut()
+                    .find(|s| s.touch.is_none() && s.center.distance(position) <= s.radius)
+                {
+                    stick.touch = Some(id);
+                    stick.push(position);
+                    return;
+                }
+                for button in &mut self.buttons {
+                    if button.center.distance(position) <= button.radius {
+                        button.touches.push(id);
+                    }
+                }
+            }
+            TouchPhase::Moved => {
+                // Sticks stay held when the finger slides off them;
+                // buttons don't.
+                for stick in &mut self.sticks {
+                    if stick.touch == Some(id) {
+                        stick.push(position);
+                    }
+                }
+                for button in &mut self.buttons {
+                    let over = button.center.distance(position) <= button.radius;
+                    let held = button.touches.contains(&id);
+                    if over && !held {
+                        button.touches.push(id);
+                    } else if !over && held {
+                        button.touches.retain(|&touch| touch != id);
+                    }
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                for stick in &mut self.sticks {
+                    if stick.touch == Some(id) {
+                        stick.touch = None;
+                        stick.value = Point2::ZERO;
+                    }
+                }
+                for button in &mut self.buttons {
+                    button.touches.retain(|&touch| touch != id);
+                }
+            }
+        }
+    }
+
+    /// Returns the value of an axis, from the stick reporting it that's
+    /// pushed the furthest, or `0.0`.
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.sticks
+            .iter()
+            .filter_map(|stick| {
+                if stick.x_axis == axis {
+                    Some(stick.value.x)
+                } else if stick.y_axis == axis {
+                    Some(stick.value.y)
+                } else {
+                    None
+                }
+            })
+            .fold(0.0, |a: f32, b: f32| if b.abs() > a.abs() { b } else { a })
+    }
+
+    /// Returns whether any button reporting as the given one is pressed.
+    pub fn button_pressed(&self, button: Button) -> bool {
+        self.buttons
+            .iter()
+            .any(|b| b.button == button && !b.touches.is_empty())
+    }
+
+    /// Builds a mesh of the sticks and buttons as they are now.
+    fn build_mesh(&self, builder: &mut MeshBuilder) -> GameResult {
+        let tint = |held: bool| {
+            let alpha = if held { PRESSED_ALPHA } else { IDLE_ALPHA };
+            Color::new(
+                self.color.r,
+                self.color.g,
+                self.color.b,
+                self.color.a * alpha,
+            )
+        };
+        for stick in &self.sticks {
+            let color = tint(stick.touch.is_some());
+            let knob = stick.center + Point2::new(stick.value.x, -stick.value.y) * stick.radius;
+            let _ = builder.circle(
+                DrawMode::stroke(2.0),
+                stick.center,
+                stick.radius,
+                0.5,
+                color,
+            )?;
+            let _ = builder.circle(DrawMode::fill(), knob, stick.radius * 0.4, 0.5, color)?;
+        }
+        for button in &self.buttons {
+            let color = tint(!button.touches.is_empty());
+            let _ = builder.circle(DrawMode::fill(), button.center, button.radius, 0.5, color)?;
+        }
+        Ok(())
+    }
+}
+
+impl Stick {
+    /// Pushes the stick toward a touch position, as far as its radius.
+    fn push(&mut self, position: Point2) {
+        let offset = (position - self.center) / self.radius;
+        let offset = if offset.length() > 1.0 {
+            offset.normalize()
+        } else {
+            offset
+        };
+        self.value = Point2::new(offset.x, -offset.y);
+    }
+}
+
+/// Turns the on-screen gamepad on, or off with `None`.
+pub fn set_virtual_pad(ctx: &mut Context, pad: Option<VirtualPad>) {
+    ctx.virtual_pad = pad;
+}
+
+/// Returns the on-screen gamepad, if it's on.
+pub fn virtual_pad(ctx: &Context) -> Option<&VirtualPad> {
+    ctx.virtual_pad.as_ref()
+}
+
+/// Returns the value of an axis on the on-screen gamepad, or `0.0` if
+/// it's off.
+pub fn axis(ctx: &Context, axis: Axis) -> f32 {
+    ctx.virtual_pad.as_ref().map_or(0.0, |pad| pad.axis(axis))
+}
+
+/// Returns whether a button on the on-screen gamepad is pressed.
+pub fn button_pressed(ctx: &Context, button: Button) -> bool {
+    ctx.virtual_pad
+        .as_ref()
+        .map_or(false, |pad| pad.button_pressed(button))
+}
+
+/// Draws the on-screen gamepad, if it's on.  Call it last in `draw()`,
+/// so it's on top of everything else.
+pub fn draw(ctx: &mut Context) -> GameResult {
+    let mut builder = MeshBuilder::new();
+    match &ctx.virtual_pad {
+        Some(pad) if !pad.sticks.is_empty() || !pad.buttons.is_empty() => {
+            pad.build_mesh(&mut builder)?
+        }
+        _ => return Ok(()),
+    }
+    let mesh = builder.build(ctx)?;
+    graphics::draw(ctx, &mesh, DrawParam::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sticks_and_buttons() {
+        let mut pad = VirtualPad::new()
+            .stick([100.0, 100.0], 50.0, Axis::LeftStickX, Axis::LeftStickY)
+            .button([300.0, 100.0], 20.0, Button::South);
+
+        pad.touch(TouchPhase::Started, 1, Point2::new(100.0, 100.0));
+        pad.touch(TouchPhase::Moved, 1, Point2::new(125.0, 0.0));
+        assert!((pad.axis(Axis::LeftStickX) - 0.242_535_6).abs() < 1e-4);
+        assert!(pad.axis(Axis::LeftStickY) > 0.9);
+
+        pad.touch(TouchPhase::Started, 2, Point2::new(305.0, 95.0));
+        assert!(pad.button_pressed(Button::South));
+        pad.touch(TouchPhase::Moved, 2, Point2::new(400.0, 100.0));
+        assert!(!pad.button_pressed(Button::South));
+
+        pad.touch(TouchPhase::Ended, 1, Point2::new(125.0, 0.0));
+        assert!(pad.axis(Axis::LeftStickX).abs() < f32::EPSILON);
+    }
+}
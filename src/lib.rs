@@ -195,7 +195,9 @@ extern crate smart_default;
 
 pub extern crate mint;
 
+#[cfg(feature = "audio")]
 pub mod audio;
+pub mod cache;
 pub mod conf;
 mod context;
 pub mod error;
@@ -203,8 +205,10 @@ pub mod event;
 pub mod filesystem;
 pub mod graphics;
 pub mod input;
+pub mod pack;
+pub mod tasks;
 pub mod timer;
-mod vfs;
+pub mod vfs;
 
 #[cfg(test)]
 pub mod tests;
@@ -0,0 +1,348 @@
+//! Packing resources into "pak" files, a simple archive format made
+//! for loading lots of small files quickly.
+//!
+//! A pak file starts with an index of the files in it, which is all
+//! that's read when it's mounted, followed by the files themselves,
+//! uncompressed.  Opening a file is then just a seek and a read, where
+//! zip files have to search their central directory and decompress.
+//! Images and sounds are already compressed anyway.
+//!
+//! Build one from the resources directory as part of packaging the
+//! game, for instance from a build script or a small tool:
+//!
+//! ```rust,no_run
+//! # fn main() -> ggez::GameResult {
+//! ggez::pack::build("resources", "resources.pak")?;
+//! # Ok(()) }
+//! ```
+//!
+//! Then put it next to the executable, where it's mounted like
+//! `resources.zip` is, or mount it yourself with
+//! [`filesystem::mount_archive()`](../filesystem/fn.mount_archive.html).
+//!
+//! With the `encryption` feature, [`build_encrypted()`](fn.build_encrypted.html)
+//! makes pak files encrypted with ChaCha20, to be mounted with
+//! [`filesystem::mount_encrypted()`](../filesystem/fn.mount_encrypted.html)
+//! and the same key.  Since the key has to be in the game for it to
+//! read its own files, this keeps casual snoopers out of the assets
+//! rather than anyone determined.
+//!
+//! The format, with all numbers little-endian, is the magic bytes
+//! `GGEZPAK1`, a `u32` count of files, then for each file a `u16`
+//! length of its path, the path itself in UTF-8 with `/` between its
+//! parts, and `u64`s of where the file starts in the pak and how long
+//! it is.  The contents of the files follow.  An encrypted pak is the
+//! magic bytes `GGEZPAKE` and a 12-byte nonce, followed by a whole
+//! pak encrypted with ChaCha20, starting at the start of its stream.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{GameError, GameResult};
+
+/// The bytes every pak file starts with.
+pub(crate) const MAGIC: &[u8; 8] = b"GGEZPAK1";
+
+/// The bytes every encrypted pak file starts with.
+pub(crate) const ENCRYPTED_MAGIC: &[u8; 8] = b"GGEZPAKE";
+
+/// A stream encrypted with ChaCha20, which decrypts what's read from
+/// it and encrypts what's written to it.  Seeking works, since any
+/// part of the key stream can be made without the rest.
+#[cfg(feature = "encryption")]
+pub(crate) struct Encrypted<T> {
+    inner: T,
+    cipher: chacha20::ChaCha20,
+    /// Where the encrypted stream starts in `inner`.
+    start: u64,
+    /// Where in the encrypted stream we are.
+    position: u64,
+}
+
+#[cfg(feature = "encryption")]
+impl<T> Encrypted<T> {
+    fn new(inner: T, key: &[u8; 32], nonce: &[u8; 12], start: u64) -> Self {
+        use chacha20::cipher::NewCipher;
+        let cipher = chacha20::ChaCha20::new(key.into(), nonce.into());
+        Encrypted {
+            inner,
+            cipher,
+            start,
+            position: 0,
+        }
+    }
+
+    fn apply(&mut self, buf: &mut [u8]) {
+        use chacha20::cipher::{StreamCipher, StreamCipherSeek};
+        self.cipher.seek(self.position);
+        self.cipher.apply_keystream(buf);
+        self.position += buf.len() as u64;
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<R: Read + io::Seek> Encrypted<R> {
+    /// Starts reading an encrypted pak file, checking its header.
+    pub(crate) fn open(mut inner: R, key: &[u8; 32]) -> GameResult<Self> {
+        let mut magic = [0; 8];
+        inner.read_exact(&mut magic)?;
+        if &magic != ENCRYPTED_MAGIC {
+            return Err(GameError::FilesystemError(
+                "Not an encrypted pak file".to_string(),
+            ));
+        }
+        let mut nonce = [0; 12];
+        inner.read_exact(&mut nonce)?;
+        let start = inner.seek(io::SeekFrom::Current(0))?;
+        Ok(Encrypted::new(inner, key, &nonce, start))
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<R: Read> Read for Encrypted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.apply(&mut buf[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<W: Write> Write for Encrypted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut encrypted = buf.to_vec();
+        self.apply(&mut encrypted);
+        self.inner.write_all(&encrypted)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<S: io::Seek> io::Seek for Encrypted<S> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            io::SeekFrom::Start(offset) => io::SeekFrom::Start(self.start + offset),
+            relative => relative,
+        };
+        let position = self.inner.seek(pos)?;
+        self.position = position.checked_sub(self.start).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seeked before the start of an encrypted pak",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+/// Packs every file in the directory `dir`, and in the directories in
+/// it, into a pak file at `out`, overwriting it if it exists.  Paths
+/// in the pak are relative to `dir`, so `dir/images/player.png` can be
+/// opened as `/images/player.png`.
+pub fn build<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, out: Q) -> GameResult {
+    let mut out = io::BufWriter::new(fs::File::create(out)?);
+    write_pak(dir.as_ref(), &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Like [`build()`](fn.build.html), but encrypts the pak file with the
+/// given key.
+#[cfg(feature = "encryption")]
+pub fn build_encrypted<P: AsRef<Path>, Q: AsRef<Path>>(
+    dir: P,
+    out: Q,
+    key: &[u8; 32],
+) -> GameResult {
+    let mut nonce = [0; 12];
+    getrandom::getrandom(&mut nonce)
+        .map_err(|e| GameError::FilesystemError(format!("Could not make a nonce: {}", e)))?;
+    let mut out = io::BufWriter::new(fs::File::create(out)?);
+    out.write_all(ENCRYPTED_MAGIC)?;
+    out.write_all(&nonce)?;
+    let mut encrypted = Encrypted::new(&mut out, key, &nonce, 0);
+    write_pak(dir.as_ref(), &mut encrypted)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Writes a pak of the files under `dir`.
+fn write_pak<W: Write>(dir: &Path, out: &mut W) -> GameResult {
+    let mut files = Vec::new();
+    collect_files(dir, dir, &mut files)?;
+    files.sort();
+
+    let mut index = Vec::with_capacity(files.len());
+    for (name, path) in files {
+        let len = fs::metadata(&path)?.len();
+        index.push((name, path, len));
+    }
+    let header_len = MAGIC.len()
+        + 4
+        + index
+            .iter()
+            .map(|(name, _, _)| 2 + name.len() + 8 + 8)
+            .sum::<usize>();
+
+    out.write_all(MAGIC)?;
+    let files = u32::try_from(index.len())
+        .map_err(|_| GameError::FilesystemError("Too many files to pack".to_string()))?;
+    out.write_all(&files.to_le_bytes())?;
+    let mut offset = header_len as u64;
+    for (name, _, len) in &index {
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| GameError::FilesystemError(format!("Path too long to pack: {}", name)))?;
+        out.write_all(&name_len.to_le_bytes())?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(&offset.to_le_bytes())?;
+        out.write_all(&len.to_le_bytes())?;
+        offset += len;
+    }
+    for (name, path, len) in &index {
+        let copied = io::copy(&mut fs::File::open(path)?, out)?;
+        if copied != *len {
+            return Err(GameError::FilesystemError(format!(
+                "{} changed while it was being packed",
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Finds the files under `dir`, with their paths in the pak.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) -> GameResult {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            let name = path
+                .strip_prefix(root)
+                .ok()
+                .and_then(|relative| {
+                    let parts = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_str())
+                        .collect::<Option<Vec<_>>>()?;
+                    Some(parts.join("/"))
+                })
+                .ok_or_else(|| {
+                    GameError::FilesystemError(format!("Invalid path to pack: {:?}", path))
+                })?;
+            files.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+/// Reads the index at the start of a pak file: where each file starts,
+/// and its length, keyed by its relative path.
+pub(crate) fn read_index<R: Read>(reader: &mut R) -> GameResult<HashMap<PathBuf, (u64, u64)>> {
+    fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+        let mut bytes = [0; 2];
+        reader.read_exact(&mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+    fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+    fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(GameError::FilesystemError(
+            "Not a pak file, or made by an incompatible version of ggez".to_string(),
+        ));
+    }
+    let files = read_u32(reader)?;
+    // The count comes from the file, so don't trust it with an allocation.
+    let mut index = HashMap::new();
+    for _ in 0..files {
+        let mut name = vec![0; usize::from(read_u16(reader)?)];
+        reader.read_exact(&mut name)?;
+        let name = String::from_utf8(name)
+            .map_err(|_| GameError::FilesystemError("Invalid path in pak file".to_string()))?;
+        let offset = read_u64(reader)?;
+        let len = read_u64(reader)?;
+        let _ = index.insert(PathBuf::from(name), (offset, len));
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::{PakFS, VFS};
+
+    #[test]
+    fn headless_test_build_and_read() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("ggez_pack_test_{}", std::process::id()));
+        let resources = dir.join("resources");
+        fs::create_dir_all(resources.join("images")).unwrap();
+        fs::write(resources.join("readme.txt"), b"Packed!").unwrap();
+        fs::write(resources.join("images/player.png"), b"png").unwrap();
+
+        let pak = dir.join("resources.pak");
+        build(&resources, &pak).unwrap();
+        let pakfs = PakFS::new(&pak).unwrap();
+
+        assert!(pakfs.exists(Path::new("/images")));
+        assert!(pakfs.metadata(Path::new("/images")).unwrap().is_dir());
+        assert_eq!(pakfs.metadata(Path::new("/readme.txt")).unwrap().len(), 7);
+        let mut contents = String::new();
+        let _ = pakfs
+            .open(Path::new("/images/player.png"))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "png");
+        assert!(pakfs.open(Path::new("/missing.txt")).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn headless_test_encrypted() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("ggez_encrypted_pack_test_{}", std::process::id()));
+        let resources = dir.join("resources");
+        fs::create_dir_all(&resources).unwrap();
+        fs::write(resources.join("secret.txt"), b"Secret!").unwrap();
+
+        let key = [7; 32];
+        let pak = dir.join("resources.pak");
+        build_encrypted(&resources, &pak, &key).unwrap();
+        let raw = fs::read(&pak).unwrap();
+        assert!(!raw.windows(7).any(|w| w == b"Secret!"));
+        assert!(PakFS::new(&pak).is_err());
+        assert!(PakFS::new_encrypted(&pak, &[8; 32]).is_err());
+
+        let pakfs = PakFS::new_encrypted(&pak, &key).unwrap();
+        let mut contents = String::new();
+        let _ = pakfs
+            .open(Path::new("/secret.txt"))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "Secret!");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+//! A tiny executor that runs futures on the main thread, a step each
+//! frame, so async code can be used without bringing in a runtime that
+//! wants the event loop to itself.
+//!
+//! [`spawn()`](fn.spawn.html) a future and keep the [`Task`](struct.Task.html)
+//! it returns; [`event::run()`](../event/fn.run.html) polls whichever
+//! tasks were woken once a frame, just before `update()`, and `update()`
+//! can [take](struct.Task.html#method.try_take) the result once it's
+//! there.  The futures run on the main thread and never see the
+//! `Context`, so they don't need to be `Send`.
+//!
+//! This is no reactor: a future only makes progress when whatever it's
+//! waiting on wakes it up.  [`unblock()`](fn.unblock.html) runs blocking
+//! work, like loading and decoding a big file, on a thread of its own
+//! and wakes the task when it's done.
+//!
+//! ```rust,no_run
+//! # use ggez::*;
+//! # fn t(ctx: &mut Context) {
+//! let mut level = tasks::spawn(ctx, async {
+//!     let bytes = tasks::unblock(|| std::fs::read("level.bin")).await;
+//!     bytes.map(|bytes| bytes.len())
+//! });
+//! // Later, in `update()`:
+//! if let Some(size) = level.try_take() {
+//!     println!("Loaded {:?} bytes", size);
+//! }
+//! # }
+//! ```
+
+use crate::context::Context;
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context as TaskContext, Poll, Wake, Waker};
+use std::thread;
+
+/// Whether a job was woken up since it was last polled.
+struct WakeFlag(AtomicBool);
+
+impl Wake for WakeFlag {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// A spawned future, and whether it should be polled.
+struct Job {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    woken: Arc<WakeFlag>,
+}
+
+/// Runs the context's spawned futures; see the
+/// [module docs](index.html).
+#[derive(Default)]
+pub struct Executor {
+    jobs: Vec<Job>,
+}
+
+impl fmt::Debug for Executor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Executor")
+            .field("jobs", &self.jobs.len())
+            .finish()
+    }
+}
+
+impl Executor {
+    /// Creates a new `Executor` with nothing to run.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts running a future, to be polled by
+    /// [`run_pending()`](#method.run_pending).
+    pub fn spawn<F>(&mut self, future: F) -> Task<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let result = Rc::new(RefCell::new(None));
+        let slot = Rc::clone(&result);
+        self.jobs.push(Job {
+            future: Box::pin(async move {
+                let output = future.await;
+                *slot.borrow_mut() = Some(output);
+            }),
+            woken: Arc::new(WakeFlag(AtomicBool::new(true))),
+        });
+        Task { result }
+    }
+
+    /// Polls every job that was woken up since it was last polled, once,
+    /// and forgets the ones that finish.
+    pub fn run_pending(&mut self) {
+        let mut i = 0;
+        while i < self.jobs.len() {
+            let job = &mut self.jobs[i];
+            if job.woken.0.swap(false, Ordering::AcqRel) {
+                let waker = Waker::from(Arc::clone(&job.woken));
+                let mut cx = TaskContext::from_waker(&waker);
+                if job.future.as_mut().poll(&mut cx).is_ready() {
+                    let _ = self.jobs.remove(i);
+                    continue;
+                }
+            }
+            i += 1;
+        }
+    }
+
+    /// Returns how many spawned futures haven't finished yet.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Returns whether every spawned future has finished.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+}
+
+/// A future started with [`spawn()`](fn.spawn.html), to get its result
+/// from once it's done.  Dropping it doesn't stop the future.
+pub struct Task<T> {
+    result: Rc<RefCell<Option<T>>>,
+}
+
+impl<T> fmt::Debug for Task<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Task")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+impl<T> Task<T> {
+    /// Returns whether the future finished and its result is waiting to
+    /// be taken.
+    pub fn is_finished(&self) -> bool {
+        self.result.borrow().is_some()
+    }
+
+    /// Takes the future's result, if it finished.  It can only be taken
+    /// once.
+    pub fn try_take(&mut self) -> Option<T> {
+        self.result.borrow_mut().take()
+    }
+}
+
+/// What a thread started by [`unblock()`](fn.unblock.html) shares with
+/// its future.
+struct Unblocked<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// The future returned by [`unblock()`](fn.unblock.html).
+pub struct Unblock<T> {
+    shared: Arc<Mutex<Unblocked<T>>>,
+}
+
+impl<T> fmt::Debug for Unblock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Unblock").finish()
+    }
+}
+
+impl<T> Future for Unblock<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<T> {
+        let mut shared = match self.shared.lock() {
+            Ok(shared) => shared,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Runs `f` on a new thread, returning a future of what it returns, so
+/// a task can wait for blocking work without holding up the frame.  If
+/// `f` panics, the future never finishes.
+pub fn unblock<F, T>(f: F) -> Unblock<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Unblocked {
+        result: None,
+        waker: None,
+    }));
+    let thread_shared = Arc::clone(&shared);
+    let _ = thread::spawn(move || {
+        let result = f();
+        let mut shared = match thread_shared.lock() {
+            Ok(shared) => shared,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        shared.result = Some(result);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    });
+    Unblock { shared }
+}
+
+/// Starts running a future on the context's
+/// [`Executor`](struct.Executor.html).
+pub fn spawn<F>(ctx: &mut Context, future: F) -> Task<F::Output>
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    ctx.executor.spawn(future)
+}
+
+/// Polls the context's tasks that were woken up.
+/// [`event::run()`](../event/fn.run.html) calls this once a frame, so it's
+/// only needed in your own event loop.
+pub fn run_pending(ctx: &mut Context) {
+    ctx.executor.run_pending();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_finish_when_woken() {
+        let mut executor = Executor::new();
+        let mut ready = executor.spawn(async { 1 + 1 });
+        let mut threaded = executor.spawn(unblock(|| 3));
+        assert!(!ready.is_finished());
+
+        executor.run_pending();
+        assert_eq!(ready.try_take(), Some(2));
+        assert_eq!(ready.try_take(), None);
+
+        // The thread wakes its task up when it's done.
+        while !executor.is_empty() {
+            thread::yield_now();
+            executor.run_pending();
+        }
+        assert_eq!(threaded.try_take(), Some(3));
+    }
+}
@@ -132,3 +132,341 @@ fn volume_persists_after_play() {
         assert_eq!(sound.volume(), volume);
     }
 }
+
+#[test]
+fn streaming_source() {
+    let (c, _e) = &mut tests::make_context();
+
+    let mut music = audio::StreamingSource::new(c, "/sound.ogg").unwrap();
+    music.set_repeat(true);
+    music.play(c).unwrap();
+    assert!(music.playing());
+    // Queueing it again reads the same file from its own position.
+    music.play_later().unwrap();
+    music.stop(c).unwrap();
+    assert!(!music.playing());
+
+    assert!(audio::StreamingSource::new(c, "/player.png").is_err());
+    assert!(audio::StreamingSource::new(c, "/does-not-exist.ogg").is_err());
+}
+
+#[test]
+fn seek_and_position() {
+    let (c, _e) = &mut tests::make_context();
+    let start = std::time::Duration::from_millis(200);
+
+    let mut sound = audio::Source::new(c, "/sound.ogg").unwrap();
+    sound.play(c).unwrap();
+    sound.pause();
+    sound.seek(c, start).unwrap();
+    assert!(sound.paused());
+    assert!(sound.position() >= start);
+
+    sound.stop(c).unwrap();
+    assert_eq!(sound.position(), std::time::Duration::from_secs(0));
+}
+
+#[test]
+fn loop_points() {
+    let (c, _e) = &mut tests::make_context();
+    let start = std::time::Duration::from_millis(100);
+    let end = std::time::Duration::from_millis(300);
+
+    let mut sound = audio::StreamingSource::new(c, "/sound.ogg").unwrap();
+    assert_eq!(
+        sound.loop_points(),
+        (std::time::Duration::from_secs(0), None)
+    );
+    sound.set_loop_points(start, Some(end));
+    assert_eq!(sound.loop_points(), (start, Some(end)));
+    sound.set_repeat(true);
+    sound.play(c).unwrap();
+    assert!(sound.playing());
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn mixer_buses() {
+    let (c, _e) = &mut tests::make_context();
+
+    assert_eq!(audio::mixer(c).volume("sfx"), 1.0);
+    assert!(!audio::mixer(c).muted("sfx"));
+
+    let mut sound = audio::Source::new(c, "/pew.ogg").unwrap();
+    assert_eq!(sound.bus(), None);
+    sound.set_bus(c, Some("sfx"));
+    assert_eq!(sound.bus(), Some("sfx"));
+    assert_eq!(audio::mixer(c).buses().collect::<Vec<_>>(), vec!["sfx"]);
+
+    audio::mixer_mut(c).set_volume("sfx", 0.25);
+    audio::mixer_mut(c).set_muted("sfx", true);
+    assert_eq!(audio::mixer(c).volume("sfx"), 0.25);
+    assert!(audio::mixer(c).muted("sfx"));
+    sound.play(c).unwrap();
+    assert!(sound.playing());
+}
+
+#[test]
+fn spatial_listener() {
+    let (c, _e) = &mut tests::make_context();
+
+    assert_eq!(audio::listener(c), audio::Listener::default());
+    let listener = audio::Listener::at([10.0, 0.0, 0.0]);
+    audio::set_listener(c, listener);
+    assert_eq!(audio::listener(c), listener);
+
+    let mut sound = audio::SpatialSource::new(c, "/pew.ogg").unwrap();
+    assert_eq!(sound.attenuation(), audio::Attenuation::default());
+    sound.set_attenuation(audio::Attenuation::None);
+    assert_eq!(sound.attenuation(), audio::Attenuation::None);
+    sound.set_position([12.0, 0.0, 0.0]);
+    sound.play(c).unwrap();
+    assert!(sound.playing());
+}
+
+#[test]
+fn effects() {
+    let (c, _e) = &mut tests::make_context();
+    let muffled = [audio::Effect::LowPass { cutoff: 800.0 }];
+
+    let mut sound = audio::Source::new(c, "/pew.ogg").unwrap();
+    assert!(sound.effects().is_empty());
+    sound.set_effects(&muffled);
+    assert_eq!(sound.effects(), muffled.to_vec());
+
+    sound.set_bus(c, Some("sfx"));
+    audio::mixer_mut(c).set_effects(
+        "sfx",
+        &[audio::Effect::Reverb {
+            delay: std::time::Duration::from_millis(120),
+            decay: 0.4,
+        }],
+    );
+    assert_eq!(audio::mixer(c).effects("sfx").len(), 1);
+    sound.play(c).unwrap();
+    assert!(sound.playing());
+    sound.set_effects(&[]);
+    assert!(sound.effects().is_empty());
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn fades() {
+    let (c, _e) = &mut tests::make_context();
+    let second = std::time::Duration::from_secs(1);
+
+    let mut music = audio::Source::new(c, "/sound.ogg").unwrap();
+    let mut other = audio::StreamingSource::new(c, "/sound.ogg").unwrap();
+    music.play(c).unwrap();
+    music.fade_to(0.25, second);
+    assert!(music.volume() <= 1.0);
+
+    audio::crossfade(c, &mut music, &mut other, second).unwrap();
+    assert!(other.playing());
+    assert!(other.volume() <= 1.0);
+    music.set_volume(0.5);
+    assert_eq!(music.volume(), 0.5);
+}
+
+#[test]
+fn generated_sound() {
+    let (c, _e) = &mut tests::make_context();
+
+    let beep = (0..4410)
+        .map(|i| if i / 50 % 2 == 0 { 8000 } else { -8000 })
+        .collect::<Vec<i16>>();
+    let mut sound = audio::Source::from_pcm(c, &beep, 44100, 1).unwrap();
+    sound.play(c).unwrap();
+    assert!(sound.playing());
+
+    let mut t = 0u32;
+    let mut synth = audio::StreamingSource::from_generator(c, 44100, 1, move |buffer| {
+        for sample in buffer.iter_mut() {
+            *sample = if t / 50 % 2 == 0 { 8000 } else { -8000 };
+            t += 1;
+        }
+        buffer.len()
+    })
+    .unwrap();
+    synth.play(c).unwrap();
+    assert!(synth.playing());
+}
+
+#[test]
+fn audio_formats() {
+    let (c, _e) = &mut tests::make_context();
+    let data = audio::SoundData::new(c, "/sound.ogg").unwrap();
+    assert_eq!(data.format(), Some(audio::AudioFormat::Vorbis));
+    assert!(audio::Source::from_data(c, data).is_ok());
+    assert!(audio::Source::new(c, "/player.png").is_err());
+}
+
+#[test]
+fn input_devices() {
+    // Test machines may well not have a microphone, so this only checks
+    // that whatever is there can be looked at.
+    for device in audio::InputDevice::list().unwrap_or_default() {
+        assert!(!device.name().is_empty());
+    }
+    if let Some(device) = audio::InputDevice::default_device() {
+        if let Ok(mut recording) = device.record(std::time::Duration::from_secs(1)) {
+            assert!(recording.channels() > 0);
+            let samples = recording.take_samples();
+            let second = recording.sample_rate() as usize * usize::from(recording.channels());
+            assert!(samples.len() <= second);
+        }
+    }
+}
+
+#[test]
+fn finished() {
+    let (c, _e) = &mut tests::make_context();
+    let mut sound = audio::Source::new(c, "/pew.ogg").unwrap();
+    assert!(!sound.finished());
+    sound.play(c).unwrap();
+    assert!(!sound.finished());
+    sound.stop(c).unwrap();
+    assert!(!sound.finished());
+}
+
+#[test]
+fn sound_pool() {
+    let (c, _e) = &mut tests::make_context();
+    let mut pool = audio::SoundPool::new(c, "/pew.ogg", 2).unwrap();
+    assert_eq!(pool.max_voices(), 2);
+    assert_eq!(pool.playing(), 0);
+    for _ in 0..5 {
+        pool.play(c).unwrap();
+    }
+    assert!(pool.playing() <= 2);
+
+    // Lowering the cap cuts off the extra instances on the next play.
+    pool.set_max_voices(1);
+    pool.play(c).unwrap();
+    assert!(pool.playing() <= 1);
+    pool.stop_all();
+    assert_eq!(pool.playing(), 0);
+
+    let pool = audio::SoundPool::new(c, "/pew.ogg", 0).unwrap();
+    assert_eq!(pool.max_voices(), 1);
+}
+
+#[test]
+fn output_devices() {
+    let (c, _e) = &mut tests::make_context();
+    let current = audio::output_device(c).map(str::to_string);
+    assert!(current.is_some());
+    if let Ok(devices) = audio::output_devices() {
+        if let Some(device) = devices.first() {
+            audio::set_output_device(c, Some(device)).unwrap();
+            assert_eq!(audio::output_device(c), Some(device.as_str()));
+            let mut sound = audio::Source::new(c, "/pew.ogg").unwrap();
+            sound.play(c).unwrap();
+        }
+    }
+    audio::set_output_device(c, None).unwrap();
+}
+
+#[test]
+fn analyzers() {
+    let (c, _e) = &mut tests::make_context();
+    let analyzer = audio::Analyzer::new(16);
+    let mut sound = audio::Source::new(c, "/sound.ogg").unwrap();
+    sound.set_analyzer(Some(analyzer.clone()));
+    sound.play(c).unwrap();
+    assert_eq!(analyzer.spectrum().len(), 16);
+    assert!(analyzer.rms() >= 0.0);
+
+    let music = audio::Analyzer::new(4);
+    audio::mixer_mut(c).set_analyzer("music", Some(music.clone()));
+    sound.set_bus(c, Some("music"));
+    sound.play(c).unwrap();
+    assert_eq!(music.bands(), 4);
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn pan() {
+    let (c, _e) = &mut tests::make_context();
+    let mut sound = audio::Source::new(c, "/pew.ogg").unwrap();
+    assert_eq!(sound.pan(), 0.0);
+    sound.set_pan(-0.5);
+    assert_eq!(sound.pan(), -0.5);
+    sound.set_pan(3.0);
+    assert_eq!(sound.pan(), 1.0);
+    sound.play(c).unwrap();
+    assert!(sound.playing());
+}
+
+#[test]
+fn speed() {
+    let (c, _e) = &mut tests::make_context();
+    let mut sound = audio::Source::new(c, "/sound.ogg").unwrap();
+    sound.set_speed(0.5, true);
+    sound.play(c).unwrap();
+    assert!(sound.playing());
+    sound.set_speed(2.0, false);
+    sound.play(c).unwrap();
+    assert!(sound.playing());
+}
+
+#[test]
+fn custom_backend() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Counts the samples it's given instead of playing them.
+    struct Counter(Arc<AtomicUsize>);
+
+    impl audio::Voice for Counter {
+        fn append(&self, sound: audio::Sound) {
+            assert_eq!(sound.channels(), 2);
+            assert_eq!(sound.sample_rate(), 8000);
+            let _ = self.0.fetch_add(sound.count(), Ordering::SeqCst);
+        }
+        fn play(&self) {}
+        fn pause(&self) {}
+        fn is_paused(&self) -> bool {
+            false
+        }
+        fn empty(&self) -> bool {
+            true
+        }
+        fn detach(self: Box<Self>) {}
+    }
+
+    struct CountingBackend(Arc<AtomicUsize>);
+
+    impl audio::AudioContext for CountingBackend {
+        fn new_voice(&self) -> GameResult<Box<dyn audio::Voice>> {
+            Ok(Box::new(Counter(self.0.clone())))
+        }
+    }
+
+    let (c, _e) = &mut tests::make_context();
+    let samples = Arc::new(AtomicUsize::new(0));
+    audio::set_backend(c, Box::new(CountingBackend(samples.clone())));
+    let mut sound = audio::Source::from_pcm(c, &[1, 2, 3, 4], 8000, 1).unwrap();
+    sound.play(c).unwrap();
+    // Mono sounds come out in stereo, ready for panning.
+    assert_eq!(samples.load(Ordering::SeqCst), 8);
+    assert!(sound.finished());
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn master_volume_and_pause_all() {
+    let (c, _e) = &mut tests::make_context();
+    assert_eq!(audio::master_volume(c), 1.0);
+    audio::set_master_volume(c, 0.25);
+    assert_eq!(audio::master_volume(c), 0.25);
+
+    let mut sound = audio::Source::new(c, "/pew.ogg").unwrap();
+    sound.play(c).unwrap();
+    audio::pause_all(c);
+    assert!(audio::all_paused(c));
+    // Sounds keep their own pause state.
+    assert!(!sound.paused());
+    audio::resume_all(c);
+    assert!(!audio::all_paused(c));
+}
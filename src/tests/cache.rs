@@ -0,0 +1,37 @@
+use crate::tests;
+use crate::*;
+
+#[test]
+fn cached_assets_are_loaded_once() {
+    let (c, _e) = &mut tests::make_context();
+    assert_eq!(cache::memory(c).total(), 0);
+
+    let image = graphics::load_cached_image(c, "/player.png").unwrap();
+    let again = graphics::load_cached_image(c, "/player.png").unwrap();
+    assert_eq!(image, again);
+    let font = graphics::load_cached_font(c, "/Tangerine_Regular.ttf").unwrap();
+    assert_eq!(
+        graphics::load_cached_font(c, "/Tangerine_Regular.ttf").unwrap(),
+        font
+    );
+    #[cfg(feature = "audio")]
+    let _sound = audio::load_cached_sound_data(c, "/pew.ogg").unwrap();
+
+    let memory = cache::memory(c);
+    assert_eq!(
+        memory.images,
+        usize::from(image.width()) * usize::from(image.height()) * 4
+    );
+    assert!(memory.fonts > 0);
+    #[cfg(feature = "audio")]
+    assert!(memory.sounds > 0);
+
+    assert!(cache::invalidate(c, "/player.png"));
+    assert!(!cache::contains(c, "/player.png"));
+    assert!(!cache::invalidate(c, "/player.png"));
+    let reloaded = graphics::load_cached_image(c, "/player.png").unwrap();
+    assert_ne!(image, reloaded);
+
+    cache::clear(c);
+    assert_eq!(cache::memory(c), cache::CacheMemory::default());
+}
@@ -0,0 +1,261 @@
+use crate::event::winit_event::{ElementState, Event, KeyboardInput, WindowEvent};
+use crate::input::keyboard::{self, KeyCode};
+use crate::tests;
+use crate::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+
+#[derive(Default)]
+struct Counter {
+    updates: usize,
+    draws: usize,
+    text: String,
+    /// Updates that saw space just pressed.
+    space_presses: usize,
+}
+
+impl event::EventHandler for Counter {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        self.updates += 1;
+        if keyboard::is_key_just_pressed(ctx, KeyCode::Space) {
+            self.space_presses += 1;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        self.draws += 1;
+        graphics::present(ctx)
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        self.text.push(character);
+    }
+}
+
+#[test]
+#[allow(unsafe_code)]
+fn step_runs_frames() {
+    let (c, _e) = &mut tests::make_context();
+    let mut counter = Counter::default();
+    let dt = Duration::from_millis(20);
+
+    let typed = Event::WindowEvent {
+        window_id: unsafe { winit::window::WindowId::dummy() },
+        event: WindowEvent::ReceivedCharacter('g'),
+    };
+    event::step(c, &mut counter, vec![typed], dt).unwrap();
+    event::step(c, &mut counter, Vec::new(), dt).unwrap();
+
+    assert_eq!(counter.updates, 2);
+    assert_eq!(counter.draws, 2);
+    assert_eq!(counter.text, "g");
+    assert_eq!(timer::delta(c), dt);
+}
+
+#[test]
+#[allow(unsafe_code)]
+fn event_filters() {
+    let (c, _e) = &mut tests::make_context();
+    let mut counter = Counter::default();
+    let typed = |ch| Event::WindowEvent {
+        window_id: unsafe { winit::window::WindowId::dummy() },
+        event: WindowEvent::ReceivedCharacter(ch),
+    };
+
+    let shout = event::add_event_filter(c, |_ctx, event| {
+        if let Event::WindowEvent {
+            event: WindowEvent::ReceivedCharacter(ch),
+            ..
+        } = event
+        {
+            *ch = ch.to_ascii_uppercase();
+        }
+        false
+    });
+    let _ = event::add_event_filter(c, |_ctx, event| {
+        matches!(
+            event,
+            Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter('X'),
+                ..
+            }
+        )
+    });
+    let events = vec![typed('g'), typed('x'), typed('z')];
+    event::step(c, &mut counter, events, Duration::from_millis(20)).unwrap();
+    assert_eq!(counter.text, "GZ");
+
+    event::remove_event_filter(c, shout);
+    event::step(c, &mut counter, vec![typed('g')], Duration::from_millis(20)).unwrap();
+    assert_eq!(counter.text, "GZg");
+}
+
+#[allow(unsafe_code, deprecated)]
+fn key_event(keycode: KeyCode, state: ElementState) -> Event<'static, ()> {
+    Event::WindowEvent {
+        window_id: unsafe { winit::window::WindowId::dummy() },
+        event: WindowEvent::KeyboardInput {
+            device_id: unsafe { winit::event::DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(keycode),
+                modifiers: Default::default(),
+            },
+            is_synthetic: false,
+        },
+    }
+}
+
+#[test]
+fn fixed_updates_per_frame() {
+    let (c, _e) = &mut tests::make_context();
+    let mut counter = Counter::default();
+    let ms = Duration::from_millis;
+    timer::set_fixed_update_rate(c, Some(100));
+
+    // 35 ms is three 10 ms updates, with 5 ms left over.
+    event::step(c, &mut counter, Vec::new(), ms(35)).unwrap();
+    assert_eq!(counter.updates, 3);
+    assert_eq!(timer::remaining_update_time(c), ms(5));
+
+    // Not enough for another one yet, but the frame is still drawn.
+    event::step(c, &mut counter, Vec::new(), ms(4)).unwrap();
+    assert_eq!(counter.updates, 3);
+    assert_eq!(counter.draws, 2);
+
+    event::step(c, &mut counter, Vec::new(), ms(2)).unwrap();
+    assert_eq!(counter.updates, 4);
+    assert_eq!(timer::remaining_update_time(c), ms(1));
+
+    // A frame of exactly one tick is one update.
+    event::step(c, &mut counter, Vec::new(), ms(9)).unwrap();
+    assert_eq!(counter.updates, 5);
+    assert_eq!(timer::remaining_update_time(c), ms(0));
+    event::step(c, &mut counter, Vec::new(), ms(10)).unwrap();
+    assert_eq!(counter.updates, 6);
+
+    timer::set_fixed_update_rate(c, None);
+    event::step(c, &mut counter, Vec::new(), ms(35)).unwrap();
+    assert_eq!(counter.updates, 7);
+}
+
+#[test]
+fn fixed_update_backlog_is_dropped() {
+    let (c, _e) = &mut tests::make_context();
+    let mut counter = Counter::default();
+    let ms = Duration::from_millis;
+    timer::set_fixed_update_rate(c, Some(100));
+
+    // A 200 ms hitch is twenty updates' worth, but only so many are run
+    // and the rest of the time is thrown away.
+    event::step(c, &mut counter, Vec::new(), ms(200)).unwrap();
+    assert_eq!(counter.updates, timer::MAX_FIXED_UPDATES_PER_FRAME as usize);
+    assert!(timer::remaining_update_time(c) <= ms(10));
+
+    event::step(c, &mut counter, Vec::new(), ms(1)).unwrap();
+    assert_eq!(
+        counter.updates,
+        timer::MAX_FIXED_UPDATES_PER_FRAME as usize + 1
+    );
+    assert!(timer::remaining_update_time(c) < ms(10));
+
+    // Just over one update too many keeps that update for the next
+    // frame, rather than running it or dropping it.
+    let mut counter = Counter::default();
+    timer::set_fixed_update_rate(c, Some(100));
+    event::step(c, &mut counter, Vec::new(), ms(65)).unwrap();
+    assert_eq!(counter.updates, timer::MAX_FIXED_UPDATES_PER_FRAME as usize);
+    assert_eq!(timer::remaining_update_time(c), ms(10));
+}
+
+#[test]
+fn fixed_updates_see_presses_from_frames_without_updates() {
+    let (c, _e) = &mut tests::make_context();
+    let mut counter = Counter::default();
+    let ms = Duration::from_millis;
+    timer::set_fixed_update_rate(c, Some(100));
+
+    let pressed = key_event(KeyCode::Space, ElementState::Pressed);
+    event::step(c, &mut counter, vec![pressed], ms(4)).unwrap();
+    event::step(c, &mut counter, Vec::new(), ms(4)).unwrap();
+    assert_eq!(counter.updates, 0);
+
+    // The first update after the press sees it, and only that one.
+    event::step(c, &mut counter, Vec::new(), ms(7)).unwrap();
+    assert_eq!(counter.updates, 1);
+    assert_eq!(counter.space_presses, 1);
+    event::step(c, &mut counter, Vec::new(), ms(10)).unwrap();
+    assert_eq!(counter.updates, 2);
+    assert_eq!(counter.space_presses, 1);
+    assert!(keyboard::is_key_pressed(c, KeyCode::Space));
+}
+
+/// Records the messages of the panics it's told about.
+#[derive(Default)]
+struct Crashy {
+    go_on: bool,
+    messages: Vec<Option<String>>,
+}
+
+impl event::EventHandler for Crashy {
+    fn update(&mut self, _ctx: &mut Context) -> GameResult {
+        Ok(())
+    }
+
+    fn draw(&mut self, _ctx: &mut Context) -> GameResult {
+        Ok(())
+    }
+
+    fn on_panic(&mut self, _ctx: &mut Context, report: &event::PanicReport) -> bool {
+        self.messages.push(report.message.clone());
+        self.go_on
+    }
+}
+
+#[test]
+fn caught_panics() {
+    let (c, _e) = &mut tests::make_context();
+    let mut crashy = Crashy {
+        go_on: true,
+        ..Crashy::default()
+    };
+    event::set_catch_panics(c, true);
+
+    let result = event::catching_panics(c, &mut crashy, |_, _| 1);
+    assert_eq!(result, Some(1));
+    let result: Option<()> = event::catching_panics(c, &mut crashy, |_, _| panic!("boom"));
+    assert_eq!(result, None);
+    let result: Option<()> = event::catching_panics(c, &mut crashy, |_, _| panic!("boom {}", 2));
+    assert_eq!(result, None);
+    let result: Option<()> = event::catching_panics(c, &mut crashy, |_, _| panic::panic_any(3));
+    assert_eq!(result, None);
+    assert_eq!(
+        crashy.messages,
+        vec![Some("boom".to_owned()), Some("boom 2".to_owned()), None]
+    );
+}
+
+#[test]
+fn panics_carry_on_unless_handled() {
+    let (c, _e) = &mut tests::make_context();
+    let mut crashy = Crashy::default();
+
+    // Caught, but on_panic() says not to go on.
+    event::set_catch_panics(c, true);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        event::catching_panics(c, &mut crashy, |_, _| panic!("boom"))
+    }));
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom"));
+    assert_eq!(crashy.messages, vec![Some("boom".to_owned())]);
+
+    // Not caught at all.
+    event::set_catch_panics(c, false);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        event::catching_panics(c, &mut crashy, |_, _| panic!("boom"))
+    }));
+    assert!(result.is_err());
+    assert_eq!(crashy.messages.len(), 1);
+}
@@ -17,3 +17,99 @@ fn filesystem_create_correct_paths() {
     println!("Userdata path: {:?}", userdata_path);
     assert!(userdata_path.is_file());
 }
+
+/// A source with a single file in memory, like a test fixture.
+#[derive(Debug)]
+struct Fixture;
+
+impl vfs::VFS for Fixture {
+    fn open_options(
+        &self,
+        path: &std::path::Path,
+        _options: filesystem::OpenOptions,
+    ) -> GameResult<Box<dyn vfs::VFile>> {
+        if path == std::path::Path::new("/fixture.txt") {
+            Ok(Box::new(std::io::Cursor::new(b"fixture".to_vec())))
+        } else {
+            Err(GameError::FilesystemError("Not a fixture".to_string()))
+        }
+    }
+
+    fn mkdir(&self, _path: &std::path::Path) -> GameResult {
+        Ok(())
+    }
+
+    fn rm(&self, _path: &std::path::Path) -> GameResult {
+        Ok(())
+    }
+
+    fn rmrf(&self, _path: &std::path::Path) -> GameResult {
+        Ok(())
+    }
+
+    fn exists(&self, path: &std::path::Path) -> bool {
+        path == std::path::Path::new("/fixture.txt")
+    }
+
+    fn metadata(&self, _path: &std::path::Path) -> GameResult<Box<dyn vfs::VMetadata>> {
+        Err(GameError::FilesystemError("No metadata".to_string()))
+    }
+
+    fn read_dir(
+        &self,
+        _path: &std::path::Path,
+    ) -> GameResult<Box<dyn Iterator<Item = GameResult<std::path::PathBuf>>>> {
+        Ok(Box::new(std::iter::once(Ok("/fixture.txt".into()))))
+    }
+
+    fn to_path_buf(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+#[test]
+fn filesystem_mount_vfs() {
+    use std::io::Read;
+    let (c, _e) = &mut tests::make_context();
+    filesystem::mount_vfs(c, Box::new(Fixture), true);
+
+    assert!(filesystem::exists(c, "/fixture.txt"));
+    let mut contents = String::new();
+    let _ = filesystem::open(c, "/fixture.txt")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, "fixture");
+    // Read-only sources can't be written to, even if they'd let you.
+    let options = filesystem::OpenOptions::new().write(true);
+    assert!(filesystem::open_options(c, "/fixture.txt", options).is_err());
+}
+
+#[test]
+fn preloader_loads_into_cache() {
+    let (c, _e) = &mut tests::make_context();
+    let mut preloader = filesystem::Preloader::new(vec![
+        "/player.png",
+        "/sound.ogg",
+        "/LiberationMono-Regular.ttf",
+        "/does_not_exist.png",
+    ]);
+    assert!(!preloader.is_done());
+    for _ in 0..1000 {
+        if preloader.is_done() {
+            break;
+        }
+        let _progress = preloader.update(c);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert!(preloader.is_done());
+    assert!((preloader.progress() - 1.0).abs() < f32::EPSILON);
+    assert!(cache::contains(c, "/player.png"));
+    assert!(cache::contains(c, "/sound.ogg"));
+    assert!(cache::contains(c, "/LiberationMono-Regular.ttf"));
+    assert_eq!(preloader.errors().len(), 1);
+    assert_eq!(
+        preloader.errors()[0].0,
+        std::path::PathBuf::from("/does_not_exist.png")
+    );
+}
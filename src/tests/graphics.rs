@@ -118,6 +118,23 @@ fn save_screenshot() {
 //     save_screenshot_test(c);
 // }
 
+#[test]
+fn window_screen_coords_round_trip() {
+    let (c, _e) = &mut tests::make_context();
+    let (w, h) = graphics::drawable_size(c);
+    graphics::set_screen_coordinates(c, graphics::Rect::new(-10.0, 20.0, w / 2.0, h / 2.0))
+        .unwrap();
+
+    let top_left = graphics::window_to_screen_coords(c, [0.0, 0.0]);
+    assert!((top_left.x - -10.0).abs() < 0.01 && (top_left.y - 20.0).abs() < 0.01);
+    let center = graphics::window_to_screen_coords(c, [w / 2.0, h / 2.0]);
+    assert!((center.x - (w / 4.0 - 10.0)).abs() < 0.01);
+    assert!((center.y - (h / 4.0 + 20.0)).abs() < 0.01);
+
+    let back = graphics::screen_to_window_coords(c, center);
+    assert!((back.x - w / 2.0).abs() < 0.01 && (back.y - h / 2.0).abs() < 0.01);
+}
+
 #[test]
 fn load_images() {
     let (c, _e) = &mut tests::make_context();
@@ -166,3 +183,489 @@ fn sanity_check_window_sizes() {
         event::process_event(&mut c, &mut event);
     });
 }
+
+#[test]
+fn set_vsync_updates_conf() {
+    let (c, _e) = &mut tests::make_context();
+    assert!(graphics::vsync(c));
+    // Not every driver lets us change this, but if it says it did then
+    // the setting should stick.
+    match graphics::set_vsync(c, false) {
+        Ok(()) => assert!(!graphics::vsync(c)),
+        Err(_) => assert!(graphics::vsync(c)),
+    }
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn runtime_window_setters_update_conf() {
+    let (c, _e) = &mut tests::make_context();
+    let original = c.conf.window_mode;
+    graphics::set_window_decorations(c, false).unwrap();
+    graphics::set_always_on_top(c, true).unwrap();
+    graphics::set_min_window_size(c, 320.0, 240.0).unwrap();
+    let mode = c.conf.window_mode;
+    assert!(mode.borderless);
+    assert!(mode.always_on_top);
+    assert_eq!((mode.min_width, mode.min_height), (320.0, 240.0));
+
+    graphics::set_window_decorations(c, true).unwrap();
+    graphics::set_always_on_top(c, false).unwrap();
+    assert_eq!(c.conf.window_mode, original.min_dimensions(320.0, 240.0));
+}
+
+#[test]
+fn window_icon_from_image() {
+    let (c, _e) = &mut tests::make_context();
+    let icon = graphics::Image::solid(c, 32, Color::new(1.0, 0.0, 1.0, 1.0)).unwrap();
+    graphics::set_window_icon_from_image(c, Some(&icon)).unwrap();
+    graphics::set_window_icon_from_image(c, None).unwrap();
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn logical_dpi_policy_sizes() {
+    let cb = ContextBuilder::new("ggez_unit_tests", "ggez")
+        .window_setup(conf::WindowSetup::default().dpi_policy(conf::DpiPolicy::Logical))
+        .window_mode(conf::WindowMode::default().dimensions(640.0, 480.0));
+    let (c, _e) = &mut tests::make_context_from_contextbuilder(cb);
+
+    // The framebuffer is full resolution, but we draw in logical pixels.
+    let scale_factor = graphics::scale_factor(c);
+    let (w, h) = graphics::drawable_size(c);
+    assert!((w - 640.0 * scale_factor).abs() < 1.0);
+    assert!((h - 480.0 * scale_factor).abs() < 1.0);
+    let screen = graphics::screen_coordinates(c);
+    assert_eq!((screen.w, screen.h), (640.0, 480.0));
+
+    let center = graphics::window_to_screen_coords(c, [320.0, 240.0]);
+    assert!((center.x - 320.0).abs() < 0.01 && (center.y - 240.0).abs() < 0.01);
+}
+
+#[test]
+fn screenshot_region_and_async() {
+    let (c, _e) = &mut tests::make_context();
+
+    graphics::clear(c, Color::new(1.0, 0.0, 0.0, 1.0));
+    let rect = graphics::Mesh::new_rectangle(
+        c,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(0.0, 0.0, 20.0, 10.0),
+        Color::new(0.0, 0.0, 1.0, 1.0),
+    )
+    .unwrap();
+    graphics::draw(c, &rect, graphics::DrawParam::new()).unwrap();
+
+    // The region covers the top-left rectangle and a strip of background
+    // to its right.
+    let region = graphics::Rect::new(10.0, 0.0, 20.0, 5.0);
+    let image = graphics::screenshot_region(c, region).unwrap();
+    assert_eq!((image.width, image.height), (20, 5));
+    let rgba_buf = image.to_rgba8(c).unwrap();
+    assert_eq!(
+        (0, 0, 255, 255),
+        get_rgba_sample(&rgba_buf, 20, Vec2::new(5.0, 2.0))
+    );
+    assert_eq!(
+        (255, 0, 0, 255),
+        get_rgba_sample(&rgba_buf, 20, Vec2::new(15.0, 2.0))
+    );
+
+    let pending = graphics::screenshot_async(c, Some(region)).unwrap();
+    assert!(!pending.is_ready(c));
+    graphics::present(c).unwrap();
+    assert!(pending.is_ready(c));
+    assert_eq!(rgba_buf, pending.to_rgba8(c).unwrap());
+
+    // Download buffers are shared out one per pending screenshot, and
+    // reused once those are dropped.
+    let size = 20 * 5 * 4;
+    let second = graphics::screenshot_async(c, Some(region)).unwrap();
+    assert_eq!(c.gfx_context.screenshot_buffers[&size].len(), 2);
+    drop(pending);
+    drop(second);
+    let _third = graphics::screenshot_async(c, Some(region)).unwrap();
+    assert_eq!(c.gfx_context.screenshot_buffers[&size].len(), 2);
+    let _other = graphics::screenshot_async(c, None).unwrap();
+    assert_eq!(c.gfx_context.screenshot_buffers[&size].len(), 1);
+
+    assert!(graphics::screenshot_region(c, graphics::Rect::new(-50.0, 0.0, 10.0, 10.0)).is_err());
+}
+
+#[test]
+fn record_image_sequence() {
+    let (c, _e) = &mut tests::make_context();
+
+    let mut recorder = graphics::Recorder::image_sequence("/recorder_test");
+    for _ in 0..3 {
+        graphics::clear(c, Color::new(0.0, 1.0, 0.0, 1.0));
+        recorder.capture(c).unwrap();
+        graphics::present(c).unwrap();
+    }
+    assert_eq!(recorder.finish(c).unwrap(), 3);
+    assert!(filesystem::exists(c, "/recorder_test/frame_000000.png"));
+    assert!(filesystem::exists(c, "/recorder_test/frame_000002.png"));
+}
+
+/// Makes a LUT strip of the given size, with each color's levels given
+/// by `level`.
+fn color_lut_strip(c: &mut Context, n: u16, level: impl Fn(u16) -> u8) -> graphics::Image {
+    let mut rgba = Vec::new();
+    for g in 0..n {
+        for b in 0..n {
+            for r in 0..n {
+                rgba.extend_from_slice(&[level(r), level(g), level(b), 255]);
+            }
+        }
+    }
+    graphics::Image::from_rgba8(c, n * n, n, &rgba).unwrap()
+}
+
+/// Runs the final pass into a canvas instead of onto the window, which
+/// can't be read back, and returns the pixel in the middle.
+fn graded_pixel(c: &mut Context) -> (u8, u8, u8, u8) {
+    let canvas = graphics::Canvas::with_window_size(c).unwrap();
+    let gfx = &mut c.gfx_context;
+    let mut final_pass = std::mem::take(&mut gfx.final_pass);
+    let result = final_pass.draw_scene(gfx, canvas.target().clone());
+    gfx.final_pass = final_pass;
+    result.unwrap();
+    let rgba = canvas.to_rgba8(c).unwrap();
+    let width = usize::from(canvas.width());
+    let middle = Vec2::new(f32::from(canvas.width()), f32::from(canvas.height())) / 2.0;
+    get_rgba_sample(&rgba, width, middle)
+}
+
+#[test]
+fn color_lut() {
+    let (c, _e) = &mut tests::make_context();
+
+    // Strips of size 4, which leave colors as they are or invert them.
+    let n = 4;
+    let identity = color_lut_strip(c, n, |v| (v * 255 / (n - 1)) as u8);
+    let inverse = color_lut_strip(c, n, |v| 255 - (v * 255 / (n - 1)) as u8);
+    let wrong_size = graphics::Image::solid(c, 4, Color::WHITE).unwrap();
+
+    assert!(graphics::set_color_lut(c, Some(&wrong_size)).is_err());
+    assert!(!graphics::has_color_lut(c));
+    graphics::set_color_lut(c, Some(&identity)).unwrap();
+    assert!(graphics::has_color_lut(c));
+
+    // Levels that fall on the LUT's own entries, so filtering between
+    // them hardly matters.
+    graphics::clear(c, Color::new(170.0 / 255.0, 85.0 / 255.0, 0.0, 1.0));
+    // Screenshots are of the scene before grading.
+    let rgba = graphics::screenshot(c).unwrap().to_rgba8(c).unwrap();
+    let width = graphics::drawable_size(c).0 as usize;
+    let scene = get_rgba_sample(&rgba, width, Vec2::new(10.0, 10.0));
+    let close = |a: u8, b: u8| (i16::from(a) - i16::from(b)).abs() <= 4;
+
+    let graded = graded_pixel(c);
+    assert!(
+        close(graded.0, scene.0) && close(graded.1, scene.1) && close(graded.2, scene.2),
+        "{:?} graded to {:?} by the identity LUT",
+        scene,
+        graded
+    );
+
+    graphics::set_color_lut(c, Some(&inverse)).unwrap();
+    let graded = graded_pixel(c);
+    assert!(
+        close(graded.0, 255 - scene.0)
+            && close(graded.1, 255 - scene.1)
+            && close(graded.2, 255 - scene.2),
+        "{:?} graded to {:?} by the inverting LUT",
+        scene,
+        graded
+    );
+    graphics::present(c).unwrap();
+
+    graphics::set_color_lut(c, None).unwrap();
+    graphics::present(c).unwrap();
+}
+
+#[test]
+fn hdr_scene_target() {
+    let (c, _e) = &mut tests::make_context();
+
+    assert_eq!(graphics::hdr(c), None);
+    let settings = graphics::HdrSettings::default()
+        .tonemapper(graphics::Tonemapper::Reinhard)
+        .exposure(0.5);
+    graphics::set_hdr(c, Some(settings)).unwrap();
+    assert_eq!(graphics::hdr(c), Some(settings));
+
+    let canvas = graphics::Canvas::new_hdr(c, 16, 16, conf::NumSamples::One).unwrap();
+    graphics::set_canvas(c, Some(&canvas));
+    graphics::clear(c, Color::new(4.0, 2.0, 1.0, 1.0));
+    graphics::set_canvas(c, None);
+    graphics::clear(c, Color::new(4.0, 2.0, 1.0, 1.0));
+    assert!(graphics::screenshot(c).is_err());
+    graphics::present(c).unwrap();
+
+    graphics::set_hdr(c, None).unwrap();
+    assert_eq!(graphics::hdr(c), None);
+    graphics::clear(c, Color::WHITE);
+    graphics::present(c).unwrap();
+    assert!(graphics::screenshot(c).is_ok());
+}
+
+#[test]
+fn lit_sprite() {
+    let (c, _e) = &mut tests::make_context();
+
+    let image = graphics::Image::solid(c, 8, Color::WHITE).unwrap();
+    let flat = graphics::Image::from_rgba8(c, 8, 8, &[128, 128, 255, 255].repeat(64)).unwrap();
+    let small = graphics::Image::solid(c, 4, Color::WHITE).unwrap();
+    assert!(graphics::LitSprite::new(c, image.clone(), &small).is_err());
+    let sprite = graphics::LitSprite::new(c, image, &flat).unwrap();
+
+    let too_many = vec![graphics::PointLight::new([0.0, 0.0], 10.0); graphics::MAX_LIGHTS + 1];
+    assert!(graphics::set_lighting(c, Color::BLACK, &too_many).is_err());
+
+    let light =
+        graphics::PointLight::new([100.0, 100.0], 200.0).color(Color::new(1.0, 0.5, 0.0, 1.0));
+    graphics::set_lighting(c, Color::BLACK, &[light]).unwrap();
+    let (ambient, lights) = graphics::lighting(c);
+    assert_eq!(ambient, Color::BLACK);
+    assert_eq!(lights, &[light]);
+
+    graphics::clear(c, Color::BLACK);
+    graphics::draw(c, &sprite, graphics::DrawParam::new().dest([96.0, 96.0])).unwrap();
+    graphics::present(c).unwrap();
+}
+
+#[test]
+fn post_effects() {
+    use crate::graphics::effects::{Bloom, Blur, Effect, EffectChain, Vignette};
+
+    let (c, _e) = &mut tests::make_context();
+    let format = graphics::get_window_color_format(c);
+    let source = graphics::Canvas::new(c, 32, 32, conf::NumSamples::One, format).unwrap();
+    let dest = graphics::Canvas::new(c, 32, 32, conf::NumSamples::One, format).unwrap();
+
+    // A white square in the middle of a black canvas.
+    graphics::set_canvas(c, Some(&source));
+    graphics::clear(c, Color::BLACK);
+    let square = graphics::Mesh::new_rectangle(
+        c,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(12.0, 12.0, 8.0, 8.0),
+        Color::WHITE,
+    )
+    .unwrap();
+    graphics::draw(c, &square, graphics::DrawParam::new()).unwrap();
+    graphics::set_canvas(c, None);
+    let screen_coords = graphics::screen_coordinates(c);
+
+    let mut blur = Blur::new(3.0);
+    blur.apply(c, &source, &dest).unwrap();
+    let rgba = dest.to_rgba8(c).unwrap();
+    // The blur spreads the square out past its edge...
+    assert!(get_rgba_sample(&rgba, 32, Vec2::new(10.0, 16.0)).0 > 0);
+    // ...but leaves the far corners black.
+    assert_eq!(get_rgba_sample(&rgba, 32, Vec2::new(0.0, 0.0)).0, 0);
+
+    let mut chain = EffectChain::new()
+        .with(Bloom::new(0.5, 1.0))
+        .with(Vignette::new(1.0));
+    assert_eq!(chain.len(), 2);
+    chain.apply(c, &source, &dest).unwrap();
+    let rgba = dest.to_rgba8(c).unwrap();
+    assert!(get_rgba_sample(&rgba, 32, Vec2::new(16.0, 16.0)).0 > 200);
+
+    // Effects leave the drawing state as it was.
+    assert_eq!(graphics::screen_coordinates(c), screen_coords);
+    graphics::present(c).unwrap();
+}
+
+#[test]
+fn transform_stack() {
+    let (c, _e) = &mut tests::make_context();
+    let identity = glam::Mat4::IDENTITY;
+    let parent = glam::Mat4::from_translation(glam::vec3(100.0, 50.0, 0.0));
+    let child = glam::Mat4::from_scale(glam::vec3(2.0, 2.0, 1.0));
+
+    assert_eq!(glam::Mat4::from(graphics::transform(c)), identity);
+    graphics::push_transform(c, parent).unwrap();
+    graphics::push_transform(c, child).unwrap();
+    assert_eq!(graphics::transform_stack_depth(c), 2);
+    assert_eq!(glam::Mat4::from(graphics::transform(c)), parent * child);
+
+    graphics::pop_transform(c).unwrap();
+    assert_eq!(glam::Mat4::from(graphics::transform(c)), parent);
+    graphics::pop_transform(c).unwrap();
+    assert!(graphics::pop_transform(c).is_err());
+
+    let result: GameResult = graphics::with_transform(c, parent, |c| {
+        assert_eq!(glam::Mat4::from(graphics::transform(c)), parent);
+        Err(GameError::RenderError("oops".to_string()))
+    });
+    assert!(result.is_err());
+    assert_eq!(graphics::transform_stack_depth(c), 0);
+
+    // Drawing a square at the origin with a translation pushed puts it
+    // somewhere else.
+    graphics::clear(c, Color::BLACK);
+    let square = graphics::Mesh::new_rectangle(
+        c,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(0.0, 0.0, 10.0, 10.0),
+        Color::WHITE,
+    )
+    .unwrap();
+    graphics::with_transform(c, parent, |c| {
+        graphics::draw(c, &square, graphics::DrawParam::new())
+    })
+    .unwrap();
+    let rgba = graphics::screenshot(c).unwrap().to_rgba8(c).unwrap();
+    let width = graphics::drawable_size(c).0 as usize;
+    assert_eq!(get_rgba_sample(&rgba, width, Vec2::new(5.0, 5.0)).0, 0);
+    assert_eq!(get_rgba_sample(&rgba, width, Vec2::new(105.0, 55.0)).0, 255);
+}
+
+#[test]
+fn depth_test() {
+    let (c, _e) = &mut tests::make_context();
+    let width = graphics::drawable_size(c).0 as usize;
+    let square = graphics::Mesh::new_rectangle(
+        c,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(0.0, 0.0, 10.0, 10.0),
+        Color::WHITE,
+    )
+    .unwrap();
+    let draw_squares = |c: &mut Context| {
+        graphics::clear(c, Color::BLACK);
+        let front = graphics::DrawParam::new().color(Color::RED).depth(0.5);
+        let back = graphics::DrawParam::new().color(Color::BLUE).depth(-0.5);
+        graphics::draw(c, &square, front).unwrap();
+        graphics::draw(c, &square, back).unwrap();
+        let rgba = graphics::screenshot(c).unwrap().to_rgba8(c).unwrap();
+        get_rgba_sample(&rgba, width, Vec2::new(5.0, 5.0))
+    };
+
+    // Without depth testing the last one drawn wins...
+    assert!(!graphics::depth_test(c));
+    let (r, _, b, _) = draw_squares(c);
+    assert_eq!((r, b), (0, 255));
+
+    // ...and with it the one in front does.
+    graphics::set_depth_test(c, true).unwrap();
+    assert!(graphics::depth_test(c));
+    let (r, _, b, _) = draw_squares(c);
+    assert_eq!((r, b), (255, 0));
+
+    graphics::set_depth_test(c, false).unwrap();
+    let (r, _, b, _) = draw_squares(c);
+    assert_eq!((r, b), (0, 255));
+}
+
+gfx_defines! {
+    vertex TestInstance {
+        offset: [f32; 2] = "a_Offset",
+        color: [f32; 4] = "a_Tint",
+    }
+}
+
+const INSTANCED_VS: &[u8] = b"#version 150 core
+in vec2 a_Pos;
+in vec2 a_Uv;
+in vec4 a_VertColor;
+in vec2 a_Offset;
+in vec4 a_Tint;
+layout (std140) uniform Globals {
+    mat4 u_MVP;
+};
+out vec4 v_Color;
+void main() {
+    v_Color = a_Tint * a_VertColor;
+    gl_Position = u_MVP * vec4(a_Pos + a_Offset, 0.0, 1.0);
+}
+";
+
+const INSTANCED_FS: &[u8] = b"#version 150 core
+uniform sampler2D t_Texture;
+in vec4 v_Color;
+out vec4 Target0;
+void main() {
+    Target0 = v_Color;
+}
+";
+
+#[test]
+fn instanced() {
+    let (c, _e) = &mut tests::make_context();
+    let square = graphics::Mesh::new_rectangle(
+        c,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(0.0, 0.0, 10.0, 10.0),
+        Color::WHITE,
+    )
+    .unwrap();
+    let mut instanced =
+        graphics::Instanced::<TestInstance>::new(c, square, INSTANCED_VS, INSTANCED_FS).unwrap();
+    instanced.push(TestInstance {
+        offset: [0.0, 0.0],
+        color: [1.0, 0.0, 0.0, 1.0],
+    });
+    instanced.push(TestInstance {
+        offset: [50.0, 0.0],
+        color: [0.0, 0.0, 1.0, 1.0],
+    });
+    assert_eq!(instanced.instances().len(), 2);
+
+    graphics::clear(c, Color::BLACK);
+    instanced.draw(c, graphics::DrawParam::new()).unwrap();
+    let rgba = graphics::screenshot(c).unwrap().to_rgba8(c).unwrap();
+    let width = graphics::drawable_size(c).0 as usize;
+    let first = get_rgba_sample(&rgba, width, Vec2::new(5.0, 5.0));
+    let second = get_rgba_sample(&rgba, width, Vec2::new(55.0, 5.0));
+    assert_eq!((first.0, first.2), (255, 0));
+    assert_eq!((second.0, second.2), (0, 255));
+    assert_eq!(get_rgba_sample(&rgba, width, Vec2::new(30.0, 5.0)).0, 0);
+}
+
+#[test]
+fn gradient() {
+    let (c, _e) = &mut tests::make_context();
+    let bar = graphics::Mesh::new_rectangle(
+        c,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(0.0, 0.0, 100.0, 10.0),
+        Color::WHITE,
+    )
+    .unwrap();
+    let fade = graphics::Gradient::linear(
+        c,
+        [0.0, 0.0],
+        [100.0, 0.0],
+        &[(0.0, Color::RED), (1.0, Color::BLUE)],
+    )
+    .unwrap();
+
+    graphics::clear(c, Color::BLACK);
+    // The gradient is in the mesh's own coordinates, so it moves with it.
+    let param = graphics::DrawParam::new().dest([20.0, 20.0]).gradient(fade);
+    graphics::draw(c, &bar, param).unwrap();
+    // Drawing without one afterwards isn't affected.
+    graphics::draw(c, &bar, ([20.0, 40.0],)).unwrap();
+    let rgba = graphics::screenshot(c).unwrap().to_rgba8(c).unwrap();
+    let width = graphics::drawable_size(c).0 as usize;
+    let left = get_rgba_sample(&rgba, width, Vec2::new(22.0, 25.0));
+    let right = get_rgba_sample(&rgba, width, Vec2::new(118.0, 25.0));
+    assert!(left.0 > 200 && left.2 < 50);
+    assert!(right.0 < 50 && right.2 > 200);
+    let plain = get_rgba_sample(&rgba, width, Vec2::new(118.0, 45.0));
+    assert_eq!((plain.0, plain.1, plain.2), (255, 255, 255));
+
+    // Making the same gradient again reuses its ramp.
+    let again = graphics::Gradient::linear(
+        c,
+        [0.0, 0.0],
+        [100.0, 0.0],
+        &[(0.0, Color::RED), (1.0, Color::BLUE)],
+    )
+    .unwrap();
+    assert_eq!(again, fade);
+}
@@ -137,3 +137,29 @@ fn test_mesh_ellipse_tolerance_nonzero() {
         )
         .unwrap();
 }
+
+#[test]
+fn test_mesh_contains_point() {
+    let (mut ctx, _ev) = tests::make_context();
+    let corners = [[0.0, 0.0], [100.0, 0.0], [0.0, 100.0]];
+    let mut mb = graphics::MeshBuilder::new();
+    let _ = mb
+        .polygon(graphics::DrawMode::fill(), &corners, graphics::Color::WHITE)
+        .unwrap();
+
+    // Without its triangles, only the bounding rect is checked.
+    let rough = mb.build(&mut ctx).unwrap();
+    assert_eq!(
+        rough.bounding_rect(),
+        graphics::Rect::new(0.0, 0.0, 100.0, 100.0)
+    );
+    assert!(rough.contains_point([10.0, 10.0]));
+    assert!(rough.contains_point([80.0, 80.0]));
+    assert!(!rough.contains_point([120.0, 10.0]));
+
+    let exact = mb.hit_testable(true).unwrap().build(&mut ctx).unwrap();
+    assert!(exact.contains_point([10.0, 10.0]));
+    assert!(exact.contains_point([40.0, 40.0]));
+    assert!(!exact.contains_point([80.0, 80.0]));
+    assert!(!exact.contains_point([120.0, 10.0]));
+}
@@ -4,14 +4,20 @@ use crate::*;
 use std::env;
 use std::path;
 
+#[cfg(feature = "audio")]
 mod audio;
+mod cache;
 mod conf;
+mod event;
 mod filesystem;
 mod graphics;
 mod mesh;
 mod text;
 
-pub fn make_context_from_contextbuilder(mut cb: ContextBuilder) -> (Context, event::EventLoop<()>) {
+/// Make a `Context` from the given builder, with the `resources` directory added.
+pub fn make_context_from_contextbuilder(
+    mut cb: ContextBuilder,
+) -> (Context, crate::event::EventLoop<()>) {
     if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
         path.push("resources");
@@ -21,7 +27,7 @@ pub fn make_context_from_contextbuilder(mut cb: ContextBuilder) -> (Context, eve
 }
 
 /// Make a basic `Context` with sane defaults.
-pub fn make_context() -> (Context, event::EventLoop<()>) {
+pub fn make_context() -> (Context, crate::event::EventLoop<()>) {
     let cb = ContextBuilder::new("ggez_unit_tests", "ggez");
     make_context_from_contextbuilder(cb)
 }
@@ -57,3 +57,26 @@ fn test_monospace_text_is_actually_monospace() {
     assert_eq!(width2, width3);
     assert_eq!(width3, width4);
 }
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn test_measurer_matches_context() {
+    let (ctx, _ev) = &mut tests::make_context();
+    let font = graphics::Font::new(ctx, "/LiberationMono-Regular.ttf").unwrap();
+
+    let mut text = graphics::Text::new(("Hello There, this wraps", font, 24.0));
+    let _ = text.set_bounds(glam::Vec2::new(100.0, f32::INFINITY), graphics::Align::Left);
+    let measurer = graphics::text_measurer(ctx);
+    let measured = std::thread::spawn(move || {
+        let rect = measurer.dimensions(&text);
+        (rect, text)
+    })
+    .join()
+    .unwrap();
+    let (rect, text) = measured;
+    assert_eq!(rect, text.dimensions(ctx));
+
+    // Without a context at all, using only the default font.
+    let text = graphics::Text::new(("Hello There", graphics::Font::default(), 24.0));
+    assert_eq!(graphics::TextMeasurer::default().width(&text), 123.0);
+}
@@ -11,12 +11,25 @@
 //! in your [`Conf`](../conf/struct.Conf.html) object is generally the best
 //! way to cap your displayed framerate.
 //!
+//! Without vsync, [`set_target_fps()`](fn.set_target_fps.html) has
+//! [`event::run()`](../event/fn.run.html) wait out the rest of each frame
+//! itself, in one of a few [ways](enum.FrameLimiter.html).
+//!
 //! For a more detailed tutorial in how to handle frame timings in games,
 //! see <http://gafferongames.com/game-physics/fix-your-timestep/>
+//!
+//! Rather than looping on [`check_update_time()`](fn.check_update_time.html)
+//! in `update()`, [`set_fixed_update_rate()`](fn.set_fixed_update_rate.html)
+//! has [`event::run()`](../event/fn.run.html) do that itself.
+//!
+//! For things that should happen later or every so often, the
+//! [`Scheduler`](struct.Scheduler.html) fires tags after a delay, at an
+//! interval or in a [`Sequence`](struct.Sequence.html).
 
 use crate::context::Context;
 
 use std::cmp;
+use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::f64;
 use std::thread;
@@ -81,12 +94,48 @@ where
         }
     }
 
+    /// Returns the contents of the buffer from oldest to newest.
+    fn ordered(&self) -> impl Iterator<Item = T> + '_ {
+        let len = self.contents.len();
+        let (start, count) = if self.samples >= len {
+            ((self.head + 1) % len, len)
+        } else {
+            (0, self.head + 1)
+        };
+        (0..count).map(move |i| self.contents[(start + i) % len])
+    }
+
     /// Returns the most recent value in the buffer.
     fn latest(&self) -> T {
         self.contents[self.head]
     }
 }
 
+/// How [`event::run()`](../event/fn.run.html) waits out the rest of a
+/// frame, when there's a [target frame rate](fn.set_target_fps.html).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FrameLimiter {
+    /// Sleeps, which uses the least CPU but can oversleep by a
+    /// millisecond or more, depending on the OS.
+    Sleep,
+    /// Sleeps until shortly before the frame should end, then spins for
+    /// the rest, which is precise and costs a little CPU.
+    SpinSleep,
+    /// Yields to the OS until the frame should end, which is precise but
+    /// keeps a core busy when nothing else wants it.
+    Yield,
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        FrameLimiter::SpinSleep
+    }
+}
+
+/// How long before the end of a frame `FrameLimiter::SpinSleep` stops
+/// sleeping and starts spinning.
+const SPIN_MARGIN: time::Duration = time::Duration::from_millis(2);
+
 /// A structure that contains our time-tracking state.
 #[derive(Debug)]
 pub struct TimeContext {
@@ -95,6 +144,9 @@ pub struct TimeContext {
     frame_durations: LogBuffer<time::Duration>,
     residual_update_dt: time::Duration,
     frame_count: usize,
+    fixed_update_rate: Option<u32>,
+    target_fps: Option<u32>,
+    frame_limiter: FrameLimiter,
 }
 
 /// How many frames we log update times for.
@@ -110,6 +162,9 @@ impl TimeContext {
             frame_durations: LogBuffer::new(TIME_LOG_FRAMES, initial_dt),
             residual_update_dt: time::Duration::from_secs(0),
             frame_count: 0,
+            fixed_update_rate: None,
+            target_fps: None,
+            frame_limiter: FrameLimiter::default(),
         }
     }
 
@@ -121,13 +176,96 @@ impl TimeContext {
     /// It's usually not necessary to call this function yourself,
     /// [`event::run()`](../event/fn.run.html) will do it for you.
     pub fn tick(&mut self) {
-        let now = time::Instant::now();
-        let time_since_last = now - self.last_instant;
-        self.frame_durations.push(time_since_last);
-        self.last_instant = now;
+        let time_since_last = time::Instant::now() - self.last_instant;
+        self.tick_by(time_since_last);
+    }
+
+    /// Records a frame that took `dt`, whatever the clock says, for
+    /// [`event::step()`](../event/fn.step.html).
+    pub(crate) fn tick_by(&mut self, dt: time::Duration) {
+        self.frame_durations.push(dt);
+        self.last_instant = time::Instant::now();
         self.frame_count += 1;
 
-        self.residual_update_dt += time_since_last;
+        self.residual_update_dt += dt;
+    }
+
+    /// Takes one fixed update's worth of the time built up, if there's
+    /// that much, for the fixed update mode.  Unlike
+    /// [`check_update_time()`](fn.check_update_time.html) a frame of
+    /// exactly one update's length runs an update.
+    pub(crate) fn take_fixed_update(&mut self, target_fps: u32) -> bool {
+        let target_dt = fps_as_duration(target_fps);
+        if self.residual_update_dt >= target_dt {
+            self.residual_update_dt -= target_dt;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Throws away the time built up for fixed updates beyond one update's
+    /// worth, after too many of them were run in one frame.
+    pub(crate) fn drop_update_backlog(&mut self, target_fps: u32) {
+        let target_dt = fps_as_duration(target_fps);
+        self.residual_update_dt = cmp::min(self.residual_update_dt, target_dt);
+    }
+
+    /// Forgets the time that passed while the app was suspended or
+    /// paused, so the first frame after it doesn't try to catch up on it.
+    pub(crate) fn skip_paused_time(&mut self) {
+        self.last_instant = time::Instant::now();
+        self.residual_update_dt = time::Duration::from_secs(0);
+    }
+
+    /// Waits until the frame that started at `frame_start` has taken as
+    /// long as the target frame rate says, if there is one.
+    pub(crate) fn limit_frame(&self, frame_start: time::Instant) {
+        let (sleep, end) = match self.frame_wait(frame_start, time::Instant::now()) {
+            Some(wait) => wait,
+            None => return,
+        };
+        if sleep > time::Duration::from_secs(0) {
+            thread::sleep(sleep);
+        }
+        match self.frame_limiter {
+            FrameLimiter::Sleep => (),
+            FrameLimiter::SpinSleep => {
+                while time::Instant::now() < end {
+                    std::hint::spin_loop();
+                }
+            }
+            FrameLimiter::Yield => {
+                while time::Instant::now() < end {
+                    thread::yield_now();
+                }
+            }
+        }
+    }
+
+    /// How long [`limit_frame()`](#method.limit_frame) sleeps, as of
+    /// `now`, for the frame that started at `frame_start`, and when the
+    /// frame should end.  `None` if there's nothing to wait for.
+    fn frame_wait(
+        &self,
+        frame_start: time::Instant,
+        now: time::Instant,
+    ) -> Option<(time::Duration, time::Instant)> {
+        let end = frame_start + fps_as_duration(self.target_fps?);
+        if end <= now {
+            return None;
+        }
+        let sleep = match self.frame_limiter {
+            FrameLimiter::Sleep => end - now,
+            FrameLimiter::SpinSleep => (end - now).checked_sub(SPIN_MARGIN).unwrap_or_default(),
+            FrameLimiter::Yield => time::Duration::from_secs(0),
+        };
+        Some((sleep, end))
+    }
+
+    /// The durations of the last 200 frames, oldest first.
+    pub(crate) fn frame_durations(&self) -> impl Iterator<Item = time::Duration> + '_ {
+        self.frame_durations.ordered()
     }
 }
 
@@ -184,7 +322,7 @@ pub fn f64_to_duration(t: f64) -> time::Duration {
 /// frame should be to match the given fps.
 ///
 /// Approximately.
-fn fps_as_duration(fps: u32) -> time::Duration {
+pub(crate) fn fps_as_duration(fps: u32) -> time::Duration {
     let target_dt_seconds = 1.0 / f64::from(fps);
     f64_to_duration(target_dt_seconds)
 }
@@ -235,6 +373,93 @@ pub fn remaining_update_time(ctx: &mut Context) -> time::Duration {
     ctx.timer_context.residual_update_dt
 }
 
+/// The most `update()` calls a frame gets in the fixed update mode, so a
+/// game that can't keep up slows down rather than falling further and
+/// further behind.
+pub const MAX_FIXED_UPDATES_PER_FRAME: u32 = 5;
+
+/// Turns the fixed update mode on, at the given number of updates per
+/// second, or off with `None`.  While it's on,
+/// [`event::run()`](../event/fn.run.html) calls
+/// [`EventHandler::update()`](../event/trait.EventHandler.html#tymethod.update)
+/// as many times each frame as it takes to keep up with that rate, which
+/// may be none at all, and then `draw()` once.  So `update()` can step the
+/// game by exactly `1.0 / rate` seconds, and must not call
+/// [`check_update_time()`](fn.check_update_time.html) itself.
+///
+/// After a lag spike, at most
+/// [`MAX_FIXED_UPDATES_PER_FRAME`](constant.MAX_FIXED_UPDATES_PER_FRAME.html)
+/// updates are run and the rest of the time is skipped.
+/// [`remainder_alpha()`](fn.remainder_alpha.html) tells `draw()` how far it is
+/// between two updates, to interpolate.  Input that's
+/// [just pressed](../input/keyboard/fn.is_key_just_pressed.html) is
+/// seen by the first update after it happens, however many frames later
+/// that is.
+pub fn set_fixed_update_rate(ctx: &mut Context, updates_per_second: Option<u32>) {
+    let tc = &mut ctx.timer_context;
+    tc.fixed_update_rate = updates_per_second.filter(|&rate| rate > 0);
+    tc.residual_update_dt = time::Duration::from_secs(0);
+}
+
+/// Returns the rate of the fixed update mode, if it's on; see
+/// [`set_fixed_update_rate()`](fn.set_fixed_update_rate.html).
+pub fn fixed_update_rate(ctx: &Context) -> Option<u32> {
+    ctx.timer_context.fixed_update_rate
+}
+
+/// Returns how far between the last fixed update and the next one the
+/// current frame is, from `0.0` to `1.0`, for drawing things
+/// interpolated between where they were and where they are.  Always `0.0`
+/// outside the fixed update mode.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # fn t(ctx: &mut Context, previous_x: f32, x: f32) {
+/// // In `draw()`, with `previous_x` saved at the start of each `update()`:
+/// let alpha = timer::remainder_alpha(ctx);
+/// let drawn_x = previous_x + (x - previous_x) * alpha;
+/// # }
+/// ```
+pub fn remainder_alpha(ctx: &Context) -> f32 {
+    let tc = &ctx.timer_context;
+    match tc.fixed_update_rate {
+        Some(rate) => {
+            let alpha = duration_to_f64(tc.residual_update_dt) * f64::from(rate);
+            (alpha as f32).min(1.0)
+        }
+        None => 0.0,
+    }
+}
+
+/// Caps how many frames a second [`event::run()`](../event/fn.run.html)
+/// runs, by waiting after each frame's `draw()` until it's taken long
+/// enough, or lets it run as fast as it can with `None`, the default.
+/// For games without vsync, so they don't draw thousands of frames a
+/// second for nothing.  [`set_frame_limiter()`](fn.set_frame_limiter.html)
+/// picks how it waits.
+pub fn set_target_fps(ctx: &mut Context, fps: Option<u32>) {
+    ctx.timer_context.target_fps = fps.filter(|&fps| fps > 0);
+}
+
+/// Returns the frame rate [`event::run()`](../event/fn.run.html) is
+/// capped at, if it is; see [`set_target_fps()`](fn.set_target_fps.html).
+pub fn target_fps(ctx: &Context) -> Option<u32> {
+    ctx.timer_context.target_fps
+}
+
+/// Sets how [`event::run()`](../event/fn.run.html) waits out the rest of
+/// a frame when there's a target frame rate.  Defaults to
+/// `FrameLimiter::SpinSleep`.
+pub fn set_frame_limiter(ctx: &mut Context, limiter: FrameLimiter) {
+    ctx.timer_context.frame_limiter = limiter;
+}
+
+/// Returns how [`event::run()`](../event/fn.run.html) waits out the rest
+/// of a frame; see [`set_frame_limiter()`](fn.set_frame_limiter.html).
+pub fn frame_limiter(ctx: &Context) -> FrameLimiter {
+    ctx.timer_context.frame_limiter
+}
+
 /// Pauses the current thread for the target duration.
 /// Just calls [`std::thread::sleep()`](https://doc.rust-lang.org/std/thread/fn.sleep.html)
 /// so it's as accurate as that is (which is usually not very).
@@ -257,3 +482,321 @@ pub fn yield_now() {
 pub fn ticks(ctx: &Context) -> usize {
     ctx.timer_context.frame_count
 }
+
+/// Identifies a timer or sequence started on the
+/// [`Scheduler`](struct.Scheduler.html), to cancel it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerId(u64);
+
+/// One step of a [`Sequence`](struct.Sequence.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Step {
+    Wait(time::Duration),
+    Fire(String),
+}
+
+/// A script of waits and tags to fire, run one step after another by the
+/// [`Scheduler`](struct.Scheduler.html), for things like cutscenes where
+/// one beat follows another.
+///
+/// ```rust,no_run
+/// # use ggez::*;
+/// # use std::time::Duration;
+/// # fn t(ctx: &mut Context) {
+/// let intro = timer::Sequence::new()
+///     .fire("show_title")
+///     .wait(Duration::from_secs(2))
+///     .fire("fade_in")
+///     .wait(Duration::from_millis(500))
+///     .fire("start");
+/// let _ = timer::start_sequence(ctx, intro);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sequence {
+    steps: VecDeque<Step>,
+}
+
+impl Sequence {
+    /// Creates an empty `Sequence`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits this long before going on to the next step.
+    pub fn wait(mut self, duration: time::Duration) -> Self {
+        self.steps.push_back(Step::Wait(duration));
+        self
+    }
+
+    /// Fires a tag, then goes straight on to the next step.
+    pub fn fire<T>(mut self, tag: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.steps.push_back(Step::Fire(tag.into()));
+        self
+    }
+}
+
+/// What a scheduled timer does when it's due.
+#[derive(Clone, Debug)]
+enum Job {
+    Once(String),
+    Every(String, time::Duration),
+    Sequence(Sequence),
+}
+
+/// A scheduled timer.
+#[derive(Clone, Debug)]
+struct Timer {
+    id: TimerId,
+    due: time::Duration,
+    job: Job,
+}
+
+/// Fires tags after a delay, every so often, or in a
+/// [`Sequence`](struct.Sequence.html), on the game's clock, so timed game
+/// logic like spawners doesn't need countdown fields everywhere.
+///
+/// Each `Context` has one, which [`event::run()`](../event/fn.run.html)
+/// advances each frame, before `update()`; time the game spends paused
+/// in the background doesn't count.  It hands the tags that fired to
+/// [`EventHandler::timer_event()`](../event/trait.EventHandler.html#method.timer_event),
+/// in the order they were due.  Timers are checked once a frame, so they
+/// fire at the end of the frame they're due in, and one that's due
+/// several times in a frame fires that many times.
+#[derive(Clone, Debug, Default)]
+pub struct Scheduler {
+    /// How much time the scheduler has seen pass.
+    now: time::Duration,
+    next_id: u64,
+    timers: Vec<Timer>,
+    fired: VecDeque<String>,
+}
+
+impl Scheduler {
+    /// Creates a new, empty `Scheduler`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, due: time::Duration, job: Job) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.push(Timer {
+            id,
+            due: self.now + due,
+            job,
+        });
+        id
+    }
+
+    /// Fires `tag` once, `delay` from now.
+    pub fn after<T>(&mut self, delay: time::Duration, tag: T) -> TimerId
+    where
+        T: Into<String>,
+    {
+        self.add(delay, Job::Once(tag.into()))
+    }
+
+    /// Fires `tag` every `interval` from now on, until it's cancelled.
+    /// An interval of zero fires once every frame.
+    pub fn every<T>(&mut self, interval: time::Duration, tag: T) -> TimerId
+    where
+        T: Into<String>,
+    {
+        self.add(interval, Job::Every(tag.into(), interval))
+    }
+
+    /// Starts running a sequence from its first step.
+    pub fn start_sequence(&mut self, sequence: Sequence) -> TimerId {
+        self.add(time::Duration::from_secs(0), Job::Sequence(sequence))
+    }
+
+    /// Stops a timer or sequence.  Returns whether it was still running.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let before = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        self.timers.len() != before
+    }
+
+    /// Stops every timer and sequence, and forgets the tags that fired
+    /// but weren't polled yet.
+    pub fn clear(&mut self) {
+        self.timers.clear();
+        self.fired.clear();
+    }
+
+    /// Returns whether a timer or sequence is still running.
+    pub fn is_running(&self, id: TimerId) -> bool {
+        self.timers.iter().any(|timer| timer.id == id)
+    }
+
+    /// Moves the scheduler's clock forward and fires everything that came
+    /// due, to be [polled](#method.poll).
+    pub fn advance(&mut self, dt: time::Duration) {
+        self.now += dt;
+        // The earliest timer due, and the oldest of those due together.
+        while let Some(i) = self
+            .timers
+            .iter()
+            .enumerate()
+            .filter(|(_, timer)| timer.due <= self.now)
+            .min_by_key(|(_, timer)| (timer.due, timer.id))
+            .map(|(i, _)| i)
+        {
+            let now = self.now;
+            let timer = &mut self.timers[i];
+            let finished = match &mut timer.job {
+                Job::Once(tag) => {
+                    self.fired.push_back(tag.clone());
+                    true
+                }
+                Job::Every(tag, interval) => {
+                    self.fired.push_back(tag.clone());
+                    timer.due = if *interval > time::Duration::from_secs(0) {
+                        timer.due + *interval
+                    } else {
+                        // Once a frame, not forever.
+                        now + time::Duration::from_nanos(1)
+                    };
+                    false
+                }
+                Job::Sequence(sequence) => loop {
+                    match sequence.steps.pop_front() {
+                        Some(Step::Fire(tag)) => self.fired.push_back(tag),
+                        Some(Step::Wait(wait)) => {
+                            timer.due += wait;
+                            break false;
+                        }
+                        None => break true,
+                    }
+                },
+            };
+            if finished {
+                let _ = self.timers.remove(i);
+            }
+        }
+    }
+
+    /// Returns the oldest tag that fired and wasn't polled yet, if any.
+    pub fn poll(&mut self) -> Option<String> {
+        self.fired.pop_front()
+    }
+}
+
+/// Fires `tag` once, `delay` from now; see
+/// [`Scheduler::after()`](struct.Scheduler.html#method.after).
+pub fn after<T>(ctx: &mut Context, delay: time::Duration, tag: T) -> TimerId
+where
+    T: Into<String>,
+{
+    ctx.scheduler.after(delay, tag)
+}
+
+/// Fires `tag` every `interval` from now on; see
+/// [`Scheduler::every()`](struct.Scheduler.html#method.every).
+pub fn every<T>(ctx: &mut Context, interval: time::Duration, tag: T) -> TimerId
+where
+    T: Into<String>,
+{
+    ctx.scheduler.every(interval, tag)
+}
+
+/// Starts running a [`Sequence`](struct.Sequence.html).
+pub fn start_sequence(ctx: &mut Context, sequence: Sequence) -> TimerId {
+    ctx.scheduler.start_sequence(sequence)
+}
+
+/// Stops a timer or sequence.  Returns whether it was still running.
+pub fn cancel(ctx: &mut Context, id: TimerId) -> bool {
+    ctx.scheduler.cancel(id)
+}
+
+/// Returns the context's [`Scheduler`](struct.Scheduler.html).
+pub fn scheduler(ctx: &Context) -> &Scheduler {
+    &ctx.scheduler
+}
+
+/// Returns the context's [`Scheduler`](struct.Scheduler.html), to
+/// advance and poll it by hand in your own event loop.
+pub fn scheduler_mut(ctx: &mut Context) -> &mut Scheduler {
+    &mut ctx.scheduler
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headless_test_scheduled_tags() {
+        let ms = time::Duration::from_millis;
+        let mut scheduler = Scheduler::new();
+        let _ = scheduler.after(ms(30), "once");
+        let every = scheduler.every(ms(20), "every");
+        let _ = scheduler.start_sequence(Sequence::new().fire("a").wait(ms(25)).fire("b"));
+
+        scheduler.advance(ms(10));
+        assert_eq!(scheduler.poll().as_deref(), Some("a"));
+        assert_eq!(scheduler.poll(), None);
+
+        scheduler.advance(ms(40));
+        let fired: Vec<_> = std::iter::from_fn(|| scheduler.poll()).collect();
+        assert_eq!(fired, ["every", "b", "once", "every"]);
+
+        assert!(scheduler.cancel(every));
+        assert!(!scheduler.is_running(every));
+        scheduler.advance(ms(100));
+        assert_eq!(scheduler.poll(), None);
+    }
+
+    #[test]
+    fn headless_test_fixed_update_tick() {
+        let mut tc = TimeContext::new();
+        // A frame of exactly one tick runs exactly one update.
+        tc.tick_by(fps_as_duration(60));
+        assert!(tc.take_fixed_update(60));
+        assert!(!tc.take_fixed_update(60));
+        assert_eq!(tc.residual_update_dt, time::Duration::from_secs(0));
+
+        tc.tick_by(fps_as_duration(60) * 2);
+        assert!(tc.take_fixed_update(60));
+        assert!(tc.take_fixed_update(60));
+        assert!(!tc.take_fixed_update(60));
+    }
+
+    #[test]
+    fn headless_test_frame_limiter() {
+        let ms = time::Duration::from_millis;
+        let mut tc = TimeContext::new();
+        let start = time::Instant::now();
+        // Without a target, frames aren't held up at all.
+        assert_eq!(tc.frame_wait(start, start + ms(5)), None);
+
+        // At 50 fps a frame lasts 20 ms, so 5 ms in there's 15 to go.
+        tc.target_fps = Some(50);
+        let end = start + ms(20);
+        tc.frame_limiter = FrameLimiter::Sleep;
+        assert_eq!(tc.frame_wait(start, start + ms(5)), Some((ms(15), end)));
+        tc.frame_limiter = FrameLimiter::SpinSleep;
+        assert_eq!(
+            tc.frame_wait(start, start + ms(5)),
+            Some((ms(15) - SPIN_MARGIN, end))
+        );
+        // Too close to the end to sleep at all, only to spin.
+        assert_eq!(tc.frame_wait(start, start + ms(19)), Some((ms(0), end)));
+        tc.frame_limiter = FrameLimiter::Yield;
+        assert_eq!(tc.frame_wait(start, start + ms(5)), Some((ms(0), end)));
+
+        // A frame that's already taken too long goes straight on.
+        for &limiter in &[
+            FrameLimiter::Sleep,
+            FrameLimiter::SpinSleep,
+            FrameLimiter::Yield,
+        ] {
+            tc.frame_limiter = limiter;
+            assert_eq!(tc.frame_wait(start, start + ms(25)), None);
+        }
+    }
+}
@@ -8,13 +8,23 @@
 //! the relevant trait for it has generic methods so we can't use it
 //! as a trait object, and its path abstraction is not the most
 //! convenient.
+//!
+//! The [`filesystem`](../filesystem/index.html) module is built on
+//! top of this.  You only need it to add your own sources of files,
+//! such as a CDN, an sqlite pack or in-memory test fixtures: implement
+//! [`VFS`](trait.VFS.html) for them and mount them with
+//! [`filesystem::mount_vfs()`](../filesystem/fn.mount_vfs.html).
 
+use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::fmt::{self, Debug};
 use std::fs;
 use std::io::{self, Read, Seek, Write};
+use std::ops::Deref;
 use std::path::{self, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use crate::error::{GameError, GameResult};
 
@@ -25,9 +35,12 @@ fn convenient_path_to_str(path: &path::Path) -> GameResult<&str> {
     })
 }
 
-pub trait VFile: Read + Write + Seek + Debug {}
+/// An open file, as returned by [`VFS::open_options()`](trait.VFS.html#tymethod.open_options).
+/// Implemented for anything that's `Read + Write + Seek + Debug + Send`;
+/// files that can't be written to can return errors from `write()`.
+pub trait VFile: Read + Write + Seek + Debug + Send {}
 
-impl<T> VFile for T where T: Read + Write + Seek + Debug {}
+impl<T> VFile for T where T: Read + Write + Seek + Debug + Send {}
 
 /// Options for opening files
 ///
@@ -93,6 +106,37 @@ impl OpenOptions {
     }
 }
 
+/// How an [`OverlayFS`](struct.OverlayFS.html) matches the case of
+/// paths.
+///
+/// Assets made on Windows or macOS, which don't care about case,
+/// often have paths that don't quite match the case of the files they
+/// point at, which then can't be found on Linux.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PathCase {
+    /// Paths are looked up exactly as they're given, so whether case
+    /// matters is up to the operating system.  The default.
+    Strict,
+    /// Paths are looked up ignoring case, with a warning logged for
+    /// each one that differs in case from the file it names so it can
+    /// be fixed, even where the operating system found it anyway.
+    Insensitive,
+}
+
+impl Default for PathCase {
+    fn default() -> Self {
+        PathCase::Strict
+    }
+}
+
+/// A source of files, with its own root directory.
+///
+/// Paths given to it are absolute, like `/images/player.png`; a
+/// source should refuse ones that try to escape its root with `..`.
+/// Sources that can't be written to return errors from the methods
+/// that would change them.  Errors are how a source says "not here",
+/// so they make the filesystem move on to the next source rather than
+/// giving up.
 #[allow(clippy::upper_case_acronyms)]
 pub trait VFS: Debug {
     /// Open the file at this path with the given options
@@ -135,8 +179,20 @@ pub trait VFS: Debug {
 
     /// Retrieve the actual location of the VFS root, if available.
     fn to_path_buf(&self) -> Option<PathBuf>;
+
+    /// Get the whole contents of the file at this path.  By default
+    /// this reads the file into memory; sources that can do better,
+    /// like `PhysicalFS` memory-mapping the file with the `mmap`
+    /// feature, override it.
+    fn map(&self, path: &Path) -> GameResult<MappedFile> {
+        let mut contents = Vec::new();
+        let _ = self.open(path)?.read_to_end(&mut contents)?;
+        Ok(MappedFile::from(contents))
+    }
 }
 
+/// Metadata of a file or directory in a [`VFS`](trait.VFS.html).
+#[allow(clippy::len_without_is_empty)]
 pub trait VMetadata {
     /// Returns whether or not it is a directory.
     /// Note that zip files don't actually have directories, awkwardly,
@@ -147,6 +203,73 @@ pub trait VMetadata {
     /// Returns the length of the thing.  If it is a directory,
     /// the result of this is undefined/platform dependent.
     fn len(&self) -> u64;
+    /// Returns when it was last modified, if the source knows.
+    fn modified(&self) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// The whole contents of a file, as returned by
+/// [`VFS::map()`](trait.VFS.html#method.map).  Derefs to `&[u8]`, so
+/// it can be sliced without copying anything.
+pub struct MappedFile(Mapping);
+
+enum Mapping {
+    #[cfg(feature = "mmap")]
+    Mmap(memmap2::Mmap),
+    Contents(Contents),
+    Owned(Vec<u8>),
+}
+
+impl MappedFile {
+    /// Whether the file is memory-mapped, rather than read into memory.
+    /// Never true without the `mmap` feature.
+    pub fn is_mapped(&self) -> bool {
+        #[cfg(feature = "mmap")]
+        {
+            matches!(self.0, Mapping::Mmap(_))
+        }
+        #[cfg(not(feature = "mmap"))]
+        {
+            false
+        }
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.0 {
+            #[cfg(feature = "mmap")]
+            Mapping::Mmap(mmap) => mmap,
+            Mapping::Contents(contents) => contents.as_ref(),
+            Mapping::Owned(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<Vec<u8>> for MappedFile {
+    fn from(bytes: Vec<u8>) -> Self {
+        MappedFile(Mapping::Owned(bytes))
+    }
+}
+
+impl Debug for MappedFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let kind = if self.is_mapped() {
+            "mapped"
+        } else {
+            "in memory"
+        };
+        write!(f, "<MappedFile: {} bytes, {}>", self.len(), kind)
+    }
 }
 
 /// A VFS that points to a directory and uses it as the root of its
@@ -161,6 +284,7 @@ pub struct PhysicalFS {
     readonly: bool,
 }
 
+/// Metadata of a file in a [`PhysicalFS`](struct.PhysicalFS.html).
 #[derive(Debug, Clone)]
 pub struct PhysicalMetadata(fs::Metadata);
 
@@ -174,6 +298,9 @@ impl VMetadata for PhysicalMetadata {
     fn len(&self) -> u64 {
         self.0.len()
     }
+    fn modified(&self) -> Option<SystemTime> {
+        self.0.modified().ok()
+    }
 }
 
 /// This takes an absolute path and returns either a sanitized relative
@@ -214,6 +341,8 @@ fn sanitize_path(path: &path::Path) -> Option<PathBuf> {
 }
 
 impl PhysicalFS {
+    /// Creates a `PhysicalFS` with the given directory as its root,
+    /// which is created the first time something is written to it.
     pub fn new(root: &Path, readonly: bool) -> Self {
         PhysicalFS {
             root: root.into(),
@@ -388,35 +517,165 @@ impl VFS for PhysicalFS {
     fn to_path_buf(&self) -> Option<PathBuf> {
         Some(self.root.clone())
     }
+
+    /// Memory-map the file at this path
+    #[cfg(feature = "mmap")]
+    #[allow(unsafe_code)]
+    fn map(&self, path: &Path) -> GameResult<MappedFile> {
+        let file = fs::File::open(self.to_absolute(path)?)?;
+        if file.metadata()?.len() == 0 {
+            // Mapping an empty file is an error on some platforms.
+            return Ok(MappedFile::from(Vec::new()));
+        }
+        // The map is only sound as long as nothing changes the file
+        // under us, which `filesystem::map()` warns about.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(MappedFile(Mapping::Mmap(mmap)))
+    }
 }
 
 /// A structure that joins several VFS's together in order.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 #[allow(clippy::upper_case_acronyms)]
 pub struct OverlayFS {
     roots: VecDeque<Box<dyn VFS>>,
+    path_case: PathCase,
+    /// Paths already looked up ignoring case, and what they were found
+    /// as.  Forgotten whenever the sources or what's in them change.
+    resolved: RefCell<HashMap<PathBuf, PathBuf>>,
 }
 
 impl OverlayFS {
+    /// Creates an empty `OverlayFS`.
     pub fn new() -> Self {
         Self {
             roots: VecDeque::new(),
+            path_case: PathCase::Strict,
+            resolved: RefCell::new(HashMap::new()),
         }
     }
 
+    /// Sets how the case of paths is matched when looking them up.
+    pub fn set_path_case(&mut self, path_case: PathCase) {
+        self.path_case = path_case;
+        self.forget_resolved();
+    }
+
+    /// Forgets the paths looked up so far, when files may have come or
+    /// gone.
+    fn forget_resolved(&self) {
+        self.resolved.borrow_mut().clear();
+    }
+
+    /// Returns how the case of paths is matched when looking them up.
+    pub fn path_case(&self) -> PathCase {
+        self.path_case
+    }
+
+    /// Returns the path to look up for the given one: itself with
+    /// `PathCase::Strict`, otherwise the path as it's named in the
+    /// sources, which may differ from it in case.
+    ///
+    /// The names are compared even when the path is found as it is,
+    /// since filesystems that ignore case find it either way, and those
+    /// are the ones where mismatches go unnoticed.
+    pub(crate) fn resolve<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        if self.path_case == PathCase::Strict {
+            return Cow::Borrowed(path);
+        }
+        // Walking the directories for every lookup is slow, so paths
+        // that were found are remembered; ones that weren't are looked
+        // for again, since they may be about to be created.
+        if let Some(found) = self.resolved.borrow().get(path) {
+            return if found == path {
+                Cow::Borrowed(path)
+            } else {
+                Cow::Owned(found.clone())
+            };
+        }
+        let found = self.find_ignoring_case(path);
+        if let Some(found) = &found {
+            let _ = self
+                .resolved
+                .borrow_mut()
+                .insert(path.to_path_buf(), found.clone());
+        }
+        match found {
+            Some(found) if found != path => {
+                warn!(
+                    "Path {:?} was found as {:?}, which differs in case; \
+                     it won't be found on case-sensitive filesystems or with PathCase::Strict",
+                    path, found
+                );
+                Cow::Owned(found)
+            }
+            _ => Cow::Borrowed(path),
+        }
+    }
+
+    /// Looks the path up one directory at a time, ignoring case but
+    /// preferring names that match it exactly.
+    fn find_ignoring_case(&self, path: &Path) -> Option<PathBuf> {
+        let mut found = PathBuf::from("/");
+        for component in path.components() {
+            let name = match component {
+                path::Component::RootDir => continue,
+                path::Component::Normal(name) => name.to_str()?,
+                _ => return None,
+            };
+            let mut next = None;
+            for entry in self
+                .roots
+                .iter()
+                .filter_map(|vfs| vfs.read_dir(&found).ok())
+                .flatten()
+                .filter_map(Result::ok)
+            {
+                let entry_name = match entry.file_name().and_then(|entry_name| entry_name.to_str())
+                {
+                    Some(entry_name) => entry_name,
+                    None => continue,
+                };
+                if entry_name == name {
+                    next = Some(entry);
+                    break;
+                } else if next.is_none() && entry_name.to_lowercase() == name.to_lowercase() {
+                    next = Some(entry);
+                }
+            }
+            found = next?;
+        }
+        Some(found)
+    }
+
     /// Adds a new VFS to the front of the list.
     /// Currently unused, I suppose, but good to
     /// have at least for tests.
     #[allow(dead_code)]
     pub fn push_front(&mut self, fs: Box<dyn VFS>) {
+        self.forget_resolved();
         self.roots.push_front(fs);
     }
 
     /// Adds a new VFS to the end of the list.
     pub fn push_back(&mut self, fs: Box<dyn VFS>) {
+        self.forget_resolved();
         self.roots.push_back(fs);
     }
 
+    /// Adds a new VFS at the given place in the list.
+    pub fn insert(&mut self, index: usize, fs: Box<dyn VFS>) {
+        self.forget_resolved();
+        self.roots.insert(index, fs);
+    }
+
+    /// Takes the VFS at the given place out of the list.
+    pub fn remove(&mut self, index: usize) -> Option<Box<dyn VFS>> {
+        self.forget_resolved();
+        self.roots.remove(index)
+    }
+
+    /// The VFS's joined together, in the order they're searched.
     pub fn roots(&self) -> &VecDeque<Box<dyn VFS>> {
         &self.roots
     }
@@ -425,6 +684,11 @@ impl OverlayFS {
 impl VFS for OverlayFS {
     /// Open the file at this path with the given options
     fn open_options(&self, path: &Path, open_options: OpenOptions) -> GameResult<Box<dyn VFile>> {
+        if open_options.create {
+            self.forget_resolved();
+        }
+        let resolved = self.resolve(path);
+        let path = &*resolved;
         let mut tried: Vec<(PathBuf, GameError)> = vec![];
 
         for vfs in &self.roots {
@@ -443,8 +707,30 @@ impl VFS for OverlayFS {
         Err(GameError::ResourceNotFound(errmessage, tried))
     }
 
+    /// Map the file at this path from the first VFS that has it
+    fn map(&self, path: &Path) -> GameResult<MappedFile> {
+        let resolved = self.resolve(path);
+        let path = &*resolved;
+        let mut tried: Vec<(PathBuf, GameError)> = vec![];
+
+        for vfs in &self.roots {
+            match vfs.map(path) {
+                Err(e) => {
+                    let vfs_path = vfs
+                        .to_path_buf()
+                        .unwrap_or_else(|| PathBuf::from("<invalid path>"));
+                    tried.push((vfs_path, e));
+                }
+                f => return f,
+            }
+        }
+        let errmessage = String::from(convenient_path_to_str(path)?);
+        Err(GameError::ResourceNotFound(errmessage, tried))
+    }
+
     /// Create a directory at the location by this path
     fn mkdir(&self, path: &Path) -> GameResult {
+        self.forget_resolved();
         for vfs in &self.roots {
             match vfs.mkdir(path) {
                 Err(_) => (),
@@ -459,6 +745,7 @@ impl VFS for OverlayFS {
 
     /// Remove a file
     fn rm(&self, path: &Path) -> GameResult {
+        self.forget_resolved();
         for vfs in &self.roots {
             match vfs.rm(path) {
                 Err(_) => (),
@@ -473,6 +760,7 @@ impl VFS for OverlayFS {
 
     /// Remove a file or directory and all its contents
     fn rmrf(&self, path: &Path) -> GameResult {
+        self.forget_resolved();
         for vfs in &self.roots {
             match vfs.rmrf(path) {
                 Err(_) => (),
@@ -487,6 +775,8 @@ impl VFS for OverlayFS {
 
     /// Check if the file exists
     fn exists(&self, path: &Path) -> bool {
+        let resolved = self.resolve(path);
+        let path = &*resolved;
         for vfs in &self.roots {
             if vfs.exists(path) {
                 return true;
@@ -498,6 +788,8 @@ impl VFS for OverlayFS {
 
     /// Get the file's metadata
     fn metadata(&self, path: &Path) -> GameResult<Box<dyn VMetadata>> {
+        let resolved = self.resolve(path);
+        let path = &*resolved;
         for vfs in &self.roots {
             match vfs.metadata(path) {
                 Err(_) => (),
@@ -512,6 +804,8 @@ impl VFS for OverlayFS {
 
     /// Retrieve the path entries in this path
     fn read_dir(&self, path: &Path) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>> {
+        let resolved = self.resolve(path);
+        let path = &*resolved;
         // This is tricky 'cause we have to actually merge iterators together...
         // Doing it the simple and stupid way works though.
         let mut v = Vec::new();
@@ -575,10 +869,12 @@ pub struct ZipFS {
     archive: RefCell<Box<dyn ZipArchiveAccess>>,
     // We keep an index of what files are in the zip file
     // because trying to read it lazily is a pain in the butt.
-    index: Vec<String>,
+    // The names are sanitized and relative, like the other archives'.
+    index: Vec<PathBuf>,
 }
 
 impl ZipFS {
+    /// Creates a `ZipFS` from the zip file at the given path.
     pub fn new(filename: &Path) -> GameResult<Self> {
         let f = fs::File::open(filename)?;
         let archive = Box::new(zip::ZipArchive::new(f)?);
@@ -597,13 +893,16 @@ impl ZipFS {
 
     fn from_boxed_archive(mut archive: Box<dyn ZipArchiveAccess>, source: Option<PathBuf>) -> Self {
         let idx = (0..archive.len())
-            .map(|i| {
-                archive
+            .filter_map(|i| {
+                let name = archive
                     .by_index(i)
                     .expect("Should never happen!")
                     .name()
-                    .to_string()
+                    .to_string();
+                // Zip names have no leading `/`, and directories end in one.
+                sanitize_path(&Path::new("/").join(name))
             })
+            .filter(|relative| relative.components().next().is_some())
             .collect();
         Self {
             source,
@@ -659,6 +958,121 @@ impl io::Seek for ZipFileWrapper {
     }
 }
 
+/// Files in a zip file at least this big are streamed out of it as
+/// they're read, rather than inflated into memory when they're opened.
+const ZIP_STREAMING_THRESHOLD: u64 = 1024 * 1024;
+
+/// A file in a zip file that's read straight out of the zip file as
+/// it's read, with its own handle to it.
+///
+/// Stored files can seek anywhere directly.  Deflated ones can only be
+/// inflated front to back, so seeking forwards inflates and throws away
+/// everything in between, and seeking backwards starts over from the
+/// beginning.
+struct ZipStream {
+    file: fs::File,
+    data_start: u64,
+    compressed_size: u64,
+    size: u64,
+    deflated: bool,
+    reader: Box<dyn Read + Send>,
+    pos: u64,
+}
+
+impl ZipStream {
+    fn new(zipfile: &zip::read::ZipFile, archive: &Path) -> GameResult<Option<Self>> {
+        let deflated = match zipfile.compression() {
+            zip::CompressionMethod::Stored => false,
+            zip::CompressionMethod::Deflated => true,
+            _ => return Ok(None),
+        };
+        let mut stream = ZipStream {
+            file: fs::File::open(archive)?,
+            data_start: zipfile.data_start(),
+            compressed_size: zipfile.compressed_size(),
+            size: zipfile.size(),
+            deflated,
+            reader: Box::new(io::empty()),
+            pos: 0,
+        };
+        stream.start_at(0)?;
+        Ok(Some(stream))
+    }
+
+    /// Starts reading over from the given place in the file.
+    fn start_at(&mut self, pos: u64) -> io::Result<()> {
+        if self.deflated {
+            let _ = self.file.seek(io::SeekFrom::Start(self.data_start))?;
+            let compressed = self.file.try_clone()?.take(self.compressed_size);
+            self.reader = Box::new(flate2::read::DeflateDecoder::new(compressed));
+            let _ = io::copy(&mut (&mut self.reader).take(pos), &mut io::sink())?;
+        } else {
+            let start = self.data_start + pos.min(self.size);
+            let _ = self.file.seek(io::SeekFrom::Start(start))?;
+            let remaining = self.size.saturating_sub(pos);
+            self.reader = Box::new(self.file.try_clone()?.take(remaining));
+        }
+        self.pos = pos;
+        Ok(())
+    }
+}
+
+impl io::Read for ZipStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl io::Write for ZipStream {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Cannot write to a zip file!",
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Seek for ZipStream {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let offset = |base: u64, offset: i64| {
+            if offset >= 0 {
+                base.checked_add(offset as u64)
+            } else {
+                base.checked_sub(offset.wrapping_neg() as u64)
+            }
+        };
+        let target = match pos {
+            io::SeekFrom::Start(target) => Some(target),
+            io::SeekFrom::End(delta) => offset(self.size, delta),
+            io::SeekFrom::Current(delta) => offset(self.pos, delta),
+        }
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Seek to a negative position")
+        })?;
+
+        if self.deflated && target >= self.pos {
+            let skip = target - self.pos;
+            let _ = io::copy(&mut (&mut self.reader).take(skip), &mut io::sink())?;
+            self.pos = target;
+        } else {
+            self.start_at(target)?;
+        }
+        Ok(target)
+    }
+}
+
+impl Debug for ZipStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "<ZipStream: {} bytes>", self.size)
+    }
+}
+
 impl Debug for ZipFileWrapper {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(f, "<Zipfile>")
@@ -722,6 +1136,15 @@ impl VFS for ZipFS {
             .try_borrow_mut()
             .expect("Couldn't borrow ZipArchive in ZipFS::open_options(); should never happen! Report a bug at https://github.com/ggez/ggez/");
         let mut f = stupid_archive_borrow.by_name(path)?;
+        // Big files are streamed, if there's a zip file on disk to
+        // stream them out of.
+        if let Some(source) = &self.source {
+            if f.size() >= ZIP_STREAMING_THRESHOLD {
+                if let Some(stream) = ZipStream::new(&f, source)? {
+                    return Ok(Box::new(stream) as Box<dyn VFile>);
+                }
+            }
+        }
         let zipfile = ZipFileWrapper::new(&mut f)?;
         Ok(Box::new(zipfile) as Box<dyn VFile>)
     }
@@ -754,39 +1177,405 @@ impl VFS for ZipFS {
         let mut stupid_archive_borrow = self.archive
             .try_borrow_mut()
             .expect("Couldn't borrow ZipArchive in ZipFS::exists(); should never happen!  Report a bug at https://github.com/ggez/ggez/");
-        if let Ok(path) = convenient_path_to_str(path) {
-            stupid_archive_borrow.by_name(path).is_ok()
-        } else {
-            false
+        match (convenient_path_to_str(path), relative_path(path)) {
+            (Ok(name), Ok(relative)) => {
+                stupid_archive_borrow.by_name(name).is_ok() || index_is_dir(&self.index, &relative)
+            }
+            _ => false,
         }
     }
 
     fn metadata(&self, path: &Path) -> GameResult<Box<dyn VMetadata>> {
+        let relative = relative_path(path)?;
         let path = convenient_path_to_str(path)?;
         let mut stupid_archive_borrow = self.archive
             .try_borrow_mut()
             .expect("Couldn't borrow ZipArchive in ZipFS::metadata(); should never happen! Report a bug at https://github.com/ggez/ggez/");
         match ZipMetadata::new(path, &mut **stupid_archive_borrow) {
+            Some(md) => Ok(Box::new(md) as Box<dyn VMetadata>),
+            // Zip files don't have real directories, just files with
+            // long names, so anything with files under it is one.
+            None if index_is_dir(&self.index, &relative) => {
+                let md = ArchiveMetadata {
+                    len: 0,
+                    is_dir: true,
+                };
+                Ok(Box::new(md) as Box<dyn VMetadata>)
+            }
             None => Err(GameError::FilesystemError(format!(
                 "Metadata not found in zip file for {}",
                 path
             ))),
-            Some(md) => Ok(Box::new(md) as Box<dyn VMetadata>),
         }
     }
 
-    #[allow(clippy::needless_collect)]
-    /// Zip files don't have real directories, so we (incorrectly) hack it by
-    /// just looking for a path prefix for now.
+    /// Lists the files and directories right inside the given one.
     fn read_dir(&self, path: &Path) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>> {
-        let path = convenient_path_to_str(path)?;
-        let itr = self
-            .index
-            .iter()
-            .filter(|s| s.starts_with(path))
-            .map(|s| Ok(PathBuf::from(s)))
-            .collect::<Vec<_>>();
-        Ok(Box::new(itr.into_iter()))
+        index_read_dir(&self.index, &relative_path(path)?)
+    }
+
+    fn to_path_buf(&self) -> Option<PathBuf> {
+        self.source.clone()
+    }
+}
+
+/// Takes an absolute path, as given to a VFS, and returns it sanitized
+/// and relative, or an error.
+fn relative_path(p: &Path) -> GameResult<PathBuf> {
+    sanitize_path(p).ok_or_else(|| {
+        GameError::FilesystemError(format!(
+            "Path {:?} is not valid: must be an absolute path with no \
+             references to parent directories",
+            p
+        ))
+    })
+}
+
+fn read_only_error(action: &str, path: &Path, fs: &dyn Debug) -> GameResult {
+    Err(GameError::FilesystemError(format!(
+        "Cannot {} {:?} in {:?}, filesystem read-only",
+        action, path, fs
+    )))
+}
+
+/// Whether any of the given (relative) file paths is under the given
+/// directory.  Archives and embedded files don't have real
+/// directories, so that's what makes one.
+fn index_is_dir<'a, I>(files: I, relative: &Path) -> bool
+where
+    I: IntoIterator<Item = &'a PathBuf>,
+{
+    files
+        .into_iter()
+        .any(|file| file != relative && file.starts_with(relative))
+}
+
+/// Lists the files and directories right inside the given directory,
+/// out of the given (relative) file paths.
+fn index_read_dir<'a, I>(
+    files: I,
+    relative: &Path,
+) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>>
+where
+    I: IntoIterator<Item = &'a PathBuf>,
+{
+    let entries = files
+        .into_iter()
+        .filter(|file| *file != relative)
+        .filter_map(|file| file.strip_prefix(relative).ok()?.components().next())
+        .map(|child| Path::new("/").join(relative).join(child))
+        .collect::<BTreeSet<_>>();
+    if entries.is_empty() {
+        return Err(GameError::FilesystemError(format!(
+            "No directory {:?}",
+            Path::new("/").join(relative)
+        )));
+    }
+    Ok(Box::new(entries.into_iter().map(Ok)))
+}
+
+/// Metadata of a file in an archive or embedded in the executable.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct ArchiveMetadata {
+    len: u64,
+    is_dir: bool,
+}
+
+impl VMetadata for ArchiveMetadata {
+    fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+    fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+/// The contents of a file held in memory.
+#[derive(Clone)]
+enum Contents {
+    Static(&'static [u8]),
+    Shared(Arc<[u8]>),
+}
+
+impl AsRef<[u8]> for Contents {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Contents::Static(bytes) => bytes,
+            Contents::Shared(bytes) => bytes,
+        }
+    }
+}
+
+/// A read-only filesystem of files embedded in the program itself,
+/// usually with `include_bytes!()`, so a game can be shipped as a
+/// single executable.
+#[derive(Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct EmbeddedFS {
+    /// The files, keyed by their sanitized, relative paths.
+    files: HashMap<PathBuf, Contents>,
+}
+
+impl EmbeddedFS {
+    /// Creates an `EmbeddedFS` from pairs of paths and file contents.
+    /// Paths are absolute, like `/images/player.png`; invalid ones are
+    /// skipped with a warning.
+    pub fn new<I>(files: I) -> Self
+    where
+        I: IntoIterator<Item = (&'static str, &'static [u8])>,
+    {
+        let files = files
+            .into_iter()
+            .filter_map(|(name, bytes)| match sanitize_path(Path::new(name)) {
+                Some(path) if path.as_os_str().is_empty() => None,
+                Some(path) => Some((path, Contents::Static(bytes))),
+                None => {
+                    warn!("Invalid path for embedded file: {:?}", name);
+                    None
+                }
+            })
+            .collect();
+        EmbeddedFS { files }
+    }
+
+    /// Creates an `EmbeddedFS` from files loaded at runtime, keyed by
+    /// relative paths.
+    #[cfg(feature = "tar")]
+    fn from_owned(files: Vec<(PathBuf, Vec<u8>)>) -> Self {
+        let files = files
+            .into_iter()
+            .map(|(path, bytes)| (path, Contents::Shared(bytes.into())))
+            .collect();
+        EmbeddedFS { files }
+    }
+}
+
+impl Debug for EmbeddedFS {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "<EmbeddedFS: {} files>", self.files.len())
+    }
+}
+
+/// An open embedded file.
+#[derive(Clone)]
+pub struct EmbeddedFile(io::Cursor<Contents>);
+
+impl io::Read for EmbeddedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl io::Write for EmbeddedFile {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Cannot write to an embedded file",
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Seek for EmbeddedFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+impl Debug for EmbeddedFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "<EmbeddedFile: {} bytes>",
+            self.0.get_ref().as_ref().len()
+        )
+    }
+}
+
+impl VFS for EmbeddedFS {
+    fn open_options(&self, path: &Path, open_options: OpenOptions) -> GameResult<Box<dyn VFile>> {
+        if open_options.write || open_options.create || open_options.append || open_options.truncate
+        {
+            read_only_error("alter file", path, self)?;
+        }
+        let relative = relative_path(path)?;
+        match self.files.get(&relative) {
+            Some(contents) => {
+                let file = EmbeddedFile(io::Cursor::new(contents.clone()));
+                Ok(Box::new(file) as Box<dyn VFile>)
+            }
+            None => Err(GameError::ResourceNotFound(
+                path.to_string_lossy().into_owned(),
+                Vec::new(),
+            )),
+        }
+    }
+
+    fn mkdir(&self, path: &Path) -> GameResult {
+        read_only_error("mkdir", path, self)
+    }
+
+    fn rm(&self, path: &Path) -> GameResult {
+        read_only_error("rm", path, self)
+    }
+
+    fn rmrf(&self, path: &Path) -> GameResult {
+        read_only_error("rmrf", path, self)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        match relative_path(path) {
+            Ok(relative) => {
+                self.files.contains_key(&relative) || index_is_dir(self.files.keys(), &relative)
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> GameResult<Box<dyn VMetadata>> {
+        let relative = relative_path(path)?;
+        let metadata = match self.files.get(&relative) {
+            Some(contents) => ArchiveMetadata {
+                len: contents.as_ref().len() as u64,
+                is_dir: false,
+            },
+            None if index_is_dir(self.files.keys(), &relative) => ArchiveMetadata {
+                len: 0,
+                is_dir: true,
+            },
+            None => {
+                return Err(GameError::FilesystemError(format!(
+                    "Metadata not found in embedded files for {:?}",
+                    path
+                )))
+            }
+        };
+        Ok(Box::new(metadata) as Box<dyn VMetadata>)
+    }
+
+    /// Lists the files and directories right inside the given one.
+    fn read_dir(&self, path: &Path) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>> {
+        index_read_dir(self.files.keys(), &relative_path(path)?)
+    }
+
+    fn to_path_buf(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn map(&self, path: &Path) -> GameResult<MappedFile> {
+        let relative = relative_path(path)?;
+        match self.files.get(&relative) {
+            Some(contents) => Ok(MappedFile(Mapping::Contents(contents.clone()))),
+            None => Err(GameError::ResourceNotFound(
+                path.to_string_lossy().into_owned(),
+                Vec::new(),
+            )),
+        }
+    }
+}
+
+/// A read-only filesystem backed by a tar file, which may be gzipped.
+/// Needs the `tar` feature.
+///
+/// Tar files have no index to look files up in, so the whole archive
+/// is unpacked into memory when it's loaded.
+#[cfg(feature = "tar")]
+#[allow(clippy::upper_case_acronyms)]
+pub struct TarFS {
+    source: Option<PathBuf>,
+    files: EmbeddedFS,
+}
+
+#[cfg(feature = "tar")]
+impl TarFS {
+    /// Loads a `TarFS` from the `.tar` or `.tar.gz` file at the given path.
+    pub fn new(filename: &Path) -> GameResult<Self> {
+        let f = fs::File::open(filename)?;
+        let mut tarfs = TarFS::from_read(f)?;
+        tarfs.source = Some(filename.into());
+        Ok(tarfs)
+    }
+
+    /// Loads a `TarFS` from any `Read` object, gzipped or not.
+    pub fn from_read<R: Read>(reader: R) -> GameResult<Self> {
+        use std::io::BufRead;
+        let mut reader = io::BufReader::new(reader);
+        let gzipped = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        let reader: Box<dyn Read> = if gzipped {
+            Box::new(flate2::read::GzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+        let mut archive = tar::Archive::new(reader);
+        let mut files = Vec::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            // Tar paths are often written like `./images/player.png`.
+            let path = entry
+                .path()?
+                .components()
+                .filter(|c| matches!(c, path::Component::Normal(_)))
+                .collect::<PathBuf>();
+            // The size in the header may be anything, so let the
+            // contents grow as they're read instead of trusting it.
+            let mut contents = Vec::new();
+            let _ = entry.read_to_end(&mut contents)?;
+            files.push((path, contents));
+        }
+        Ok(TarFS {
+            source: None,
+            files: EmbeddedFS::from_owned(files),
+        })
+    }
+}
+
+#[cfg(feature = "tar")]
+impl Debug for TarFS {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match &self.source {
+            Some(source) => write!(f, "<TarFS: {}>", source.display()),
+            None => write!(f, "<TarFS: {} files>", self.files.files.len()),
+        }
+    }
+}
+
+#[cfg(feature = "tar")]
+impl VFS for TarFS {
+    fn open_options(&self, path: &Path, open_options: OpenOptions) -> GameResult<Box<dyn VFile>> {
+        self.files.open_options(path, open_options)
+    }
+
+    fn mkdir(&self, path: &Path) -> GameResult {
+        read_only_error("mkdir", path, self)
+    }
+
+    fn rm(&self, path: &Path) -> GameResult {
+        read_only_error("rm", path, self)
+    }
+
+    fn rmrf(&self, path: &Path) -> GameResult {
+        read_only_error("rmrf", path, self)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.exists(path)
+    }
+
+    fn metadata(&self, path: &Path) -> GameResult<Box<dyn VMetadata>> {
+        self.files.metadata(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>> {
+        self.files.read_dir(path)
     }
 
     fn to_path_buf(&self) -> Option<PathBuf> {
@@ -794,6 +1583,196 @@ impl VFS for ZipFS {
     }
 }
 
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// A read-only filesystem backed by a pak file, as made by
+/// [`pack::build()`](../pack/fn.build.html).
+///
+/// Only the pak's index is read when it's loaded; each file is read
+/// straight from its place in the pak when it's opened.
+#[allow(clippy::upper_case_acronyms)]
+pub struct PakFS {
+    source: Option<PathBuf>,
+    reader: RefCell<Box<dyn ReadSeek>>,
+    /// Where each file starts in the pak, and its length.
+    index: HashMap<PathBuf, (u64, u64)>,
+}
+
+impl PakFS {
+    /// Loads a `PakFS` from the pak file at the given path.
+    pub fn new(filename: &Path) -> GameResult<Self> {
+        let f = fs::File::open(filename)?;
+        let mut pakfs = PakFS::from_read(io::BufReader::new(f))?;
+        pakfs.source = Some(filename.into());
+        Ok(pakfs)
+    }
+
+    /// Loads a `PakFS` from an encrypted pak file made with
+    /// [`pack::build_encrypted()`](../pack/fn.build_encrypted.html),
+    /// given the key it was made with.
+    #[cfg(feature = "encryption")]
+    pub fn new_encrypted(filename: &Path, key: &[u8; 32]) -> GameResult<Self> {
+        let f = io::BufReader::new(fs::File::open(filename)?);
+        let mut pakfs = PakFS::from_read(crate::pack::Encrypted::open(f, key)?).map_err(|_| {
+            GameError::FilesystemError(format!(
+                "Could not read encrypted pak file {:?}; is the key right?",
+                filename
+            ))
+        })?;
+        pakfs.source = Some(filename.into());
+        Ok(pakfs)
+    }
+
+    /// Loads a `PakFS` from any `Read + Seek` object.
+    pub fn from_read<R>(mut reader: R) -> GameResult<Self>
+    where
+        R: Read + Seek + 'static,
+    {
+        let index = crate::pack::read_index(&mut reader)?;
+        Ok(PakFS {
+            source: None,
+            reader: RefCell::new(Box::new(reader)),
+            index,
+        })
+    }
+}
+
+impl Debug for PakFS {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match &self.source {
+            Some(source) => write!(f, "<PakFS: {}>", source.display()),
+            None => write!(f, "<PakFS: {} files>", self.index.len()),
+        }
+    }
+}
+
+impl VFS for PakFS {
+    fn open_options(&self, path: &Path, open_options: OpenOptions) -> GameResult<Box<dyn VFile>> {
+        if open_options.write || open_options.create || open_options.append || open_options.truncate
+        {
+            read_only_error("alter file", path, self)?;
+        }
+        let relative = relative_path(path)?;
+        let (offset, len) = match self.index.get(&relative) {
+            Some(entry) => *entry,
+            None => {
+                return Err(GameError::ResourceNotFound(
+                    path.to_string_lossy().into_owned(),
+                    Vec::new(),
+                ))
+            }
+        };
+        let mut reader = self.reader.try_borrow_mut().map_err(|_| {
+            GameError::FilesystemError("Pak file is already being read".to_string())
+        })?;
+        let _ = reader.seek(io::SeekFrom::Start(offset))?;
+        let mut contents = Vec::new();
+        let _ = reader.as_mut().take(len).read_to_end(&mut contents)?;
+        let file = EmbeddedFile(io::Cursor::new(Contents::Shared(contents.into())));
+        Ok(Box::new(file) as Box<dyn VFile>)
+    }
+
+    fn mkdir(&self, path: &Path) -> GameResult {
+        read_only_error("mkdir", path, self)
+    }
+
+    fn rm(&self, path: &Path) -> GameResult {
+        read_only_error("rm", path, self)
+    }
+
+    fn rmrf(&self, path: &Path) -> GameResult {
+        read_only_error("rmrf", path, self)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        match relative_path(path) {
+            Ok(relative) => {
+                self.index.contains_key(&relative) || index_is_dir(self.index.keys(), &relative)
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> GameResult<Box<dyn VMetadata>> {
+        let relative = relative_path(path)?;
+        let metadata = match self.index.get(&relative) {
+            Some((_, len)) => ArchiveMetadata {
+                len: *len,
+                is_dir: false,
+            },
+            None if index_is_dir(self.index.keys(), &relative) => ArchiveMetadata {
+                len: 0,
+                is_dir: true,
+            },
+            None => {
+                return Err(GameError::FilesystemError(format!(
+                    "Metadata not found in pak file for {:?}",
+                    path
+                )))
+            }
+        };
+        Ok(Box::new(metadata) as Box<dyn VMetadata>)
+    }
+
+    /// Lists the files and directories right inside the given one.
+    fn read_dir(&self, path: &Path) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>> {
+        index_read_dir(self.index.keys(), &relative_path(path)?)
+    }
+
+    fn to_path_buf(&self) -> Option<PathBuf> {
+        self.source.clone()
+    }
+}
+
+/// Wraps a VFS so that nothing can be written to it.
+#[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) struct ReadOnlyFS(pub(crate) Box<dyn VFS>);
+
+impl VFS for ReadOnlyFS {
+    fn open_options(&self, path: &Path, open_options: OpenOptions) -> GameResult<Box<dyn VFile>> {
+        if open_options.write || open_options.create || open_options.append || open_options.truncate
+        {
+            read_only_error("alter file", path, &self.0)?;
+        }
+        self.0.open_options(path, open_options)
+    }
+
+    fn mkdir(&self, path: &Path) -> GameResult {
+        read_only_error("mkdir", path, &self.0)
+    }
+
+    fn rm(&self, path: &Path) -> GameResult {
+        read_only_error("rm", path, &self.0)
+    }
+
+    fn rmrf(&self, path: &Path) -> GameResult {
+        read_only_error("rmrf", path, &self.0)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.0.exists(path)
+    }
+
+    fn metadata(&self, path: &Path) -> GameResult<Box<dyn VMetadata>> {
+        self.0.metadata(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> GameResult<Box<dyn Iterator<Item = GameResult<PathBuf>>>> {
+        self.0.read_dir(path)
+    }
+
+    fn to_path_buf(&self) -> Option<PathBuf> {
+        self.0.to_path_buf()
+    }
+
+    fn map(&self, path: &Path) -> GameResult<MappedFile> {
+        self.0.map(path)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -963,5 +1942,252 @@ mod tests {
         assert_eq!(contents, "Zip contents!");
     }
 
+    #[test]
+    fn headless_test_zip_streaming() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("ggez_zip_stream_test_{}.zip", std::process::id()));
+        let contents = (0..3 * ZIP_STREAMING_THRESHOLD as usize)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect::<Vec<u8>>();
+        {
+            let mut zip_archive = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+            for &(name, method) in &[
+                ("stored.bin", zip::CompressionMethod::Stored),
+                ("deflated.bin", zip::CompressionMethod::Deflated),
+            ] {
+                let options = zip::write::FileOptions::default().compression_method(method);
+                zip_archive.start_file(name, options).unwrap();
+                zip_archive.write_all(&contents).unwrap();
+            }
+            let _file = zip_archive.finish().unwrap();
+        }
+
+        let zfs = ZipFS::new(&path).unwrap();
+        for name in &["/stored.bin", "/deflated.bin"] {
+            let mut f = zfs.open(Path::new(name)).unwrap();
+            assert!(format!("{:?}", f).starts_with("<ZipStream"));
+            let mut read = Vec::new();
+            let _bytes = f.read_to_end(&mut read).unwrap();
+            assert_eq!(read, contents);
+
+            let mut buf = [0; 16];
+            for &pos in &[1_000_000, 12, 2_500_000] {
+                assert_eq!(f.seek(io::SeekFrom::Start(pos)).unwrap(), pos);
+                f.read_exact(&mut buf).unwrap();
+                assert_eq!(&buf[..], &contents[pos as usize..pos as usize + 16]);
+            }
+            let end = f.seek(io::SeekFrom::End(-16)).unwrap();
+            assert_eq!(end, contents.len() as u64 - 16);
+            f.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf[..], &contents[contents.len() - 16..]);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn headless_test_map() {
+        let cargo_path = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let expected = fs::read(cargo_path.join("Cargo.toml")).unwrap();
+        let physfs = PhysicalFS::new(cargo_path, true);
+        let mapped = physfs.map(Path::new("/Cargo.toml")).unwrap();
+        assert_eq!(mapped.is_mapped(), cfg!(feature = "mmap"));
+        assert_eq!(&mapped[..], &expected[..]);
+
+        let embedded = EmbeddedFS::new(vec![("/readme.txt", &b"Embedded!"[..])]);
+        let mut ofs = OverlayFS::new();
+        ofs.push_back(Box::new(embedded));
+        ofs.push_back(Box::new(physfs));
+        let mapped = ofs.map(Path::new("/readme.txt")).unwrap();
+        assert!(!mapped.is_mapped());
+        assert_eq!(&mapped[..8], b"Embedded");
+        assert_eq!(
+            ofs.map(Path::new("/Cargo.toml")).unwrap().is_mapped(),
+            cfg!(feature = "mmap")
+        );
+        assert!(ofs.map(Path::new("/foobaz.rs")).is_err());
+    }
+
+    #[test]
+    fn headless_test_path_case() {
+        let mut ofs = OverlayFS::new();
+        ofs.push_back(Box::new(EmbeddedFS::new(vec![(
+            "/Images/Player.png",
+            &b"png"[..],
+        )])));
+        let wrong_case = Path::new("/images/player.PNG");
+        assert!(!ofs.exists(wrong_case));
+
+        ofs.set_path_case(PathCase::Insensitive);
+        assert!(ofs.exists(wrong_case));
+        assert!(ofs.exists(Path::new("/Images/Player.png")));
+        assert!(!ofs.exists(Path::new("/images/enemy.png")));
+        assert_eq!(ofs.metadata(wrong_case).unwrap().len(), 3);
+        let mut contents = Vec::new();
+        let _bytes = ofs
+            .open(wrong_case)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, b"png");
+    }
+
+    #[test]
+    fn headless_test_path_case_in_zip() {
+        let zip_bytes = {
+            let mut zip_archive = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+            zip_archive
+                .start_file("Images/Player.png", zip::write::FileOptions::default())
+                .unwrap();
+            let _bytes = zip_archive.write(b"png").unwrap();
+            zip_archive.finish().unwrap()
+        };
+        let zfs = ZipFS::from_read(zip_bytes).unwrap();
+        assert!(zfs.metadata(Path::new("/Images")).unwrap().is_dir());
+        assert_eq!(
+            zfs.read_dir(Path::new("/"))
+                .unwrap()
+                .collect::<GameResult<Vec<_>>>()
+                .unwrap(),
+            vec![PathBuf::from("/Images")]
+        );
+
+        let mut ofs = OverlayFS::new();
+        ofs.push_back(Box::new(zfs));
+        let wrong_case = Path::new("/images/player.PNG");
+        assert!(!ofs.exists(wrong_case));
+        ofs.set_path_case(PathCase::Insensitive);
+        assert_eq!(ofs.resolve(wrong_case), Path::new("/Images/Player.png"));
+        let mut contents = Vec::new();
+        let _bytes = ofs
+            .open(wrong_case)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, b"png");
+    }
+
+    #[test]
+    fn headless_test_path_case_found_anyway() {
+        // Stands in for a filesystem that ignores case, like the ones on
+        // Windows and macOS, which find the wrong-case path by themselves.
+        let mut ignores_case = OverlayFS::new();
+        ignores_case.push_back(Box::new(EmbeddedFS::new(vec![(
+            "/Images/Player.png",
+            &b"png"[..],
+        )])));
+        ignores_case.set_path_case(PathCase::Insensitive);
+
+        let mut ofs = OverlayFS::new();
+        ofs.push_back(Box::new(ignores_case));
+        let wrong_case = Path::new("/images/player.PNG");
+        assert!(ofs.exists(wrong_case));
+        assert_eq!(ofs.resolve(wrong_case), Path::new("/images/player.PNG"));
+
+        ofs.set_path_case(PathCase::Insensitive);
+        assert_eq!(ofs.resolve(wrong_case), Path::new("/Images/Player.png"));
+        assert!(matches!(
+            ofs.resolve(Path::new("/Images/Player.png")),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn headless_test_path_case_cached() {
+        let mut ofs = OverlayFS::new();
+        ofs.set_path_case(PathCase::Insensitive);
+        ofs.push_back(Box::new(EmbeddedFS::new(vec![(
+            "/Images/Player.png",
+            &b"png"[..],
+        )])));
+        let wrong_case = Path::new("/images/player.PNG");
+        assert_eq!(ofs.resolve(wrong_case), Path::new("/Images/Player.png"));
+        assert_eq!(
+            ofs.resolved.borrow().get(wrong_case).map(PathBuf::as_path),
+            Some(Path::new("/Images/Player.png"))
+        );
+        assert_eq!(ofs.resolve(wrong_case), Path::new("/Images/Player.png"));
+
+        // Paths that aren't there aren't remembered.
+        assert!(!ofs.exists(Path::new("/images/enemy.png")));
+        assert_eq!(ofs.resolved.borrow().len(), 1);
+
+        // A source with the exact name takes over once it's added.
+        ofs.push_front(Box::new(EmbeddedFS::new(vec![(
+            "/images/player.PNG",
+            &b"new"[..],
+        )])));
+        assert!(ofs.resolved.borrow().is_empty());
+        assert_eq!(ofs.resolve(wrong_case), wrong_case);
+    }
+
+    #[test]
+    fn headless_test_embedded_files() {
+        let fs = EmbeddedFS::new(vec![
+            ("/readme.txt", &b"Embedded!"[..]),
+            ("/images/player.png", &b"png"[..]),
+            ("/images/enemies/rock.png", &b"png"[..]),
+        ]);
+
+        assert!(fs.exists(Path::new("/readme.txt")));
+        assert!(fs.exists(Path::new("/images")));
+        assert!(!fs.exists(Path::new("/images/ship.png")));
+        assert!(fs.metadata(Path::new("/images")).unwrap().is_dir());
+        assert_eq!(fs.metadata(Path::new("/readme.txt")).unwrap().len(), 9);
+
+        let mut contents = String::new();
+        let _bytes = fs
+            .open(Path::new("/readme.txt"))
+            .unwrap()
+            .read_to_string(&mut contents);
+        assert_eq!(contents, "Embedded!");
+        assert!(fs.create(Path::new("/readme.txt")).is_err());
+
+        let listed = fs
+            .read_dir(Path::new("/images"))
+            .unwrap()
+            .collect::<GameResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            listed,
+            vec![
+                PathBuf::from("/images/enemies"),
+                PathBuf::from("/images/player.png")
+            ]
+        );
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn headless_test_tar_files() {
+        let tar_bytes = {
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(13);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, "./data/file.txt", &b"Tar contents!"[..])
+                .unwrap();
+            builder.into_inner().unwrap()
+        };
+        let gzipped = {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&tar_bytes).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        for bytes in vec![tar_bytes, gzipped] {
+            let tfs = TarFS::from_read(io::Cursor::new(bytes)).unwrap();
+            assert!(tfs.exists(Path::new("/data/file.txt")));
+            assert!(tfs.metadata(Path::new("/data")).unwrap().is_dir());
+            let mut contents = String::new();
+            let _bytes = tfs
+                .open(Path::new("/data/file.txt"))
+                .unwrap()
+                .read_to_string(&mut contents);
+            assert_eq!(contents, "Tar contents!");
+        }
+    }
+
     // BUGGO: TODO: Make sure all functions are tested for OverlayFS and ZipFS!!
 }